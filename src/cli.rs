@@ -1,4 +1,4 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
 
 use crate::error::{MoteError, Result};
@@ -24,6 +24,31 @@ pub struct Cli {
     #[arg(long, global = true)]
     pub config_dir: Option<PathBuf>,
 
+    /// Display timestamps in UTC instead of local time
+    #[arg(long, global = true)]
+    pub utc: bool,
+
+    /// Display sizes as exact byte counts
+    #[arg(long, global = true)]
+    pub bytes: bool,
+
+    /// Don't draw progress bars (also MOTE_NO_PROGRESS=1)
+    #[arg(long, global = true)]
+    pub no_progress: bool,
+
+    /// Also apply the project's .gitignore (ignore.use_gitignore)
+    #[arg(long, global = true)]
+    pub use_gitignore: bool,
+
+    /// Extra ignore file layered after the context's (relative to the
+    /// project root)
+    #[arg(long, global = true, value_name = "PATH")]
+    pub ignore_file: Option<PathBuf>,
+
+    /// Color output: auto (terminal, unless NO_COLOR is set), always or never
+    #[arg(long, value_enum, global = true, default_value_t = ColorChoice::Auto)]
+    pub color: ColorChoice,
+
     // Deprecated options (hidden, for backward compatibility)
     #[arg(short = 'p', long, global = true, hide = true)]
     pub project: Option<String>,
@@ -37,10 +62,23 @@ pub struct Cli {
 
 #[derive(Subcommand)]
 pub enum Commands {
-    /// Snapshot operations
+    /// Snapshot operations (bare `mote snap` creates a snapshot)
+    #[command(args_conflicts_with_subcommands = true)]
     Snap {
         #[command(subcommand)]
         command: Option<SnapCommands>,
+
+        /// Optional message for the snapshot (same as `snap create -m`)
+        #[arg(short, long)]
+        message: Option<String>,
+
+        /// Trigger source (same as `snap create -t`)
+        #[arg(short, long)]
+        trigger: Option<String>,
+
+        /// Auto mode (same as `snap create --auto`)
+        #[arg(long)]
+        auto: bool,
     },
 
     /// Project management
@@ -68,6 +106,75 @@ pub enum Commands {
         shell: String,
     },
 
+    /// Install git hooks that snapshot on commit and checkout
+    Hook {
+        #[command(subcommand)]
+        command: HookCommands,
+    },
+
+    /// Print a shell completion script (usage is in its header comment)
+    Completions {
+        #[arg(value_enum)]
+        shell: CompletionShell,
+    },
+
+    /// Answer read-only JSON requests for editor plugins (see README)
+    Serve {
+        /// Read line-delimited JSON requests from stdin, write responses to stdout
+        #[arg(long, required = true)]
+        stdio: bool,
+    },
+
+    /// Show working-tree changes since the latest snapshot
+    /// (exits with status 1 if there are any)
+    Status {
+        /// Print `A`, `M` or `D`, a tab and the path per line, without color
+        #[arg(long)]
+        porcelain: bool,
+    },
+
+    /// Snapshot automatically while files change, until Ctrl-C
+    /// (see the [watch] config section)
+    Watch {
+        /// Exit after the first snapshot
+        #[arg(long)]
+        once: bool,
+    },
+
+    /// Show object store usage (snapshots, objects, reclaimable space)
+    Stats {
+        /// Objects and compressed bytes added per period, by first snapshot referencing them
+        #[arg(long)]
+        history: bool,
+
+        /// Period to group --history by
+        #[arg(long, value_enum, default_value_t = StatsPeriod::Week, requires = "history")]
+        by: StatsPeriod,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Inspect this storage directory directly, without detecting a project
+        #[arg(long, value_name = "PATH")]
+        storage_dir: Option<PathBuf>,
+    },
+
+    /// Browse snapshot history interactively (read-only)
+    #[cfg(feature = "tui")]
+    Tui,
+
+    /// Set up the project, default context, ignore file and a first snapshot
+    Quickstart {
+        /// Accept all defaults without prompting
+        #[arg(short, long)]
+        yes: bool,
+
+        /// Skip the first snapshot
+        #[arg(long)]
+        no_snapshot: bool,
+    },
+
     /// Migrate existing .mote directory to new structure
     Migrate {
         /// Show what would be migrated without actually migrating
@@ -92,6 +199,8 @@ pub enum Commands {
         limit: usize,
         #[arg(long)]
         oneline: bool,
+        #[arg(long)]
+        trigger: Option<String>,
     },
 
     #[command(hide = true)]
@@ -129,7 +238,11 @@ pub enum Commands {
     },
 
     #[command(hide = true)]
-    Init,
+    Init {
+        /// Create an ignore file with only a header comment
+        #[arg(long)]
+        minimal: bool,
+    },
 }
 
 #[derive(Subcommand)]
@@ -147,33 +260,160 @@ pub enum SnapCommands {
         /// Auto mode: skip if no changes, quiet output (for git/jj hooks)
         #[arg(long)]
         auto: bool,
+
+        /// Attach metadata as key=value (repeatable)
+        #[arg(long = "meta", value_name = "KEY=VALUE", value_parser = parse_metadata)]
+        metadata: Vec<(String, String)>,
+
+        /// Always record a snapshot, even if nothing changed since the latest one
+        /// (only snapshot metadata is stored; file contents are shared)
+        #[arg(long)]
+        checkpoint: bool,
+
+        /// Snapshot even if the project root is / or your home directory,
+        /// or contains more than snapshot.max_files files
+        #[arg(long)]
+        force_large_root: bool,
+
+        /// Abort without writing a snapshot if any file can't be read
+        /// (see snapshot.fail_fast; ignored with --auto)
+        #[arg(long)]
+        fail_fast: bool,
+
+        /// zstd level (1-22) for this snapshot's new objects,
+        /// overriding storage.compression_level
+        #[arg(long, value_name = "LEVEL", value_parser = clap::value_parser!(i32).range(1..=22))]
+        compression: Option<i32>,
     },
 
     /// Show snapshot history
     List {
-        /// Maximum number of snapshots to show
-        #[arg(short, long, default_value = "20")]
-        limit: usize,
+        /// Maximum number of snapshots to show (default: 20 on a terminal, unlimited otherwise)
+        #[arg(short, long)]
+        limit: Option<usize>,
+
+        /// Show all snapshots
+        #[arg(long, conflicts_with = "limit")]
+        all: bool,
 
         /// Show compact one-line format
         #[arg(long)]
         oneline: bool,
+
+        /// Only show snapshots whose metadata matches key=value (repeatable)
+        #[arg(long = "meta", value_name = "KEY=VALUE", value_parser = parse_metadata)]
+        metadata: Vec<(String, String)>,
+
+        /// Output as a JSON array
+        #[arg(long, conflicts_with = "oneline")]
+        json: bool,
+
+        /// Include the full file list in JSON output
+        #[arg(short, long, requires = "json")]
+        verbose: bool,
+
+        /// Only show snapshots at or after this time (RFC3339, YYYY-MM-DD, or 2h/3d/1w ago)
+        #[arg(long, value_name = "TIME")]
+        since: Option<String>,
+
+        /// Only show snapshots at or before this time (RFC3339, YYYY-MM-DD, or 2h/3d/1w ago)
+        #[arg(long, value_name = "TIME")]
+        until: Option<String>,
+
+        /// Only show snapshots whose message contains this text (case-insensitive)
+        #[arg(long, value_name = "PATTERN")]
+        grep: Option<String>,
+
+        /// Treat the --grep pattern as a regular expression
+        #[arg(long, requires = "grep")]
+        regex: bool,
+
+        /// Only show snapshots recorded by this trigger ('none' or 'manual' match untriggered ones)
+        #[arg(long)]
+        trigger: Option<String>,
+
+        /// Show added/modified/deleted file counts against the previous snapshot
+        #[arg(long)]
+        stat: bool,
+
+        /// Print one line per snapshot using a template, e.g. "{short_id} {message}"
+        /// (placeholders: id, short_id, timestamp, timestamp_iso, message, trigger, file_count)
+        #[arg(long, value_name = "TEMPLATE", conflicts_with_all = ["oneline", "json"])]
+        format: Option<String>,
+
+        /// Only show snapshots where this file was added, modified or deleted
+        #[arg(long, value_name = "PATH", conflicts_with = "json")]
+        follow: Option<String>,
+
+        /// List oldest snapshots first
+        #[arg(long)]
+        reverse: bool,
+
+        /// Skip this many snapshots (after filtering and ordering) before --limit applies
+        #[arg(long, value_name = "N", default_value_t = 0)]
+        offset: usize,
+
+        /// Hide snapshots whose content is identical to the previous snapshot
+        /// (with --stat they are shown marked "(no changes)")
+        #[arg(long)]
+        skip_empty: bool,
     },
 
     /// Show details of a specific snapshot
     Show {
-        /// Snapshot ID (can be abbreviated)
-        snapshot_id: String,
+        /// Snapshot ID (can be abbreviated) or `latest` (default);
+        /// `<id>:<path>` prints that file's content
+        snapshot_id: Option<String>,
+
+        /// Show per-file added/removed line counts against the previous snapshot
+        #[arg(long)]
+        stat: bool,
+
+        /// Print the full snapshot (including its file list) as JSON
+        #[arg(long, conflicts_with = "stat")]
+        json: bool,
+
+        /// Print this file's stored content (for paths containing ':')
+        #[arg(long, conflicts_with_all = ["stat", "json"])]
+        path: Option<String>,
+
+        /// List files as a directory tree with per-directory counts and sizes
+        #[arg(long, conflicts_with_all = ["stat", "json", "path"])]
+        tree: bool,
+
+        /// Collapse tree levels deeper than this into a file count
+        #[arg(long, requires = "tree")]
+        depth: Option<usize>,
     },
 
     /// Show differences between snapshots or working directory
     Diff {
-        /// First snapshot ID (if omitted, uses latest snapshot)
+        /// First snapshot ID (if omitted, uses latest snapshot), or a range:
+        /// `old..new` compares two snapshots, `old..` the working directory
         snapshot_id: Option<String>,
 
         /// Second snapshot ID (optional, compares with current working directory if omitted)
         snapshot_id2: Option<String>,
 
+        /// Compare across the last N snapshots, or `old..new`, and note the
+        /// snapshot in between that last changed each file
+        #[arg(
+            long,
+            value_name = "N|OLD..NEW",
+            conflicts_with_all = ["snapshot_id", "snapshot_id2"]
+        )]
+        range: Option<String>,
+
+        /// Compare the newest snapshot taken at or before this time with the
+        /// working directory (RFC3339, YYYY-MM-DD HH:MM in local time or UTC
+        /// with --utc, or 30m/2h/3d ago)
+        #[arg(
+            long,
+            value_name = "TIME",
+            conflicts_with_all = ["snapshot_id", "snapshot_id2", "range"]
+        )]
+        at: Option<String>,
+
         /// Show only file names without diff content
         #[arg(long)]
         name_only: bool,
@@ -182,27 +422,168 @@ pub enum SnapCommands {
         #[arg(short, long)]
         output: Option<String>,
 
+        /// Replace the --output file if it already exists
+        #[arg(long, requires = "output")]
+        force_output: bool,
+
         /// Number of context lines (default: 3)
         #[arg(short = 'U', long, default_value = "3")]
         unified: usize,
+
+        /// Only print added/modified/deleted counts and byte deltas (no content is read)
+        #[arg(long, conflicts_with_all = ["name_only", "output"])]
+        summary_only: bool,
+
+        /// Per-file insertion/deletion counts with a histogram instead of the diff
+        #[arg(long, conflicts_with_all = ["name_only", "output", "summary_only"])]
+        stat: bool,
+
+        /// Print the changed files as JSON: path, status, hashes and sizes
+        /// (with --summary-only, the counts; with --output, written to the file)
+        #[arg(long, conflicts_with_all = ["name_only", "stat"])]
+        json: bool,
+
+        /// Include line-level hunks in the JSON output
+        #[arg(long, requires = "json", conflicts_with = "summary_only")]
+        with_hunks: bool,
+
+        /// Exit with status 1 if there are differences
+        #[arg(long)]
+        exit_code: bool,
+
+        /// Print nothing; implies --exit-code
+        #[arg(
+            short,
+            long,
+            conflicts_with_all = ["name_only", "output", "summary_only", "stat", "json"]
+        )]
+        quiet: bool,
+
+        /// Hide files whose only change is their mode (see diff.ignore_mode_changes)
+        #[arg(long)]
+        ignore_mode: bool,
+
+        /// Ignore whitespace when comparing lines; files that only differ in
+        /// whitespace are left out
+        #[arg(short = 'w', long, conflicts_with_all = ["summary_only", "stat", "json"])]
+        ignore_all_space: bool,
+
+        /// Ignore changes that only add or remove blank lines
+        #[arg(long, conflicts_with_all = ["summary_only", "stat", "json"])]
+        ignore_blank_lines: bool,
+
+        /// Write `diff --git` headers with new/deleted file modes, as `git diff` does
+        #[arg(long, conflicts_with_all = ["name_only", "summary_only", "stat", "json"])]
+        git_format: bool,
+
+        /// Mark changed words within modified lines as [-old-]{+new+}, or in
+        /// color on a terminal
+        #[arg(
+            long,
+            conflicts_with_all = ["name_only", "summary_only", "stat", "json", "git_format"]
+        )]
+        word_diff: bool,
+
+        /// Show each changed file with this command (e.g. `delta`, `difft`),
+        /// passing the old and new file paths; overrides diff.external
+        #[arg(long, value_name = "CMD")]
+        tool: Option<String>,
+
+        /// Only compare files at or under these paths (after `--`)
+        #[arg(last = true)]
+        paths: Vec<String>,
     },
 
     /// Restore files from a snapshot
     Restore {
         /// Snapshot ID to restore from
-        snapshot_id: String,
+        #[arg(required_unless_present = "at")]
+        snapshot_id: Option<String>,
+
+        /// Restore the newest snapshot taken at or before this time instead
+        /// (RFC3339, YYYY-MM-DD HH:MM in local time or UTC with --utc, or
+        /// 30m/2h/3d ago)
+        #[arg(long, value_name = "TIME", conflicts_with = "snapshot_id")]
+        at: Option<String>,
 
-        /// Specific file to restore (restores entire snapshot if omitted)
+        /// File to restore, or a glob pattern such as `src/handlers/*.rs`;
+        /// repeat for more (restores entire snapshot if omitted)
         #[arg(short, long)]
-        file: Option<String>,
+        file: Vec<String>,
 
-        /// Skip automatic backup creation before restore
+        /// Skip automatic backup creation before restore, and overwrite
+        /// files with local edits that no snapshot holds
         #[arg(long)]
         force: bool,
 
         /// Show what would be restored without actually restoring
         #[arg(long)]
         dry_run: bool,
+
+        /// Also delete working files that aren't in the snapshot (ignored
+        /// files are left alone)
+        #[arg(long, conflicts_with = "file")]
+        clean: bool,
+
+        /// Confirm --clean together with --force, when no backup is taken
+        #[arg(long, requires = "clean")]
+        yes: bool,
+
+        /// Extract into this directory (created if missing) instead of the
+        /// project; no backup snapshot is taken
+        #[arg(long, value_name = "DIR", conflicts_with = "clean")]
+        target: Option<PathBuf>,
+
+        /// Choose which changed files to restore, one prompt per file
+        /// (y/n/a/q, or d to see its diff first)
+        #[arg(short, long, conflicts_with_all = ["file", "dry_run", "clean", "target"])]
+        interactive: bool,
+
+        /// Retrieve and write one file at a time instead of prefetching
+        /// objects on restore.threads workers
+        #[arg(long)]
+        serial: bool,
+
+        /// Write the file selected with --file to stdout, byte for byte,
+        /// instead of restoring it (no backup, nothing else printed)
+        #[arg(long, requires = "file", conflicts_with_all = ["dry_run", "clean", "target", "interactive"])]
+        stdout: bool,
+        /// With --dry-run, also list files that are already identical
+        #[arg(short, long, requires = "dry_run")]
+        verbose: bool,
+
+        /// Leave files matching this gitignore-style pattern alone, and keep
+        /// them with --clean; repeatable (e.g. `.env.local`, `config/*.toml`)
+        #[arg(short = 'x', long, value_name = "PATTERN", conflicts_with_all = ["file", "interactive"])]
+        exclude: Vec<String>,
+    },
+
+    /// Apply a patch written by `mote snap diff --output` to the project
+    Apply {
+        /// Patch file (`-` reads stdin)
+        patch_file: String,
+
+        /// Check that the patch applies and list the files it would change
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Undo the patch instead of applying it
+        #[arg(short = 'R', long)]
+        reverse: bool,
+
+        /// Change nothing unless every file in the patch applies
+        #[arg(long)]
+        atomic: bool,
+    },
+
+    /// Reapply file modes recorded in a snapshot without touching content (Unix only)
+    FixPerms {
+        /// Snapshot ID (can be abbreviated) or `latest` (default)
+        snapshot_id: Option<String>,
+
+        /// Show which modes would change without changing them
+        #[arg(long)]
+        dry_run: bool,
     },
 
     /// Delete a snapshot
@@ -210,9 +591,75 @@ pub enum SnapCommands {
         /// Snapshot ID to delete
         snapshot_id: String,
 
-        /// Skip confirmation prompt
+        /// Skip confirmation prompt (required when stdin isn't a terminal)
+        #[arg(short = 'y', long, visible_alias = "yes")]
+        force: bool,
+    },
+
+    /// Export the newest snapshots and only the objects they reference
+    ///
+    /// The output is a context directory (usable with -d/--context-dir),
+    /// or a tar archive of one if the output path ends in .tar
+    ExportStore {
+        /// Number of newest snapshots to keep
+        #[arg(long, conflicts_with = "ids")]
+        keep: Option<usize>,
+
+        /// Explicit snapshot ID to export (repeatable)
+        #[arg(long = "id")]
+        ids: Vec<String>,
+
+        /// Output directory or .tar archive
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+
+    /// Delete the backup snapshots taken before each restore
+    ///
+    /// The newest backup is kept unless --keep says otherwise
+    PruneBackups {
+        /// Only delete backups older than this (e.g. 12h, 7d, 2w)
+        #[arg(long, value_name = "DURATION")]
+        older_than: Option<String>,
+
+        /// Number of newest backups to keep
+        #[arg(long, default_value_t = 1)]
+        keep: usize,
+
+        /// Skip confirmation prompt (required when stdin isn't a terminal)
+        #[arg(short = 'y', long, visible_alias = "yes")]
+        force: bool,
+
+        /// List the backups that would be deleted without deleting them
+        #[arg(long, conflicts_with = "force")]
+        dry_run: bool,
+
+        /// Run garbage collection afterwards to reclaim their objects
+        #[arg(long, conflicts_with = "dry_run")]
+        gc: bool,
+    },
+
+    /// Check that every snapshot loads and every object it needs is stored
+    ///
+    /// Exits non-zero if an object is missing (or, with --full, corrupt) or a
+    /// snapshot file can't be parsed. Orphaned objects are only reported.
+    #[command(visible_alias = "fsck")]
+    Verify {
+        /// Also decompress every object and check its SHA-256
+        #[arg(long)]
+        full: bool,
+
+        /// Delete snapshot files that fail to parse (asks first)
         #[arg(long)]
+        delete_corrupt: bool,
+
+        /// Delete them without asking (required when stdin isn't a terminal)
+        #[arg(short = 'y', long, visible_alias = "yes", requires = "delete_corrupt")]
         force: bool,
+
+        /// Verify this storage directory directly, without detecting a project
+        #[arg(long, value_name = "PATH")]
+        storage_dir: Option<PathBuf>,
     },
 
     /// Run garbage collection to remove unreferenced objects
@@ -224,6 +671,15 @@ pub enum SnapCommands {
         /// Show detailed progress information
         #[arg(long)]
         verbose: bool,
+
+        /// Also delete objects newer than gc.grace_period_secs, which a
+        /// snapshot being taken right now may still need
+        #[arg(long)]
+        no_grace: bool,
+
+        /// Collect garbage in this storage directory directly, without detecting a project
+        #[arg(long, value_name = "PATH")]
+        storage_dir: Option<PathBuf>,
     },
 }
 
@@ -256,6 +712,15 @@ pub enum ContextCommands {
         /// Do not register this context in project config (for temporary contexts)
         #[arg(long)]
         no_register: bool,
+
+        /// Allow a new project rooted at / or your home directory
+        #[arg(long)]
+        force_large_root: bool,
+
+        /// Create an ignore file with only a header comment instead of the
+        /// default patterns
+        #[arg(long)]
+        minimal: bool,
     },
 
     /// Delete a context
@@ -263,17 +728,46 @@ pub enum ContextCommands {
         /// Context name
         name: String,
     },
+
+    /// Mark a context as archived (read-only)
+    Archive {
+        /// Context name
+        name: String,
+    },
+
+    /// Make an archived context writable again
+    Unarchive {
+        /// Context name
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum HookCommands {
+    /// Add a mote section to the pre-commit and post-checkout hooks
+    Install,
+
+    /// Remove the mote section, leaving the rest of each hook alone
+    Uninstall,
+
+    /// Show which hooks are installed and whether their mote binary exists
+    Status,
 }
 
 #[derive(Subcommand)]
 pub enum IgnoreCommands {
-    /// List ignore patterns
+    /// List ignore patterns, including the local ignore file
     List,
 
     /// Add ignore pattern
     Add {
         /// Pattern to add
         pattern: String,
+
+        /// Add to the personal `.local` ignore file next to the shared one,
+        /// which is never snapshotted
+        #[arg(long)]
+        local: bool,
     },
 
     /// Remove ignore pattern
@@ -284,6 +778,71 @@ pub enum IgnoreCommands {
 
     /// Edit ignore file in editor
     Edit,
+
+    /// Suggest patterns for ecosystems detected in the project root
+    Suggest {
+        /// Append the suggested patterns to the ignore file
+        #[arg(long)]
+        apply: bool,
+    },
+
+    /// Explain whether snapshots capture each path, and which pattern
+    /// decides it (exits with status 1 if any path is ignored)
+    Check {
+        /// Paths relative to the project root, or absolute
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+    },
+}
+
+/// When to color terminal output
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// Shells `mote completions` writes scripts for
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum CompletionShell {
+    Bash,
+    Zsh,
+    Fish,
+    Powershell,
+}
+
+/// Period `mote stats --history` groups objects by
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum StatsPeriod {
+    #[default]
+    Week,
+    Month,
+}
+
+/// Parse a `key=value` metadata pair.
+/// The key must be non-empty and free of control characters; the value may
+/// contain anything (including spaces and further `=` signs).
+pub fn parse_metadata(s: &str) -> Result<(String, String)> {
+    let (key, value) = s.split_once('=').ok_or_else(|| {
+        MoteError::InvalidArguments(format!("Invalid metadata '{}': expected key=value", s))
+    })?;
+
+    if key.is_empty() {
+        return Err(MoteError::InvalidArguments(format!(
+            "Invalid metadata '{}': key cannot be empty",
+            s
+        )));
+    }
+    if key.chars().any(|c| c.is_control()) {
+        return Err(MoteError::InvalidArguments(format!(
+            "Invalid metadata key '{}': cannot contain control characters",
+            key.escape_debug()
+        )));
+    }
+
+    Ok((key.to_string(), value.to_string()))
 }
 
 impl Cli {
@@ -293,8 +852,9 @@ impl Cli {
     /// - "feature" -> (None, Some("feature"))
     /// - "myproject" -> (Some("myproject"), None)
     pub fn parse_context_spec(&self) -> Result<(Option<String>, Option<String>)> {
-        // Validate exclusivity
-        if self.context_dir.is_some() {
+        // Validate exclusivity. `context` commands take -d as the directory of
+        // the context they act on, so it combines with -c there.
+        if self.context_dir.is_some() && !matches!(self.command, Commands::Context { .. }) {
             if self.context_spec.is_some() {
                 return Err(MoteError::InvalidArguments(
                     "-d/--context-dir cannot be used with -c/--context".to_string(),
@@ -317,23 +877,27 @@ impl Cli {
             }
         }
 
+        // Old and new forms could name different contexts; don't guess which wins
+        if self.context_spec.is_some() && (self.project.is_some() || self.old_context.is_some()) {
+            return Err(MoteError::InvalidArguments(
+                "-c/--context cannot be combined with the deprecated -p/--project or --old-context"
+                    .to_string(),
+            ));
+        }
+
         // Parse context_spec
         if let Some(ref spec) = self.context_spec {
             if let Some(pos) = spec.find('/') {
                 // Check for multiple slashes
                 if spec.rfind('/') != Some(pos) {
-                    return Err(MoteError::InvalidArguments(
-                        "Invalid context specifier format. Use [project/]context".to_string(),
-                    ));
+                    return Err(MoteError::InvalidContextSpec(spec.clone()));
                 }
 
                 let project = spec[..pos].to_string();
                 let context = spec[pos + 1..].to_string();
 
                 if project.is_empty() || context.is_empty() {
-                    return Err(MoteError::InvalidArguments(
-                        "Invalid context specifier format. Use [project/]context".to_string(),
-                    ));
+                    return Err(MoteError::InvalidContextSpec(spec.clone()));
                 }
 
                 Ok((Some(project), Some(context)))
@@ -350,4 +914,134 @@ impl Cli {
             Ok((None, None))
         }
     }
+
+    /// One-line notice for the deprecated `-p`/`--old-context` flags, naming
+    /// the equivalent `-c` specifier
+    pub fn deprecation_warning(&self) -> Option<String> {
+        let (flags, spec) = match (&self.project, &self.old_context) {
+            (Some(project), Some(context)) => (
+                "-p/--project and --old-context are",
+                format!("{}/{}", project, context),
+            ),
+            // A project alone selected its default context
+            (Some(project), None) => ("-p/--project is", format!("{}/default", project)),
+            (None, Some(context)) => ("--old-context is", context.clone()),
+            (None, None) => return None,
+        };
+        Some(format!("{} deprecated; use -c {} instead", flags, spec))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Cli {
+        let mut argv = vec!["mote"];
+        argv.extend_from_slice(args);
+        argv.push("snap");
+        Cli::try_parse_from(argv).unwrap()
+    }
+
+    fn spec(project: Option<&str>, context: Option<&str>) -> (Option<String>, Option<String>) {
+        (project.map(String::from), context.map(String::from))
+    }
+
+    #[test]
+    fn test_context_spec_forms() {
+        let cli = parse(&["-c", "proj/feature"]);
+        assert_eq!(
+            cli.parse_context_spec().unwrap(),
+            spec(Some("proj"), Some("feature"))
+        );
+        assert_eq!(cli.deprecation_warning(), None);
+
+        let cli = parse(&["-c", "feature"]);
+        assert_eq!(
+            cli.parse_context_spec().unwrap(),
+            spec(None, Some("feature"))
+        );
+
+        let cli = parse(&[]);
+        assert_eq!(cli.parse_context_spec().unwrap(), spec(None, None));
+        assert_eq!(cli.deprecation_warning(), None);
+    }
+
+    #[test]
+    fn test_deprecated_flags_warn_with_equivalent_spec() {
+        let cli = parse(&["-p", "proj", "--old-context", "feature"]);
+        assert_eq!(
+            cli.parse_context_spec().unwrap(),
+            spec(Some("proj"), Some("feature"))
+        );
+        assert_eq!(
+            cli.deprecation_warning().unwrap(),
+            "-p/--project and --old-context are deprecated; use -c proj/feature instead"
+        );
+
+        let cli = parse(&["-p", "proj"]);
+        assert_eq!(cli.parse_context_spec().unwrap(), spec(Some("proj"), None));
+        assert_eq!(
+            cli.deprecation_warning().unwrap(),
+            "-p/--project is deprecated; use -c proj/default instead"
+        );
+
+        let cli = parse(&["--old-context", "feature"]);
+        assert_eq!(
+            cli.parse_context_spec().unwrap(),
+            spec(None, Some("feature"))
+        );
+        assert_eq!(
+            cli.deprecation_warning().unwrap(),
+            "--old-context is deprecated; use -c feature instead"
+        );
+    }
+
+    #[test]
+    fn test_mixing_old_and_new_context_flags_is_an_error() {
+        for args in [
+            &["-c", "proj/feature", "-p", "other"][..],
+            &["-c", "feature", "--old-context", "other"],
+            &[
+                "-c",
+                "proj/feature",
+                "-p",
+                "proj",
+                "--old-context",
+                "feature",
+            ],
+        ] {
+            let err = parse(args).parse_context_spec().unwrap_err();
+            assert!(
+                err.to_string()
+                    .contains("cannot be combined with the deprecated"),
+                "{:?}: {}",
+                args,
+                err
+            );
+        }
+
+        let err = parse(&["-d", "/tmp/ctx", "-p", "proj"])
+            .parse_context_spec()
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("-d/--context-dir cannot be used with -p/--project"));
+    }
+
+    #[test]
+    fn test_malformed_context_spec_is_typed_error() {
+        for bad in ["proj/feature/extra", "/feature", "proj/", "/"] {
+            match parse(&["-c", bad]).parse_context_spec() {
+                Err(MoteError::InvalidContextSpec(spec)) => assert_eq!(spec, bad),
+                other => panic!("{}: {:?}", bad, other),
+            }
+        }
+
+        // Conflicting flags are an argument error, not a bad specifier
+        assert!(matches!(
+            parse(&["-d", "/tmp/ctx", "-c", "proj/feature"]).parse_context_spec(),
+            Err(MoteError::InvalidArguments(_))
+        ));
+    }
 }
@@ -0,0 +1,317 @@
+//! Shell completion scripts (`mote completions <shell>`)
+//!
+//! Scripts are generated from the clap command tree: for every command path
+//! (`mote snap restore`, ...) they offer its subcommands and flags. Commands
+//! taking a snapshot id also offer the ids of the 50 newest snapshots, read
+//! from `mote snap list --oneline` when completing.
+
+use std::fmt::Write;
+
+use clap::Command;
+
+use crate::cli::CompletionShell;
+
+/// Shell command printing one candidate snapshot id per line
+const SNAPSHOT_IDS: &str = "mote snap list --oneline --limit 50 2>/dev/null | cut -d' ' -f1";
+
+/// Completions of one command path
+struct Node {
+    /// Canonical path, e.g. `mote snap verify`
+    path: String,
+    /// Names and visible aliases this node is reached by from its parent
+    names: Vec<String>,
+    /// Subcommands and flags offered at this node
+    candidates: Vec<String>,
+    /// Whether a positional argument is a snapshot id
+    takes_snapshot_ids: bool,
+}
+
+impl Node {
+    /// `parent name` for every name this node is reached by
+    fn entries(&self) -> Vec<String> {
+        let parent = self.path.rsplit_once(' ').map_or("", |(parent, _)| parent);
+        self.names
+            .iter()
+            .map(|name| format!("{} {}", parent, name))
+            .collect()
+    }
+}
+
+pub fn cmd_completions(mut command: Command, shell: CompletionShell) {
+    command.build();
+    let mut nodes = Vec::new();
+    collect_nodes(&command, "mote".to_string(), Vec::new(), &mut nodes);
+    let script = match shell {
+        CompletionShell::Bash => bash(&nodes),
+        CompletionShell::Zsh => zsh(&nodes),
+        CompletionShell::Fish => fish(&nodes),
+        CompletionShell::Powershell => powershell(&nodes),
+    };
+    print!("{}", script);
+}
+
+fn collect_nodes(command: &Command, path: String, names: Vec<String>, nodes: &mut Vec<Node>) {
+    let subcommands: Vec<&Command> = command
+        .get_subcommands()
+        .filter(|c| !c.is_hide_set())
+        .collect();
+    let mut candidates = Vec::new();
+    for subcommand in &subcommands {
+        candidates.push(subcommand.get_name().to_string());
+        candidates.extend(subcommand.get_visible_aliases().map(str::to_string));
+    }
+    for arg in command.get_arguments().filter(|a| !a.is_hide_set()) {
+        if let Some(long) = arg.get_long() {
+            candidates.push(format!("--{}", long));
+        }
+        if let Some(short) = arg.get_short() {
+            candidates.push(format!("-{}", short));
+        }
+    }
+    let takes_snapshot_ids = command
+        .get_positionals()
+        .any(|arg| arg.get_id().as_str().starts_with("snapshot_id"));
+    nodes.push(Node {
+        path: path.clone(),
+        names,
+        candidates,
+        takes_snapshot_ids,
+    });
+
+    // clap's `help` mirrors the whole tree; offering its name is enough
+    for subcommand in subcommands.into_iter().filter(|c| c.get_name() != "help") {
+        let mut names = vec![subcommand.get_name().to_string()];
+        names.extend(subcommand.get_visible_aliases().map(str::to_string));
+        let child = format!("{} {}", path, subcommand.get_name());
+        collect_nodes(subcommand, child, names, nodes);
+    }
+}
+
+/// Nodes below the root, i.e. those reached through a subcommand
+fn subcommand_nodes(nodes: &[Node]) -> impl Iterator<Item = &Node> {
+    nodes.iter().filter(|node| !node.names.is_empty())
+}
+
+fn id_paths(nodes: &[Node]) -> Vec<&str> {
+    nodes
+        .iter()
+        .filter(|node| node.takes_snapshot_ids)
+        .map(|node| node.path.as_str())
+        .collect()
+}
+
+/// `"a"|"b"` case pattern for bash and zsh
+fn case_pattern<S: AsRef<str>>(items: &[S]) -> String {
+    items
+        .iter()
+        .map(|item| format!("\"{}\"", item.as_ref()))
+        .collect::<Vec<_>>()
+        .join("|")
+}
+
+fn bash(nodes: &[Node]) -> String {
+    let mut out = String::from(
+        "# bash completion for mote\n\
+         # Load it in the current shell:  source <(mote completions bash)\n\
+         # Or install it for every shell: mote completions bash > ~/.local/share/bash-completion/completions/mote\n\n",
+    );
+    let _ = writeln!(out, "_mote_snapshot_ids() {{\n    {}\n}}\n", SNAPSHOT_IDS);
+    out.push_str(
+        "_mote() {\n    \
+         local cur=\"${COMP_WORDS[COMP_CWORD]}\" cmd_path=\"mote\" candidates=\"\" i\n    \
+         for ((i = 1; i < COMP_CWORD; i++)); do\n        \
+         case \"$cmd_path ${COMP_WORDS[i]}\" in\n",
+    );
+    for node in subcommand_nodes(nodes) {
+        let _ = writeln!(
+            out,
+            "            {}) cmd_path=\"{}\" ;;",
+            case_pattern(&node.entries()),
+            node.path
+        );
+    }
+    out.push_str("        esac\n    done\n    case \"$cmd_path\" in\n");
+    for node in nodes {
+        let _ = writeln!(
+            out,
+            "        \"{}\") candidates=\"{}\" ;;",
+            node.path,
+            node.candidates.join(" ")
+        );
+    }
+    let _ = write!(
+        out,
+        "    esac\n    \
+         if [[ $cur != -* ]]; then\n        \
+         case \"$cmd_path\" in\n            \
+         {}) candidates=\"$candidates $(_mote_snapshot_ids)\" ;;\n        \
+         esac\n    \
+         fi\n    \
+         COMPREPLY=($(compgen -W \"$candidates\" -- \"$cur\"))\n\
+         }}\n\n\
+         complete -F _mote mote\n",
+        case_pattern(&id_paths(nodes))
+    );
+    out
+}
+
+fn zsh(nodes: &[Node]) -> String {
+    let mut out = String::from(
+        "#compdef mote\n\
+         # zsh completion for mote\n\
+         # Load it in the current shell:  source <(mote completions zsh)\n\
+         # Or save it as _mote in a directory on $fpath: mote completions zsh > ~/.zfunc/_mote\n\n",
+    );
+    let _ = writeln!(out, "_mote_snapshot_ids() {{\n    {}\n}}\n", SNAPSHOT_IDS);
+    // `path` is zsh's array twin of $PATH, hence `cmd_path`
+    out.push_str(
+        "_mote() {\n    \
+         local cmd_path=mote i\n    \
+         local -a candidates\n    \
+         for ((i = 2; i < CURRENT; i++)); do\n        \
+         case \"$cmd_path ${words[i]}\" in\n",
+    );
+    for node in subcommand_nodes(nodes) {
+        let _ = writeln!(
+            out,
+            "            {}) cmd_path=\"{}\" ;;",
+            case_pattern(&node.entries()),
+            node.path
+        );
+    }
+    out.push_str("        esac\n    done\n    case \"$cmd_path\" in\n");
+    for node in nodes {
+        let _ = writeln!(
+            out,
+            "        \"{}\") candidates=({}) ;;",
+            node.path,
+            node.candidates.join(" ")
+        );
+    }
+    let _ = write!(
+        out,
+        "    esac\n    \
+         if [[ $PREFIX != -* ]]; then\n        \
+         case \"$cmd_path\" in\n            \
+         {}) candidates+=(${{(f)\"$(_mote_snapshot_ids)\"}}) ;;\n        \
+         esac\n    \
+         fi\n    \
+         compadd -a candidates\n\
+         }}\n\n\
+         if [ \"$funcstack[1]\" = \"_mote\" ]; then\n    \
+         _mote \"$@\"\n\
+         else\n    \
+         compdef _mote mote\n\
+         fi\n",
+        case_pattern(&id_paths(nodes))
+    );
+    out
+}
+
+/// `'a' 'b'` list for fish `case`
+fn fish_list<S: AsRef<str>>(items: &[S]) -> String {
+    items
+        .iter()
+        .map(|item| format!("'{}'", item.as_ref()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn fish(nodes: &[Node]) -> String {
+    let mut out = String::from(
+        "# fish completion for mote\n\
+         # Load it in the current shell:  mote completions fish | source\n\
+         # Or install it for every shell: mote completions fish > ~/.config/fish/completions/mote.fish\n\n\
+         function __mote_path\n    \
+         set -l cmd_path mote\n    \
+         for word in (commandline -opc)[2..-1]\n        \
+         switch \"$cmd_path $word\"\n",
+    );
+    for node in subcommand_nodes(nodes) {
+        let _ = writeln!(
+            out,
+            "            case {}\n                set cmd_path '{}'",
+            fish_list(&node.entries()),
+            node.path
+        );
+    }
+    out.push_str(
+        "        end\n    end\n    echo $cmd_path\nend\n\n\
+         function __mote_candidates\n    \
+         set -l cmd_path (__mote_path)\n    \
+         switch $cmd_path\n",
+    );
+    for node in nodes {
+        let _ = writeln!(
+            out,
+            "        case '{}'\n            printf '%s\\n' {}",
+            node.path,
+            node.candidates.join(" ")
+        );
+    }
+    let _ = write!(
+        out,
+        "    end\n    \
+         if contains -- $cmd_path {}; and not string match -q -- '-*' (commandline -ct)\n        \
+         mote snap list --oneline --limit 50 2>/dev/null | string split -f1 ' '\n    \
+         end\n\
+         end\n\n\
+         complete -c mote -f -a '(__mote_candidates)'\n",
+        fish_list(&id_paths(nodes))
+    );
+    out
+}
+
+fn powershell(nodes: &[Node]) -> String {
+    let mut out = String::from(
+        "# PowerShell completion for mote\n\
+         # Load it in the current session: mote completions powershell | Out-String | Invoke-Expression\n\
+         # Add that line to $PROFILE to load it in every session\n\n\
+         Register-ArgumentCompleter -Native -CommandName mote -ScriptBlock {\n    \
+         param($wordToComplete, $commandAst, $cursorPosition)\n\n    \
+         $transitions = @{\n",
+    );
+    for node in subcommand_nodes(nodes) {
+        for entry in node.entries() {
+            let _ = writeln!(out, "        '{}' = '{}'", entry, node.path);
+        }
+    }
+    out.push_str("    }\n    $candidates = @{\n");
+    for node in nodes {
+        let _ = writeln!(
+            out,
+            "        '{}' = @({})",
+            node.path,
+            node.candidates
+                .iter()
+                .map(|c| format!("'{}'", c))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+    let _ = write!(
+        out,
+        "    }}\n    \
+         $idPaths = @({})\n\n    \
+         $cmdPath = 'mote'\n    \
+         foreach ($element in $commandAst.CommandElements | Select-Object -Skip 1) {{\n        \
+         if ($element.Extent.EndOffset -ge $cursorPosition) {{ break }}\n        \
+         $next = $transitions[\"$cmdPath $element\"]\n        \
+         if ($next) {{ $cmdPath = $next }}\n    \
+         }}\n\n    \
+         $words = @($candidates[$cmdPath])\n    \
+         if ($idPaths -contains $cmdPath -and -not $wordToComplete.StartsWith('-')) {{\n        \
+         $words += mote snap list --oneline --limit 50 2>$null | ForEach-Object {{ ($_ -split ' ')[0] }}\n    \
+         }}\n    \
+         $words | Where-Object {{ $_ -like \"$wordToComplete*\" }} | ForEach-Object {{\n        \
+         [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)\n    \
+         }}\n\
+         }}\n",
+        id_paths(nodes)
+            .iter()
+            .map(|p| format!("'{}'", p))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    out
+}
@@ -1,10 +1,12 @@
 use colored::*;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
+use super::ignore::print_detected_ecosystems;
 use crate::cli::ContextCommands;
 use crate::config::{Config, ConfigResolver, ContextConfig, ProjectConfig};
 use crate::error::Result;
-use crate::ignore::create_ignore_file;
+use crate::ignore::{create_ignore_file, detect_ecosystems, IgnoreTemplate};
+use crate::path_resolver::is_unsafe_project_root;
 
 pub fn cmd_context(
     config_resolver: &ConfigResolver,
@@ -31,11 +33,17 @@ pub fn cmd_context(
             } else {
                 println!("Contexts for project '{}':", project_name);
                 for ctx in contexts {
+                    let context_dir = project_config.get_context_dir(&project_dir, &ctx);
+                    let archived = ContextConfig::load(&project_dir, &ctx, Some(&context_dir))
+                        .is_ok_and(|c| c.archived);
+                    let mut line = format!("  {}", ctx.cyan());
                     if ctx == "default" {
-                        println!("  {} (default)", ctx.cyan());
-                    } else {
-                        println!("  {}", ctx.cyan());
+                        line.push_str(" (default)");
                     }
+                    if archived {
+                        line.push_str(&format!(" {}", "[archived]".yellow()));
+                    }
+                    println!("{}", line);
                 }
             }
         }
@@ -43,6 +51,8 @@ pub fn cmd_context(
             name,
             cwd,
             no_register,
+            force_large_root,
+            minimal,
         } => {
             validate_context_name(&name)?;
 
@@ -59,6 +69,11 @@ pub fn cmd_context(
                         ))
                     })?
                 };
+                if !force_large_root && is_unsafe_project_root(&project_cwd) {
+                    return Err(crate::error::MoteError::UnsafeProjectRoot(
+                        project_cwd.display().to_string(),
+                    ));
+                }
                 let config = ProjectConfig {
                     path: project_cwd.canonicalize().unwrap_or(project_cwd),
                     contexts: None,
@@ -66,24 +81,21 @@ pub fn cmd_context(
                 };
                 config.save(config_dir, project_name)?;
 
-                println!(
-                    "{} Created project '{}'",
-                    "✓".green().bold(),
-                    project_name
-                );
+                println!("{} Created project '{}'", "✓".green().bold(), project_name);
                 config
             };
 
-            // Determine the actual context directory
-            let actual_context_dir = if let Some(custom_dir) = context_dir {
-                custom_dir.clone()
-            } else {
-                project_dir.join("contexts").join(&name)
-            };
+            // Determine the actual context directory, absolute so the
+            // registered path resolves from any working directory
+            let actual_context_dir = std::path::absolute(match context_dir {
+                Some(custom_dir) => custom_dir.clone(),
+                None => project_dir.join("contexts").join(&name),
+            })?;
 
             let context_config = ContextConfig {
                 cwd,
-                context_dir: context_dir.cloned(),
+                context_dir: context_dir.map(|_| actual_context_dir.clone()),
+                archived: false,
                 config: Config::default(),
             };
 
@@ -91,7 +103,14 @@ pub fn cmd_context(
             context_config.save(&project_dir, &name)?;
 
             let ignore_path = context_config.ignore_path(&actual_context_dir);
-            create_ignore_file(&ignore_path)?;
+            let root = context_config.cwd.as_ref().unwrap_or(&project_config.path);
+            let ecosystems = detect_ecosystems(root);
+            let template = if minimal {
+                IgnoreTemplate::Minimal
+            } else {
+                IgnoreTemplate::Default
+            };
+            create_ignore_file(&ignore_path, &ecosystems, template)?;
 
             // Register context in map only after successful creation
             if !no_register {
@@ -117,6 +136,9 @@ pub fn cmd_context(
                     "Not registered in project config (temporary context)".yellow()
                 );
             }
+            if !minimal {
+                print_detected_ecosystems(&ecosystems);
+            }
         }
         ContextCommands::Delete { name } => {
             validate_context_name(&name)?;
@@ -146,11 +168,40 @@ pub fn cmd_context(
                 project_name
             );
         }
+        ContextCommands::Archive { name } => {
+            set_archived(config_dir, project_name, &project_dir, &name, true)?;
+            println!(
+                "{} Archived context '{}' (read-only)",
+                "✓".green().bold(),
+                name
+            );
+        }
+        ContextCommands::Unarchive { name } => {
+            set_archived(config_dir, project_name, &project_dir, &name, false)?;
+            println!("{} Unarchived context '{}'", "✓".green().bold(), name);
+        }
     }
 
     Ok(())
 }
 
+fn set_archived(
+    config_dir: &Path,
+    project_name: &str,
+    project_dir: &Path,
+    name: &str,
+    archived: bool,
+) -> Result<()> {
+    validate_context_name(name)?;
+
+    let project_config = ProjectConfig::load(config_dir, project_name)?;
+    let context_dir = project_config.get_context_dir(project_dir, name);
+    let mut context_config = ContextConfig::load(project_dir, name, Some(&context_dir))?;
+
+    context_config.archived = archived;
+    context_config.update(&context_dir)
+}
+
 fn validate_context_name(name: &str) -> Result<()> {
     if name.is_empty() {
         return Err(crate::error::MoteError::InvalidName(
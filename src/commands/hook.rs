@@ -0,0 +1,293 @@
+//! VCS hooks taking auto snapshots (`mote hook install`)
+//!
+//! Each hook gets a section between marker comments that runs
+//! `mote snap create --auto`, placed right after the shebang so an `exit` in
+//! an existing hook can't skip it. Hook files written by others are kept:
+//! install only adds or refreshes the section, uninstall only removes it (and
+//! deletes the file when nothing else is left). jj has no hooks; in a
+//! colocated repo only git commands run these, jj commands are covered by the
+//! shell integration (`mote setup`).
+
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use colored::*;
+
+use crate::cli::HookCommands;
+use crate::error::{MoteError, Result};
+
+const BEGIN_MARKER: &str = "# >>> mote >>>";
+const END_MARKER: &str = "# <<< mote <<<";
+
+/// Hooks mote installs, with the trigger their snapshots record
+const HOOKS: [(&str, &str); 2] = [
+    ("pre-commit", "git-pre-commit"),
+    ("post-checkout", "git-post-checkout"),
+];
+
+pub fn cmd_hook(project_root: &Path, command: HookCommands) -> Result<()> {
+    let hooks_dir = git_hooks_dir(project_root)?;
+    match command {
+        HookCommands::Install => {
+            let mote_bin = std::env::current_exe()?;
+            fs::create_dir_all(&hooks_dir)?;
+            for (name, trigger) in HOOKS {
+                let path = hooks_dir.join(name);
+                let existing = read_hook(&path)?;
+                let updated = existing.as_deref().is_some_and(|s| section(s).is_some());
+                let content = with_section(existing.as_deref(), &mote_bin, trigger);
+                fs::write(&path, content)?;
+                make_executable(&path)?;
+                println!(
+                    "{} {} {} hook ({})",
+                    "✓".green().bold(),
+                    if updated { "Updated" } else { "Installed" },
+                    name.cyan(),
+                    path.display()
+                );
+            }
+            print_jj_note(project_root);
+        }
+        HookCommands::Uninstall => {
+            for (name, _) in HOOKS {
+                let path = hooks_dir.join(name);
+                let Some(existing) = read_hook(&path)? else {
+                    continue;
+                };
+                let Some(rest) = without_section(&existing) else {
+                    continue;
+                };
+                if is_empty_script(&rest) {
+                    fs::remove_file(&path)?;
+                } else {
+                    fs::write(&path, rest)?;
+                }
+                println!("{} Removed {} hook", "✓".green().bold(), name.cyan());
+            }
+        }
+        HookCommands::Status => {
+            println!("Git hooks in {}:", hooks_dir.display());
+            for (name, _) in HOOKS {
+                let path = hooks_dir.join(name);
+                let content = read_hook(&path)?;
+                let state = match content.as_deref().and_then(section) {
+                    None => "not installed".dimmed().to_string(),
+                    Some(lines) => match embedded_binary(lines) {
+                        Some(bin) if bin.exists() => {
+                            format!("{} ({})", "installed".green(), bin.display())
+                        }
+                        Some(bin) => format!(
+                            "{}: {} no longer exists; run `mote hook install` again",
+                            "broken".red(),
+                            bin.display()
+                        ),
+                        None => format!("{}: no mote command in its section", "broken".red()),
+                    },
+                };
+                println!("  {:<14} {}", name, state);
+            }
+            print_jj_note(project_root);
+        }
+    }
+    Ok(())
+}
+
+/// Hooks directory git uses for `project_root`, honoring `core.hooksPath`
+/// when git is available
+fn git_hooks_dir(project_root: &Path) -> Result<PathBuf> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(project_root)
+        .args(["rev-parse", "--git-path", "hooks"])
+        .output();
+    if let Some(output) = output.ok().filter(|o| o.status.success()) {
+        let dir = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if !dir.is_empty() {
+            return Ok(project_root.join(dir));
+        }
+    }
+    let git_dir = project_root.join(".git");
+    if git_dir.is_dir() {
+        return Ok(git_dir.join("hooks"));
+    }
+    Err(MoteError::NoGitRepository(
+        project_root.display().to_string(),
+    ))
+}
+
+fn print_jj_note(project_root: &Path) {
+    if project_root.join(".jj").is_dir() {
+        println!(
+            "  {} jj runs no hooks; `mote setup` snapshots around jj commands",
+            "note:".dimmed()
+        );
+    }
+}
+
+fn read_hook(path: &Path) -> Result<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(Some(content)),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let mut permissions = fs::metadata(path)?.permissions();
+    permissions.set_mode(permissions.mode() | 0o111);
+    fs::set_permissions(path, permissions)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+/// The marked mote section, markers included
+fn render_section(mote_bin: &Path, trigger: &str) -> String {
+    format!(
+        "{}\n\
+         # Added by `mote hook install`; `mote hook uninstall` removes this section\n\
+         {} snap create --auto -t {} || true\n\
+         {}\n",
+        BEGIN_MARKER,
+        shell_quote(&mote_bin.to_string_lossy()),
+        trigger,
+        END_MARKER
+    )
+}
+
+/// `existing` hook script with the mote section added after its shebang, or
+/// refreshed in place; a new script when there is none
+fn with_section(existing: Option<&str>, mote_bin: &Path, trigger: &str) -> String {
+    let section = render_section(mote_bin, trigger);
+    let Some(existing) = existing.filter(|s| !s.trim().is_empty()) else {
+        return format!("#!/bin/sh\n{}", section);
+    };
+    if let Some(rest) = without_section(existing) {
+        if let Some(at) = existing.find(BEGIN_MARKER) {
+            return format!("{}{}{}", &rest[..at], section, &rest[at..]);
+        }
+    }
+    match existing.strip_prefix("#!") {
+        Some(_) => {
+            let (shebang, body) = existing.split_once('\n').unwrap_or((existing, ""));
+            format!("{}\n{}{}", shebang, section, body)
+        }
+        None => format!("{}{}", section, existing),
+    }
+}
+
+/// Lines between the markers of the mote section, if `content` has one
+fn section(content: &str) -> Option<Vec<&str>> {
+    let mut lines = content
+        .lines()
+        .skip_while(|line| line.trim() != BEGIN_MARKER);
+    lines.next()?;
+    Some(lines.take_while(|line| line.trim() != END_MARKER).collect())
+}
+
+/// `content` without the mote section, or `None` if it has none
+fn without_section(content: &str) -> Option<String> {
+    let mut inside = false;
+    let mut found = false;
+    let mut rest = String::new();
+    for line in content.split_inclusive('\n') {
+        match line.trim() {
+            BEGIN_MARKER => {
+                inside = true;
+                found = true;
+            }
+            END_MARKER if inside => inside = false,
+            _ if inside => {}
+            _ => rest.push_str(line),
+        }
+    }
+    found.then_some(rest)
+}
+
+/// Whether only a shebang and blank lines are left
+fn is_empty_script(content: &str) -> bool {
+    content
+        .lines()
+        .all(|line| line.trim().is_empty() || line.starts_with("#!"))
+}
+
+/// Path of the mote binary the section's command runs
+fn embedded_binary(lines: Vec<&str>) -> Option<PathBuf> {
+    lines
+        .into_iter()
+        .find_map(|line| shell_unquote_prefix(line.trim()))
+        .map(PathBuf::from)
+}
+
+/// Single-quote `s` for sh
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+/// The single-quoted word `line` starts with, as written by [`shell_quote`]
+fn shell_unquote_prefix(line: &str) -> Option<String> {
+    let mut rest = line.strip_prefix('\'')?;
+    let mut word = String::new();
+    loop {
+        let end = rest.find('\'')?;
+        word.push_str(&rest[..end]);
+        rest = &rest[end + 1..];
+        match rest.strip_prefix("\\''") {
+            Some(next) => {
+                word.push('\'');
+                rest = next;
+            }
+            None => return Some(word),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BIN: &str = "/opt/it's/mote";
+
+    #[test]
+    fn test_section_goes_after_shebang_and_round_trips() {
+        let existing = "#!/bin/bash\nnpm test\nexit 0\n";
+        let installed = with_section(Some(existing), Path::new(BIN), "git-pre-commit");
+
+        assert!(installed.starts_with(&format!("#!/bin/bash\n{}\n", BEGIN_MARKER)));
+        assert!(installed.ends_with("npm test\nexit 0\n"));
+        assert_eq!(
+            embedded_binary(section(&installed).unwrap()),
+            Some(PathBuf::from(BIN))
+        );
+        assert_eq!(without_section(&installed).as_deref(), Some(existing));
+        assert_eq!(without_section(existing), None);
+    }
+
+    #[test]
+    fn test_reinstall_refreshes_section_in_place() {
+        let first = with_section(Some("#!/bin/sh\necho hi\n"), Path::new("/old/mote"), "t");
+        let second = with_section(Some(&first), Path::new(BIN), "t");
+
+        assert_eq!(second.matches(BEGIN_MARKER).count(), 1);
+        assert!(second.ends_with("echo hi\n"));
+        assert_eq!(
+            embedded_binary(section(&second).unwrap()),
+            Some(PathBuf::from(BIN))
+        );
+    }
+
+    #[test]
+    fn test_new_hook_is_empty_without_section() {
+        let created = with_section(None, Path::new(BIN), "git-post-checkout");
+        assert!(created.starts_with("#!/bin/sh\n"));
+        assert!(is_empty_script(&without_section(&created).unwrap()));
+        assert!(!is_empty_script("#!/bin/sh\nmake lint\n"));
+    }
+}
@@ -1,24 +1,60 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use colored::*;
 
+use super::CommandContext;
 use crate::cli::IgnoreCommands;
 use crate::error::Result;
-use crate::ignore::create_ignore_file;
+use crate::ignore::{
+    create_ignore_file, detect_ecosystems, local_ignore_path, missing_patterns, Ecosystem,
+    IgnoreTemplate, Reason,
+};
+use crate::path_resolver::snapshot_relative_path;
+
+/// Print which ecosystems shaped a newly created ignore file
+pub(crate) fn print_detected_ecosystems(ecosystems: &[&Ecosystem]) {
+    if ecosystems.is_empty() {
+        return;
+    }
+    let names: Vec<&str> = ecosystems.iter().map(|eco| eco.name).collect();
+    println!("  Detected {}", names.join(", ").cyan());
+}
 
-pub fn cmd_ignore(ignore_file_path: &Path, command: IgnoreCommands) -> Result<()> {
+/// Returns whether `ignore check` found an ignored path, so the caller can
+/// exit non-zero; `false` for the other subcommands.
+pub fn cmd_ignore(ctx: &CommandContext, command: IgnoreCommands) -> Result<bool> {
+    let project_root = ctx.project_root;
+    let ignore_file_path = ctx.ignore_file_path.as_path();
     match command {
         IgnoreCommands::List => {
-            if !ignore_file_path.exists() {
+            let local_path = local_ignore_path(ignore_file_path);
+            if !ignore_file_path.exists() && !local_path.exists() {
                 println!("{} No ignore file found", "!".yellow().bold());
-                return Ok(());
+                return Ok(false);
             }
 
-            let content = std::fs::read_to_string(ignore_file_path)?;
-            println!("Ignore patterns in {}:", ignore_file_path.display());
-            println!("{}", content);
+            if ignore_file_path.exists() {
+                let content = std::fs::read_to_string(ignore_file_path)?;
+                println!("Ignore patterns in {}:", ignore_file_path.display());
+                println!("{}", content);
+            }
+            // Local patterns come last because they take precedence
+            if local_path.exists() {
+                let content = std::fs::read_to_string(&local_path)?;
+                println!(
+                    "Ignore patterns in {} {}:",
+                    local_path.display(),
+                    "(local)".dimmed()
+                );
+                println!("{}", content);
+            }
         }
-        IgnoreCommands::Add { pattern } => {
+        IgnoreCommands::Add { pattern, local } => {
+            let ignore_file_path = &if local {
+                local_ignore_path(ignore_file_path)
+            } else {
+                ignore_file_path.to_path_buf()
+            };
             let mut content = if ignore_file_path.exists() {
                 std::fs::read_to_string(ignore_file_path)?
             } else {
@@ -43,7 +79,7 @@ pub fn cmd_ignore(ignore_file_path: &Path, command: IgnoreCommands) -> Result<()
         IgnoreCommands::Remove { pattern } => {
             if !ignore_file_path.exists() {
                 println!("{} No ignore file found", "!".yellow().bold());
-                return Ok(());
+                return Ok(false);
             }
 
             let content = std::fs::read_to_string(ignore_file_path)?;
@@ -66,17 +102,24 @@ pub fn cmd_ignore(ignore_file_path: &Path, command: IgnoreCommands) -> Result<()
             let editor = std::env::var("EDITOR").unwrap_or_else(|_| "vi".to_string());
 
             if !ignore_file_path.exists() {
-                create_ignore_file(ignore_file_path)?;
+                create_ignore_file(
+                    ignore_file_path,
+                    &detect_ecosystems(project_root),
+                    IgnoreTemplate::Default,
+                )?;
             }
 
-            let parts = shell_words::split(&editor).map_err(|e| {
-                crate::error::MoteError::ConfigRead(format!("Failed to parse EDITOR: {}", e))
-            })?;
+            let parts =
+                shell_words::split(&editor).map_err(|e| crate::error::MoteError::EditorFailed {
+                    editor: editor.clone(),
+                    status: format!("could not parse EDITOR ({})", e),
+                })?;
 
             if parts.is_empty() {
-                return Err(crate::error::MoteError::ConfigRead(
-                    "EDITOR variable is empty".to_string(),
-                ));
+                return Err(crate::error::MoteError::EditorFailed {
+                    editor,
+                    status: "EDITOR is empty".to_string(),
+                });
             }
 
             let status = std::process::Command::new(&parts[0])
@@ -85,15 +128,123 @@ pub fn cmd_ignore(ignore_file_path: &Path, command: IgnoreCommands) -> Result<()
                 .status()?;
 
             if !status.success() {
-                return Err(crate::error::MoteError::ConfigRead(format!(
-                    "Editor '{}' exited with error",
-                    editor
-                )));
+                return Err(crate::error::MoteError::EditorFailed {
+                    editor,
+                    status: status.to_string(),
+                });
+            }
+
+            println!(
+                "{} Edited {}",
+                "✓".green().bold(),
+                ignore_file_path.display()
+            );
+        }
+        IgnoreCommands::Suggest { apply } => {
+            let ecosystems = detect_ecosystems(project_root);
+            if ecosystems.is_empty() {
+                println!("{} No known ecosystems detected", "!".yellow().bold());
+                return Ok(false);
+            }
+            print_detected_ecosystems(&ecosystems);
+
+            let content = if ignore_file_path.exists() {
+                std::fs::read_to_string(ignore_file_path)?
+            } else {
+                String::new()
+            };
+            let missing = missing_patterns(&content, &ecosystems);
+            if missing.is_empty() {
+                println!("{} All suggested patterns are present", "✓".green().bold());
+                return Ok(false);
+            }
+
+            if !apply {
+                println!("Suggested patterns (use --apply to add them):");
+                for pattern in &missing {
+                    println!("  {}", pattern);
+                }
+                return Ok(false);
+            }
+
+            let mut content = content;
+            if !content.ends_with('\n') && !content.is_empty() {
+                content.push('\n');
             }
+            content.push_str("\n# Suggested by mote\n");
+            for pattern in &missing {
+                content.push_str(pattern);
+                content.push('\n');
+            }
+            std::fs::write(ignore_file_path, content)?;
+
+            println!(
+                "{} Added {} pattern(s) to {}",
+                "✓".green().bold(),
+                missing.len(),
+                ignore_file_path.display()
+            );
+        }
+        IgnoreCommands::Check { paths } => return cmd_ignore_check(ctx, &paths),
+    }
+
+    Ok(false)
+}
 
-            println!("{} Edited {}", "✓".green().bold(), ignore_file_path.display());
+/// Explain for each of `paths` whether snapshots capture it, using the same
+/// filter as snapshotting. Returns whether any of them is ignored, so the
+/// caller can exit non-zero.
+fn cmd_ignore_check(ctx: &CommandContext, paths: &[PathBuf]) -> Result<bool> {
+    let ignore_filter = ctx.ignore_filter();
+    let mut any_ignored = false;
+    for path in paths {
+        let display = path.to_string_lossy();
+        let relative = snapshot_relative_path(ctx.project_root, &display);
+        let relative = Path::new(relative.trim_end_matches('/'));
+        let is_dir = display.ends_with('/') || ctx.project_root.join(relative).is_dir();
+        let verdict = ignore_filter.explain(ctx.project_root, relative, is_dir);
+        any_ignored |= verdict.ignored;
+
+        let status = if verdict.ignored {
+            "ignored ".red()
+        } else {
+            "captured".green()
+        };
+        let mut reason = describe_reason(ctx.project_root, &verdict.reason);
+        if is_dir && verdict.ignored {
+            reason.push_str("; not descended into, nothing under it is captured");
         }
+        println!("{} {}  {}", status, display, reason.dimmed());
     }
+    Ok(any_ignored)
+}
 
-    Ok(())
+fn describe_reason(project_root: &Path, reason: &Reason) -> String {
+    match reason {
+        Reason::NoMatch => "no pattern matches".to_string(),
+        Reason::HardExcluded(dir) => format!("{} is always excluded", dir),
+        Reason::LocalIgnoreFile => "local ignore files are never snapshotted".to_string(),
+        Reason::ExcludedIgnoreFile => "ignore.snapshot_ignore_file = false".to_string(),
+        Reason::TooLong(limit) => format!("path longer than snapshot.max_path_len ({})", limit),
+        Reason::Pattern { rule, dir } => {
+            let source = rule
+                .source
+                .as_deref()
+                .map(|source| source.strip_prefix(project_root).unwrap_or(source))
+                .map_or_else(|| "?".to_string(), |source| source.display().to_string());
+            let location = match rule.line {
+                Some(line) => format!("{}:{}", source, line),
+                None => source,
+            };
+            match dir {
+                Some(dir) => format!(
+                    "{}: {} (directory {}/)",
+                    location,
+                    rule.pattern,
+                    dir.display()
+                ),
+                None => format!("{}: {}", location, rule.pattern),
+            }
+        }
+    }
 }
@@ -1,15 +1,29 @@
+use std::path::Path;
+
 use colored::*;
 
+use super::ignore::print_detected_ecosystems;
 use super::CommandContext;
 use crate::config::Config;
 use crate::error::{MoteError, Result};
-use crate::ignore::create_ignore_file;
+use crate::ignore::{create_ignore_file, detect_ecosystems, IgnoreTemplate};
 use crate::storage::StorageLocation;
 
-pub fn cmd_init(ctx: &CommandContext) -> Result<()> {
-    Config::save_default()?;
+/// `config_dir` receives a default global config; `None` (standalone
+/// `--context-dir` mode) writes no config at all
+pub fn cmd_init(
+    ctx: &CommandContext,
+    template: IgnoreTemplate,
+    config_dir: Option<&Path>,
+) -> Result<()> {
+    if let Some(config_dir) = config_dir {
+        Config::save_default(config_dir)?;
+    }
     let location = StorageLocation::init(ctx.project_root, ctx.config, ctx.storage_dir)?;
-    let created_path = create_ignore_file(&ctx.ignore_file_path)?;
+    let ecosystems = detect_ecosystems(ctx.project_root);
+    let ignore_existed = ctx.ignore_file_path.exists();
+    let minimal = template == IgnoreTemplate::Minimal;
+    let created_path = create_ignore_file(&ctx.ignore_file_path, &ecosystems, template)?;
     let display_path = created_path
         .strip_prefix(ctx.project_root)
         .unwrap_or(&created_path);
@@ -23,6 +37,9 @@ pub fn cmd_init(ctx: &CommandContext) -> Result<()> {
         "  Created {} for ignore patterns",
         display_path.display().to_string().cyan()
     );
+    if !ignore_existed && !minimal {
+        print_detected_ecosystems(&ecosystems);
+    }
     Ok(())
 }
 
@@ -30,13 +47,21 @@ pub fn cmd_setup_shell(shell: &str) -> Result<()> {
     let script = match shell {
         "bash" | "zsh" => include_str!("../../scripts/shell_integration.sh"),
         "fish" => include_str!("../../scripts/shell_integration.fish"),
-        _ => {
-            return Err(MoteError::ConfigRead(format!(
-                "Unsupported shell: {}. Use bash, zsh, or fish.",
-                shell
-            )));
-        }
+        _ => return Err(MoteError::UnsupportedShell(shell.to_string())),
     };
     println!("{}", script);
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unsupported_shell_is_typed_error() {
+        match cmd_setup_shell("powershell") {
+            Err(MoteError::UnsupportedShell(shell)) => assert_eq!(shell, "powershell"),
+            other => panic!("{:?}", other),
+        }
+    }
+}
@@ -4,7 +4,7 @@ use colored::*;
 
 use crate::config::{Config, ConfigResolver, ContextConfig, ProjectConfig};
 use crate::error::Result;
-use crate::ignore::create_ignore_file;
+use crate::ignore::{create_ignore_file, detect_ecosystems, IgnoreTemplate};
 
 pub fn cmd_migrate(
     project_root: &Path,
@@ -59,6 +59,7 @@ pub fn cmd_migrate(
     let context_config = ContextConfig {
         cwd: Some(project_root.to_path_buf()),
         context_dir: None,
+        archived: false,
         config: Config::default(),
     };
     context_config.save(&new_project_dir, "default")?;
@@ -69,7 +70,7 @@ pub fn cmd_migrate(
         if entry.path().is_dir() {
             copy_dir_all(&entry.path(), &dest)?;
         } else {
-            std::fs::copy(&entry.path(), &dest)?;
+            std::fs::copy(entry.path(), &dest)?;
         }
     }
 
@@ -77,10 +78,15 @@ pub fn cmd_migrate(
     let new_ignore = new_context_dir.join("ignore");
 
     if old_ignore.exists() {
-        std::fs::copy(&old_ignore, &new_ignore)?;
+        let content = std::fs::read_to_string(&old_ignore)?;
+        create_ignore_file(&new_ignore, &[], IgnoreTemplate::Custom(content))?;
         println!("  Copied .moteignore to context");
     } else {
-        create_ignore_file(&new_ignore)?;
+        create_ignore_file(
+            &new_ignore,
+            &detect_ecosystems(project_root),
+            IgnoreTemplate::Default,
+        )?;
     }
 
     println!("\n{} Migration complete!", "✓".green().bold());
@@ -90,7 +96,7 @@ pub fn cmd_migrate(
     Ok(())
 }
 
-fn sanitize_project_name(name: &str) -> String {
+pub(super) fn sanitize_project_name(name: &str) -> String {
     let mut sanitized = String::new();
 
     for c in name.chars() {
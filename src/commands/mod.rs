@@ -1,26 +1,53 @@
+mod completions;
 mod context;
+mod hook;
 mod ignore;
 mod init;
 mod migrate;
+mod quickstart;
+mod serve;
 mod snapshot;
+#[cfg(feature = "tui")]
+mod tui;
+mod watch;
 
 use std::path::Path;
 
 use crate::config::Config;
 use crate::error::{MoteError, Result};
-use crate::storage::StorageLocation;
+use crate::ignore::IgnoreFilter;
+use crate::storage::{ObjectStore, StorageLocation, COMPRESSION_LEVELS};
 
+pub use completions::cmd_completions;
 pub use context::cmd_context;
+pub use hook::cmd_hook;
 pub use ignore::cmd_ignore;
 pub use init::{cmd_init, cmd_setup_shell};
 pub use migrate::cmd_migrate;
-pub use snapshot::{cmd_delete, cmd_diff, cmd_gc, cmd_log, cmd_restore, cmd_show, cmd_snapshot};
+pub use quickstart::{cmd_quickstart, QuickstartOptions};
+pub use serve::cmd_serve;
+pub use snapshot::{
+    cmd_apply, cmd_delete, cmd_diff, cmd_export_store, cmd_fix_perms, cmd_gc, cmd_log,
+    cmd_prune_backups, cmd_restore, cmd_show, cmd_snapshot, cmd_stats, cmd_status, cmd_verify,
+    snapshot_id_at, use_color, ApplyOptions, DiffOptions, GcOptions, LogOptions,
+    PruneBackupsOptions, RestoreOptions, ShowOptions, SnapshotOptions, StatsOptions, StatusOptions,
+    VerifyOptions,
+};
+#[cfg(feature = "tui")]
+pub use tui::cmd_tui;
+pub use watch::{cmd_watch, WatchOptions};
 
 pub struct CommandContext<'a> {
     pub project_root: &'a Path,
     pub config: &'a Config,
     pub storage_dir: Option<&'a Path>,
     pub ignore_file_path: std::path::PathBuf,
+    /// Ignore file shared by all projects, layered before `ignore_file_path`
+    pub global_ignore_path: Option<std::path::PathBuf>,
+    /// `--ignore-file`, layered after `ignore_file_path`
+    pub extra_ignore_path: Option<std::path::PathBuf>,
+    pub context_name: &'a str,
+    pub archived: bool,
 }
 
 impl<'a> CommandContext<'a> {
@@ -33,4 +60,58 @@ impl<'a> CommandContext<'a> {
             Err(e) => Err(e),
         }
     }
+
+    /// Ignore filter for walking the project tree, layering the global
+    /// ignore file, `ignore_file_path` and `--ignore-file` in that order and
+    /// honoring `ignore.snapshot_ignore_file`, `ignore.use_gitignore` and
+    /// `snapshot.max_files`
+    pub fn ignore_filter(&self) -> IgnoreFilter {
+        let ignore_files: Vec<_> = self
+            .global_ignore_path
+            .iter()
+            .chain([&self.ignore_file_path])
+            .chain(&self.extra_ignore_path)
+            .cloned()
+            .collect();
+        IgnoreFilter::new(&ignore_files)
+            .respecting_gitignore(self.project_root, self.config.ignore.use_gitignore)
+            .excluding_ignore_file(!self.config.ignore.snapshot_ignore_file)
+            .limit_files(self.config.snapshot.max_files)
+            .limit_path_len(self.config.snapshot.max_path_len)
+    }
+
+    /// Object store of `location`, caching up to `storage.object_cache_mb`
+    /// of retrieved content, syncing writes if `storage.fsync` is set and
+    /// storing `storage.no_compress_extensions` files raw
+    pub fn object_store(&self, location: &StorageLocation) -> ObjectStore {
+        let storage = &self.config.storage;
+        ObjectStore::new(location.objects_dir())
+            .cache_limit(storage.object_cache_mb * 1024 * 1024)
+            .fsync(storage.fsync)
+            .no_compress_extensions(&storage.no_compress_extensions)
+            .compression_level(storage.compression_level)
+    }
+
+    /// `level`, or `storage.compression_level` when `None`, rejecting levels
+    /// zstd doesn't have instead of letting it clamp them
+    pub fn compression_level(&self, level: Option<i32>) -> Result<i32> {
+        let level = level.unwrap_or(self.config.storage.compression_level);
+        if !COMPRESSION_LEVELS.contains(&level) {
+            return Err(MoteError::ConfigParse(format!(
+                "storage.compression_level must be between {} and {}, got {}",
+                COMPRESSION_LEVELS.start(),
+                COMPRESSION_LEVELS.end(),
+                level
+            )));
+        }
+        Ok(level)
+    }
+
+    /// Fail with `ContextArchived` if the resolved context is read-only
+    pub fn ensure_writable(&self) -> Result<()> {
+        if self.archived {
+            return Err(MoteError::ContextArchived(self.context_name.to_string()));
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,233 @@
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use colored::*;
+
+use super::ignore::print_detected_ecosystems;
+use super::migrate::sanitize_project_name;
+use super::{cmd_snapshot, CommandContext, SnapshotOptions};
+use crate::config::{Config, ConfigResolver, ContextConfig, ProjectConfig, ResolveOptions};
+use crate::error::{MoteError, Result};
+use crate::ignore::{create_ignore_file, detect_ecosystems, IgnoreTemplate};
+use crate::path_resolver::is_unsafe_project_root;
+use crate::storage::SnapshotStore;
+
+const DEFAULT_CONTEXT: &str = "default";
+
+/// Options for `mote quickstart`
+#[derive(Debug, Default)]
+pub struct QuickstartOptions {
+    /// Accept every default instead of prompting
+    pub yes: bool,
+    /// Don't take a first snapshot
+    pub no_snapshot: bool,
+}
+
+/// Set up the project, its default context, the ignore file and a first
+/// snapshot. Each step only creates what is missing, so running it again
+/// reports everything as found and changes nothing.
+pub fn cmd_quickstart(
+    project_root: &Path,
+    config_resolver: &ConfigResolver,
+    opts: &QuickstartOptions,
+) -> Result<()> {
+    let config_dir = config_resolver.config_dir();
+
+    let found_project = match config_resolver.project_name() {
+        Some(name) => ProjectConfig::load(config_dir, name)
+            .ok()
+            .map(|config| (name.to_string(), config)),
+        None => ProjectConfig::find_by_path(config_dir, project_root)?
+            .map(|name| ProjectConfig::load(config_dir, &name).map(|config| (name, config)))
+            .transpose()?,
+    };
+    let (project_name, project_config) = match found_project {
+        Some((name, config)) => {
+            report_found(&format!("project '{}' ({})", name, config.path.display()));
+            (name, config)
+        }
+        None => {
+            let name = match config_resolver.project_name() {
+                Some(name) => name.to_string(),
+                None => prompt_project_name(project_root, opts.yes)?,
+            };
+            let config = create_project(config_dir, &name, project_root)?;
+            report_created(&format!("project '{}' ({})", name, config.path.display()));
+            (name, config)
+        }
+    };
+
+    let project_dir = config_dir.join("projects").join(&project_name);
+    let context_dir = project_config.get_context_dir(&project_dir, DEFAULT_CONTEXT);
+    let context_config =
+        match ContextConfig::load(&project_dir, DEFAULT_CONTEXT, Some(&context_dir)) {
+            Ok(config) => {
+                report_found(&format!("context '{}'", DEFAULT_CONTEXT));
+                config
+            }
+            Err(_) => {
+                let config = ContextConfig {
+                    cwd: None,
+                    context_dir: None,
+                    archived: false,
+                    config: Config::default(),
+                };
+                config.save(&project_dir, DEFAULT_CONTEXT)?;
+                report_created(&format!("context '{}'", DEFAULT_CONTEXT));
+                config
+            }
+        };
+
+    let ignore_path = context_config.ignore_path(&context_dir);
+    if ignore_path.exists() {
+        report_found(&format!("ignore file {}", ignore_path.display()));
+    } else {
+        let ecosystems = detect_ecosystems(&project_config.path);
+        create_ignore_file(&ignore_path, &ecosystems, IgnoreTemplate::Default)?;
+        report_created(&format!("ignore file {}", ignore_path.display()));
+        print_detected_ecosystems(&ecosystems);
+    }
+
+    let storage_dir = context_config.storage_path(&context_dir);
+    let snapshot_count = SnapshotStore::new(storage_dir.join("snapshots"))
+        .list()?
+        .len();
+    if snapshot_count > 0 {
+        report_found(&format!(
+            "{} snapshot{}",
+            snapshot_count,
+            if snapshot_count == 1 { "" } else { "s" }
+        ));
+    } else if opts.no_snapshot || !confirm("Take a first snapshot now?", opts.yes)? {
+        println!("  {}  first snapshot", "skipped".yellow());
+    } else {
+        take_first_snapshot(config_dir, &project_name, &project_config.path, ignore_path)?;
+    }
+
+    println!();
+    println!("Enable automatic snapshots from your shell:");
+    println!("  {}", shell_setup_command().cyan());
+    Ok(())
+}
+
+fn report_created(what: &str) {
+    println!("  {}  {}", "created".green(), what);
+}
+
+fn report_found(what: &str) {
+    println!("  {}    {}", "found".dimmed(), what);
+}
+
+fn prompt_project_name(project_root: &Path, yes: bool) -> Result<String> {
+    let derived = sanitize_project_name(
+        project_root
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("project"),
+    );
+    if yes {
+        return Ok(derived);
+    }
+
+    print!("Project name [{}]: ", derived);
+    io::stdout().flush()?;
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    let input = input.trim();
+    Ok(if input.is_empty() {
+        derived
+    } else {
+        input.to_string()
+    })
+}
+
+/// Yes/no question defaulting to yes; `yes` answers without asking
+fn confirm(question: &str, yes: bool) -> Result<bool> {
+    if yes {
+        return Ok(true);
+    }
+
+    print!("{} [Y/n] ", question);
+    io::stdout().flush()?;
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    let answer = input.trim().to_lowercase();
+    Ok(answer.is_empty() || answer == "y" || answer == "yes")
+}
+
+fn create_project(config_dir: &Path, name: &str, project_root: &Path) -> Result<ProjectConfig> {
+    if is_unsafe_project_root(project_root) {
+        return Err(MoteError::UnsafeProjectRoot(
+            project_root.display().to_string(),
+        ));
+    }
+    if let Ok(existing) = ProjectConfig::load(config_dir, name) {
+        return Err(MoteError::ConfigRead(format!(
+            "Project '{}' already exists for {}; choose another name",
+            name,
+            existing.path.display()
+        )));
+    }
+
+    let config = ProjectConfig {
+        path: project_root
+            .canonicalize()
+            .unwrap_or_else(|_| project_root.to_path_buf()),
+        contexts: None,
+        config: Config::default(),
+    };
+    config.save(config_dir, name)?;
+    Ok(config)
+}
+
+/// Snapshot the project through the regular resolution path, so the new
+/// project's and context's configuration applies
+fn take_first_snapshot(
+    config_dir: &Path,
+    project_name: &str,
+    project_root: &Path,
+    ignore_file_path: PathBuf,
+) -> Result<()> {
+    let resolver = ConfigResolver::load(&ResolveOptions {
+        config_dir: Some(config_dir.to_path_buf()),
+        project: Some(project_name.to_string()),
+        context: None,
+        context_dir: None,
+        project_root: project_root.to_path_buf(),
+        allow_missing_project: false,
+    })?;
+    let config = resolver.resolve();
+    let storage_dir = resolver.context_storage_dir();
+    let ctx = CommandContext {
+        project_root,
+        config: &config,
+        storage_dir: storage_dir.as_deref(),
+        ignore_file_path,
+        global_ignore_path: Some(resolver.global_ignore_path()),
+        extra_ignore_path: None,
+        context_name: resolver.context_name(),
+        archived: false,
+    };
+    cmd_snapshot(
+        &ctx,
+        &SnapshotOptions {
+            message: Some("Initial snapshot".to_string()),
+            ..Default::default()
+        },
+    )
+}
+
+/// `mote setup` invocation for the user's login shell (`$SHELL`)
+fn shell_setup_command() -> String {
+    let shell = std::env::var("SHELL").unwrap_or_default();
+    let rc_file = match Path::new(&shell).file_name().and_then(|n| n.to_str()) {
+        Some("zsh") => Some(("zsh", "~/.zshrc")),
+        Some("bash") => Some(("bash", "~/.bashrc")),
+        Some("fish") => Some(("fish", "~/.config/fish/config.fish")),
+        _ => None,
+    };
+    match rc_file {
+        Some((name, rc)) => format!("mote setup {} >> {}", name, rc),
+        None => "mote setup <bash|zsh|fish> >> <your shell's rc file>".to_string(),
+    }
+}
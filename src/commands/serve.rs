@@ -0,0 +1,348 @@
+//! Line-delimited JSON request/response loop for editor integrations (`mote serve --stdio`)
+//!
+//! Each input line is one request object with a `cmd` field and an optional `id`
+//! that is echoed back. Each request gets exactly one response line:
+//! `{"id":..,"ok":true,"result":{..}}` or `{"id":..,"ok":false,"error":{"code":..,"message":..}}`.
+//! Only read-only commands are served; the loop ends at EOF.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io::{BufRead, Write};
+use std::path::Path;
+use std::time::SystemTime;
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::snapshot::{generate_unified_diff_with_content, scan_files, DiffOutput, Whitespace};
+use crate::commands::CommandContext;
+use crate::error::{MoteError, Result};
+use crate::ignore::IgnoreFilter;
+use crate::path_resolver::snapshot_relative_path;
+use crate::storage::{
+    ambiguous_error, not_found_error, nth_newest, relative_offset, Index, ObjectStore, Snapshot,
+    SnapshotStore, StorageLocation, LATEST_ALIAS,
+};
+
+/// Context lines in `diff` responses unless the request sets `unified`
+const DEFAULT_UNIFIED: usize = 3;
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "cmd", rename_all = "lowercase", deny_unknown_fields)]
+enum Request {
+    /// Files added, modified and deleted since a snapshot (default: latest)
+    Status {
+        #[serde(default)]
+        snapshot: Option<String>,
+    },
+    /// Unified diff of one file between a snapshot and the working directory
+    Diff {
+        file: String,
+        #[serde(default)]
+        snapshot: Option<String>,
+        #[serde(default)]
+        unified: Option<usize>,
+    },
+    /// Content of one file as stored in a snapshot
+    Cat { snapshot: String, path: String },
+    /// Snapshot summaries, newest first
+    List {
+        #[serde(default)]
+        limit: Option<usize>,
+    },
+}
+
+/// Error returned to the client; `code` is stable, `message` is for humans
+struct RequestError {
+    code: &'static str,
+    message: String,
+}
+
+impl RequestError {
+    fn new(code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<MoteError> for RequestError {
+    fn from(e: MoteError) -> Self {
+        let code = match e {
+            MoteError::SnapshotNotFound { .. }
+            | MoteError::SnapshotRefOutOfRange { .. }
+            | MoteError::NoSnapshotsAvailable => "not_found",
+            MoteError::AmbiguousSnapshotId { .. } => "ambiguous_snapshot",
+            MoteError::ObjectNotFound(_) => "object_not_found",
+            _ => "internal",
+        };
+        Self::new(code, e.to_string())
+    }
+}
+
+/// Storage state kept across requests. The snapshot listing and index are
+/// reloaded only when their files change on disk (e.g. after a shell-hook
+/// snapshot taken by another process).
+struct Server<'a> {
+    ctx: &'a CommandContext<'a>,
+    location: StorageLocation,
+    object_store: ObjectStore,
+    ignore_filter: IgnoreFilter,
+    snapshots: Vec<Snapshot>,
+    snapshots_stamp: Option<SystemTime>,
+    index: Index,
+    index_stamp: Option<SystemTime>,
+}
+
+pub fn cmd_serve(ctx: &CommandContext) -> Result<()> {
+    let location = ctx.resolve_location()?;
+    let mut server = Server {
+        ctx,
+        object_store: ctx.object_store(&location),
+        ignore_filter: ctx.ignore_filter(),
+        location,
+        snapshots: Vec::new(),
+        snapshots_stamp: None,
+        index: Index::new(),
+        index_stamp: None,
+    };
+
+    let stdin = std::io::stdin();
+    let mut stdout = std::io::stdout().lock();
+    for line in stdin.lock().lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = server.handle_line(&line);
+        writeln!(stdout, "{}", response)?;
+        stdout.flush()?;
+    }
+    Ok(())
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+impl Server<'_> {
+    fn handle_line(&mut self, line: &str) -> Value {
+        let value: Value = match serde_json::from_str(line) {
+            Ok(value) => value,
+            Err(e) => {
+                return error_response(Value::Null, RequestError::new("parse_error", e.to_string()))
+            }
+        };
+        let id = value.get("id").cloned().unwrap_or(Value::Null);
+
+        let mut fields = value;
+        if let Some(object) = fields.as_object_mut() {
+            object.remove("id");
+        }
+        let result = serde_json::from_value::<Request>(fields)
+            .map_err(|e| RequestError::new("invalid_request", e.to_string()))
+            .and_then(|request| self.handle(request));
+
+        match result {
+            Ok(result) => json!({ "id": id, "ok": true, "result": result }),
+            Err(e) => error_response(id, e),
+        }
+    }
+
+    fn handle(&mut self, request: Request) -> std::result::Result<Value, RequestError> {
+        match request {
+            Request::Status { snapshot } => self.status(snapshot.as_deref()),
+            Request::Diff {
+                file,
+                snapshot,
+                unified,
+            } => self.diff(&file, snapshot.as_deref(), unified),
+            Request::Cat { snapshot, path } => self.cat(&snapshot, &path),
+            Request::List { limit } => self.list(limit),
+        }
+    }
+
+    fn refresh_snapshots(&mut self) -> Result<()> {
+        let snapshots_dir = self.location.snapshots_dir();
+        let stamp = modified(&snapshots_dir);
+        if stamp.is_none() || stamp != self.snapshots_stamp {
+            self.snapshots = SnapshotStore::new(snapshots_dir).list()?;
+            self.snapshots_stamp = stamp;
+        }
+        Ok(())
+    }
+
+    fn refresh_index(&mut self) -> Result<()> {
+        let index_path = self.location.index_path();
+        let stamp = modified(&index_path);
+        if stamp.is_none() || stamp != self.index_stamp {
+            self.index = Index::load(&index_path)?;
+            self.index_stamp = stamp;
+        }
+        Ok(())
+    }
+
+    /// Resolve `latest`, `@~N` or an (abbreviated) snapshot ID against the
+    /// cached listing; call `refresh_snapshots` first
+    fn find_snapshot(&self, reference: Option<&str>) -> Result<&Snapshot> {
+        let reference = reference.unwrap_or(LATEST_ALIAS);
+        if let Some(offset) = relative_offset(reference) {
+            return nth_newest(&self.snapshots, reference, offset?);
+        }
+
+        let matches: Vec<&Snapshot> = self
+            .snapshots
+            .iter()
+            .filter(|s| s.id.starts_with(reference))
+            .collect();
+        match matches[..] {
+            [snapshot] => Ok(snapshot),
+            [] => Err(not_found_error(&self.snapshots, reference)),
+            _ => Err(ambiguous_error(&matches, reference)),
+        }
+    }
+
+    fn status(&mut self, reference: Option<&str>) -> std::result::Result<Value, RequestError> {
+        self.refresh_index()?;
+        self.refresh_snapshots()?;
+        let current = scan_files(
+            self.ctx.project_root,
+            &self.ignore_filter,
+            &self.index,
+            self.ctx.config.snapshot.status_threads,
+        )?;
+        let snapshot = self.find_snapshot(reference)?;
+
+        let mut added = Vec::new();
+        let mut modified = Vec::new();
+        for file in &current {
+            match snapshot.find_file(&file.path) {
+                None => added.push(file.path.as_str()),
+                Some(stored) if stored.hash != file.hash => modified.push(file.path.as_str()),
+                Some(_) => {}
+            }
+        }
+        let current_paths: HashSet<&str> = current.iter().map(|f| f.path.as_str()).collect();
+        let deleted: Vec<&str> = snapshot
+            .files
+            .iter()
+            .filter(|f| !current_paths.contains(f.path.as_str()))
+            .filter(|f| {
+                !self
+                    .ignore_filter
+                    .is_excluded_ignore_file(self.ctx.project_root, Path::new(&f.path))
+            })
+            .map(|f| f.path.as_str())
+            .collect();
+
+        Ok(json!({
+            "snapshot": snapshot.id,
+            "added": added,
+            "modified": modified,
+            "deleted": deleted,
+        }))
+    }
+
+    fn diff(
+        &mut self,
+        file: &str,
+        reference: Option<&str>,
+        unified: Option<usize>,
+    ) -> std::result::Result<Value, RequestError> {
+        let path = snapshot_relative_path(self.ctx.project_root, file);
+        let current = fs::read(self.ctx.project_root.join(&path)).ok();
+        self.refresh_snapshots()?;
+        let snapshot = self.find_snapshot(reference)?;
+        let stored = snapshot.find_file(&path).map(|f| f.hash.as_str());
+
+        let status = match (stored, &current) {
+            (None, None) => {
+                return Err(RequestError::new(
+                    "not_found",
+                    format!(
+                        "{} is neither in snapshot {} nor on disk",
+                        path,
+                        snapshot.short_id()
+                    ),
+                ))
+            }
+            (None, Some(_)) => "added",
+            (Some(_), None) => "deleted",
+            (Some(hash), Some(content)) if ObjectStore::compute_hash(content) == hash => {
+                "unchanged"
+            }
+            (Some(_), Some(_)) => "modified",
+        };
+
+        let mut diff = DiffOutput::default();
+        if status != "unchanged" {
+            generate_unified_diff_with_content(
+                &self.object_store,
+                &path,
+                stored,
+                current.as_deref(),
+                unified.unwrap_or(DEFAULT_UNIFIED),
+                Whitespace::default(),
+                &mut diff,
+            )?;
+        }
+
+        Ok(json!({
+            "snapshot": snapshot.id,
+            "path": path,
+            "status": status,
+            "diff": diff.to_string(),
+        }))
+    }
+
+    fn cat(&mut self, reference: &str, path: &str) -> std::result::Result<Value, RequestError> {
+        let path = snapshot_relative_path(self.ctx.project_root, path);
+        self.refresh_snapshots()?;
+        let snapshot = self.find_snapshot(Some(reference))?;
+        let entry = snapshot.find_file(&path).ok_or_else(|| {
+            RequestError::new(
+                "not_found",
+                format!("{} is not in snapshot {}", path, snapshot.short_id()),
+            )
+        })?;
+        let content = self.object_store.retrieve(&entry.hash)?;
+        let content = String::from_utf8(content).map_err(|_| {
+            RequestError::new("binary_content", format!("{} is not valid UTF-8", path))
+        })?;
+
+        Ok(json!({
+            "snapshot": snapshot.id,
+            "path": path,
+            "content": content,
+        }))
+    }
+
+    fn list(&mut self, limit: Option<usize>) -> std::result::Result<Value, RequestError> {
+        self.refresh_snapshots()?;
+        let entries: Vec<Value> = self
+            .snapshots
+            .iter()
+            .take(limit.unwrap_or(usize::MAX))
+            .map(|s| {
+                json!({
+                    "id": s.id,
+                    "short_id": s.short_id(),
+                    "timestamp": s.timestamp.to_rfc3339(),
+                    "message": s.message,
+                    "trigger": s.trigger,
+                    "file_count": s.file_count(),
+                })
+            })
+            .collect();
+        Ok(Value::Array(entries))
+    }
+}
+
+fn error_response(id: Value, error: RequestError) -> Value {
+    json!({
+        "id": id,
+        "ok": false,
+        "error": { "code": error.code, "message": error.message },
+    })
+}
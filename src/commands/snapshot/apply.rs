@@ -0,0 +1,166 @@
+use std::fs;
+use std::io::{ErrorKind, Read};
+use std::path::{Component, Path, PathBuf};
+
+use colored::*;
+
+use crate::commands::CommandContext;
+use crate::error::{MoteError, Result};
+use crate::patch::{parse_patch, FilePatch};
+
+/// Options for `mote snap apply`
+#[derive(Debug, Default)]
+pub struct ApplyOptions {
+    /// Report what would change without writing
+    pub dry_run: bool,
+    /// Undo the patch instead of applying it
+    pub reverse: bool,
+    /// Write nothing unless every file applies
+    pub atomic: bool,
+}
+
+/// A file whose new content has been computed but not yet written
+struct PlannedFile {
+    path: String,
+    dest: PathBuf,
+    /// `None` when the file is created
+    original: Option<String>,
+    /// `None` when the file is deleted
+    patched: Option<String>,
+}
+
+impl PlannedFile {
+    fn verb(&self, dry_run: bool) -> ColoredString {
+        match (&self.original, &self.patched, dry_run) {
+            (None, _, false) => "created".green(),
+            (None, _, true) => "create".green(),
+            (_, None, false) => "deleted".red(),
+            (_, None, true) => "delete".red(),
+            (_, _, false) => "patched".yellow(),
+            (_, _, true) => "patch".yellow(),
+        }
+    }
+}
+
+/// Apply a patch written by `mote snap diff` to the project. Every file is
+/// patched in memory first; files that conflict are reported and skipped
+/// (or, with `--atomic`, nothing is written).
+pub fn cmd_apply(ctx: &CommandContext, patch_file: &str, opts: &ApplyOptions) -> Result<()> {
+    let text = if patch_file == "-" {
+        let mut text = String::new();
+        std::io::stdin().read_to_string(&mut text)?;
+        text
+    } else {
+        fs::read_to_string(patch_file)?
+    };
+    let patches = parse_patch(&text)?;
+    if patches.is_empty() {
+        println!("{} No file changes in patch", "!".yellow().bold());
+        return Ok(());
+    }
+
+    let mut planned = Vec::new();
+    let mut failed = 0;
+    for patch in &patches {
+        let patch = if opts.reverse {
+            patch.reversed()
+        } else {
+            patch.clone()
+        };
+        match plan_file(ctx.project_root, &patch) {
+            Ok(Some(file)) => planned.push(file),
+            Ok(None) => {}
+            Err(reason) => {
+                println!("  {}  {}: {}", "conflict".red().bold(), patch.path, reason);
+                failed += 1;
+            }
+        }
+    }
+
+    if opts.atomic && failed > 0 {
+        println!("No files were changed (--atomic)");
+        return Err(MoteError::PatchFailed { failed });
+    }
+
+    let mut written = 0;
+    for file in &planned {
+        println!("  {}  {}", file.verb(opts.dry_run), file.path);
+        if opts.dry_run {
+            continue;
+        }
+        if let Err(e) = write_file(file) {
+            if opts.atomic {
+                rollback(&planned[..written]);
+                println!("No files were changed (--atomic)");
+            }
+            return Err(e);
+        }
+        written += 1;
+    }
+
+    if opts.dry_run {
+        println!("Would apply {} file(s)", planned.len());
+    } else {
+        println!("{} Applied {} file(s)", "✓".green().bold(), written);
+    }
+    if failed > 0 {
+        return Err(MoteError::PatchFailed { failed });
+    }
+    Ok(())
+}
+
+/// Compute a file's patched content; `Ok(None)` when the patch has nothing
+/// to write (mode-only changes)
+fn plan_file(
+    project_root: &Path,
+    patch: &FilePatch,
+) -> std::result::Result<Option<PlannedFile>, String> {
+    let inside_project = Path::new(&patch.path)
+        .components()
+        .all(|c| matches!(c, Component::Normal(_)));
+    if !inside_project {
+        return Err("path is outside the project".to_string());
+    }
+    if patch.hunks.is_empty() && !patch.binary {
+        return Ok(None);
+    }
+
+    let dest = project_root.join(&patch.path);
+    let original = match fs::read(&dest) {
+        Ok(bytes) => {
+            Some(String::from_utf8(bytes).map_err(|_| "file is not valid UTF-8".to_string())?)
+        }
+        Err(e) if e.kind() == ErrorKind::NotFound => None,
+        Err(e) => return Err(e.to_string()),
+    };
+    let patched = patch.apply(original.as_deref())?;
+    Ok(Some(PlannedFile {
+        path: patch.path.clone(),
+        dest,
+        original,
+        patched,
+    }))
+}
+
+fn write_file(file: &PlannedFile) -> Result<()> {
+    match &file.patched {
+        Some(content) => {
+            if let Some(parent) = file.dest.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&file.dest, content)?;
+        }
+        None => fs::remove_file(&file.dest)?,
+    }
+    Ok(())
+}
+
+/// Best-effort restore of files already written when a later write fails
+fn rollback(written: &[PlannedFile]) {
+    for file in written.iter().rev() {
+        let _ = match &file.original {
+            Some(content) => fs::write(&file.dest, content),
+            None => fs::remove_file(&file.dest),
+        };
+    }
+}
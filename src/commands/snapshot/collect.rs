@@ -1,23 +1,91 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::SystemTime;
 
 use colored::*;
 
-use crate::ignore::IgnoreFilter;
-use crate::storage::{FileEntry, Index, IndexEntry, ObjectStore};
+use crate::error::{MoteError, Result};
+use crate::ignore::{IgnoreFilter, SkippedFiles};
+use crate::path_resolver::common_dir_prefix;
+use crate::progress::Progress;
+use crate::storage::{FileEntry, FileKind, Index, IndexEntry, ObjectStore, Snapshot};
 
+/// Relative paths longer than this may not restore elsewhere (macOS caps
+/// whole paths at 1024 bytes), so snapshots warn about them
+const LONG_PATH_WARN_LEN: usize = 1024;
+
+/// How much of a shared directory prefix warnings show
+const WARN_PREFIX_LEN: usize = 80;
+
+/// How `collect_files` reports and parallelizes its work
+#[derive(Debug, Clone, Copy)]
+pub struct CollectOptions {
+    /// No warnings or progress (`--auto`)
+    pub quiet: bool,
+    /// Stop at the first file that can't be read or stored
+    pub fail_fast: bool,
+    /// Workers that hash, compress and store changed files
+    pub threads: usize,
+    /// Draw a progress bar of files processed
+    pub progress: bool,
+}
+
+impl Default for CollectOptions {
+    fn default() -> Self {
+        Self {
+            quiet: false,
+            fail_fast: false,
+            threads: 1,
+            progress: false,
+        }
+    }
+}
+
+/// Walk the project, storing changed files in the object store.
+/// Returns the collected entries, the number of bytes of content that
+/// were not already present in the store, and the paths left out (unreadable,
+/// vanished or too long). Fails before storing anything if the walk exceeds
+/// the filter's file limit.
+/// Files unchanged since the index was written take their content kind from
+/// `previous`, since their bytes are never read. Changed files are hashed,
+/// compressed and stored on up to `threads` workers once the walk is done;
+/// entries keep walk order and warnings are printed afterwards, in order.
+/// A file that can't be read is skipped with a warning, or with `fail_fast`
+/// ends the walk with an error naming it. Paths over `snapshot.max_path_len`
+/// are skipped, and both those and merely long paths get a single warning.
 pub fn collect_files(
     project_root: &Path,
-    ignore_file_path: &Path,
+    ignore_filter: &IgnoreFilter,
     object_store: &ObjectStore,
     index: &mut Index,
-    quiet: bool,
-) -> Vec<FileEntry> {
-    let ignore_filter = IgnoreFilter::new(ignore_file_path);
-    let mut files = Vec::new();
+    previous: Option<&Snapshot>,
+    opts: &CollectOptions,
+) -> Result<(Vec<FileEntry>, u64, Vec<String>)> {
+    let CollectOptions {
+        quiet, fail_fast, ..
+    } = *opts;
+    let known_kinds: HashMap<&str, FileKind> = previous
+        .map(|s| {
+            s.files
+                .iter()
+                .filter_map(|f| Some((f.hash.as_str(), f.kind?)))
+                .collect()
+        })
+        .unwrap_or_default();
 
-    for entry in ignore_filter.walk_files(project_root) {
+    let (entries, skipped) = ignore_filter.walk_files_with_skipped(project_root)?;
+    if !quiet {
+        warn_skipped(project_root, &entries, &skipped);
+    }
+    let mut left_out = skipped.too_long;
+    left_out.extend(skipped.non_utf8);
+    let progress = Progress::new("Snapshotting", entries.len(), opts.progress && !quiet);
+
+    let mut walked = Vec::new();
+    let mut pending = Vec::new();
+    for entry in &entries {
         let path = entry.path();
         let relative_path = path
             .strip_prefix(project_root)
@@ -25,57 +93,99 @@ pub fn collect_files(
             .to_string_lossy()
             .to_string();
 
+        let fail = |reason: String| MoteError::SnapshotFileFailed {
+            path: relative_path.clone(),
+            processed: walked.len(),
+            reason,
+        };
+
         let metadata = match fs::symlink_metadata(path) {
             Ok(m) => m,
-            Err(e) if !quiet => {
-                eprintln!(
-                    "{}: Failed to read metadata for {}: {}",
-                    "warning".yellow(),
-                    relative_path,
-                    e
-                );
+            Err(e) if fail_fast => return Err(fail(format!("failed to read metadata: {}", e))),
+            Err(e) => {
+                if !quiet {
+                    progress.warn(format!(
+                        "Failed to read metadata for {}: {}",
+                        relative_path, e
+                    ));
+                }
+                progress.inc(None);
+                left_out.push(relative_path);
                 continue;
             }
-            Err(_) => continue,
         };
 
         if metadata.file_type().is_symlink() {
+            progress.inc(None);
             continue;
         }
 
         let mtime = match metadata.modified() {
             Ok(t) => t,
-            Err(e) if !quiet => {
-                eprintln!(
-                    "{}: Failed to get mtime for {}: {}",
-                    "warning".yellow(),
-                    relative_path,
-                    e
-                );
+            Err(e) if fail_fast => return Err(fail(format!("failed to get mtime: {}", e))),
+            Err(e) => {
+                if !quiet {
+                    progress.warn(format!("Failed to get mtime for {}: {}", relative_path, e));
+                }
+                progress.inc(None);
+                left_out.push(relative_path);
                 continue;
             }
-            Err(_) => continue,
         };
 
         let size = metadata.len();
 
         if let Some(cached_entry) = index.is_unchanged(&relative_path, mtime, size) {
-            files.push(FileEntry {
+            walked.push(Walked::Cached(FileEntry {
                 path: relative_path,
                 hash: cached_entry.hash.clone(),
                 size: cached_entry.size,
                 mode: None,
-            });
+                kind: known_kinds.get(cached_entry.hash.as_str()).copied(),
+            }));
+            progress.inc(None);
             continue;
         }
 
-        match object_store.store_file(path) {
-            Ok((hash, file_size)) => {
+        pending.push(path);
+        walked.push(Walked::Changed {
+            relative_path,
+            mtime,
+        });
+    }
+
+    let mut stored =
+        store_files(object_store, &pending, opts.threads, fail_fast, &progress).into_iter();
+    let mut files = Vec::new();
+    let mut new_bytes = 0;
+    // Files with the same content may each have written the object
+    let mut counted = HashSet::new();
+    for item in walked {
+        let (relative_path, mtime) = match item {
+            Walked::Cached(entry) => {
+                files.push(entry);
+                continue;
+            }
+            Walked::Changed {
+                relative_path,
+                mtime,
+            } => (relative_path, mtime),
+        };
+        // Files after a --fail-fast failure are never stored
+        let Some(result) = stored.next().flatten() else {
+            continue;
+        };
+        match result {
+            Ok((hash, file_size, written, kind)) => {
+                if written && counted.insert(hash.clone()) {
+                    new_bytes += file_size;
+                }
                 let entry = FileEntry {
                     path: relative_path.clone(),
                     hash: hash.clone(),
                     size: file_size,
                     mode: None,
+                    kind: Some(kind),
                 };
 
                 index.insert(IndexEntry {
@@ -87,18 +197,211 @@ pub fn collect_files(
 
                 files.push(entry);
             }
-            Err(e) if !quiet => {
-                eprintln!(
-                    "{}: Failed to store {}: {}",
-                    "warning".yellow(),
-                    relative_path,
-                    e
-                );
+            Err(e) if fail_fast => {
+                return Err(MoteError::SnapshotFileFailed {
+                    path: relative_path,
+                    processed: files.len(),
+                    reason: format!("failed to store: {}", e),
+                })
+            }
+            Err(e) => {
+                if !quiet {
+                    progress.warn(format!("Failed to store {}: {}", relative_path, e));
+                }
+                left_out.push(relative_path);
+            }
+        }
+    }
+    progress.finish();
+    Ok((files, new_bytes, left_out))
+}
+
+/// A walked file: its entry when the index says it's unchanged, else what's
+/// needed to record it once stored
+enum Walked {
+    Cached(FileEntry),
+    Changed {
+        relative_path: String,
+        mtime: SystemTime,
+    },
+}
+
+type Stored = Result<(String, u64, bool, FileKind)>;
+
+/// Store `paths` on up to `threads` workers, each taking the next file as it
+/// finishes one, and advance `progress` by the bytes each writes. Results are
+/// in `paths` order; with `stop_on_error`, files not yet started when one
+/// fails are left `None`.
+fn store_files(
+    object_store: &ObjectStore,
+    paths: &[&Path],
+    threads: usize,
+    stop_on_error: bool,
+    progress: &Progress,
+) -> Vec<Option<Stored>> {
+    let next = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let work = || {
+        let mut done = Vec::new();
+        while !(stop_on_error && failed.load(Ordering::Relaxed)) {
+            let i = next.fetch_add(1, Ordering::Relaxed);
+            let Some(path) = paths.get(i) else {
+                break;
+            };
+            let result = object_store.store_file(path);
+            match &result {
+                Ok((_, size, written, _)) => progress.inc(Some(if *written { *size } else { 0 })),
+                Err(_) => {
+                    failed.store(true, Ordering::Relaxed);
+                    progress.inc(None);
+                }
             }
-            _ => {}
+            done.push((i, result));
         }
+        done
+    };
+
+    let threads = threads.clamp(1, paths.len().max(1));
+    let done = if threads == 1 {
+        work()
+    } else {
+        let work = &work;
+        std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads).map(|_| scope.spawn(work)).collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| {
+                    worker
+                        .join()
+                        .unwrap_or_else(|e| std::panic::resume_unwind(e))
+                })
+                .collect()
+        })
+    };
+
+    let mut results: Vec<Option<Stored>> = paths.iter().map(|_| None).collect();
+    for (i, result) in done {
+        results[i] = Some(result);
     }
-    files
+    results
+}
+
+fn warn_skipped(project_root: &Path, entries: &[walkdir::DirEntry], skipped: &SkippedFiles) {
+    if !skipped.too_long.is_empty() {
+        eprintln!(
+            "{}: Skipped {} file(s) with paths over snapshot.max_path_len{}",
+            "warning".yellow(),
+            skipped.too_long.len(),
+            under_prefix(skipped.too_long.iter().map(String::as_str))
+        );
+    }
+    if let Some(first) = skipped.non_utf8.first() {
+        eprintln!(
+            "{}: Skipped {} file(s) whose names aren't valid UTF-8 and can't be stored unchanged (e.g. {})",
+            "warning".yellow(),
+            skipped.non_utf8.len(),
+            first
+        );
+    }
+
+    let long: Vec<String> = entries
+        .iter()
+        .filter_map(|entry| {
+            let relative_path = entry.path().strip_prefix(project_root).ok()?;
+            let relative_path = relative_path.to_string_lossy();
+            (relative_path.len() > LONG_PATH_WARN_LEN).then(|| relative_path.into_owned())
+        })
+        .collect();
+    if !long.is_empty() {
+        eprintln!(
+            "{}: {} file(s) have paths over {} bytes{} and may not restore on other systems; set snapshot.max_path_len to skip them",
+            "warning".yellow(),
+            long.len(),
+            LONG_PATH_WARN_LEN,
+            under_prefix(long.iter().map(String::as_str))
+        );
+    }
+}
+
+/// ` under <dir>` for the directory the paths share, if any
+pub(super) fn under_prefix<'a>(paths: impl IntoIterator<Item = &'a str>) -> String {
+    match common_dir_prefix(paths, WARN_PREFIX_LEN) {
+        prefix if prefix.is_empty() => String::new(),
+        prefix => format!(" under {}/", prefix),
+    }
+}
+
+/// Compute the current file list without touching the object store or index.
+/// Unchanged files take their hash from the index; others are read and hashed.
+/// Work is split across `threads` workers; the result order matches a serial scan.
+pub fn scan_files(
+    project_root: &Path,
+    ignore_filter: &IgnoreFilter,
+    index: &Index,
+    threads: usize,
+) -> Result<Vec<FileEntry>> {
+    let entries = ignore_filter.walk_files(project_root)?;
+    let threads = threads.clamp(1, entries.len().max(1));
+    if threads == 1 {
+        return Ok(entries
+            .iter()
+            .filter_map(|entry| scan_entry(project_root, entry.path(), index))
+            .collect());
+    }
+
+    let chunk_size = entries.len().div_ceil(threads);
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = entries
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .filter_map(|entry| scan_entry(project_root, entry.path(), index))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        // A panicked worker's files would otherwise look deleted
+        let mut files = Vec::new();
+        for worker in workers {
+            files.extend(worker.join().map_err(|_| MoteError::HashWorkerPanicked)?);
+        }
+        Ok(files)
+    })
+}
+
+fn scan_entry(project_root: &Path, path: &Path, index: &Index) -> Option<FileEntry> {
+    let relative_path = path
+        .strip_prefix(project_root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .to_string();
+
+    let metadata = fs::symlink_metadata(path).ok()?;
+    if metadata.file_type().is_symlink() {
+        return None;
+    }
+    let mtime = metadata.modified().ok()?;
+
+    if let Some(cached_entry) = index.is_unchanged(&relative_path, mtime, metadata.len()) {
+        return Some(FileEntry {
+            path: relative_path,
+            hash: cached_entry.hash.clone(),
+            size: cached_entry.size,
+            mode: None,
+            kind: None,
+        });
+    }
+
+    let content = fs::read(path).ok()?;
+    Some(FileEntry {
+        path: relative_path,
+        hash: ObjectStore::compute_hash(&content),
+        size: content.len() as u64,
+        mode: None,
+        kind: Some(FileKind::detect(&content)),
+    })
 }
 
 pub fn have_same_file_hashes(files1: &[FileEntry], files2: &[FileEntry]) -> bool {
@@ -108,3 +411,193 @@ pub fn have_same_file_hashes(files1: &[FileEntry], files2: &[FileEntry]) -> bool
     let map: HashMap<_, _> = files1.iter().map(|f| (&f.path, &f.hash)).collect();
     files2.iter().all(|f| map.get(&f.path) == Some(&&f.hash))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::MoteError;
+    use tempfile::TempDir;
+
+    fn fixture() -> TempDir {
+        let temp = TempDir::new().unwrap();
+        for i in 0..40 {
+            let dir = temp.path().join(format!("dir{}", i % 5));
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join(format!("file{}.txt", i)), format!("content {}", i)).unwrap();
+        }
+        temp
+    }
+
+    fn quiet(threads: usize) -> CollectOptions {
+        CollectOptions {
+            quiet: true,
+            threads,
+            ..CollectOptions::default()
+        }
+    }
+
+    fn pairs(files: &[FileEntry]) -> Vec<(String, String)> {
+        files
+            .iter()
+            .map(|f| (f.path.clone(), f.hash.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parallel_scan_matches_serial() {
+        let temp = fixture();
+        let ignore = IgnoreFilter::new(&[temp.path().join(".moteignore")]);
+        let index = Index::new();
+
+        let serial = scan_files(temp.path(), &ignore, &index, 1).unwrap();
+        assert_eq!(serial.len(), 40);
+        for threads in [2, 3, 4, 64] {
+            let parallel = scan_files(temp.path(), &ignore, &index, threads).unwrap();
+            assert_eq!(pairs(&parallel), pairs(&serial), "threads = {}", threads);
+        }
+    }
+
+    #[test]
+    fn test_scan_matches_collect_without_storing() {
+        let temp = fixture();
+        let ignore = IgnoreFilter::new(&[temp.path().join(".moteignore")]);
+        let objects = TempDir::new().unwrap();
+        let object_store = ObjectStore::new(objects.path().join("objects"));
+
+        let scanned = scan_files(temp.path(), &ignore, &Index::new(), 4).unwrap();
+        assert!(!objects.path().join("objects").exists());
+
+        let mut index = Index::new();
+        let (collected, _, _) = collect_files(
+            temp.path(),
+            &ignore,
+            &object_store,
+            &mut index,
+            None,
+            &quiet(4),
+        )
+        .unwrap();
+        assert!(have_same_file_hashes(&scanned, &collected));
+
+        // Index hits give the same answer as hashing
+        let cached = scan_files(temp.path(), &ignore, &index, 4).unwrap();
+        assert_eq!(pairs(&cached), pairs(&scanned));
+    }
+
+    #[test]
+    fn test_parallel_collect_matches_serial() {
+        let temp = fixture();
+        for i in 0..10 {
+            fs::write(temp.path().join(format!("same{}.txt", i)), "same").unwrap();
+        }
+        let ignore = IgnoreFilter::new(&[temp.path().join(".moteignore")]);
+
+        let collect = |threads| {
+            let objects = TempDir::new().unwrap();
+            let object_store = ObjectStore::new(objects.path().join("objects"));
+            let mut index = Index::new();
+            let (files, new_bytes, left_out) = collect_files(
+                temp.path(),
+                &ignore,
+                &object_store,
+                &mut index,
+                None,
+                &quiet(threads),
+            )
+            .unwrap();
+            assert!(left_out.is_empty());
+            (pairs(&files), new_bytes)
+        };
+        let serial = collect(1);
+        assert_eq!(serial.0.len(), 50);
+        for threads in [2, 8] {
+            assert_eq!(collect(threads), serial, "threads = {}", threads);
+        }
+    }
+
+    #[test]
+    fn test_collect_records_kind_and_reuses_previous_for_cached_files() {
+        let temp = TempDir::new().unwrap();
+        fs::write(temp.path().join("a.txt"), "text").unwrap();
+        fs::write(temp.path().join("b.bin"), b"\x00\x01").unwrap();
+        let ignore = IgnoreFilter::new(&[temp.path().join(".moteignore")]);
+        let objects = TempDir::new().unwrap();
+        let object_store = ObjectStore::new(objects.path().join("objects"));
+        let mut index = Index::new();
+
+        let kinds = |files: &[FileEntry]| {
+            let mut kinds: Vec<_> = files.iter().map(|f| (f.path.clone(), f.kind)).collect();
+            kinds.sort_by(|a, b| a.0.cmp(&b.0));
+            kinds.into_iter().map(|(_, kind)| kind).collect::<Vec<_>>()
+        };
+        let (first, _, _) = collect_files(
+            temp.path(),
+            &ignore,
+            &object_store,
+            &mut index,
+            None,
+            &quiet(4),
+        )
+        .unwrap();
+        assert_eq!(
+            kinds(&first),
+            [Some(FileKind::Text), Some(FileKind::Binary)]
+        );
+
+        // Index hits don't read content; the kind comes from the previous snapshot
+        let previous = Snapshot::new(first, None, None);
+        let (cached, _, _) = collect_files(
+            temp.path(),
+            &ignore,
+            &object_store,
+            &mut index,
+            Some(&previous),
+            &quiet(4),
+        )
+        .unwrap();
+        assert_eq!(
+            kinds(&cached),
+            [Some(FileKind::Text), Some(FileKind::Binary)]
+        );
+        let (unknown, _, _) = collect_files(
+            temp.path(),
+            &ignore,
+            &object_store,
+            &mut index,
+            None,
+            &quiet(4),
+        )
+        .unwrap();
+        assert_eq!(kinds(&unknown), [None, None]);
+    }
+
+    #[test]
+    fn test_walk_aborts_past_file_limit_before_storing() {
+        let temp = fixture();
+        let ignore = IgnoreFilter::new(&[temp.path().join(".moteignore")]).limit_files(10);
+        let objects = TempDir::new().unwrap();
+        let object_store = ObjectStore::new(objects.path().join("objects"));
+
+        let result = collect_files(
+            temp.path(),
+            &ignore,
+            &object_store,
+            &mut Index::new(),
+            None,
+            &quiet(4),
+        );
+        assert!(matches!(
+            result,
+            Err(MoteError::TooManyFiles { limit: 10, .. })
+        ));
+        assert!(!objects.path().join("objects").exists());
+
+        let ignore = ignore.limit_files(40);
+        assert_eq!(
+            scan_files(temp.path(), &ignore, &Index::new(), 4)
+                .unwrap()
+                .len(),
+            40
+        );
+    }
+}
@@ -1,29 +1,24 @@
-use std::io::{self, Write};
-
 use colored::*;
 
+use super::{confirm, maybe_auto_gc};
 use crate::commands::CommandContext;
 use crate::error::Result;
 use crate::storage::SnapshotStore;
 
 pub fn cmd_delete(ctx: &CommandContext, snapshot_id: &str, force: bool) -> Result<()> {
+    ctx.ensure_writable()?;
     let location = ctx.resolve_location()?;
     let snapshot_store = SnapshotStore::new(location.snapshots_dir());
-    let snapshot = snapshot_store.find_by_id(snapshot_id)?;
+    let snapshot = snapshot_store.resolve_ref(snapshot_id)?;
 
     if !force {
-        print!(
-            "Delete snapshot {} ({} files)? [y/N] ",
+        let question = format!(
+            "Delete snapshot {} ({} files)?",
             snapshot.short_id().cyan(),
             snapshot.file_count()
         );
-        io::stdout().flush()?;
-
-        let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
-
-        let answer = input.trim().to_lowercase();
-        if answer != "y" && answer != "yes" {
+        let action = format!("deleting snapshot {}", snapshot.short_id());
+        if !confirm(&question, &action)? {
             println!("{} Deletion cancelled", "!".yellow().bold());
             return Ok(());
         }
@@ -37,6 +32,7 @@ pub fn cmd_delete(ctx: &CommandContext, snapshot_id: &str, force: bool) -> Resul
         snapshot.short_id().cyan(),
         snapshot.file_count()
     );
+    maybe_auto_gc(ctx, &location, 1, false)?;
 
     Ok(())
 }
@@ -1,323 +1,2361 @@
-use std::collections::{HashMap, HashSet};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fmt::Write;
 use std::fs;
-use std::path::Path;
+use std::ops::Range;
+use std::path::{Component, Path};
 
+use chrono::{DateTime, Utc};
 use colored::*;
-use similar::{ChangeTag, TextDiff};
+use serde::Serialize;
+use similar::{
+    capture_diff_slices, group_diff_ops, Algorithm, ChangeTag, DiffOp, DiffTag, TextDiff,
+};
 
+use super::collect::scan_files;
+use super::external::{parse_tool, run_tool, ScratchDir};
+use super::print_line_stats;
+use super::use_color;
+use crate::cli::ColorChoice;
 use crate::commands::CommandContext;
+use crate::config::DisplayTimezone;
 use crate::error::{MoteError, Result};
 use crate::ignore::IgnoreFilter;
-use crate::storage::{FileEntry, ObjectStore, Snapshot, SnapshotStore};
+use crate::path_resolver::snapshot_relative_path;
+use crate::size_format::format_size;
+use crate::storage::{
+    write_atomic, FileEntry, FileKind, Index, ObjectStore, Snapshot, SnapshotStore,
+};
+use crate::time_format::format_timestamp;
 
+/// `--range` warns past this many snapshots, each of which is read in full
+const RANGE_WARN_SNAPSHOTS: usize = 1000;
+
+/// Options for `mote snap diff`
+#[derive(Debug, Default)]
+pub struct DiffOptions {
+    /// Compare the ends of a range of snapshots (`N` for the last N, or
+    /// `old..new`), noting where each file last changed within it
+    pub range: Option<String>,
+    pub name_only: bool,
+    /// Write the diff to this file instead of stdout
+    pub output: Option<String>,
+    /// Replace `output` if it already exists
+    pub force_output: bool,
+    /// Context lines around each hunk
+    pub unified: usize,
+    /// Only print counts and byte deltas from file metadata
+    pub summary_only: bool,
+    /// Per-file insertion/deletion counts instead of the diff text
+    pub stat: bool,
+    /// Print the changed files (or the summary) as JSON
+    pub json: bool,
+    /// Include line-level hunks in the JSON changes
+    pub with_hunks: bool,
+    /// Only detect differences; print nothing
+    pub quiet: bool,
+    /// Hide files whose only change is their mode
+    pub ignore_mode: bool,
+    /// Compare lines with all whitespace removed
+    pub ignore_all_space: bool,
+    /// Drop changes that only add or remove blank lines
+    pub ignore_blank_lines: bool,
+    /// Write `diff --git` headers with file modes
+    pub git_format: bool,
+    /// Show changed words within modified lines instead of whole lines
+    pub word_diff: bool,
+    /// External command to show each changed file with
+    pub tool: Option<String>,
+    /// When to color output written to stdout
+    pub color: ColorChoice,
+    /// Only compare files at or under these paths (all files if empty)
+    pub paths: Vec<String>,
+}
+
+/// Restricts a diff to files at or under the given project-relative paths
+#[derive(Debug)]
+struct PathFilter {
+    prefixes: Vec<String>,
+}
+
+impl PathFilter {
+    /// Normalize `paths` relative to `project_root`; `.` or the root itself
+    /// selects everything
+    fn new(project_root: &Path, paths: &[String]) -> Self {
+        let prefixes = paths
+            .iter()
+            .map(|p| {
+                let relative = snapshot_relative_path(project_root, p);
+                Path::new(&relative)
+                    .components()
+                    .filter_map(|c| match c {
+                        Component::Normal(name) => Some(name.to_string_lossy().to_string()),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>()
+                    .join("/")
+            })
+            .collect();
+        Self { prefixes }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.prefixes.is_empty()
+    }
+
+    fn matches(&self, path: &str) -> bool {
+        self.is_empty() || self.prefixes.iter().any(|p| matches_prefix(p, path))
+    }
+
+    /// Warn about each path that selects nothing in `known` (snapshot paths)
+    /// and, when given, on disk under `project_root`
+    fn warn_unmatched<'a>(
+        &self,
+        known: impl Iterator<Item = &'a str> + Clone,
+        project_root: Option<&Path>,
+    ) {
+        for prefix in &self.prefixes {
+            let on_disk = project_root.is_some_and(|root| root.join(prefix).exists());
+            if !on_disk && !known.clone().any(|path| matches_prefix(prefix, path)) {
+                eprintln!(
+                    "{}: path '{}' did not match any files",
+                    "warning".yellow(),
+                    prefix
+                );
+            }
+        }
+    }
+}
+
+fn matches_prefix(prefix: &str, path: &str) -> bool {
+    prefix.is_empty()
+        || path == prefix
+        || (path.starts_with(prefix) && path.as_bytes().get(prefix.len()) == Some(&b'/'))
+}
+
+/// Print the differences selected by `opts`; returns whether there were any
 pub fn cmd_diff(
     ctx: &CommandContext,
     snapshot_id: Option<String>,
     snapshot_id2: Option<String>,
-    name_only: bool,
-    output: Option<String>,
-    unified: usize,
-) -> Result<()> {
+    opts: &DiffOptions,
+) -> Result<bool> {
     let location = ctx.resolve_location()?;
     let snapshot_store = SnapshotStore::new(location.snapshots_dir());
-    let object_store = ObjectStore::new(location.objects_dir());
+    let object_store = ctx.object_store(&location);
 
-    let snapshot_id = match snapshot_id {
-        Some(id) => id,
+    let (mut snapshot1, mut snapshot2, last_changed) = match opts.range {
+        Some(ref spec) => {
+            let range = range_snapshots(&snapshot_store, spec)?;
+            let last_changed = last_changed_in(&range);
+            (range[0].clone(), range.last().cloned(), last_changed)
+        }
         None => {
-            let snapshots = snapshot_store.list()?;
-            if snapshots.is_empty() {
-                return Err(MoteError::NoSnapshotsAvailable);
-            }
-            snapshots[0].id.clone()
+            let (snapshot1, snapshot2) =
+                resolve_snapshots(&snapshot_store, snapshot_id, snapshot_id2)?;
+            (snapshot1, snapshot2, HashMap::new())
         }
     };
 
-    let snapshot1 = snapshot_store.find_by_id(&snapshot_id)?;
-    let mut diff_output = String::new();
+    let path_filter = PathFilter::new(ctx.project_root, &opts.paths);
+    if !path_filter.is_empty() {
+        let known = snapshot1
+            .files
+            .iter()
+            .chain(snapshot2.iter().flat_map(|s| &s.files))
+            .map(|f| f.path.as_str());
+        let on_disk = snapshot2.is_none().then_some(ctx.project_root);
+        path_filter.warn_unmatched(known, on_disk);
 
-    if let Some(ref id2) = snapshot_id2 {
-        let snapshot2 = snapshot_store.find_by_id(id2)?;
+        snapshot1.files.retain(|f| path_filter.matches(&f.path));
+        if let Some(ref mut snapshot2) = snapshot2 {
+            snapshot2.files.retain(|f| path_filter.matches(&f.path));
+        }
+    }
+
+    if opts.summary_only {
+        let stats = match snapshot2 {
+            Some(ref snapshot2) => change_stats(Some(&snapshot1), snapshot2),
+            None => {
+                working_dir_change_stats(ctx, &location.index_path(), &snapshot1, &path_filter)?
+            }
+        };
+        print_summary(stats, opts.json, ctx.config.display.exact_sizes)?;
+        return Ok(stats.total() > 0);
+    }
+
+    if opts.stat {
+        let stats = match snapshot2 {
+            Some(ref snapshot2) => line_stats(&object_store, Some(&snapshot1), snapshot2)?,
+            None => working_dir_line_stats(
+                ctx.project_root,
+                &ctx.ignore_filter(),
+                &path_filter,
+                &snapshot1,
+                &object_store,
+            )?,
+        };
+        print_line_stats(&stats);
+        return Ok(!stats.is_empty());
+    }
+
+    let ignore_mode = opts.ignore_mode || ctx.config.diff.ignore_mode_changes;
+    if opts.json {
+        let hunk_context = opts.with_hunks.then_some(opts.unified);
+        let mut changes = match snapshot2 {
+            Some(ref snapshot2) => snapshot_json_changes(
+                &object_store,
+                &snapshot1,
+                snapshot2,
+                ignore_mode,
+                hunk_context,
+            )?,
+            None => working_dir_json_changes(
+                ctx.project_root,
+                &ctx.ignore_filter(),
+                &path_filter,
+                &snapshot1,
+                &object_store,
+                hunk_context,
+            )?,
+        };
+        for change in &mut changes {
+            change.last_changed = last_changed.get(&change.path).cloned();
+        }
+        let json = serde_json::to_string_pretty(&changes)?;
+        match opts.output {
+            Some(ref output_file) => {
+                write_output(
+                    output_file,
+                    opts,
+                    format!("{}\n", json).as_bytes(),
+                    ctx.config.storage.fsync,
+                )?;
+                println!("Diff written to {}", output_file.cyan());
+            }
+            None => println!("{}", json),
+        }
+        return Ok(!changes.is_empty());
+    }
+
+    // Files, patches and the other built-in renderings never go to the tool
+    let builtin_only =
+        opts.name_only || opts.output.is_some() || opts.quiet || opts.git_format || opts.word_diff;
+    let tool = opts
+        .tool
+        .as_ref()
+        .or(ctx.config.diff.external.as_ref())
+        .filter(|_| !builtin_only);
+    if let Some(tool) = tool {
+        let changes = match snapshot2 {
+            Some(ref snapshot2) => {
+                snapshot_json_changes(&object_store, &snapshot1, snapshot2, ignore_mode, None)?
+            }
+            None => working_dir_json_changes(
+                ctx.project_root,
+                &ctx.ignore_filter(),
+                &path_filter,
+                &snapshot1,
+                &object_store,
+                None,
+            )?,
+        };
+        let working_dir = snapshot2.is_none().then_some(ctx.project_root);
+        external_diff(tool, &changes, working_dir, &object_store, opts)?;
+        return Ok(!changes.is_empty());
+    }
+
+    let text_opts = TextDiffOptions {
+        name_only: opts.name_only,
+        unified: opts.unified,
+        ignore_mode,
+        whitespace: Whitespace {
+            ignore_all_space: opts.ignore_all_space,
+            ignore_blank_lines: opts.ignore_blank_lines,
+        },
+        tz: ctx.config.display.timezone,
+    };
+    let mut diff_output = DiffOutput {
+        git_headers: opts.git_format,
+        word_diff: opts.word_diff,
+        last_changed: last_changed
+            .into_iter()
+            .map(|(path, at)| {
+                let timestamp = format_timestamp(&at.timestamp, ctx.config.display.timezone, true);
+                (path, format!("{} ({})", at.id, timestamp))
+            })
+            .collect(),
+        ..DiffOutput::default()
+    };
+    let changed = if let Some(ref snapshot2) = snapshot2 {
         diff_snapshots(
             &snapshot1,
-            &snapshot2,
+            snapshot2,
             &object_store,
-            name_only,
-            unified,
+            &text_opts,
             &mut diff_output,
+        )?
+    } else {
+        diff_with_working_dir(
+            ctx.project_root,
+            &ctx.ignore_filter(),
+            &path_filter,
+            &snapshot1,
+            &object_store,
+            &text_opts,
+            &mut diff_output,
+        )?
+    };
+
+    if opts.quiet {
+        return Ok(changed);
+    }
+    if let Some(ref output_file) = opts.output {
+        write_output(
+            output_file,
+            opts,
+            diff_output.to_string().as_bytes(),
+            ctx.config.storage.fsync,
         )?;
+        println!("Diff written to {}", output_file.cyan());
+    } else {
+        print!("{}", diff_output.render(use_color(opts.color)));
+    }
+
+    Ok(changed)
+}
+
+/// The snapshots named on the command line: the first defaults to the latest
+/// and may be an `old..new` range; `None` on the new side is the working
+/// directory
+fn resolve_snapshots(
+    store: &SnapshotStore,
+    snapshot_id: Option<String>,
+    snapshot_id2: Option<String>,
+) -> Result<(Snapshot, Option<Snapshot>)> {
+    let (snapshot_id, snapshot_id2) = match snapshot_id.as_deref().map(parse_range) {
+        Some(Ok(Some(_))) if snapshot_id2.is_some() => {
+            return Err(MoteError::InvalidArguments(
+                "A range can't be combined with a second snapshot ID".to_string(),
+            ))
+        }
+        Some(Ok(Some((old, new)))) => (Some(old), new),
+        Some(Err(e)) => return Err(e),
+        _ => (snapshot_id, snapshot_id2),
+    };
+    let snapshot_id = match snapshot_id {
+        Some(id) => id,
+        None => store.latest()?.ok_or(MoteError::NoSnapshotsAvailable)?.id,
+    };
+
+    let snapshot1 = store.resolve_ref(&snapshot_id)?;
+    let snapshot2 = snapshot_id2
+        .as_deref()
+        .map(|id| store.resolve_ref(id))
+        .transpose()?;
+    Ok((snapshot1, snapshot2))
+}
+
+/// Snapshots in `--range`, oldest first: `N` spans the latest snapshot and
+/// the one N before it, `old..new` two given snapshots
+fn range_snapshots(store: &SnapshotStore, spec: &str) -> Result<Vec<Snapshot>> {
+    // Newest first
+    let snapshots = store.list()?;
+    let (newest, oldest) = match spec.parse::<usize>() {
+        Ok(0) => {
+            return Err(MoteError::InvalidArguments(
+                "--range needs at least 1 snapshot to look back".to_string(),
+            ))
+        }
+        Ok(count) => {
+            if snapshots.is_empty() {
+                return Err(MoteError::NoSnapshotsAvailable);
+            }
+            if count >= snapshots.len() {
+                eprintln!(
+                    "{}: only {} snapshot(s) before the latest; starting from the oldest",
+                    "warning".yellow(),
+                    snapshots.len() - 1
+                );
+            }
+            (0, count.min(snapshots.len() - 1))
+        }
+        Err(_) => {
+            let Some((old, Some(new))) = parse_range(spec)? else {
+                return Err(MoteError::InvalidArguments(format!(
+                    "Invalid range '{}': expected a number of snapshots or <old>..<new>",
+                    spec
+                )));
+            };
+            let position = |id: &str| -> Result<usize> {
+                let id = store.resolve_ref(id)?.id;
+                Ok(snapshots
+                    .iter()
+                    .position(|s| s.id == id)
+                    .unwrap_or_default())
+            };
+            let (newest, oldest) = (position(&new)?, position(&old)?);
+            if newest > oldest {
+                return Err(MoteError::InvalidArguments(format!(
+                    "Invalid range '{}': {} is newer than {}",
+                    spec, old, new
+                )));
+            }
+            (newest, oldest)
+        }
+    };
+
+    let range = &snapshots[newest..=oldest];
+    if range.len() > RANGE_WARN_SNAPSHOTS {
+        eprintln!(
+            "{}: range spans {} snapshots; reading each of them may take a while",
+            "warning".yellow(),
+            range.len()
+        );
+    }
+    Ok(range.iter().rev().cloned().collect())
+}
+
+/// Where a file last changed within `--range`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct LastChanged {
+    id: String,
+    timestamp: DateTime<Utc>,
+}
+
+/// Walk consecutive snapshots in `range` (oldest first) and record, per path,
+/// the latest one whose content or mode differs from the snapshot before it
+fn last_changed_in(range: &[Snapshot]) -> HashMap<String, LastChanged> {
+    let mut last_changed = HashMap::new();
+    for pair in range.windows(2) {
+        let before = files_to_map(&pair[0].files);
+        let after = files_to_map(&pair[1].files);
+        for path in before.keys().chain(after.keys()) {
+            let changed = match (before.get(path), after.get(path)) {
+                (Some(old), Some(new)) => old.hash != new.hash || old.mode != new.mode,
+                _ => true,
+            };
+            if changed {
+                last_changed.insert(
+                    path.to_string(),
+                    LastChanged {
+                        id: pair[1].short_id().to_string(),
+                        timestamp: pair[1].timestamp,
+                    },
+                );
+            }
+        }
+    }
+    last_changed
+}
+
+/// Write `--output` with `write_atomic`, so an interrupted run never leaves a
+/// partial patch behind. An existing file is only replaced with
+/// `--force-output`; missing parent directories are created.
+fn write_output(output_file: &str, opts: &DiffOptions, bytes: &[u8], fsync: bool) -> Result<()> {
+    let path = Path::new(output_file);
+    if path.exists() && !opts.force_output {
+        return Err(MoteError::OutputExists(output_file.to_string()));
+    }
+
+    let expected: &[&str] = if opts.json {
+        &["json"]
+    } else {
+        &["diff", "patch"]
+    };
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default();
+    if !expected.contains(&extension) {
+        let format = if opts.json { "JSON" } else { "a unified diff" };
+        eprintln!(
+            "{} {} doesn't end in .{}; writing {} anyway",
+            "info".blue().bold(),
+            output_file,
+            expected.join(" or ."),
+            format
+        );
+    }
+
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    write_atomic(path, bytes, fsync)?;
+    Ok(())
+}
+
+/// Split `old..new` into its snapshot IDs; `old..` leaves the new side to the
+/// working directory. `None` when `spec` is a plain ID.
+fn parse_range(spec: &str) -> Result<Option<(String, Option<String>)>> {
+    let Some((old, new)) = spec.split_once("..") else {
+        return Ok(None);
+    };
+    let invalid = |reason: &str| {
+        Err(MoteError::InvalidArguments(format!(
+            "Invalid range '{}': {}",
+            spec, reason
+        )))
+    };
+    if old.is_empty() {
+        return invalid("a starting snapshot is required (use <old>..<new>, or <old>.. for the working directory)");
+    }
+    if new.contains('.') {
+        return invalid("expected a single '..' between two snapshot IDs");
+    }
+    let new = (!new.is_empty()).then(|| new.to_string());
+    Ok(Some((old.to_string(), new)))
+}
+
+/// Show each changed file with an external tool (`--tool`, `diff.external`),
+/// falling back to the built-in diff for files the tool fails on. New
+/// content is read from `working_dir` when comparing against it.
+fn external_diff(
+    tool: &str,
+    changes: &[JsonChange],
+    working_dir: Option<&Path>,
+    object_store: &ObjectStore,
+    opts: &DiffOptions,
+) -> Result<()> {
+    let tool = parse_tool(tool)?;
+    let scratch = ScratchDir::new()?;
+    let color = use_color(opts.color);
+
+    for change in changes {
+        // Mode-only changes have no content for the tool to compare
+        if change.old_hash.is_some() && change.old_hash == change.new_hash {
+            continue;
+        }
+        let old = retrieve_or_empty(object_store, &change.path, change.old_hash.as_deref())?;
+        let new = match (&change.new_hash, working_dir) {
+            (None, _) => None,
+            (Some(_), Some(root)) => Some(fs::read(root.join(&change.path))?),
+            (Some(hash), None) => Some(object_store.retrieve(hash)?),
+        };
+        if run_tool(
+            &tool,
+            &scratch,
+            &change.path,
+            &old,
+            new.as_deref().unwrap_or_default(),
+        )? {
+            continue;
+        }
+
+        let mut output = DiffOutput::default();
+        generate_unified_diff_with_content(
+            object_store,
+            &change.path,
+            change.old_hash.as_deref(),
+            new.as_deref(),
+            opts.unified,
+            Whitespace::default(),
+            &mut output,
+        )?;
+        print!("{}", output.render(color));
+    }
+    Ok(())
+}
+
+/// Summary of the working directory against `snapshot`, hashing only files
+/// whose index entry is stale; the object store is never read
+fn working_dir_change_stats(
+    ctx: &CommandContext,
+    index_path: &Path,
+    snapshot: &Snapshot,
+    path_filter: &PathFilter,
+) -> Result<ChangeStats> {
+    let ignore_filter = ctx.ignore_filter();
+    let mut current = scan_files(
+        ctx.project_root,
+        &ignore_filter,
+        &Index::load(index_path)?,
+        ctx.config.snapshot.status_threads,
+    )?;
+    current.retain(|f| path_filter.matches(&f.path));
+    let stored: Vec<FileEntry> = snapshot
+        .files
+        .iter()
+        .filter(|f| !ignore_filter.is_excluded_ignore_file(ctx.project_root, Path::new(&f.path)))
+        .cloned()
+        .collect();
+    Ok(file_change_stats(&stored, &current))
+}
+
+fn print_summary(stats: ChangeStats, json: bool, exact_sizes: bool) -> Result<()> {
+    if json {
+        let summary = serde_json::json!({
+            "added": stats.added,
+            "modified": stats.modified,
+            "deleted": stats.deleted,
+            "bytes_added": stats.bytes_added,
+            "bytes_removed": stats.bytes_removed,
+        });
+        println!("{}", serde_json::to_string_pretty(&summary)?);
+        return Ok(());
+    }
+
+    println!(
+        "{} added, {} modified, {} deleted ({}, {})",
+        stats.added,
+        stats.modified,
+        stats.deleted,
+        format!("+{}", format_size(stats.bytes_added, exact_sizes)).green(),
+        format!("-{}", format_size(stats.bytes_removed, exact_sizes)).red()
+    );
+    Ok(())
+}
+
+fn files_to_map(files: &[FileEntry]) -> HashMap<&str, &FileEntry> {
+    files.iter().map(|f| (f.path.as_str(), f)).collect()
+}
+
+/// Files added, modified and deleted between two snapshots, with the bytes
+/// gained and lost (a modified file counts its size difference)
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub(super) struct ChangeStats {
+    pub added: usize,
+    pub modified: usize,
+    pub deleted: usize,
+    pub bytes_added: u64,
+    pub bytes_removed: u64,
+}
+
+impl ChangeStats {
+    pub fn total(&self) -> usize {
+        self.added + self.modified + self.deleted
+    }
+}
+
+/// Compare file lists by path and hash only; no content is read.
+/// Without an `old` snapshot every file counts as added.
+pub(super) fn change_stats(old: Option<&Snapshot>, new: &Snapshot) -> ChangeStats {
+    let old_files = old.map(|s| s.files.as_slice()).unwrap_or_default();
+    file_change_stats(old_files, &new.files)
+}
+
+fn file_change_stats(old: &[FileEntry], new: &[FileEntry]) -> ChangeStats {
+    let files1 = files_to_map(old);
+    let files2 = files_to_map(new);
+    let mut stats = ChangeStats::default();
+
+    for (path, file2) in &files2 {
+        match files1.get(path) {
+            Some(file1) if file1.hash != file2.hash => {
+                stats.modified += 1;
+                if file2.size >= file1.size {
+                    stats.bytes_added += file2.size - file1.size;
+                } else {
+                    stats.bytes_removed += file1.size - file2.size;
+                }
+            }
+            Some(_) => {}
+            None => {
+                stats.added += 1;
+                stats.bytes_added += file2.size;
+            }
+        }
+    }
+    for (path, file1) in &files1 {
+        if !files2.contains_key(path) {
+            stats.deleted += 1;
+            stats.bytes_removed += file1.size;
+        }
+    }
+    stats
+}
+
+/// Lines inserted and deleted in one file; `lines` is `None` for binary content
+#[derive(Debug, PartialEq)]
+pub(super) struct FileLineStat {
+    pub path: String,
+    pub lines: Option<(usize, usize)>,
+}
+
+/// Per-file line statistics between two snapshots, sorted by path.
+/// Files with identical hashes are skipped without reading their content;
+/// without an `old` snapshot every file counts as added.
+pub(super) fn line_stats(
+    object_store: &ObjectStore,
+    old: Option<&Snapshot>,
+    new: &Snapshot,
+) -> Result<Vec<FileLineStat>> {
+    let files1 = old.map(|s| files_to_map(&s.files)).unwrap_or_default();
+    let files2 = files_to_map(&new.files);
+    let mut paths: Vec<&str> = files1.keys().chain(files2.keys()).copied().collect();
+    paths.sort_unstable();
+    paths.dedup();
+
+    let mut stats = Vec::new();
+    for path in paths {
+        let hash1 = files1.get(path).map(|f| f.hash.as_str());
+        let hash2 = files2.get(path).map(|f| f.hash.as_str());
+        if hash1 == hash2 {
+            continue;
+        }
+
+        let known_binary = [files1.get(path), files2.get(path)]
+            .into_iter()
+            .flatten()
+            .any(|f| f.is_known_binary());
+        if known_binary {
+            stats.push(FileLineStat {
+                path: path.to_string(),
+                lines: None,
+            });
+            continue;
+        }
+
+        let content1 = retrieve_or_empty(object_store, path, hash1)?;
+        let content2 = retrieve_or_empty(object_store, path, hash2)?;
+        stats.push(FileLineStat {
+            path: path.to_string(),
+            lines: count_lines(&content1, &content2),
+        });
+    }
+    Ok(stats)
+}
+
+/// A path whose content differs between a snapshot and the working directory
+struct WorkingDirChange<'a> {
+    path: String,
+    /// Its entry in the snapshot; `None` when added
+    old: Option<&'a FileEntry>,
+    /// Its current content and hash; `None` when deleted
+    new: Option<WorkingFile>,
+}
+
+struct WorkingFile {
+    content: Vec<u8>,
+    hash: String,
+}
+
+impl WorkingDirChange<'_> {
+    /// Current content, empty when deleted
+    fn new_content(&self) -> &[u8] {
+        self.new
+            .as_ref()
+            .map_or(&[], |file| file.content.as_slice())
+    }
+}
+
+/// Files added, modified or deleted in the working directory since
+/// `snapshot`, in path order. The working tree is walked up front; each
+/// file is read and hashed as the iterator reaches it, and one that can't
+/// be read is skipped with a warning rather than reported as deleted. Modes
+/// of working-tree files aren't read, so only content changes are found,
+/// and an excluded ignore file, absent from the walk, is never deleted.
+fn working_dir_changes<'a>(
+    project_root: &Path,
+    ignore_filter: &IgnoreFilter,
+    path_filter: &'a PathFilter,
+    snapshot: &'a Snapshot,
+) -> Result<impl Iterator<Item = WorkingDirChange<'a>>> {
+    let mut snapshot_files = files_to_map(&snapshot.files);
+    snapshot_files
+        .retain(|path, _| !ignore_filter.is_excluded_ignore_file(project_root, Path::new(path)));
+    let mut current_files = BTreeMap::new();
+    for entry in ignore_filter.walk_files(project_root)? {
+        let path = entry.path();
+        let relative_path = path
+            .strip_prefix(project_root)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .to_string();
+        if path_filter.matches(&relative_path) {
+            current_files.insert(relative_path, path.to_path_buf());
+        }
+    }
+    let paths: BTreeSet<String> = snapshot_files
+        .keys()
+        .map(|path| path.to_string())
+        .chain(current_files.keys().cloned())
+        .collect();
+
+    Ok(paths.into_iter().filter_map(move |path| {
+        let old = snapshot_files.get(path.as_str()).copied();
+        let Some(full_path) = current_files.get(&path) else {
+            return Some(WorkingDirChange {
+                path,
+                old,
+                new: None,
+            });
+        };
+        let content = match fs::read(full_path) {
+            Ok(content) => content,
+            Err(e) => {
+                eprintln!("{}: Failed to read {}: {}", "warning".yellow(), path, e);
+                return None;
+            }
+        };
+        let hash = ObjectStore::compute_hash(&content);
+        if old.is_some_and(|file| file.hash == hash) {
+            return None;
+        }
+        Some(WorkingDirChange {
+            path,
+            old,
+            new: Some(WorkingFile { content, hash }),
+        })
+    }))
+}
+
+/// Per-file line statistics between a snapshot and the working directory,
+/// sorted by path
+fn working_dir_line_stats(
+    project_root: &Path,
+    ignore_filter: &IgnoreFilter,
+    path_filter: &PathFilter,
+    snapshot: &Snapshot,
+    object_store: &ObjectStore,
+) -> Result<Vec<FileLineStat>> {
+    let mut stats = Vec::new();
+    for change in working_dir_changes(project_root, ignore_filter, path_filter, snapshot)? {
+        let lines = if change.old.is_some_and(|file| file.is_known_binary()) {
+            None
+        } else {
+            let old_hash = change.old.map(|file| file.hash.as_str());
+            let content1 = retrieve_or_empty(object_store, &change.path, old_hash)?;
+            count_lines(&content1, change.new_content())
+        };
+        stats.push(FileLineStat {
+            path: change.path,
+            lines,
+        });
+    }
+    Ok(stats)
+}
+
+/// Inserted and deleted line counts, or `None` if either side is binary
+fn count_lines(content1: &[u8], content2: &[u8]) -> Option<(usize, usize)> {
+    if is_binary(content1) || is_binary(content2) {
+        return None;
+    }
+
+    let text1 = String::from_utf8_lossy(content1);
+    let text2 = String::from_utf8_lossy(content2);
+    let diff = TextDiff::from_lines(&text1, &text2);
+    let mut counts = (0, 0);
+    for change in diff.iter_all_changes() {
+        match change.tag() {
+            ChangeTag::Insert => counts.0 += 1,
+            ChangeTag::Delete => counts.1 += 1,
+            ChangeTag::Equal => {}
+        }
+    }
+    Some(counts)
+}
+
+/// Object content for `hash`, or empty when the file is absent on this side
+/// or its object is missing (with a warning)
+fn retrieve_or_empty(
+    object_store: &ObjectStore,
+    path: &str,
+    hash: Option<&str>,
+) -> Result<Vec<u8>> {
+    let Some(hash) = hash else {
+        return Ok(Vec::new());
+    };
+    match object_store.retrieve(hash) {
+        Ok(content) => Ok(content),
+        Err(MoteError::ObjectNotFound(hash)) => {
+            eprintln!(
+                "{}: Object not found for {}: {}",
+                "warning".yellow(),
+                path,
+                hash
+            );
+            Ok(Vec::new())
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// One changed file in `--json` output; hashes, sizes and modes are absent on
+/// the side where the file doesn't exist
+#[derive(Debug, Serialize)]
+struct JsonChange {
+    path: String,
+    status: &'static str,
+    old_hash: Option<String>,
+    new_hash: Option<String>,
+    old_size: Option<u64>,
+    new_size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    old_mode: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    new_mode: Option<String>,
+    /// Binary files get no hunks
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    binary: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hunks: Option<Vec<JsonHunk>>,
+    /// The snapshot in `--range` that last changed the file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_changed: Option<LastChanged>,
+}
+
+/// Lines are 1-based; a side with no lines in the hunk starts at 0, as in a
+/// unified diff header
+#[derive(Debug, PartialEq, Serialize)]
+struct JsonHunk {
+    old_start: usize,
+    old_lines: usize,
+    new_start: usize,
+    new_lines: usize,
+    lines: Vec<JsonLine>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct JsonLine {
+    /// `context`, `insert` or `delete`
+    tag: &'static str,
+    old_line: Option<usize>,
+    new_line: Option<usize>,
+    /// Line text without its trailing newline
+    text: String,
+}
+
+impl JsonChange {
+    fn new(path: &str, old: Option<&FileEntry>, new: Option<&FileEntry>) -> Self {
+        let status = match (old, new) {
+            (None, _) => "added",
+            (_, None) => "deleted",
+            _ => "modified",
+        };
+        Self {
+            path: path.to_string(),
+            status,
+            old_hash: old.map(|f| f.hash.clone()),
+            new_hash: new.map(|f| f.hash.clone()),
+            old_size: old.map(|f| f.size),
+            new_size: new.map(|f| f.size),
+            old_mode: old.and_then(|f| f.mode.clone()),
+            new_mode: new.and_then(|f| f.mode.clone()),
+            binary: false,
+            hunks: None,
+            last_changed: None,
+        }
+    }
+
+    /// Flag a change whose content won't be diffed; it gets no hunks
+    fn binary(self) -> Self {
+        Self {
+            binary: true,
+            ..self
+        }
+    }
+
+    /// Attach hunks with `context` lines around each change, or mark the
+    /// change as binary. Mode-only changes have no content to compare.
+    fn with_hunks(self, content1: &[u8], content2: &[u8], context: usize) -> Self {
+        let hunks = if self.old_hash.is_some() && self.old_hash == self.new_hash {
+            Vec::new()
+        } else if is_binary(content1) || is_binary(content2) {
+            return self.binary();
+        } else {
+            json_hunks(content1, content2, context)
+        };
+        Self {
+            hunks: Some(hunks),
+            ..self
+        }
+    }
+}
+
+fn json_hunks(content1: &[u8], content2: &[u8], context: usize) -> Vec<JsonHunk> {
+    let text1 = String::from_utf8_lossy(content1);
+    let text2 = String::from_utf8_lossy(content2);
+    let diff = TextDiff::from_lines(&text1, &text2);
+
+    let mut hunks = Vec::new();
+    for hunk in diff.unified_diff().context_radius(context).iter_hunks() {
+        let (Some(first), Some(last)) = (hunk.ops().first(), hunk.ops().last()) else {
+            continue;
+        };
+        let old_lines = last.old_range().end - first.old_range().start;
+        let new_lines = last.new_range().end - first.new_range().start;
+        let start = |start: usize, lines: usize| if lines == 0 { start } else { start + 1 };
+        hunks.push(JsonHunk {
+            old_start: start(first.old_range().start, old_lines),
+            old_lines,
+            new_start: start(first.new_range().start, new_lines),
+            new_lines,
+            lines: hunk
+                .iter_changes()
+                .map(|change| JsonLine {
+                    tag: match change.tag() {
+                        ChangeTag::Equal => "context",
+                        ChangeTag::Insert => "insert",
+                        ChangeTag::Delete => "delete",
+                    },
+                    old_line: change.old_index().map(|i| i + 1),
+                    new_line: change.new_index().map(|i| i + 1),
+                    text: change.value().trim_end_matches(['\n', '\r']).to_string(),
+                })
+                .collect(),
+        });
+    }
+    hunks
+}
+
+/// Changed files between two snapshots, sorted by path; content is only read
+/// when `hunk_context` asks for hunks
+fn snapshot_json_changes(
+    object_store: &ObjectStore,
+    old: &Snapshot,
+    new: &Snapshot,
+    ignore_mode: bool,
+    hunk_context: Option<usize>,
+) -> Result<Vec<JsonChange>> {
+    let files1 = files_to_map(&old.files);
+    let files2 = files_to_map(&new.files);
+    let paths: BTreeSet<&str> = files1.keys().chain(files2.keys()).copied().collect();
+
+    let mut changes = Vec::new();
+    for path in paths {
+        let (file1, file2) = (files1.get(path).copied(), files2.get(path).copied());
+        if let (Some(file1), Some(file2)) = (file1, file2) {
+            let change = FileChange::between(
+                &file1.hash,
+                file1.mode.as_deref(),
+                &file2.hash,
+                file2.mode.as_deref(),
+            );
+            if !change.is_reported(ignore_mode) {
+                continue;
+            }
+        }
+
+        let mut change = JsonChange::new(path, file1, file2);
+        if let Some(context) = hunk_context {
+            let known_binary = file1.into_iter().chain(file2).any(|f| f.is_known_binary());
+            change = if known_binary {
+                change.binary()
+            } else {
+                let content1 =
+                    retrieve_or_empty(object_store, path, file1.map(|f| f.hash.as_str()))?;
+                let content2 =
+                    retrieve_or_empty(object_store, path, file2.map(|f| f.hash.as_str()))?;
+                change.with_hunks(&content1, &content2, context)
+            };
+        }
+        changes.push(change);
+    }
+    Ok(changes)
+}
+
+/// Changed files between a snapshot and the working directory, sorted by
+/// path
+fn working_dir_json_changes(
+    project_root: &Path,
+    ignore_filter: &IgnoreFilter,
+    path_filter: &PathFilter,
+    snapshot: &Snapshot,
+    object_store: &ObjectStore,
+    hunk_context: Option<usize>,
+) -> Result<Vec<JsonChange>> {
+    let mut changes = Vec::new();
+    for change in working_dir_changes(project_root, ignore_filter, path_filter, snapshot)? {
+        let current = change.new.as_ref().map(|file| FileEntry {
+            path: change.path.clone(),
+            hash: file.hash.clone(),
+            size: file.content.len() as u64,
+            mode: None,
+            kind: None,
+        });
+        let json = JsonChange::new(&change.path, change.old, current.as_ref());
+        changes.push(match hunk_context {
+            Some(_) if change.old.is_some_and(|file| file.is_known_binary()) => json.binary(),
+            Some(context) => {
+                let old_hash = change.old.map(|file| file.hash.as_str());
+                let content1 = retrieve_or_empty(object_store, &change.path, old_hash)?;
+                json.with_hunks(&content1, change.new_content(), context)
+            }
+            None => json,
+        });
+    }
+    Ok(changes)
+}
+
+/// Role of a piece of diff text, used to pick its color
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LineKind {
+    Plain,
+    FileHeader,
+    HunkHeader,
+    Context,
+    Insert,
+    Delete,
+    /// Words removed from a modified line (`--word-diff`)
+    WordDelete,
+    /// Words added to a modified line (`--word-diff`)
+    WordInsert,
+}
+
+/// Diff text built up with the role of each line, so that the same diff can
+/// be rendered plain (files, `Display`) or colored (terminals).
+/// `write!`/`writeln!` append plain text.
+#[derive(Debug, Default)]
+pub(crate) struct DiffOutput {
+    segments: Vec<(LineKind, String)>,
+    /// Write `diff --git` headers with file modes (`--git-format`)
+    git_headers: bool,
+    /// Render modified lines word by word (`--word-diff`)
+    word_diff: bool,
+    /// Per-path note on where the file last changed (`--range`)
+    last_changed: HashMap<String, String>,
+}
+
+impl DiffOutput {
+    pub(crate) fn push(&mut self, kind: LineKind, text: impl Into<String>) {
+        self.segments.push((kind, text.into()));
+    }
+
+    /// First line of a file's diff: `diff --mote a/<path> b/<path>`, or
+    /// `diff --git` with git headers
+    fn push_file_header(&mut self, path: &str) {
+        // Text between files is ignored by patch tools
+        if let Some(note) = self.last_changed.get(path) {
+            let note = format!("# {}: last changed in {}\n", path, note);
+            self.push(LineKind::Plain, note);
+        }
+        let tool = if self.git_headers { "git" } else { "mote" };
+        self.push(
+            LineKind::FileHeader,
+            format!("diff --{} a/{} b/{}\n", tool, path, path),
+        );
+    }
+
+    /// A `--name-only` line: status and path, then where the file last
+    /// changed (`--range`)
+    fn push_name(&mut self, status: &str, path: &str) {
+        let line = match self.last_changed.get(path) {
+            Some(note) => format!("{}\t{}\t{}\n", status, path, note),
+            None => format!("{}\t{}\n", status, path),
+        };
+        self.push(LineKind::Plain, line);
+    }
+
+    /// Plain text, or ANSI-colored text when `color` is set
+    pub(crate) fn render(&self, color: bool) -> String {
+        if !color {
+            return self.to_string();
+        }
+
+        let mut rendered = String::new();
+        for (kind, text) in &self.segments {
+            // Color the line itself, not its newline
+            let body = text.trim_end_matches('\n');
+            let colored = match kind {
+                LineKind::Plain | LineKind::Context => body.normal(),
+                LineKind::FileHeader => body.bold(),
+                LineKind::HunkHeader => body.cyan(),
+                LineKind::Insert | LineKind::WordInsert => body.green(),
+                LineKind::Delete | LineKind::WordDelete => body.red(),
+            };
+            rendered.push_str(&colored.to_string());
+            rendered.push_str(&text[body.len()..]);
+        }
+        rendered
+    }
+}
+
+impl std::fmt::Write for DiffOutput {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        self.push(LineKind::Plain, s);
+        Ok(())
+    }
+}
+
+/// Uncolored word changes are wrapped in markers, as `git diff --word-diff` does
+impl std::fmt::Display for DiffOutput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (kind, text) in &self.segments {
+            match kind {
+                LineKind::WordDelete => write!(f, "[-{}-]", text)?,
+                LineKind::WordInsert => write!(f, "{{+{}+}}", text)?,
+                _ => f.write_str(text)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How a file present on both sides of a comparison differs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FileChange {
+    Unchanged,
+    Content,
+    Mode,
+    ContentAndMode,
+}
+
+impl FileChange {
+    /// Compare content hashes, and modes when both sides recorded one
+    pub(crate) fn between(
+        old_hash: &str,
+        old_mode: Option<&str>,
+        new_hash: &str,
+        new_mode: Option<&str>,
+    ) -> Self {
+        let content = old_hash != new_hash;
+        let mode = matches!((old_mode, new_mode), (Some(old), Some(new)) if old != new);
+        match (content, mode) {
+            (false, false) => FileChange::Unchanged,
+            (true, false) => FileChange::Content,
+            (false, true) => FileChange::Mode,
+            (true, true) => FileChange::ContentAndMode,
+        }
+    }
+
+    /// The change with any content difference discarded
+    pub(crate) fn without_content(self) -> Self {
+        match self {
+            FileChange::Content => FileChange::Unchanged,
+            FileChange::ContentAndMode => FileChange::Mode,
+            other => other,
+        }
+    }
+
+    /// Whether the change shows up in diff output; mode-only changes are
+    /// dropped when `ignore_mode` is set
+    pub(crate) fn is_reported(self, ignore_mode: bool) -> bool {
+        match self {
+            FileChange::Unchanged => false,
+            FileChange::Mode => !ignore_mode,
+            FileChange::Content | FileChange::ContentAndMode => true,
+        }
+    }
+}
+
+/// `git diff`-style header for a file whose content is unchanged but whose
+/// mode differs
+fn write_mode_change(path: &str, old_mode: &str, new_mode: &str, output: &mut DiffOutput) {
+    output.push_file_header(path);
+    if output.git_headers {
+        writeln!(output, "old mode {}", git_mode(old_mode)).unwrap();
+        writeln!(output, "new mode {}", git_mode(new_mode)).unwrap();
     } else {
-        diff_with_working_dir(
-            ctx.project_root,
-            &ctx.ignore_file_path,
-            &snapshot1,
-            &object_store,
-            name_only,
-            unified,
-            &mut diff_output,
-        )?;
+        writeln!(output, "old mode {}", old_mode).unwrap();
+        writeln!(output, "new mode {}", new_mode).unwrap();
     }
+}
 
-    if let Some(output_file) = output {
-        fs::write(&output_file, &diff_output)?;
-        println!("Diff written to {}", output_file.cyan());
+/// Mode git records for regular files when the snapshot has none
+const GIT_FILE_MODE: &str = "100644";
+
+/// Permission bits (`755`) as a git regular-file mode (`100755`)
+fn git_mode(mode: &str) -> String {
+    if mode.len() <= 4 {
+        format!("100{:0>3}", mode.trim_start_matches('0'))
     } else {
-        print!("{}", diff_output);
+        mode.to_string()
     }
+}
 
-    Ok(())
+/// How `diff_snapshots` and `diff_with_working_dir` write a diff, with the
+/// configuration already applied to the `snap diff` flags
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct TextDiffOptions {
+    /// Only list the changed files
+    pub name_only: bool,
+    /// Context lines around each hunk
+    pub unified: usize,
+    /// Hide files whose only change is their mode
+    pub ignore_mode: bool,
+    pub whitespace: Whitespace,
+    pub tz: DisplayTimezone,
 }
 
-fn files_to_map(files: &[FileEntry]) -> HashMap<&str, &FileEntry> {
-    files.iter().map(|f| (f.path.as_str(), f)).collect()
+impl Default for TextDiffOptions {
+    fn default() -> Self {
+        Self {
+            name_only: false,
+            unified: 3,
+            ignore_mode: false,
+            whitespace: Whitespace::default(),
+            tz: DisplayTimezone::default(),
+        }
+    }
 }
 
-fn diff_snapshots(
+/// Write the differences between two snapshots; returns whether there were any
+pub(crate) fn diff_snapshots(
     snapshot1: &Snapshot,
     snapshot2: &Snapshot,
     object_store: &ObjectStore,
-    name_only: bool,
-    unified: usize,
-    output: &mut String,
-) -> Result<()> {
+    opts: &TextDiffOptions,
+    output: &mut DiffOutput,
+) -> Result<bool> {
+    let TextDiffOptions {
+        name_only,
+        unified,
+        ignore_mode,
+        whitespace,
+        tz,
+    } = *opts;
     writeln!(
         output,
-        "Comparing {} -> {}",
+        "Comparing {} ({}) -> {} ({})",
         snapshot1.short_id(),
-        snapshot2.short_id()
+        format_timestamp(&snapshot1.timestamp, tz, true),
+        snapshot2.short_id(),
+        format_timestamp(&snapshot2.timestamp, tz, true)
     )
     .unwrap();
     writeln!(output).unwrap();
 
     let files1 = files_to_map(&snapshot1.files);
     let files2 = files_to_map(&snapshot2.files);
+    let paths: BTreeSet<&str> = files1.keys().chain(files2.keys()).copied().collect();
+    let mut changed = false;
 
-    for (path, file2) in &files2 {
-        if let Some(file1) = files1.get(path) {
-            if file1.hash != file2.hash {
+    for path in paths {
+        match (files1.get(path), files2.get(path)) {
+            (Some(file1), Some(file2)) => {
+                let mut change = FileChange::between(
+                    &file1.hash,
+                    file1.mode.as_deref(),
+                    &file2.hash,
+                    file2.mode.as_deref(),
+                );
+                let known_binary = file1.is_known_binary() || file2.is_known_binary();
+                if whitespace.is_active() && change.without_content() != change && !known_binary {
+                    let content1 = retrieve_or_empty(object_store, path, Some(&file1.hash))?;
+                    let content2 = retrieve_or_empty(object_store, path, Some(&file2.hash))?;
+                    if !whitespace.differs(&content1, &content2) {
+                        change = change.without_content();
+                    }
+                }
+                if !change.is_reported(ignore_mode) {
+                    continue;
+                }
+                changed = true;
+                if change == FileChange::Mode && name_only {
+                    output.push_name("M(mode)", path);
+                } else if change == FileChange::Mode {
+                    write_mode_change(
+                        path,
+                        file1.mode.as_deref().unwrap_or_default(),
+                        file2.mode.as_deref().unwrap_or_default(),
+                        output,
+                    );
+                } else if name_only {
+                    output.push_name("M", path);
+                } else if known_binary {
+                    write_binary_diff(path, file1.size, file2.size, output);
+                } else {
+                    generate_unified_diff(
+                        object_store,
+                        path,
+                        Some(&file1.hash),
+                        Some(&file2.hash),
+                        unified,
+                        whitespace,
+                        output,
+                    )?;
+                }
+            }
+            (None, Some(file2)) => {
+                changed = true;
                 if name_only {
-                    writeln!(output, "M\t{}", path).unwrap();
+                    output.push_name("A", path);
+                } else if file2.is_known_binary() {
+                    write_binary_diff(path, 0, file2.size, output);
                 } else {
                     generate_unified_diff(
                         object_store,
                         path,
-                        &file1.hash,
-                        &file2.hash,
+                        None,
+                        Some(&file2.hash),
                         unified,
+                        whitespace,
                         output,
                     )?;
                 }
             }
-        } else if name_only {
-            writeln!(output, "A\t{}", path).unwrap();
-        } else {
-            generate_unified_diff(object_store, path, "", &file2.hash, unified, output)?;
-        }
-    }
-
-    for path in files1.keys() {
-        if !files2.contains_key(path) {
-            if name_only {
-                writeln!(output, "D\t{}", path).unwrap();
-            } else {
-                let file1 = files1.get(path).unwrap();
-                generate_unified_diff(object_store, path, &file1.hash, "", unified, output)?;
+            (Some(file1), None) => {
+                changed = true;
+                if name_only {
+                    output.push_name("D", path);
+                } else if file1.is_known_binary() {
+                    write_binary_diff(path, file1.size, 0, output);
+                } else {
+                    generate_unified_diff(
+                        object_store,
+                        path,
+                        Some(&file1.hash),
+                        None,
+                        unified,
+                        whitespace,
+                        output,
+                    )?;
+                }
             }
+            (None, None) => unreachable!("path comes from one of the snapshots"),
         }
     }
-    Ok(())
+    Ok(changed)
 }
 
 fn diff_with_working_dir(
     project_root: &Path,
-    ignore_file_path: &Path,
+    ignore_filter: &IgnoreFilter,
+    path_filter: &PathFilter,
     snapshot: &Snapshot,
     object_store: &ObjectStore,
-    name_only: bool,
-    unified: usize,
-    output: &mut String,
-) -> Result<()> {
+    opts: &TextDiffOptions,
+    output: &mut DiffOutput,
+) -> Result<bool> {
+    // Modes of working-tree files aren't read, so there are no mode-only
+    // changes to hide
+    let TextDiffOptions {
+        name_only,
+        unified,
+        whitespace,
+        tz,
+        ..
+    } = *opts;
     writeln!(
         output,
-        "Comparing {} -> working directory",
-        snapshot.short_id()
+        "Comparing {} ({}) -> working directory",
+        snapshot.short_id(),
+        format_timestamp(&snapshot.timestamp, tz, true)
     )
     .unwrap();
     writeln!(output).unwrap();
 
-    let ignore_filter = IgnoreFilter::new(ignore_file_path);
-    let snapshot_files = files_to_map(&snapshot.files);
-    let mut current_files = HashSet::new();
-
-    for entry in ignore_filter.walk_files(project_root) {
-        let path = entry.path();
-        let relative_path = path
-            .strip_prefix(project_root)
-            .unwrap_or(path)
-            .to_string_lossy()
-            .to_string();
-
-        current_files.insert(relative_path.clone());
-
-        if let Some(snapshot_file) = snapshot_files.get(relative_path.as_str()) {
-            let current_content = match fs::read(path) {
-                Ok(content) => content,
-                Err(e) => {
-                    eprintln!(
-                        "{}: Failed to read {}: {}",
-                        "warning".yellow(),
-                        relative_path,
-                        e
-                    );
-                    continue;
-                }
-            };
-            let current_hash = ObjectStore::compute_hash(&current_content);
-            if current_hash != snapshot_file.hash {
-                if name_only {
-                    writeln!(output, "M\t{}", relative_path).unwrap();
-                } else {
-                    generate_unified_diff_with_content(
-                        object_store,
-                        &relative_path,
-                        &snapshot_file.hash,
-                        &current_content,
-                        unified,
-                        output,
-                    )?;
-                }
+    let mut changed = false;
+    for change in working_dir_changes(project_root, ignore_filter, path_filter, snapshot)? {
+        let path = change.path.as_str();
+        let binary = change.old.is_some_and(|file| file.is_known_binary());
+        let old_hash = change.old.map(|file| file.hash.as_str());
+        if whitespace.is_active() && change.old.is_some() && change.new.is_some() && !binary {
+            let stored = retrieve_or_empty(object_store, path, old_hash)?;
+            if !whitespace.differs(&stored, change.new_content()) {
+                continue;
             }
-        } else if name_only {
-            writeln!(output, "A\t{}", relative_path).unwrap();
+        }
+
+        changed = true;
+        let status = match (change.old, &change.new) {
+            (None, _) => 'A',
+            (_, None) => 'D',
+            _ => 'M',
+        };
+        if name_only {
+            writeln!(output, "{}\t{}", status, path).unwrap();
+        } else if binary {
+            let old_size = change.old.map_or(0, |file| file.size);
+            write_binary_diff(path, old_size, change.new_content().len() as u64, output);
         } else {
-            let current_content = match fs::read(path) {
-                Ok(content) => content,
-                Err(e) => {
-                    eprintln!(
-                        "{}: Failed to read {}: {}",
-                        "warning".yellow(),
-                        relative_path,
-                        e
-                    );
-                    continue;
-                }
-            };
             generate_unified_diff_with_content(
                 object_store,
-                &relative_path,
-                "",
-                &current_content,
+                path,
+                old_hash,
+                change.new.as_ref().map(|file| file.content.as_slice()),
                 unified,
+                whitespace,
                 output,
             )?;
         }
     }
-
-    for path in snapshot_files.keys() {
-        if !current_files.contains(*path) {
-            if name_only {
-                writeln!(output, "D\t{}", path).unwrap();
-            } else {
-                let file = snapshot_files.get(path).unwrap();
-                generate_unified_diff_with_content(
-                    object_store,
-                    path,
-                    &file.hash,
-                    &[],
-                    unified,
-                    output,
-                )?;
-            }
-        }
-    }
-    Ok(())
+    Ok(changed)
 }
 
+/// Unified diff between two stored objects; `None` is the side where the
+/// file doesn't exist
 fn generate_unified_diff(
     object_store: &ObjectStore,
     path: &str,
-    hash1: &str,
-    hash2: &str,
+    hash1: Option<&str>,
+    hash2: Option<&str>,
     context_lines: usize,
-    output: &mut String,
+    whitespace: Whitespace,
+    output: &mut DiffOutput,
 ) -> Result<()> {
-    let content2 = if hash2.is_empty() {
-        Vec::new()
-    } else {
-        match object_store.retrieve(hash2) {
-            Ok(c) => c,
-            Err(MoteError::ObjectNotFound(hash)) => {
-                eprintln!(
-                    "{}: Object not found for {}: {}",
-                    "warning".yellow(),
-                    path,
-                    hash
-                );
-                return Ok(());
-            }
-            Err(e) => return Err(e),
+    let content2 = match hash2.map(|hash| object_store.retrieve(hash)).transpose() {
+        Ok(content) => content,
+        Err(MoteError::ObjectNotFound(hash)) => {
+            eprintln!(
+                "{}: Object not found for {}: {}",
+                "warning".yellow(),
+                path,
+                hash
+            );
+            return Ok(());
         }
+        Err(e) => return Err(e),
     };
 
-    generate_unified_diff_with_content(object_store, path, hash1, &content2, context_lines, output)
+    generate_unified_diff_with_content(
+        object_store,
+        path,
+        hash1,
+        content2.as_deref(),
+        context_lines,
+        whitespace,
+        output,
+    )
 }
 
-fn generate_unified_diff_with_content(
+/// Unified diff from a stored object to `content2`. A missing side (`None`)
+/// is written as `/dev/null`, so added and deleted files apply as patches.
+pub(crate) fn generate_unified_diff_with_content(
     object_store: &ObjectStore,
     path: &str,
-    hash1: &str,
-    content2: &[u8],
+    hash1: Option<&str>,
+    content2: Option<&[u8]>,
     context_lines: usize,
-    output: &mut String,
+    whitespace: Whitespace,
+    output: &mut DiffOutput,
 ) -> Result<()> {
-    let content1 = if hash1.is_empty() {
-        Vec::new()
-    } else {
-        match object_store.retrieve(hash1) {
-            Ok(c) => c,
-            Err(MoteError::ObjectNotFound(hash)) => {
-                eprintln!(
-                    "{}: Object not found for {}: {}",
-                    "warning".yellow(),
-                    path,
-                    hash
-                );
-                Vec::new()
-            }
-            Err(e) => return Err(e),
-        }
-    };
+    let content1 = retrieve_or_empty(object_store, path, hash1)?;
+    let added = hash1.is_none();
+    let deleted = content2.is_none();
+    let content2 = content2.unwrap_or_default();
+
+    if content1.is_empty() && content2.is_empty() {
+        return Ok(());
+    }
+
+    // Snapshots recorded before content kinds were stored fall back to sniffing
+    if is_binary(&content1) || is_binary(content2) {
+        write_binary_diff(path, content1.len() as u64, content2.len() as u64, output);
+        return Ok(());
+    }
 
     let text1 = String::from_utf8_lossy(&content1);
     let text2 = String::from_utf8_lossy(content2);
-
-    if text1.is_empty() && text2.is_empty() {
+    let lines1: Vec<&str> = text1.split_inclusive('\n').collect();
+    let lines2: Vec<&str> = text2.split_inclusive('\n').collect();
+    let hunks = whitespace.hunks(&lines1, &lines2, context_lines);
+    // Everything that differed was filtered out by the whitespace options
+    if hunks.is_empty() && whitespace.is_active() {
         return Ok(());
     }
 
-    let diff = TextDiff::from_lines(&text1, &text2);
+    output.push_file_header(path);
+    if output.git_headers && added {
+        output.push(
+            LineKind::FileHeader,
+            format!("new file mode {}\n", GIT_FILE_MODE),
+        );
+    } else if output.git_headers && deleted {
+        output.push(
+            LineKind::FileHeader,
+            format!("deleted file mode {}\n", GIT_FILE_MODE),
+        );
+    }
+    let old_name = if added {
+        "/dev/null".to_string()
+    } else {
+        format!("a/{}", path)
+    };
+    let new_name = if deleted {
+        "/dev/null".to_string()
+    } else {
+        format!("b/{}", path)
+    };
+    output.push(LineKind::FileHeader, format!("--- {}\n", old_name));
+    output.push(LineKind::FileHeader, format!("+++ {}\n", new_name));
 
-    writeln!(output, "diff --mote a/{} b/{}", path, path).unwrap();
-    writeln!(output, "--- a/{}", path).unwrap();
-    writeln!(output, "+++ b/{}", path).unwrap();
-
-    for hunk in diff
-        .unified_diff()
-        .context_radius(context_lines)
-        .iter_hunks()
-    {
-        write!(output, "{}", hunk.header()).unwrap();
-        for change in hunk.iter_changes() {
-            let sign = match change.tag() {
-                ChangeTag::Delete => "-",
-                ChangeTag::Insert => "+",
-                ChangeTag::Equal => " ",
-            };
-            write!(output, "{}{}", sign, change.value()).unwrap();
+    for hunk in hunks {
+        let (Some(first), Some(last)) = (hunk.first(), hunk.last()) else {
+            continue;
+        };
+        output.push(
+            LineKind::HunkHeader,
+            format!(
+                "@@ -{} +{} @@\n",
+                unified_range(first.old_range().start..last.old_range().end),
+                unified_range(first.new_range().start..last.new_range().end)
+            ),
+        );
+        if output.word_diff {
+            write_word_diff_hunk(&hunk, &lines1, &lines2, output);
+            continue;
+        }
+        for op in &hunk {
+            for change in op.iter_changes(&lines1, &lines2) {
+                let (kind, sign) = match change.tag() {
+                    ChangeTag::Delete => (LineKind::Delete, "-"),
+                    ChangeTag::Insert => (LineKind::Insert, "+"),
+                    ChangeTag::Equal => (LineKind::Context, " "),
+                };
+                let line = change.value();
+                if line.ends_with('\n') {
+                    output.push(kind, format!("{}{}", sign, line));
+                } else {
+                    output.push(kind, format!("{}{}\n", sign, line));
+                    writeln!(output, "\\ No newline at end of file").unwrap();
+                }
+            }
         }
     }
 
     writeln!(output).unwrap();
     Ok(())
 }
+
+/// Hunk body for `--word-diff`: context lines as usual, and each run of
+/// changed lines as the new text with removed and added words marked
+fn write_word_diff_hunk(hunk: &[DiffOp], old: &[&str], new: &[&str], output: &mut DiffOutput) {
+    let mut i = 0;
+    while i < hunk.len() {
+        if hunk[i].tag() == DiffTag::Equal {
+            for line in &old[hunk[i].old_range()] {
+                output.push(LineKind::Context, format!(" {}", line));
+                if !line.ends_with('\n') {
+                    writeln!(output).unwrap();
+                }
+            }
+            i += 1;
+            continue;
+        }
+
+        // Deletions and insertions next to each other are one modification
+        let start = i;
+        while i < hunk.len() && hunk[i].tag() != DiffTag::Equal {
+            i += 1;
+        }
+        let old_text: String = hunk[start..i]
+            .iter()
+            .flat_map(|op| &old[op.old_range()])
+            .copied()
+            .collect();
+        let new_text: String = hunk[start..i]
+            .iter()
+            .flat_map(|op| &new[op.new_range()])
+            .copied()
+            .collect();
+
+        let words = TextDiff::from_words(old_text.as_str(), new_text.as_str());
+        // Adjacent words with the same change are marked together
+        let mut line: Vec<(LineKind, String)> = Vec::new();
+        for change in words.iter_all_changes() {
+            let kind = match change.tag() {
+                ChangeTag::Equal => LineKind::Context,
+                ChangeTag::Delete => LineKind::WordDelete,
+                ChangeTag::Insert => LineKind::WordInsert,
+            };
+            // Markers never span lines, so a change across lines is split
+            for piece in change.value().split_inclusive('\n') {
+                let text = piece.trim_end_matches('\n');
+                match line.last_mut() {
+                    Some((last, pending)) if *last == kind => pending.push_str(text),
+                    _ if !text.is_empty() => line.push((kind, text.to_string())),
+                    _ => {}
+                }
+                if text.len() < piece.len() {
+                    push_word_diff_line(std::mem::take(&mut line), output);
+                }
+            }
+        }
+        if !line.is_empty() {
+            push_word_diff_line(line, output);
+        }
+    }
+}
+
+fn push_word_diff_line(line: Vec<(LineKind, String)>, output: &mut DiffOutput) {
+    output.push(LineKind::Context, " ");
+    for (kind, text) in line {
+        output.push(kind, text);
+    }
+    writeln!(output).unwrap();
+}
+
+/// Line range as written in a unified diff hunk header: 1-based `start,len`,
+/// just `start` for one line, and the line before for an empty range
+fn unified_range(range: Range<usize>) -> String {
+    let len = range.len();
+    match len {
+        0 => format!("{},0", range.start),
+        1 => format!("{}", range.start + 1),
+        _ => format!("{},{}", range.start + 1, len),
+    }
+}
+
+/// Whitespace differences `snap diff` leaves out (`-w`, `--ignore-blank-lines`)
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct Whitespace {
+    /// Compare lines with all whitespace removed
+    pub ignore_all_space: bool,
+    /// Drop changes that only add or remove blank lines
+    pub ignore_blank_lines: bool,
+}
+
+impl Whitespace {
+    pub(crate) fn is_active(self) -> bool {
+        self.ignore_all_space || self.ignore_blank_lines
+    }
+
+    /// What a line is compared by; output always shows the original line
+    fn key(self, line: &str) -> Cow<'_, str> {
+        if self.ignore_all_space {
+            Cow::Owned(line.chars().filter(|c| !c.is_whitespace()).collect())
+        } else {
+            Cow::Borrowed(line)
+        }
+    }
+
+    /// An insertion or deletion of blank lines only, under --ignore-blank-lines
+    fn is_ignored(self, op: &DiffOp, old: &[&str], new: &[&str]) -> bool {
+        self.ignore_blank_lines
+            && old[op.old_range()]
+                .iter()
+                .chain(&new[op.new_range()])
+                .all(|line| line.trim().is_empty())
+    }
+
+    /// Hunks of line operations with `context` lines around each change.
+    /// Hunks whose changes are all ignored are dropped.
+    fn hunks(self, old: &[&str], new: &[&str], context: usize) -> Vec<Vec<DiffOp>> {
+        let old_keys: Vec<Cow<str>> = old.iter().map(|line| self.key(line)).collect();
+        let new_keys: Vec<Cow<str>> = new.iter().map(|line| self.key(line)).collect();
+        let ops = capture_diff_slices(Algorithm::Myers, &old_keys, &new_keys);
+        group_diff_ops(ops, context)
+            .into_iter()
+            .filter(|hunk| {
+                hunk.iter()
+                    .any(|op| op.tag() != DiffTag::Equal && !self.is_ignored(op, old, new))
+            })
+            .collect()
+    }
+
+    /// Whether two file contents still differ under these options; binary
+    /// content is compared byte for byte
+    pub(crate) fn differs(self, content1: &[u8], content2: &[u8]) -> bool {
+        if is_binary(content1) || is_binary(content2) {
+            return content1 != content2;
+        }
+        let text1 = String::from_utf8_lossy(content1);
+        let text2 = String::from_utf8_lossy(content2);
+        let lines1: Vec<&str> = text1.split_inclusive('\n').collect();
+        let lines2: Vec<&str> = text2.split_inclusive('\n').collect();
+        !self.hunks(&lines1, &lines2, 0).is_empty()
+    }
+}
+
+fn is_binary(content: &[u8]) -> bool {
+    FileKind::detect(content) == FileKind::Binary
+}
+
+/// Binary changes get a one-line summary instead of a patch
+fn write_binary_diff(path: &str, size1: u64, size2: u64, output: &mut DiffOutput) {
+    output.push_file_header(path);
+    writeln!(
+        output,
+        "Binary files a/{} and b/{} differ ({} -> {} bytes)",
+        path, path, size1, size2
+    )
+    .unwrap();
+    writeln!(output).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn entry(path: &str, hash: &str, size: u64, kind: Option<FileKind>) -> FileEntry {
+        FileEntry {
+            path: path.to_string(),
+            hash: hash.to_string(),
+            size,
+            mode: None,
+            kind,
+        }
+    }
+
+    fn diff(old: Vec<FileEntry>, new: Vec<FileEntry>, object_store: &ObjectStore) -> String {
+        let mut output = DiffOutput::default();
+        diff_snapshots(
+            &Snapshot::new(old, None, None),
+            &Snapshot::new(new, None, None),
+            object_store,
+            &TextDiffOptions {
+                tz: DisplayTimezone::Utc,
+                ..TextDiffOptions::default()
+            },
+            &mut output,
+        )
+        .unwrap();
+        output.to_string()
+    }
+
+    #[test]
+    fn test_known_binary_pairs_are_not_retrieved() {
+        // The store is empty: any retrieve would fail with ObjectNotFound and
+        // print a warning instead of the summary line
+        let temp = TempDir::new().unwrap();
+        let object_store = ObjectStore::new(temp.path().join("objects"));
+        let binary = Some(FileKind::Binary);
+
+        let output = diff(
+            vec![
+                entry("img.png", &"a".repeat(64), 100, binary),
+                entry("old.bin", &"c".repeat(64), 7, binary),
+            ],
+            vec![
+                entry("img.png", &"b".repeat(64), 120, binary),
+                entry("new.bin", &"d".repeat(64), 9, binary),
+            ],
+            &object_store,
+        );
+        assert!(output.contains("Binary files a/img.png and b/img.png differ (100 -> 120 bytes)"));
+        assert!(output.contains("Binary files a/new.bin and b/new.bin differ (0 -> 9 bytes)"));
+        assert!(output.contains("Binary files a/old.bin and b/old.bin differ (7 -> 0 bytes)"));
+
+        let stats = line_stats(
+            &object_store,
+            Some(&Snapshot::new(
+                vec![entry("img.png", &"a".repeat(64), 100, binary)],
+                None,
+                None,
+            )),
+            &Snapshot::new(
+                vec![entry("img.png", &"b".repeat(64), 120, binary)],
+                None,
+                None,
+            ),
+        )
+        .unwrap();
+        assert_eq!(stats[0].lines, None);
+    }
+
+    #[test]
+    fn test_consecutive_diffs_decompress_each_object_once() {
+        let temp = TempDir::new().unwrap();
+        let object_store = ObjectStore::new(temp.path().join("objects"));
+        let versions: Vec<FileEntry> = ["one\n", "two\n", "three\n"]
+            .iter()
+            .map(|content| {
+                let (hash, _) = object_store.store(content.as_bytes()).unwrap();
+                entry("a.txt", &hash, content.len() as u64, None)
+            })
+            .collect();
+
+        // A script diffing consecutive snapshots; the middle version is on
+        // both sides, and also copied to a second path
+        let mut copy = versions[1].clone();
+        copy.path = "copy.txt".to_string();
+        diff(
+            vec![versions[0].clone()],
+            vec![versions[1].clone(), copy.clone()],
+            &object_store,
+        );
+        diff(
+            vec![versions[1].clone(), copy],
+            vec![versions[2].clone()],
+            &object_store,
+        );
+        assert_eq!(object_store.decompressions(), 3);
+    }
+
+    #[test]
+    fn test_unknown_kind_falls_back_to_sniffing() {
+        let temp = TempDir::new().unwrap();
+        let object_store = ObjectStore::new(temp.path().join("objects"));
+        let (hash1, _) = object_store.store(b"\x00\x01").unwrap();
+        let (hash2, _) = object_store.store(b"\x00\x02\x03").unwrap();
+        let (text1, _) = object_store.store(b"one\n").unwrap();
+        let (text2, _) = object_store.store(b"two\n").unwrap();
+
+        let output = diff(
+            vec![
+                entry("a.bin", &hash1, 2, None),
+                entry("a.txt", &text1, 4, None),
+            ],
+            vec![
+                entry("a.bin", &hash2, 3, None),
+                entry("a.txt", &text2, 4, None),
+            ],
+            &object_store,
+        );
+        assert!(output.contains("Binary files a/a.bin and b/a.bin differ (2 -> 3 bytes)"));
+        assert!(output.contains("-one\n+two\n"));
+    }
+
+    #[test]
+    fn test_file_change_between() {
+        let cases = [
+            ("a", "a", FileChange::Unchanged),
+            ("a", "b", FileChange::Content),
+        ];
+        for (old_hash, new_hash, expected) in cases {
+            assert_eq!(
+                FileChange::between(old_hash, Some("644"), new_hash, Some("644")),
+                expected
+            );
+        }
+        assert_eq!(
+            FileChange::between("a", Some("644"), "a", Some("755")),
+            FileChange::Mode
+        );
+        assert_eq!(
+            FileChange::between("a", Some("644"), "b", Some("755")),
+            FileChange::ContentAndMode
+        );
+        // A side without a recorded mode never reports a mode change
+        assert_eq!(
+            FileChange::between("a", None, "a", Some("755")),
+            FileChange::Unchanged
+        );
+    }
+
+    #[test]
+    fn test_file_change_is_reported() {
+        for ignore_mode in [false, true] {
+            assert!(!FileChange::Unchanged.is_reported(ignore_mode));
+            assert!(FileChange::Content.is_reported(ignore_mode));
+            assert!(FileChange::ContentAndMode.is_reported(ignore_mode));
+        }
+        assert!(FileChange::Mode.is_reported(false));
+        assert!(!FileChange::Mode.is_reported(true));
+    }
+
+    #[test]
+    fn test_mode_only_changes() {
+        let temp = TempDir::new().unwrap();
+        let object_store = ObjectStore::new(temp.path().join("objects"));
+        let with_mode = |mode: &str| FileEntry {
+            mode: Some(mode.to_string()),
+            ..entry("run.sh", "abc", 3, None)
+        };
+        let diff_with = |name_only: bool, ignore_mode: bool| {
+            let mut output = DiffOutput::default();
+            diff_snapshots(
+                &Snapshot::new(vec![with_mode("644")], None, None),
+                &Snapshot::new(vec![with_mode("755")], None, None),
+                &object_store,
+                &TextDiffOptions {
+                    name_only,
+                    ignore_mode,
+                    tz: DisplayTimezone::Utc,
+                    ..TextDiffOptions::default()
+                },
+                &mut output,
+            )
+            .unwrap();
+            output.to_string()
+        };
+
+        assert!(diff_with(true, false).contains("M(mode)\trun.sh\n"));
+        assert!(diff_with(false, false).contains("old mode 644\nnew mode 755\n"));
+        assert!(!diff_with(true, true).contains("run.sh"));
+        assert!(!diff_with(false, true).contains("run.sh"));
+    }
+
+    #[test]
+    fn test_path_filter_matches_paths_at_or_under() {
+        let root = Path::new("/project");
+        let filter = PathFilter::new(
+            root,
+            &["./src/".to_string(), "/project/docs/a.md".to_string()],
+        );
+        assert!(filter.matches("src"));
+        assert!(filter.matches("src/main.rs"));
+        assert!(filter.matches("src/cmd/run.rs"));
+        assert!(!filter.matches("srcs/main.rs"));
+        assert!(filter.matches("docs/a.md"));
+        assert!(!filter.matches("docs/b.md"));
+
+        assert!(PathFilter::new(root, &[]).matches("anything"));
+        assert!(PathFilter::new(root, &[".".to_string()]).matches("anything"));
+    }
+
+    #[test]
+    fn test_working_dir_renderings_agree() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().join("project");
+        let object_store = ObjectStore::new(temp.path().join("objects"));
+        let stored = |path: &str, content: &[u8]| {
+            let (hash, _) = object_store.store(content).unwrap();
+            entry(path, &hash, content.len() as u64, None)
+        };
+        let snapshot = Snapshot::new(
+            vec![
+                stored("same.txt", b"same\n"),
+                stored("edited.txt", b"one\n"),
+                stored("gone.txt", b"gone\n"),
+            ],
+            None,
+            None,
+        );
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("same.txt"), "same\n").unwrap();
+        fs::write(root.join("edited.txt"), "one\ntwo\n").unwrap();
+        fs::write(root.join("new.txt"), "new\n").unwrap();
+        let ignore_filter = IgnoreFilter::new(&[]);
+        let path_filter = PathFilter::new(&root, &[]);
+
+        let stats = working_dir_line_stats(
+            &root,
+            &ignore_filter,
+            &path_filter,
+            &snapshot,
+            &object_store,
+        )
+        .unwrap();
+        assert_eq!(
+            stats,
+            [
+                FileLineStat {
+                    path: "edited.txt".to_string(),
+                    lines: Some((1, 0)),
+                },
+                FileLineStat {
+                    path: "gone.txt".to_string(),
+                    lines: Some((0, 1)),
+                },
+                FileLineStat {
+                    path: "new.txt".to_string(),
+                    lines: Some((1, 0)),
+                },
+            ]
+        );
+
+        let json = working_dir_json_changes(
+            &root,
+            &ignore_filter,
+            &path_filter,
+            &snapshot,
+            &object_store,
+            None,
+        )
+        .unwrap();
+        let statuses: Vec<(&str, &str)> = json
+            .iter()
+            .map(|change| (change.path.as_str(), change.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            [
+                ("edited.txt", "modified"),
+                ("gone.txt", "deleted"),
+                ("new.txt", "added"),
+            ]
+        );
+
+        let mut output = DiffOutput::default();
+        let changed = diff_with_working_dir(
+            &root,
+            &ignore_filter,
+            &path_filter,
+            &snapshot,
+            &object_store,
+            &TextDiffOptions {
+                name_only: true,
+                ..TextDiffOptions::default()
+            },
+            &mut output,
+        )
+        .unwrap();
+        assert!(changed);
+        assert!(output
+            .to_string()
+            .ends_with("M\tedited.txt\nD\tgone.txt\nA\tnew.txt\n"));
+    }
+
+    #[test]
+    fn test_diff_output_records_line_kinds() {
+        let temp = TempDir::new().unwrap();
+        let object_store = ObjectStore::new(temp.path().join("objects"));
+        let (old, _) = object_store.store(b"keep\nold\n").unwrap();
+
+        let mut output = DiffOutput::default();
+        generate_unified_diff_with_content(
+            &object_store,
+            "a.txt",
+            Some(&old),
+            Some(b"keep\nnew\n"),
+            3,
+            Whitespace::default(),
+            &mut output,
+        )
+        .unwrap();
+
+        let kind_of = |text: &str| {
+            output
+                .segments
+                .iter()
+                .find(|(_, t)| t == text)
+                .map(|(kind, _)| *kind)
+        };
+        assert_eq!(kind_of("--- a/a.txt\n"), Some(LineKind::FileHeader));
+        assert_eq!(kind_of("@@ -1,2 +1,2 @@\n"), Some(LineKind::HunkHeader));
+        assert_eq!(kind_of(" keep\n"), Some(LineKind::Context));
+        assert_eq!(kind_of("-old\n"), Some(LineKind::Delete));
+        assert_eq!(kind_of("+new\n"), Some(LineKind::Insert));
+
+        let plain = output.render(false);
+        assert_eq!(plain, output.to_string());
+        assert!(plain.contains(" keep\n-old\n+new\n"));
+        assert!(!plain.contains('\x1b'));
+    }
+
+    #[test]
+    fn test_json_hunks_line_numbers() {
+        let hunks = json_hunks(b"a\nb\nc\n", b"a\nB\nc\n", 1);
+        assert_eq!(hunks.len(), 1);
+        let hunk = &hunks[0];
+        assert_eq!(
+            (
+                hunk.old_start,
+                hunk.old_lines,
+                hunk.new_start,
+                hunk.new_lines
+            ),
+            (1, 3, 1, 3)
+        );
+        let lines: Vec<_> = hunk
+            .lines
+            .iter()
+            .map(|l| (l.tag, l.old_line, l.new_line, l.text.as_str()))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                ("context", Some(1), Some(1), "a"),
+                ("delete", Some(2), None, "b"),
+                ("insert", None, Some(2), "B"),
+                ("context", Some(3), Some(3), "c"),
+            ]
+        );
+
+        // An empty side starts at line 0, as in `@@ -0,0 +1 @@`
+        let hunks = json_hunks(b"", b"x\n", 3);
+        assert_eq!(
+            (
+                hunks[0].old_start,
+                hunks[0].old_lines,
+                hunks[0].new_start,
+                hunks[0].new_lines
+            ),
+            (0, 0, 1, 1)
+        );
+    }
+
+    #[test]
+    fn test_ignore_all_space_hides_indentation_changes() {
+        let temp = TempDir::new().unwrap();
+        let object_store = ObjectStore::new(temp.path().join("objects"));
+        let (old, _) = object_store.store(b"fn main() {\n    run();\n}\n").unwrap();
+        let (reindented, _) = object_store.store(b"fn main() {\n\trun();\n}\n").unwrap();
+        let (edited, _) = object_store
+            .store(b"fn main() {\n\trun();\n}\nfn run() {}\n")
+            .unwrap();
+        let diff_with = |new: &str, name_only: bool, whitespace: Whitespace| {
+            let mut output = DiffOutput::default();
+            let changed = diff_snapshots(
+                &Snapshot::new(vec![entry("main.rs", &old, 24, None)], None, None),
+                &Snapshot::new(vec![entry("main.rs", new, 24, None)], None, None),
+                &object_store,
+                &TextDiffOptions {
+                    name_only,
+                    whitespace,
+                    tz: DisplayTimezone::Utc,
+                    ..TextDiffOptions::default()
+                },
+                &mut output,
+            )
+            .unwrap();
+            (changed, output.to_string())
+        };
+        let ignore_space = Whitespace {
+            ignore_all_space: true,
+            ..Whitespace::default()
+        };
+
+        let (changed, output) = diff_with(&reindented, false, Whitespace::default());
+        assert!(changed);
+        assert!(output.contains("-    run();\n+\trun();\n"));
+
+        for name_only in [false, true] {
+            let (changed, output) = diff_with(&reindented, name_only, ignore_space);
+            assert!(!changed);
+            assert!(!output.contains("main.rs"), "{}", output);
+        }
+
+        // A real change is still shown; the reindented line is context
+        let (changed, output) = diff_with(&edited, false, ignore_space);
+        assert!(changed);
+        assert!(output.contains("@@ -1,3 +1,4 @@\n"));
+        assert!(output.contains("     run();\n }\n+fn run() {}\n"));
+        assert!(!output.contains("-    run();"));
+    }
+
+    #[test]
+    fn test_ignore_blank_lines() {
+        let whitespace = Whitespace {
+            ignore_blank_lines: true,
+            ..Whitespace::default()
+        };
+        assert!(!whitespace.differs(b"a\nb\n", b"a\n\n  \nb\n"));
+        assert!(!whitespace.differs(b"a\n\nb\n", b"a\nb\n"));
+        assert!(whitespace.differs(b"a\nb\n", b"a\n\nc\n"));
+        // Whitespace inside lines still counts without -w
+        assert!(whitespace.differs(b"a b\n", b"a  b\n"));
+        assert!(Whitespace::default().differs(b"a\n", b"a\n\n"));
+    }
+
+    #[test]
+    fn test_unified_range() {
+        assert_eq!(unified_range(0..0), "0,0");
+        assert_eq!(unified_range(4..4), "4,0");
+        assert_eq!(unified_range(0..1), "1");
+        assert_eq!(unified_range(2..5), "3,3");
+    }
+
+    #[test]
+    fn test_patch_headers_for_added_and_deleted_files() {
+        let temp = TempDir::new().unwrap();
+        let object_store = ObjectStore::new(temp.path().join("objects"));
+        let (old, _) = object_store.store(b"old\n").unwrap();
+        let write = |hash1: Option<&str>, content2: Option<&[u8]>, git_headers: bool| {
+            let mut output = DiffOutput {
+                git_headers,
+                ..DiffOutput::default()
+            };
+            generate_unified_diff_with_content(
+                &object_store,
+                "f.txt",
+                hash1,
+                content2,
+                3,
+                Whitespace::default(),
+                &mut output,
+            )
+            .unwrap();
+            output.to_string()
+        };
+
+        let added = write(None, Some(b"new\n"), false);
+        assert!(added.starts_with("diff --mote a/f.txt b/f.txt\n--- /dev/null\n+++ b/f.txt\n"));
+        assert!(added.contains("@@ -0,0 +1 @@\n+new\n"));
+
+        let deleted = write(Some(&old), None, true);
+        assert!(deleted.starts_with(
+            "diff --git a/f.txt b/f.txt\ndeleted file mode 100644\n--- a/f.txt\n+++ /dev/null\n"
+        ));
+
+        // Emptied, not deleted
+        let emptied = write(Some(&old), Some(b""), true);
+        assert!(emptied.contains("--- a/f.txt\n+++ b/f.txt\n"));
+        assert!(!emptied.contains("deleted file mode"));
+
+        let added = write(None, Some(b"new\n"), true);
+        assert!(added.contains("new file mode 100644\n--- /dev/null\n"));
+    }
+
+    #[test]
+    fn test_missing_trailing_newline_marker() {
+        let temp = TempDir::new().unwrap();
+        let object_store = ObjectStore::new(temp.path().join("objects"));
+        let (old, _) = object_store.store(b"a\nb").unwrap();
+
+        let mut output = DiffOutput::default();
+        generate_unified_diff_with_content(
+            &object_store,
+            "f.txt",
+            Some(&old),
+            Some(b"a\nb\n"),
+            3,
+            Whitespace::default(),
+            &mut output,
+        )
+        .unwrap();
+        assert!(output
+            .to_string()
+            .contains(" a\n-b\n\\ No newline at end of file\n+b\n"));
+    }
+
+    #[test]
+    fn test_word_diff_marks_changed_words() {
+        let temp = TempDir::new().unwrap();
+        let object_store = ObjectStore::new(temp.path().join("objects"));
+        let (old, _) = object_store
+            .store(b"first\nctx\nlet x = 1;\ntail\nlast\n")
+            .unwrap();
+        let new = b"first\nctx\nlet x = 2;\ntail\nlast\nextra line";
+
+        let word_diff = |context: usize| {
+            let mut output = DiffOutput {
+                word_diff: true,
+                ..DiffOutput::default()
+            };
+            generate_unified_diff_with_content(
+                &object_store,
+                "f.txt",
+                Some(&old),
+                Some(new),
+                context,
+                Whitespace::default(),
+                &mut output,
+            )
+            .unwrap();
+            output
+        };
+
+        let output = word_diff(1);
+        assert!(output.to_string().ends_with(
+            "@@ -2,4 +2,5 @@\n ctx\n let x = [-1;-]{+2;+}\n tail\n last\n {+extra line+}\n\n"
+        ));
+        assert!(word_diff(0)
+            .to_string()
+            .contains("@@ -3 +3 @@\n let x = [-1;-]{+2;+}\n@@"));
+
+        let colored = output.render(true);
+        assert!(!colored.contains("[-"));
+        assert!(colored.contains(&"1;".red().to_string()));
+    }
+
+    #[test]
+    fn test_write_output_creates_dirs_and_keeps_existing_file() {
+        let temp = TempDir::new().unwrap();
+        let out = temp.path().join("review").join("changes.patch");
+        let output_file = out.to_string_lossy().to_string();
+        let mut opts = DiffOptions::default();
+
+        write_output(&output_file, &opts, b"first\n", false).unwrap();
+        let refused = write_output(&output_file, &opts, b"second\n", false);
+        assert!(matches!(refused, Err(MoteError::OutputExists(_))));
+        assert_eq!(fs::read_to_string(&out).unwrap(), "first\n");
+
+        opts.force_output = true;
+        write_output(&output_file, &opts, b"second\n", false).unwrap();
+        assert_eq!(fs::read_to_string(&out).unwrap(), "second\n");
+        assert_eq!(fs::read_dir(out.parent().unwrap()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_parse_range() {
+        let range = |old: &str, new: Option<&str>| Some((old.to_string(), new.map(String::from)));
+        assert_eq!(parse_range("abc123").unwrap(), None);
+        assert_eq!(
+            parse_range("abc123..def456").unwrap(),
+            range("abc123", Some("def456"))
+        );
+        assert_eq!(parse_range("abc123..").unwrap(), range("abc123", None));
+
+        for spec in ["..def456", "..", "a..b..c", "a...b", "a..b.."] {
+            assert!(
+                matches!(parse_range(spec), Err(MoteError::InvalidArguments(_))),
+                "{}",
+                spec
+            );
+        }
+        let err = parse_range("..def456").unwrap_err().to_string();
+        assert!(err.contains("a starting snapshot is required"), "{}", err);
+    }
+
+    #[test]
+    fn test_git_mode() {
+        assert_eq!(git_mode("644"), "100644");
+        assert_eq!(git_mode("0755"), "100755");
+        assert_eq!(git_mode("100755"), "100755");
+    }
+}
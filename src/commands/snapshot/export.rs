@@ -0,0 +1,181 @@
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use colored::*;
+use serde::Serialize;
+
+use crate::commands::CommandContext;
+use crate::error::{MoteError, Result};
+use crate::size_format::format_size;
+use crate::storage::{ObjectReferences, ObjectStore, Snapshot, SnapshotStore};
+
+#[derive(Serialize)]
+struct ExportManifest {
+    created_at: DateTime<Utc>,
+    snapshots: Vec<String>,
+    object_count: usize,
+    object_bytes: u64,
+}
+
+/// Destination of an export: a context-shaped directory or a tar archive of one
+enum ExportTarget {
+    Dir(PathBuf),
+    Tar(tar::Builder<File>),
+}
+
+impl ExportTarget {
+    fn create(output: &Path) -> Result<Self> {
+        let is_empty_dir = output.is_dir() && fs::read_dir(output)?.next().is_none();
+        if output.exists() && !is_empty_dir {
+            return Err(MoteError::InvalidArguments(format!(
+                "Output '{}' already exists",
+                output.display()
+            )));
+        }
+
+        if output.extension().is_some_and(|e| e == "tar") {
+            if let Some(parent) = output.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            Ok(Self::Tar(tar::Builder::new(File::create(output)?)))
+        } else {
+            fs::create_dir_all(output)?;
+            Ok(Self::Dir(output.to_path_buf()))
+        }
+    }
+
+    fn write(&mut self, relative_path: &Path, content: &[u8]) -> Result<()> {
+        match self {
+            Self::Dir(root) => {
+                let dest = root.join(relative_path);
+                if let Some(parent) = dest.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(dest, content)?;
+            }
+            Self::Tar(builder) => {
+                let mut header = tar::Header::new_gnu();
+                header.set_size(content.len() as u64);
+                header.set_mode(0o644);
+                header.set_mtime(Utc::now().timestamp().max(0) as u64);
+                header.set_cksum();
+                builder.append_data(&mut header, relative_path, content)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<()> {
+        if let Self::Tar(builder) = self {
+            builder.into_inner()?.sync_all()?;
+        }
+        Ok(())
+    }
+}
+
+pub fn cmd_export_store(
+    ctx: &CommandContext,
+    keep: Option<usize>,
+    ids: Vec<String>,
+    output: &Path,
+) -> Result<()> {
+    let location = ctx.resolve_location()?;
+    let snapshot_store = SnapshotStore::new(location.snapshots_dir());
+    let object_store = ctx.object_store(&location);
+
+    let snapshots = select_snapshots(&snapshot_store, keep, &ids)?;
+
+    let mut refs = ObjectReferences::new();
+    for snapshot in &snapshots {
+        refs.mark_from_snapshot(snapshot);
+    }
+    let mut hashes: Vec<&String> = refs.iter().collect();
+    hashes.sort();
+
+    let storage = Path::new("storage");
+    let mut target = ExportTarget::create(output)?;
+    let mut object_bytes = 0;
+
+    for hash in &hashes {
+        let compressed = object_store.retrieve_compressed(hash)?;
+        object_bytes += compressed.len() as u64;
+        target.write(
+            &storage
+                .join("objects")
+                .join(ObjectStore::relative_path(hash)),
+            &compressed,
+        )?;
+    }
+
+    for snapshot in &snapshots {
+        let json = serde_json::to_string_pretty(snapshot)?;
+        target.write(
+            &storage
+                .join("snapshots")
+                .join(SnapshotStore::file_name(snapshot)),
+            json.as_bytes(),
+        )?;
+    }
+
+    if ctx.ignore_file_path.exists() {
+        target.write(Path::new("ignore"), &fs::read(&ctx.ignore_file_path)?)?;
+    }
+
+    let manifest = ExportManifest {
+        created_at: Utc::now(),
+        snapshots: snapshots.iter().map(|s| s.id.clone()).collect(),
+        object_count: hashes.len(),
+        object_bytes,
+    };
+    target.write(
+        Path::new("manifest.json"),
+        serde_json::to_string_pretty(&manifest)?.as_bytes(),
+    )?;
+
+    if let ExportTarget::Dir(ref root) = target {
+        let exported = ObjectStore::new(root.join(storage).join("objects"));
+        for hash in &hashes {
+            exported.retrieve(hash)?;
+        }
+    }
+    target.finish()?;
+
+    println!(
+        "{} Exported {} snapshot(s) and {} object(s) ({}) to {}",
+        "✓".green().bold(),
+        snapshots.len(),
+        hashes.len(),
+        format_size(object_bytes, ctx.config.display.exact_sizes),
+        output.display().to_string().cyan()
+    );
+    Ok(())
+}
+
+fn select_snapshots(
+    snapshot_store: &SnapshotStore,
+    keep: Option<usize>,
+    ids: &[String],
+) -> Result<Vec<Snapshot>> {
+    if !ids.is_empty() {
+        return ids
+            .iter()
+            .map(|id| snapshot_store.resolve_ref(id))
+            .collect();
+    }
+
+    let keep = match keep {
+        Some(0) | None => {
+            return Err(MoteError::InvalidArguments(
+                "Specify --keep <N> (N > 0) or at least one --id".to_string(),
+            ))
+        }
+        Some(n) => n,
+    };
+
+    let snapshots = snapshot_store.list()?;
+    if snapshots.is_empty() {
+        return Err(MoteError::NoSnapshotsAvailable);
+    }
+    Ok(snapshots.into_iter().take(keep).collect())
+}
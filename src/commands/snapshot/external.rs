@@ -0,0 +1,89 @@
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use colored::*;
+
+use crate::error::{MoteError, Result};
+
+/// Directory for the files handed to an external diff tool; removed when
+/// dropped, so nothing is left behind on any return path
+pub(super) struct ScratchDir(PathBuf);
+
+impl ScratchDir {
+    pub(super) fn new() -> Result<Self> {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        let path = std::env::temp_dir().join(format!("mote-diff-{}-{}", std::process::id(), nanos));
+        fs::create_dir_all(&path)?;
+        Ok(Self(path))
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Split a tool command the way the shell would, like `EDITOR` for
+/// `mote ignore edit`
+pub(super) fn parse_tool(tool: &str) -> Result<Vec<String>> {
+    let parts = shell_words::split(tool)
+        .map_err(|e| MoteError::ConfigRead(format!("Failed to parse diff tool: {}", e)))?;
+    if parts.is_empty() {
+        return Err(MoteError::ConfigRead(
+            "Diff tool command is empty".to_string(),
+        ));
+    }
+    Ok(parts)
+}
+
+/// Run the tool on one file's old and new content, written under `a/` and
+/// `b/` in `scratch` so the file name (and extension) is kept. A missing side
+/// is an empty file. Returns false when the tool couldn't start or exited
+/// with anything but 0 or 1 (differences found), after warning.
+pub(super) fn run_tool(
+    tool: &[String],
+    scratch: &ScratchDir,
+    path: &str,
+    old: &[u8],
+    new: &[u8],
+) -> Result<bool> {
+    let old_path = scratch.0.join("a").join(path);
+    let new_path = scratch.0.join("b").join(path);
+    for (side, content) in [(&old_path, old), (&new_path, new)] {
+        if let Some(parent) = side.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(side, content)?;
+    }
+
+    // Keep our output ahead of the tool's
+    std::io::stdout().flush()?;
+    let status = Command::new(&tool[0])
+        .args(&tool[1..])
+        .arg(&old_path)
+        .arg(&new_path)
+        .status();
+    let _ = fs::remove_file(&old_path);
+    let _ = fs::remove_file(&new_path);
+
+    let failure = match status {
+        Ok(status) if matches!(status.code(), Some(0 | 1)) => return Ok(true),
+        Ok(status) => status.to_string(),
+        Err(e) => e.to_string(),
+    };
+    eprintln!(
+        "{}: Diff tool '{}' failed for {} ({}); showing the built-in diff",
+        "warning".yellow(),
+        tool[0],
+        path,
+        failure
+    );
+    Ok(false)
+}
@@ -0,0 +1,107 @@
+use std::path::Path;
+
+use colored::*;
+
+use crate::commands::CommandContext;
+use crate::error::Result;
+use crate::storage::{Snapshot, SnapshotStore, LATEST_ALIAS};
+
+/// Apply the permission bits recorded in a snapshot to project files whose
+/// mode has drifted. Content is never touched, files the snapshot doesn't
+/// list are left alone, and files missing from disk are only reported.
+pub fn cmd_fix_perms(ctx: &CommandContext, snapshot_id: Option<&str>, dry_run: bool) -> Result<()> {
+    let location = ctx.resolve_location()?;
+    let snapshot_store = SnapshotStore::new(location.snapshots_dir());
+    let snapshot = snapshot_store.resolve_ref(snapshot_id.unwrap_or(LATEST_ALIAS))?;
+    fix_perms(ctx.project_root, &snapshot, dry_run)
+}
+
+#[cfg(not(unix))]
+fn fix_perms(_project_root: &Path, _snapshot: &Snapshot, _dry_run: bool) -> Result<()> {
+    println!(
+        "{} fix-perms needs Unix permission bits; nothing changed",
+        "!".yellow().bold()
+    );
+    Ok(())
+}
+
+#[cfg(unix)]
+fn fix_perms(project_root: &Path, snapshot: &Snapshot, dry_run: bool) -> Result<()> {
+    use std::fs;
+    use std::io::ErrorKind;
+    use std::os::unix::fs::PermissionsExt;
+
+    let mut fixed = 0;
+    let mut missing = 0;
+    let mut unrecorded = 0;
+
+    for file in &snapshot.files {
+        let Some(recorded) = file.mode.as_deref().and_then(parse_mode) else {
+            unrecorded += 1;
+            continue;
+        };
+        let path = project_root.join(&file.path);
+        let metadata = match fs::symlink_metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                println!("  {}  {}", "missing".red(), file.path);
+                missing += 1;
+                continue;
+            }
+            Err(e) => return Err(e.into()),
+        };
+        // Symlink modes aren't meaningful and chmod would follow the link
+        if !metadata.is_file() {
+            continue;
+        }
+
+        let current = metadata.permissions().mode() & 0o7777;
+        if current == recorded {
+            continue;
+        }
+        println!("  {:o} -> {:o}  {}", current, recorded, file.path);
+        if !dry_run {
+            fs::set_permissions(&path, fs::Permissions::from_mode(recorded))?;
+        }
+        fixed += 1;
+    }
+
+    let mut summary = if dry_run {
+        format!("Would fix {} file(s)", fixed)
+    } else {
+        format!("{} Fixed {} file(s)", "✓".green().bold(), fixed)
+    };
+    summary.push_str(&format!(" from snapshot {}", snapshot.short_id().cyan()));
+    if missing > 0 {
+        summary.push_str(&format!(", {} missing", missing));
+    }
+    println!("{}", summary);
+    if unrecorded > 0 {
+        println!(
+            "{}",
+            format!("{} file(s) have no recorded mode", unrecorded).dimmed()
+        );
+    }
+    Ok(())
+}
+
+/// Permission bits of a recorded octal mode, with or without the file type
+/// bits (`755`, `100755`)
+#[cfg(unix)]
+fn parse_mode(mode: &str) -> Option<u32> {
+    u32::from_str_radix(mode, 8).ok().map(|mode| mode & 0o7777)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mode() {
+        assert_eq!(parse_mode("644"), Some(0o644));
+        assert_eq!(parse_mode("100755"), Some(0o755));
+        assert_eq!(parse_mode("4755"), Some(0o4755));
+        assert_eq!(parse_mode("rwx"), None);
+        assert_eq!(parse_mode(""), None);
+    }
+}
@@ -1,15 +1,42 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+
 use colored::*;
 
 use crate::commands::CommandContext;
 use crate::error::Result;
-use crate::storage::{delete_objects, list_all_objects, ObjectReferences, SnapshotStore};
+use crate::size_format::format_size;
+use crate::storage::{
+    delete_objects, list_all_objects, object_disk_size, object_is_raw, remove_stale_temp_files,
+    reset_gc_state, skip_recent_objects, ObjectReferences, Snapshot, SnapshotStore, StorageLock,
+    LOCK_WAIT,
+};
+
+/// Options for `mote snap gc`
+#[derive(Debug, Default)]
+pub struct GcOptions {
+    /// Report what would be deleted without deleting
+    pub dry_run: bool,
+    /// Show progress and every object
+    pub verbose: bool,
+    /// Also delete objects written within `gc.grace_period_secs`
+    pub no_grace: bool,
+}
 
-pub fn cmd_gc(ctx: &CommandContext, dry_run: bool, verbose: bool) -> Result<()> {
+pub fn cmd_gc(ctx: &CommandContext, opts: &GcOptions) -> Result<()> {
+    if !opts.dry_run {
+        ctx.ensure_writable()?;
+    }
     let location = ctx.resolve_location()?;
+    // A snapshot running alongside could reference objects as they're deleted
+    let _lock = (!opts.dry_run)
+        .then(|| StorageLock::acquire(&location, LOCK_WAIT))
+        .transpose()?;
     let snapshot_store = SnapshotStore::new(location.snapshots_dir());
     let objects_dir = location.objects_dir();
 
-    if verbose {
+    if opts.verbose {
         println!("{} Starting garbage collection...", "->".cyan().bold());
         println!("  Marking referenced objects...");
     }
@@ -20,24 +47,25 @@ pub fn cmd_gc(ctx: &CommandContext, dry_run: bool, verbose: bool) -> Result<()>
         refs.mark_from_snapshot(snapshot);
     }
 
-    if verbose {
+    if opts.verbose {
         println!(
             "  Found {} snapshots with {} unique objects",
             snapshots.len(),
             refs.referenced_count()
         );
+        print_compression(&objects_dir, &snapshots, ctx.config.display.exact_sizes)?;
         println!("  Scanning objects directory...");
     }
 
     let all_objects = list_all_objects(&objects_dir)?;
     let total_objects = all_objects.len();
 
-    let unreferenced: Vec<String> = all_objects
+    let mut unreferenced: Vec<String> = all_objects
         .into_iter()
         .filter(|hash| !refs.is_referenced(hash))
         .collect();
 
-    if verbose {
+    if opts.verbose {
         println!(
             "  Total objects: {}, Unreferenced: {}",
             total_objects,
@@ -45,41 +73,118 @@ pub fn cmd_gc(ctx: &CommandContext, dry_run: bool, verbose: bool) -> Result<()>
         );
     }
 
+    let grace_period_secs = if opts.no_grace {
+        0
+    } else {
+        ctx.config.gc.grace_period_secs
+    };
+    let recent = skip_recent_objects(&objects_dir, &mut unreferenced, grace_period_secs)?;
+
+    // Interrupted snapshot and index writes leave temp files next to them;
+    // those in the objects directory are collected as unreferenced objects
+    if !opts.dry_run {
+        let grace = Duration::from_secs(grace_period_secs);
+        let stale = remove_stale_temp_files(&location.snapshots_dir(), grace)
+            + location
+                .index_path()
+                .parent()
+                .map_or(0, |dir| remove_stale_temp_files(dir, grace));
+        if opts.verbose && stale > 0 {
+            println!("  Removed {} leftover temp file(s)", stale);
+        }
+    }
+
     if unreferenced.is_empty() {
-        println!("{} No unreferenced objects found", "✓".green().bold());
+        if !opts.dry_run {
+            reset_gc_state(&location)?;
+        }
+        if recent == 0 {
+            println!("{} No unreferenced objects found", "✓".green().bold());
+        }
+        print_recent(recent, grace_period_secs);
         return Ok(());
     }
 
-    if dry_run {
+    if opts.dry_run {
+        let exact = ctx.config.display.exact_sizes;
+        let mut total_bytes = 0;
+        for hash in &unreferenced {
+            let size = object_disk_size(&objects_dir, hash)?.unwrap_or(0);
+            total_bytes += size;
+            if opts.verbose {
+                println!(
+                    "  Would delete: {}  {}",
+                    hash.dimmed(),
+                    format_size(size, exact)
+                );
+            }
+        }
         println!(
-            "{} Would delete {} unreferenced object(s)",
+            "{} Would delete {} object(s), reclaiming {} (compressed)",
             "dry-run".cyan().bold(),
-            unreferenced.len()
+            unreferenced.len(),
+            format_size(total_bytes, exact)
         );
-        if verbose {
-            for hash in &unreferenced {
-                println!("  Would delete: {}", hash.dimmed());
-            }
-        }
+        print_recent(recent, grace_period_secs);
         return Ok(());
     }
 
-    let stats = delete_objects(&objects_dir, &unreferenced, verbose)?;
+    let stats = delete_objects(&objects_dir, &unreferenced, opts.verbose)?;
+    reset_gc_state(&location)?;
     println!(
         "{} Deleted {} object(s), reclaimed {}",
         "✓".green().bold(),
         stats.deleted_objects,
-        format_size(stats.deleted_bytes)
+        format_size(stats.deleted_bytes, ctx.config.display.exact_sizes)
     );
+    print_recent(recent, grace_period_secs);
 
     Ok(())
 }
 
-fn format_size(bytes: u64) -> String {
-    let kb = bytes as f64 / 1024.0;
-    if kb < 1024.0 {
-        format!("{:.2} KB", kb)
-    } else {
-        format!("{:.2} MB", kb / 1024.0)
+/// Note the unreferenced objects left alone by the grace period
+fn print_recent(recent: usize, grace_period_secs: u64) {
+    if recent > 0 {
+        println!(
+            "  Kept {} unreferenced object(s) written in the last {}s; a snapshot in progress \
+             may need them (--no-grace deletes them)",
+            recent, grace_period_secs
+        );
     }
 }
+
+/// Referenced content against what it takes on disk, and how many objects
+/// are stored raw because compressing them gained nothing
+fn print_compression(objects_dir: &Path, snapshots: &[Snapshot], exact: bool) -> Result<()> {
+    let sizes: HashMap<&str, u64> = snapshots
+        .iter()
+        .flat_map(|snapshot| &snapshot.files)
+        .map(|file| (file.hash.as_str(), file.size))
+        .collect();
+
+    let (mut objects, mut raw, mut content_bytes, mut stored_bytes) = (0, 0, 0, 0);
+    for (hash, size) in sizes {
+        let Some(stored) = object_disk_size(objects_dir, hash)? else {
+            continue;
+        };
+        objects += 1;
+        content_bytes += size;
+        stored_bytes += stored;
+        if object_is_raw(objects_dir, hash)? == Some(true) {
+            raw += 1;
+        }
+    }
+    if objects == 0 {
+        return Ok(());
+    }
+
+    println!(
+        "  Compression: {} of content stored in {} (ratio {:.2}), {} of {} object(s) raw",
+        format_size(content_bytes, exact),
+        format_size(stored_bytes, exact),
+        stored_bytes as f64 / content_bytes.max(1) as f64,
+        raw,
+        objects
+    );
+    Ok(())
+}
@@ -0,0 +1,65 @@
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use colored::*;
+
+use crate::error::{MoteError, Result};
+
+/// Run each hook command through the shell with `project_root` as CWD.
+///
+/// Output is inherited unless `quiet` is set. Stops at the first failing
+/// command and returns `HookFailed`.
+pub fn run_hooks(
+    commands: &[String],
+    project_root: &Path,
+    envs: &[(&str, String)],
+    quiet: bool,
+) -> Result<()> {
+    for command in commands {
+        let mut cmd = shell_command(command);
+        cmd.current_dir(project_root)
+            .envs(envs.iter().map(|(k, v)| (*k, v.as_str())));
+        if quiet {
+            cmd.stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null());
+        }
+
+        let status = cmd.status()?;
+        if !status.success() {
+            return Err(MoteError::HookFailed {
+                command: command.clone(),
+                status: status.to_string(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Run post-snapshot hooks; failures are reported but do not fail the snapshot
+pub fn run_post_hooks(
+    commands: &[String],
+    project_root: &Path,
+    envs: &[(&str, String)],
+    quiet: bool,
+) {
+    if let Err(e) = run_hooks(commands, project_root, envs, quiet) {
+        if !quiet {
+            eprintln!("{}: {}", "warning".yellow(), e);
+        }
+    }
+}
+
+#[cfg(not(windows))]
+fn shell_command(command: &str) -> Command {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(command);
+    cmd
+}
+
+#[cfg(windows)]
+fn shell_command(command: &str) -> Command {
+    let mut cmd = Command::new("cmd");
+    cmd.arg("/C").arg(command);
+    cmd
+}
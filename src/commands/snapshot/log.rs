@@ -0,0 +1,349 @@
+use chrono::Utc;
+use colored::*;
+use regex::{Regex, RegexBuilder};
+
+use super::diff::{change_stats, ChangeStats};
+use super::log_format::LogTemplate;
+use super::{format_optional_size, partial_marker, stdout_is_tty};
+use crate::commands::CommandContext;
+use crate::error::{MoteError, Result};
+use crate::path_resolver::snapshot_relative_path;
+use crate::storage::{SearchEntry, Snapshot, SnapshotStore};
+use crate::time_format::format_timestamp;
+use crate::time_spec::parse_time_spec;
+
+/// Options for `mote snap list`
+#[derive(Debug, Default)]
+pub struct LogOptions {
+    /// Explicit limit; when unset, see [`DEFAULT_LIMIT`]
+    pub limit: Option<usize>,
+    /// Show every snapshot regardless of the default limit
+    pub all: bool,
+    pub oneline: bool,
+    /// Only include snapshots whose metadata contains all these pairs
+    pub metadata: Vec<(String, String)>,
+    /// Print a JSON array instead of text
+    pub json: bool,
+    /// Include the full file list in JSON output
+    pub verbose: bool,
+    /// Only include snapshots at or after this time
+    pub since: Option<String>,
+    /// Only include snapshots at or before this time
+    pub until: Option<String>,
+    /// Only include snapshots whose message contains this text
+    pub grep: Option<String>,
+    /// Match `grep` as a regular expression instead of a substring
+    pub regex: bool,
+    /// Only include snapshots recorded by this trigger
+    pub trigger: Option<String>,
+    /// Show added/modified/deleted counts against the previous snapshot
+    pub stat: bool,
+    /// One-line-per-snapshot template (see `LogTemplate`)
+    pub format: Option<String>,
+    /// Only include snapshots where this file changed
+    pub follow: Option<String>,
+    /// List oldest first
+    pub reverse: bool,
+    /// Entries to skip after filtering and ordering
+    pub offset: usize,
+    /// Hide snapshots with the same content as the previous one
+    pub skip_empty: bool,
+}
+
+/// Entries shown by default when stdout is a terminal.
+/// Piped and JSON output is unlimited unless `--limit` is given.
+pub const DEFAULT_LIMIT: usize = 20;
+
+/// Resolve the number of entries to show and whether the default was applied
+fn effective_limit(opts: &LogOptions) -> (Option<usize>, bool) {
+    if let Some(limit) = opts.limit {
+        return (Some(limit), false);
+    }
+    if opts.all || opts.json || !stdout_is_tty() {
+        return (None, false);
+    }
+    (Some(DEFAULT_LIMIT), true)
+}
+
+/// How `path` changed in `snapshot` relative to the next-older snapshot:
+/// `A` (added), `M` (modified), `D` (deleted), or `None` if unchanged
+fn file_change(path: &str, snapshot: &Snapshot, older: Option<&Snapshot>) -> Option<char> {
+    let current = snapshot.find_file(path);
+    let previous = older.and_then(|s| s.find_file(path));
+    match (previous, current) {
+        (None, Some(_)) => Some('A'),
+        (Some(prev), Some(cur)) if prev.hash != cur.hash => Some('M'),
+        (Some(_), None) => Some('D'),
+        _ => None,
+    }
+}
+
+/// Case-insensitive message matcher built from `--grep`/`--regex`
+enum MessageFilter {
+    Substring(String),
+    Regex(Regex),
+}
+
+impl MessageFilter {
+    fn new(pattern: &str, regex: bool) -> Result<Self> {
+        if !regex {
+            return Ok(Self::Substring(pattern.to_lowercase()));
+        }
+        RegexBuilder::new(pattern)
+            .case_insensitive(true)
+            .build()
+            .map(Self::Regex)
+            .map_err(|e| MoteError::InvalidArguments(format!("Invalid regex '{}': {}", pattern, e)))
+    }
+
+    /// Snapshots without a message never match
+    fn matches(&self, message: Option<&str>) -> bool {
+        let Some(message) = message else {
+            return false;
+        };
+        match self {
+            Self::Substring(needle) => message.to_lowercase().contains(needle),
+            Self::Regex(re) => re.is_match(message),
+        }
+    }
+}
+
+pub fn cmd_log(ctx: &CommandContext, opts: &LogOptions) -> Result<()> {
+    let now = Utc::now();
+    let since = opts
+        .since
+        .as_deref()
+        .map(|s| parse_time_spec(s, now, ctx.config.display.timezone))
+        .transpose()?;
+    let until = opts
+        .until
+        .as_deref()
+        .map(|s| parse_time_spec(s, now, ctx.config.display.timezone))
+        .transpose()?;
+    let message_filter = opts
+        .grep
+        .as_deref()
+        .map(|p| MessageFilter::new(p, opts.regex))
+        .transpose()?;
+
+    let template = opts.format.as_deref().map(LogTemplate::parse).transpose()?;
+    let follow = opts
+        .follow
+        .as_deref()
+        .map(|p| snapshot_relative_path(ctx.project_root, p));
+
+    let location = ctx.resolve_location()?;
+    let snapshot_store = SnapshotStore::new(location.snapshots_dir());
+    // Message, trigger and metadata filters are answered from the search
+    // sidecar so only matching snapshots are loaded. --stat and --follow
+    // compare each snapshot with its neighbour and need the full list, as
+    // does --skip-empty.
+    let narrow = (message_filter.is_some() || opts.trigger.is_some() || !opts.metadata.is_empty())
+        && !opts.stat
+        && !opts.skip_empty
+        && follow.is_none();
+    let (snapshots, store_is_empty) = if narrow {
+        let entries = snapshot_store.search_entries()?;
+        let store_is_empty = entries.is_empty();
+        let matching: Vec<SearchEntry> = entries
+            .into_iter()
+            .filter(|e| e.matches_metadata(&opts.metadata))
+            .filter(|e| {
+                message_filter
+                    .as_ref()
+                    .is_none_or(|f| f.matches(e.message.as_deref()))
+            })
+            .filter(|e| opts.trigger.as_deref().is_none_or(|t| e.matches_trigger(t)))
+            .collect();
+        (snapshot_store.load_entries(&matching)?, store_is_empty)
+    } else {
+        let snapshots = snapshot_store.list()?;
+        let store_is_empty = snapshots.is_empty();
+        (snapshots, store_is_empty)
+    };
+    let change_of = |index: usize| {
+        let path = follow.as_deref()?;
+        file_change(path, &snapshots[index], snapshots.get(index + 1))
+    };
+    // File lists are only compared when --skip-empty asks for it, so plain
+    // listing stays a read of the snapshot files
+    let is_empty = |index: usize| {
+        snapshots
+            .get(index + 1)
+            .is_some_and(|older| change_stats(Some(older), &snapshots[index]).total() == 0)
+    };
+
+    if store_is_empty && !opts.json && template.is_none() {
+        println!("{} No snapshots yet", "!".yellow().bold());
+        return Ok(());
+    }
+
+    // Keep each snapshot's position so --stat and --follow can compare against
+    // the chronologically previous one (the next entry, newest first)
+    let mut selected: Vec<(usize, &Snapshot)> = snapshots
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| s.matches_metadata(&opts.metadata))
+        .filter(|(_, s)| since.is_none_or(|since| s.timestamp >= since))
+        .filter(|(_, s)| until.is_none_or(|until| s.timestamp <= until))
+        .filter(|(_, s)| {
+            message_filter
+                .as_ref()
+                .is_none_or(|f| f.matches(s.message.as_deref()))
+        })
+        .filter(|(_, s)| opts.trigger.as_deref().is_none_or(|t| s.matches_trigger(t)))
+        .filter(|(i, _)| follow.is_none() || change_of(*i).is_some())
+        .filter(|(i, _)| !opts.skip_empty || !is_empty(*i))
+        .collect();
+    let total = selected.len();
+    if opts.reverse {
+        selected.reverse();
+    }
+    selected.drain(..opts.offset.min(total));
+    let (limit, default_limit) = effective_limit(opts);
+    if let Some(limit) = limit {
+        selected.truncate(limit);
+    }
+    let shown = selected.len();
+    let stats_for = |index: usize| {
+        opts.stat
+            .then(|| change_stats(snapshots.get(index + 1), &snapshots[index]))
+    };
+
+    if opts.json {
+        return print_json(&selected, opts.verbose, stats_for);
+    }
+
+    // Strictly one line per snapshot: no colors, hints or empty-state notices
+    let tz = ctx.config.display.timezone;
+    if let Some(template) = template {
+        for (_, snapshot) in selected {
+            println!("{}", template.render(snapshot, tz));
+        }
+        return Ok(());
+    }
+
+    if selected.is_empty() {
+        println!(
+            "{} No snapshots match the given filters",
+            "!".yellow().bold()
+        );
+        return Ok(());
+    }
+
+    for (index, snapshot) in selected {
+        let stats = stats_for(index);
+        let change = change_of(index);
+        // --stat has already compared the snapshot with its predecessor
+        let no_changes = stats.is_some_and(|s| s.total() == 0) && index + 1 < snapshots.len();
+        let no_changes_suffix = if no_changes {
+            format!("  {}", "(no changes)".dimmed())
+        } else {
+            String::new()
+        };
+        if opts.oneline {
+            let mut line = change
+                .map(|c| format!("{} ", format_change(c)))
+                .unwrap_or_default();
+            line.push_str(&format!(
+                "{} {}  {}  ({} files, {})",
+                snapshot.short_id().cyan(),
+                format_timestamp(&snapshot.timestamp, tz, false),
+                snapshot.message.as_deref().unwrap_or("-").dimmed(),
+                snapshot.file_count(),
+                format_optional_size(snapshot.total_size, ctx.config.display.exact_sizes)
+            ));
+            if let Some(stats) = stats {
+                line.push_str(&format!("  {}", format_stats(stats)));
+            }
+            line.push_str(&no_changes_suffix);
+            line.push_str(&partial_marker(snapshot));
+            println!("{}", line);
+        } else {
+            println!(
+                "{} {}{}{}",
+                "snapshot".yellow(),
+                snapshot.short_id().cyan(),
+                no_changes_suffix,
+                partial_marker(snapshot)
+            );
+            println!(
+                "Date:    {}",
+                format_timestamp(&snapshot.timestamp, tz, true)
+            );
+            if let Some(ref msg) = snapshot.message {
+                println!("Message: {}", msg);
+            }
+            if let Some(ref trigger) = snapshot.trigger {
+                println!("Trigger: {}", trigger);
+            }
+            println!("Files:   {}", snapshot.file_count());
+            if let (Some(c), Some(path)) = (change, follow.as_deref()) {
+                println!("File:    {} {}", format_change(c), path);
+            }
+            if let Some(stats) = stats {
+                println!("Changes: {}", format_stats(stats));
+            }
+            println!();
+        }
+    }
+
+    if default_limit && total > opts.offset + shown {
+        println!(
+            "{}",
+            format!("(showing {} of {}, use --all)", shown, total).dimmed()
+        );
+    }
+    Ok(())
+}
+
+fn format_change(change: char) -> ColoredString {
+    let letter = change.to_string();
+    match change {
+        'A' => letter.green(),
+        'D' => letter.red(),
+        _ => letter.yellow(),
+    }
+}
+
+fn format_stats(stats: ChangeStats) -> String {
+    format!(
+        "{} {} {}",
+        format!("+{}", stats.added).green(),
+        format!("~{}", stats.modified).yellow(),
+        format!("-{}", stats.deleted).red()
+    )
+}
+
+/// Serialize snapshots as a JSON array, adding `short_id`, `file_count` and
+/// (with --stat) `changes`, and dropping the `files` list unless `verbose` is set
+fn print_json(
+    snapshots: &[(usize, &Snapshot)],
+    verbose: bool,
+    stats_for: impl Fn(usize) -> Option<ChangeStats>,
+) -> Result<()> {
+    let mut entries = Vec::with_capacity(snapshots.len());
+    for &(index, snapshot) in snapshots {
+        let mut value = serde_json::to_value(snapshot)?;
+        if let Some(object) = value.as_object_mut() {
+            object.insert("short_id".to_string(), snapshot.short_id().into());
+            object.insert("file_count".to_string(), snapshot.file_count().into());
+            if let Some(stats) = stats_for(index) {
+                object.insert(
+                    "changes".to_string(),
+                    serde_json::json!({
+                        "added": stats.added,
+                        "modified": stats.modified,
+                        "deleted": stats.deleted,
+                    }),
+                );
+            }
+            if !verbose {
+                object.remove("files");
+            }
+        }
+        entries.push(value);
+    }
+    println!("{}", serde_json::to_string_pretty(&entries)?);
+    Ok(())
+}
@@ -0,0 +1,178 @@
+use crate::config::DisplayTimezone;
+use crate::error::{MoteError, Result};
+use crate::storage::Snapshot;
+use crate::time_format::format_timestamp;
+
+const PLACEHOLDERS: &[&str] = &[
+    "id",
+    "short_id",
+    "timestamp",
+    "timestamp_iso",
+    "message",
+    "trigger",
+    "file_count",
+];
+
+#[derive(Debug, PartialEq)]
+enum Segment {
+    Literal(String),
+    Placeholder(&'static str),
+}
+
+/// A parsed `--format` template such as `"{short_id} {message}"`.
+/// `{{` and `}}` produce literal braces.
+#[derive(Debug)]
+pub struct LogTemplate {
+    segments: Vec<Segment>,
+}
+
+impl LogTemplate {
+    pub fn parse(template: &str) -> Result<Self> {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => return Err(invalid(template, "unclosed '{'")),
+                        }
+                    }
+                    let key = PLACEHOLDERS
+                        .iter()
+                        .find(|&&key| key == name)
+                        .ok_or_else(|| {
+                            invalid(template, &format!("unknown placeholder '{{{}}}'", name))
+                        })?;
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    segments.push(Segment::Placeholder(key));
+                }
+                '}' => {
+                    return Err(invalid(
+                        template,
+                        "unmatched '}' (use '}}' for a literal brace)",
+                    ))
+                }
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+
+        Ok(Self { segments })
+    }
+
+    pub fn render(&self, snapshot: &Snapshot, tz: DisplayTimezone) -> String {
+        let mut output = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(text) => output.push_str(text),
+                Segment::Placeholder(key) => {
+                    let value = match *key {
+                        "id" => snapshot.id.clone(),
+                        "short_id" => snapshot.short_id().to_string(),
+                        "timestamp" => format_timestamp(&snapshot.timestamp, tz, false),
+                        "timestamp_iso" => snapshot.timestamp.to_rfc3339(),
+                        "message" => snapshot.message.clone().unwrap_or_default(),
+                        "trigger" => snapshot.trigger.clone().unwrap_or_default(),
+                        "file_count" => snapshot.file_count().to_string(),
+                        _ => unreachable!("placeholders are validated in parse"),
+                    };
+                    output.push_str(&value);
+                }
+            }
+        }
+        output
+    }
+}
+
+fn invalid(template: &str, reason: &str) -> MoteError {
+    let valid: Vec<String> = PLACEHOLDERS
+        .iter()
+        .map(|key| format!("{{{}}}", key))
+        .collect();
+    MoteError::InvalidArguments(format!(
+        "Invalid format '{}': {}. Valid placeholders: {}",
+        template,
+        reason,
+        valid.join(", ")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, Utc};
+
+    fn snapshot() -> Snapshot {
+        let mut snapshot = Snapshot::new(
+            Vec::new(),
+            Some("Before refactor".to_string()),
+            Some("manual".to_string()),
+        );
+        snapshot.id = "abcdef0123456789".to_string();
+        snapshot.timestamp = DateTime::parse_from_rfc3339("2024-05-01T14:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        snapshot
+    }
+
+    fn render(template: &str) -> String {
+        LogTemplate::parse(template)
+            .unwrap()
+            .render(&snapshot(), DisplayTimezone::Utc)
+    }
+
+    #[test]
+    fn test_render_placeholders() {
+        assert_eq!(render("{short_id} {message}"), "abcdef0 Before refactor");
+        assert_eq!(
+            render("{id}|{timestamp}|{timestamp_iso}|{trigger}|{file_count}"),
+            "abcdef0123456789|2024-05-01 14:00:00|2024-05-01T14:00:00+00:00|manual|0"
+        );
+    }
+
+    #[test]
+    fn test_render_missing_fields_as_empty() {
+        let mut snapshot = snapshot();
+        snapshot.message = None;
+        snapshot.trigger = None;
+        let template = LogTemplate::parse("[{message}][{trigger}]").unwrap();
+        assert_eq!(template.render(&snapshot, DisplayTimezone::Utc), "[][]");
+    }
+
+    #[test]
+    fn test_escaped_braces() {
+        assert_eq!(render("{{{short_id}}}"), "{abcdef0}");
+        assert_eq!(render("{{message}}"), "{message}");
+        assert_eq!(render("no placeholders"), "no placeholders");
+    }
+
+    #[test]
+    fn test_invalid_templates() {
+        for template in ["{author}", "{short_id", "short_id}", "{}"] {
+            match LogTemplate::parse(template) {
+                Err(MoteError::InvalidArguments(msg)) => {
+                    assert!(msg.contains("{short_id}"), "{}", msg)
+                }
+                other => panic!("{:?} parsed as {:?}", template, other),
+            }
+        }
+    }
+}
@@ -1,44 +1,176 @@
+mod apply;
 mod collect;
 mod delete;
 mod diff;
+mod export;
+mod external;
+mod fix_perms;
 mod gc;
+mod hooks;
+mod log;
+mod log_format;
+mod prune;
 mod restore;
+mod stats;
+mod status;
+mod tree;
+mod verify;
+
+use std::io::{IsTerminal, Write};
 
 use colored::*;
 
+use crate::cli::ColorChoice;
 use crate::commands::CommandContext;
 use crate::error::{MoteError, Result};
-use crate::storage::{check_auto_gc, run_auto_gc, Index, ObjectStore, Snapshot, SnapshotStore};
-use collect::{collect_files, have_same_file_hashes};
+use crate::path_resolver::{is_unsafe_project_root, snapshot_relative_path};
+use crate::size_format::format_size;
+use crate::storage::{
+    auto_gc, Index, Snapshot, SnapshotStore, StorageLocation, StorageLock, AUTO_LOCK_WAIT,
+    LATEST_ALIAS, LOCK_WAIT,
+};
+use crate::time_format::format_timestamp;
+use crate::time_spec::parse_time_spec;
+use collect::{collect_files, have_same_file_hashes, CollectOptions};
+use diff::{line_stats, FileLineStat};
+use hooks::{run_hooks, run_post_hooks};
+use tree::render_tree;
 
+pub use apply::{cmd_apply, ApplyOptions};
+pub(crate) use collect::scan_files;
 pub use delete::cmd_delete;
-pub use diff::cmd_diff;
-pub use gc::cmd_gc;
-pub use restore::cmd_restore;
+pub use diff::{cmd_diff, DiffOptions};
+#[cfg(feature = "tui")]
+pub(crate) use diff::{diff_snapshots, TextDiffOptions};
+pub(crate) use diff::{generate_unified_diff_with_content, DiffOutput, Whitespace};
+pub use export::cmd_export_store;
+pub use fix_perms::cmd_fix_perms;
+pub use gc::{cmd_gc, GcOptions};
+pub use log::{cmd_log, LogOptions};
+pub use prune::{cmd_prune_backups, PruneBackupsOptions};
+pub use restore::{cmd_restore, RestoreOptions};
+pub use stats::{cmd_stats, StatsOptions};
+pub use status::{cmd_status, StatusOptions};
+pub use verify::{cmd_verify, VerifyOptions};
+
+/// Options for `mote snap create`
+#[derive(Debug, Default)]
+pub struct SnapshotOptions {
+    pub message: Option<String>,
+    pub trigger: Option<String>,
+    /// Quiet, and skipped without error when nothing changed or the snapshot
+    /// can't be taken (shell hooks, editor plugins)
+    pub auto: bool,
+    /// `key=value` pairs recorded on the snapshot
+    pub metadata: Vec<(String, String)>,
+    /// Record the snapshot even when nothing changed
+    pub checkpoint: bool,
+    /// Snapshot even if the project root is / or the home directory, or has
+    /// more than `snapshot.max_files` files
+    pub force_large_root: bool,
+    /// Abort on the first unreadable file (also `snapshot.fail_fast`)
+    pub fail_fast: bool,
+    /// zstd level overriding `storage.compression_level`
+    pub compression: Option<i32>,
+}
+
+pub fn cmd_snapshot(ctx: &CommandContext, opts: &SnapshotOptions) -> Result<()> {
+    let SnapshotOptions {
+        auto,
+        checkpoint,
+        force_large_root,
+        fail_fast,
+        compression,
+        ..
+    } = *opts;
+    if let Err(e) = ctx.ensure_writable() {
+        return if auto { Ok(()) } else { Err(e) };
+    }
+
+    if !force_large_root && is_unsafe_project_root(ctx.project_root) {
+        return if auto {
+            Ok(())
+        } else {
+            Err(MoteError::UnsafeProjectRoot(
+                ctx.project_root.display().to_string(),
+            ))
+        };
+    }
 
-pub fn cmd_snapshot(
-    ctx: &CommandContext,
-    message: Option<String>,
-    trigger: Option<String>,
-    auto: bool,
-) -> Result<()> {
     let location = match ctx.resolve_location() {
         Ok(loc) => loc,
         Err(MoteError::NotInitialized) if auto => return Ok(()),
         Err(e) => return Err(e),
     };
-    let object_store = ObjectStore::new(location.objects_dir());
-    let snapshot_store = SnapshotStore::new(location.snapshots_dir());
+    let object_store = ctx
+        .object_store(&location)
+        .compression_level(ctx.compression_level(compression)?);
+    let snapshot_store =
+        SnapshotStore::new(location.snapshots_dir()).fsync(ctx.config.storage.fsync);
+
+    let hooks = &ctx.config.hooks;
+    match run_hooks(&hooks.pre_snapshot, ctx.project_root, &[], auto) {
+        Ok(()) => {}
+        Err(MoteError::HookFailed { .. }) if auto => return Ok(()),
+        Err(e) => return Err(e),
+    }
+
+    // Taken after the pre-snapshot hooks and released before the post ones,
+    // so hooks can run mote themselves
+    let wait = if auto { AUTO_LOCK_WAIT } else { LOCK_WAIT };
+    let lock = match StorageLock::acquire(&location, wait) {
+        Ok(lock) => lock,
+        Err(MoteError::StorageLocked { .. }) if auto => return Ok(()),
+        Err(e) => return Err(e),
+    };
 
+    let mut ignore_filter = ctx.ignore_filter();
+    if force_large_root {
+        ignore_filter = ignore_filter.limit_files(0);
+    }
     let mut index = Index::load(&location.index_path())?;
-    let files = collect_files(
+    let previous = snapshot_store.latest().ok().flatten();
+
+    // Checkpoints are recorded even when identical to the latest snapshot.
+    // Auto snapshots detect "no change" with a read-only scan before storing anything.
+    // An oversized tree is skipped quietly in auto mode, like other hook failures
+    if auto && !checkpoint {
+        if let Some(ref latest) = previous {
+            let current = match scan_files(
+                ctx.project_root,
+                &ignore_filter,
+                &index,
+                ctx.config.snapshot.status_threads,
+            ) {
+                Ok(current) => current,
+                Err(MoteError::TooManyFiles { .. }) => return Ok(()),
+                Err(e) => return Err(e),
+            };
+            if have_same_file_hashes(&latest.files, &current) {
+                return Ok(());
+            }
+        }
+    }
+
+    let (files, new_bytes, skipped) = match collect_files(
         ctx.project_root,
-        &ctx.ignore_file_path,
+        &ignore_filter,
         &object_store,
         &mut index,
-        auto,
-    );
-    index.save(&location.index_path())?;
+        previous.as_ref(),
+        &CollectOptions {
+            quiet: auto,
+            // Shell hooks must never fail the command they run after
+            fail_fast: (fail_fast || ctx.config.snapshot.fail_fast) && !auto,
+            threads: ctx.config.snapshot.threads,
+            progress: show_progress(ctx, auto),
+        },
+    ) {
+        Ok(collected) => collected,
+        Err(MoteError::TooManyFiles { .. }) if auto => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    index.save(&location.index_path(), ctx.config.storage.fsync)?;
 
     if files.is_empty() {
         if !auto {
@@ -47,33 +179,47 @@ pub fn cmd_snapshot(
         return Ok(());
     }
 
-    if auto {
-        if let Ok(snapshots) = snapshot_store.list() {
-            if let Some(latest) = snapshots.iter().max_by_key(|s| s.timestamp) {
-                if have_same_file_hashes(&latest.files, &files) {
-                    return Ok(());
-                }
-            }
+    let trigger = opts
+        .trigger
+        .clone()
+        .or_else(|| checkpoint.then(|| "checkpoint".to_string()));
+    let mut snapshot = Snapshot::new(files, opts.message.clone(), trigger);
+    snapshot.metadata = opts.metadata.iter().cloned().collect();
+    snapshot.new_bytes = Some(new_bytes);
+    snapshot.mark_partial(skipped);
+    snapshot.seq = snapshot_store.next_seq()?;
+    snapshot_store.save(&snapshot)?;
+
+    if let Some(ref previous) = previous {
+        if snapshot.timestamp < previous.timestamp && !auto {
+            let tz = ctx.config.display.timezone;
+            eprintln!(
+                "{}: Clock skew detected: snapshot {} ({}) is older than the previous snapshot {} ({}). Ordering uses sequence numbers.",
+                "warning".yellow(),
+                snapshot.short_id(),
+                format_timestamp(&snapshot.timestamp, tz, false),
+                previous.short_id(),
+                format_timestamp(&previous.timestamp, tz, false)
+            );
         }
     }
 
-    let snapshot = Snapshot::new(files, message.clone(), trigger);
-    snapshot_store.save(&snapshot)?;
-
     if !auto {
         println!(
-            "{} Created snapshot {} ({} files)",
+            "{} Created snapshot {} ({} files){}",
             "✓".green().bold(),
             snapshot.short_id().cyan(),
-            snapshot.file_count()
+            snapshot.file_count(),
+            partial_marker(&snapshot)
         );
-        if let Some(msg) = message {
+        if let Some(ref msg) = opts.message {
             println!("  Message: {}", msg);
         }
     }
 
+    let mut removed = 0;
     if ctx.config.snapshot.auto_cleanup {
-        let removed = snapshot_store.cleanup(
+        removed = snapshot_store.cleanup(
             ctx.config.snapshot.max_snapshots,
             ctx.config.snapshot.max_age_days,
         )?;
@@ -81,76 +227,123 @@ pub fn cmd_snapshot(
             println!("  Cleaned up {} old snapshot(s)", removed);
         }
     }
+    maybe_auto_gc(ctx, &location, removed as usize, auto)?;
+    drop(lock);
 
-    if ctx.config.snapshot.gc_auto_enabled {
-        let gc_info = check_auto_gc(
-            &location.snapshots_dir(),
-            &location.objects_dir(),
-            ctx.config.snapshot.gc_auto,
-        )?;
-
-        if gc_info.should_run {
-            if let Some(stats) = run_auto_gc(&location.snapshots_dir(), &location.objects_dir())? {
-                if !auto {
-                    println!(
-                        "  Auto GC: cleaned {} unreferenced object(s)",
-                        stats.deleted_objects
-                    );
-                }
-            }
-        }
-    }
+    let hook_env = [
+        ("MOTE_SNAPSHOT_ID", snapshot.id.clone()),
+        ("MOTE_FILE_COUNT", snapshot.file_count().to_string()),
+        ("MOTE_TRIGGER", snapshot.trigger.clone().unwrap_or_default()),
+    ];
+    run_post_hooks(&hooks.post_snapshot, ctx.project_root, &hook_env, auto);
 
     Ok(())
 }
 
-pub fn cmd_log(ctx: &CommandContext, limit: usize, oneline: bool) -> Result<()> {
-    let location = ctx.resolve_location()?;
-    let snapshot_store = SnapshotStore::new(location.snapshots_dir());
-    let snapshots = snapshot_store.list()?;
-
-    if snapshots.is_empty() {
-        println!("{} No snapshots yet", "!".yellow().bold());
+/// Count `removed` snapshots toward `gc_auto` and collect unreferenced
+/// objects when it's due, with one summary line unless `quiet`. Does nothing
+/// unless `gc_auto_enabled`.
+pub(super) fn maybe_auto_gc(
+    ctx: &CommandContext,
+    location: &StorageLocation,
+    removed: usize,
+    quiet: bool,
+) -> Result<()> {
+    if !ctx.config.snapshot.gc_auto_enabled {
         return Ok(());
     }
-
-    for snapshot in snapshots.into_iter().take(limit) {
-        if oneline {
-            println!(
-                "{} {}  {}  ({} files)",
-                snapshot.short_id().cyan(),
-                snapshot.timestamp.format("%Y-%m-%d %H:%M:%S"),
-                snapshot.message.as_deref().unwrap_or("-").dimmed(),
-                snapshot.file_count()
-            );
-        } else {
-            println!("{} {}", "snapshot".yellow(), snapshot.short_id().cyan());
+    let (threshold, grace) = (ctx.config.snapshot.gc_auto, ctx.config.gc.grace_period_secs);
+    if let Some(stats) = auto_gc(location, threshold, removed, grace)? {
+        if !quiet {
             println!(
-                "Date:    {}",
-                snapshot.timestamp.format("%Y-%m-%d %H:%M:%S %Z")
+                "  Auto GC: cleaned {} unreferenced object(s), reclaimed {}",
+                stats.deleted_objects,
+                format_size(stats.deleted_bytes, ctx.config.display.exact_sizes)
             );
-            if let Some(ref msg) = snapshot.message {
-                println!("Message: {}", msg);
-            }
-            if let Some(ref trigger) = snapshot.trigger {
-                println!("Trigger: {}", trigger);
-            }
-            println!("Files:   {}", snapshot.file_count());
-            println!();
         }
     }
     Ok(())
 }
 
-pub fn cmd_show(ctx: &CommandContext, snapshot_id: &str) -> Result<()> {
+/// `  (partial)` after a snapshot that is missing files, else nothing
+pub(super) fn partial_marker(snapshot: &Snapshot) -> String {
+    if snapshot.partial {
+        format!("  {}", "(partial)".yellow())
+    } else {
+        String::new()
+    }
+}
+
+/// Widest +/- bar in `snap show --stat`; longer bars are scaled down
+const STAT_BAR_WIDTH: usize = 40;
+
+/// Options for `mote snap show`
+#[derive(Debug, Default)]
+pub struct ShowOptions {
+    /// Per-file line counts against the previous snapshot
+    pub stat: bool,
+    pub json: bool,
+    /// Print this file's stored content instead of the summary
+    pub path: Option<String>,
+    /// List files as a directory tree
+    pub tree: bool,
+    /// Collapse tree levels below this depth
+    pub depth: Option<usize>,
+}
+
+pub fn cmd_show(ctx: &CommandContext, snapshot_id: Option<&str>, opts: &ShowOptions) -> Result<()> {
+    let ShowOptions { stat, json, .. } = *opts;
+    let exact_sizes = ctx.config.display.exact_sizes;
+    let snapshot_id = snapshot_id.unwrap_or(LATEST_ALIAS);
+    // `<id>:<path>` unless the path was given separately; `:<path>` means latest
+    let (snapshot_id, path) = match opts.path.as_deref() {
+        Some(path) => (snapshot_id, Some(path)),
+        None => match snapshot_id.split_once(':') {
+            Some(("", path)) => (LATEST_ALIAS, Some(path)),
+            Some((id, path)) => (id, Some(path)),
+            None => (snapshot_id, None),
+        },
+    };
+    if path.is_some() && (stat || json || opts.tree) {
+        return Err(MoteError::InvalidArguments(
+            "--stat, --json and --tree cannot be combined with a file path".to_string(),
+        ));
+    }
+
     let location = ctx.resolve_location()?;
     let snapshot_store = SnapshotStore::new(location.snapshots_dir());
-    let snapshot = snapshot_store.find_by_id(snapshot_id)?;
+    let snapshot = snapshot_store.resolve_ref(snapshot_id)?;
+
+    if let Some(path) = path {
+        let path = snapshot_relative_path(ctx.project_root, path);
+        let file = snapshot
+            .find_file(&path)
+            .ok_or_else(|| MoteError::FileNotFoundInSnapshot {
+                snapshot: snapshot.short_id().to_string(),
+                path: path.clone(),
+            })?;
+        let content = ctx.object_store(&location).retrieve(&file.hash)?;
+        // Verbatim bytes so binary content can be redirected to a file
+        let mut stdout = std::io::stdout().lock();
+        stdout.write_all(&content)?;
+        stdout.flush()?;
+        return Ok(());
+    }
 
-    println!("{} {}", "snapshot".yellow(), snapshot.id.cyan());
+    if json {
+        println!("{}", serde_json::to_string_pretty(&snapshot)?);
+        return Ok(());
+    }
+
+    println!(
+        "{} {}{}",
+        "snapshot".yellow(),
+        snapshot.id.cyan(),
+        partial_marker(&snapshot)
+    );
     println!(
         "Date:    {}",
-        snapshot.timestamp.format("%Y-%m-%d %H:%M:%S %Z")
+        format_timestamp(&snapshot.timestamp, ctx.config.display.timezone, true)
     );
     if let Some(ref msg) = snapshot.message {
         println!("Message: {}", msg);
@@ -159,11 +352,214 @@ pub fn cmd_show(ctx: &CommandContext, snapshot_id: &str) -> Result<()> {
         println!("Trigger: {}", trigger);
     }
     println!("Files:   {}", snapshot.file_count());
+    println!(
+        "Size:    {} total, {} new",
+        format_optional_size(snapshot.total_size, exact_sizes),
+        format_optional_size(snapshot.new_bytes, exact_sizes)
+    );
+    if let Some(ref skipped) = snapshot.skipped_paths {
+        println!();
+        println!("{}:", "Not captured".bold());
+        for path in skipped {
+            println!("  {}", path.yellow());
+        }
+    }
+    if !snapshot.metadata.is_empty() {
+        println!();
+        println!("{}:", "Metadata".bold());
+        let mut entries: Vec<_> = snapshot.metadata.iter().collect();
+        entries.sort();
+        for (key, value) in entries {
+            println!("  {} = {}", key.cyan(), value);
+        }
+    }
     println!();
+
+    if stat {
+        // The chronologically previous snapshot is the next entry (newest first)
+        let snapshots = snapshot_store.list()?;
+        let parent = snapshots
+            .iter()
+            .position(|s| s.id == snapshot.id)
+            .and_then(|i| snapshots.get(i + 1));
+        match parent {
+            Some(parent) => println!("{} (vs {}):", "Changes".bold(), parent.short_id()),
+            None => println!("{} (initial snapshot):", "Changes".bold()),
+        }
+        let object_store = ctx.object_store(&location);
+        print_line_stats(&line_stats(&object_store, parent, &snapshot)?);
+        return Ok(());
+    }
+
     println!("{}:", "Files".bold());
 
+    if opts.tree {
+        print!("{}", render_tree(&snapshot.files, opts.depth, exact_sizes));
+        return Ok(());
+    }
     for file in &snapshot.files {
-        println!("  {} ({} bytes)", file.path.cyan(), file.size);
+        println!(
+            "  {} ({})",
+            file.path.cyan(),
+            format_size(file.size, exact_sizes)
+        );
     }
     Ok(())
 }
+
+/// Git-style stat table: `path | count +++--` per file, then a totals line
+fn print_line_stats(stats: &[FileLineStat]) {
+    if stats.is_empty() {
+        println!("  No changes");
+        return;
+    }
+
+    let name_width = stats.iter().map(|s| s.path.len()).max().unwrap_or(0);
+    let max_lines = stats
+        .iter()
+        .filter_map(|s| s.lines.map(|(ins, del)| ins + del))
+        .max()
+        .unwrap_or(0);
+    let count_width = max_lines.to_string().len();
+
+    let (mut insertions, mut deletions) = (0, 0);
+    for stat in stats {
+        let Some((ins, del)) = stat.lines else {
+            println!("  {:<name_width$} | bin", stat.path);
+            continue;
+        };
+        insertions += ins;
+        deletions += del;
+
+        let (mut plus, mut minus) = (ins, del);
+        if max_lines > STAT_BAR_WIDTH {
+            plus = scale_bar(ins, max_lines);
+            minus = scale_bar(del, max_lines);
+        }
+        println!(
+            "  {:<name_width$} | {:>count_width$} {}{}",
+            stat.path,
+            ins + del,
+            "+".repeat(plus).green(),
+            "-".repeat(minus).red()
+        );
+    }
+
+    println!(
+        "  {} file{} changed, {} insertion{}(+), {} deletion{}(-)",
+        stats.len(),
+        plural(stats.len()),
+        insertions,
+        plural(insertions),
+        deletions,
+        plural(deletions)
+    );
+}
+
+/// Scale `count` to the bar width, keeping any non-zero count visible
+fn scale_bar(count: usize, max: usize) -> usize {
+    if count == 0 {
+        return 0;
+    }
+    (count * STAT_BAR_WIDTH / max).max(1)
+}
+
+fn plural(count: usize) -> &'static str {
+    if count == 1 {
+        ""
+    } else {
+        "s"
+    }
+}
+
+/// Overrides terminal detection (`1` or `0`), mainly for tests
+const FORCE_TTY_ENV: &str = "MOTE_FORCE_TTY";
+
+fn stdout_is_tty() -> bool {
+    match std::env::var(FORCE_TTY_ENV).as_deref() {
+        Ok("1") => true,
+        Ok("0") => false,
+        _ => std::io::stdout().is_terminal(),
+    }
+}
+
+/// Resolve `--color`: `auto` colors only a terminal, and only when `NO_COLOR`
+/// is unset
+pub fn use_color(choice: ColorChoice) -> bool {
+    match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => {
+            stdout_is_tty() && std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+        }
+    }
+}
+
+/// Whether long operations draw a progress bar: not for `quiet` (`--auto`)
+/// runs, with `--no-progress`, or when stdout isn't a terminal
+fn show_progress(ctx: &CommandContext, quiet: bool) -> bool {
+    !quiet && !ctx.config.display.no_progress && stdout_is_tty()
+}
+
+/// Whether prompts can be answered; honors the same override
+fn stdin_is_tty() -> bool {
+    match std::env::var(FORCE_TTY_ENV).as_deref() {
+        Ok("1") => true,
+        Ok("0") => false,
+        _ => std::io::stdin().is_terminal(),
+    }
+}
+
+/// Ask `question` with a `[y/N]` prompt. Without a terminal to answer on,
+/// fails with a hint to pass `--force` rather than waiting on a pipe, which
+/// would hang scripts; `action` names what couldn't be confirmed.
+fn confirm(question: &str, action: &str) -> Result<bool> {
+    if !stdin_is_tty() {
+        return Err(MoteError::InvalidArguments(format!(
+            "stdin is not a terminal, so {} can't be confirmed; pass --force (or -y/--yes) \
+             to skip the prompt",
+            action
+        )));
+    }
+    print!("{} [y/N] ", question);
+    std::io::stdout().flush()?;
+
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+    let answer = input.trim().to_lowercase();
+    Ok(answer == "y" || answer == "yes")
+}
+
+/// ID of the newest snapshot taken at or before `time` (see `parse_time_spec`),
+/// for `--at`. Unless `quiet`, the chosen snapshot is noted on stderr.
+pub fn snapshot_id_at(ctx: &CommandContext, time: &str, quiet: bool) -> Result<String> {
+    let timezone = ctx.config.display.timezone;
+    let time = parse_time_spec(time, chrono::Utc::now(), timezone)?;
+    let location = ctx.resolve_location()?;
+    let store = SnapshotStore::new(location.snapshots_dir());
+    let Some(snapshot) = store.latest_at(time)? else {
+        let earliest = store.list()?.iter().map(|s| s.timestamp).min();
+        return Err(match earliest {
+            Some(earliest) => MoteError::NoSnapshotAtTime {
+                time: format_timestamp(&time, timezone, true),
+                earliest: format_timestamp(&earliest, timezone, true),
+            },
+            None => MoteError::NoSnapshotsAvailable,
+        });
+    };
+    if !quiet {
+        eprintln!(
+            "{} Using snapshot {} from {}",
+            "info".blue().bold(),
+            snapshot.short_id().cyan(),
+            format_timestamp(&snapshot.timestamp, timezone, true)
+        );
+    }
+    Ok(snapshot.id)
+}
+
+/// Display size, or `-` for snapshots recorded before sizes were tracked
+pub(super) fn format_optional_size(size: Option<u64>, exact: bool) -> String {
+    size.map(|size| format_size(size, exact))
+        .unwrap_or_else(|| "-".to_string())
+}
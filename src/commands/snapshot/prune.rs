@@ -0,0 +1,110 @@
+use chrono::Utc;
+use colored::*;
+
+use super::restore::BACKUP_TRIGGER;
+use super::{cmd_gc, confirm, maybe_auto_gc, GcOptions};
+use crate::commands::CommandContext;
+use crate::error::{MoteError, Result};
+use crate::storage::SnapshotStore;
+use crate::time_format::format_timestamp;
+use crate::time_spec::parse_relative_duration;
+
+/// Options for `mote snap prune-backups`
+#[derive(Debug)]
+pub struct PruneBackupsOptions {
+    /// Only prune backups older than this duration (e.g. `7d`)
+    pub older_than: Option<String>,
+    /// Number of newest backups that are never pruned
+    pub keep: usize,
+    /// Delete without asking
+    pub force: bool,
+    /// List the backups that would be deleted without deleting them
+    pub dry_run: bool,
+    /// Collect the objects only the deleted backups referenced
+    pub gc: bool,
+}
+
+impl Default for PruneBackupsOptions {
+    fn default() -> Self {
+        Self {
+            older_than: None,
+            keep: 1,
+            force: false,
+            dry_run: false,
+            gc: false,
+        }
+    }
+}
+
+/// Delete the snapshots restore takes before overwriting files, except the
+/// newest `keep` of them
+pub fn cmd_prune_backups(ctx: &CommandContext, opts: &PruneBackupsOptions) -> Result<()> {
+    if !opts.dry_run {
+        ctx.ensure_writable()?;
+    }
+    let cutoff = match &opts.older_than {
+        Some(spec) => {
+            let age = parse_relative_duration(spec.trim()).ok_or_else(|| {
+                MoteError::InvalidArguments(format!(
+                    "Invalid --older-than '{}': expected a duration like 30m, 12h, 7d or 2w",
+                    spec
+                ))
+            })?;
+            Some(Utc::now() - age)
+        }
+        None => None,
+    };
+
+    let location = ctx.resolve_location()?;
+    let snapshot_store = SnapshotStore::new(location.snapshots_dir());
+    let backups: Vec<_> = snapshot_store
+        .list()?
+        .into_iter()
+        .filter(|s| s.trigger.as_deref() == Some(BACKUP_TRIGGER))
+        .skip(opts.keep)
+        .filter(|s| cutoff.is_none_or(|cutoff| s.timestamp < cutoff))
+        .collect();
+
+    if backups.is_empty() {
+        println!("No backup snapshots to prune");
+        return Ok(());
+    }
+
+    let tz = ctx.config.display.timezone;
+    println!("Backup snapshots to delete:");
+    for backup in &backups {
+        println!(
+            "  {}  {}  {}",
+            backup.short_id().cyan(),
+            format_timestamp(&backup.timestamp, tz, false),
+            backup.message.as_deref().unwrap_or("")
+        );
+    }
+
+    if opts.dry_run {
+        println!("Would delete {} backup snapshot(s)", backups.len());
+        return Ok(());
+    }
+    if !opts.force {
+        let question = format!("Delete {} backup snapshot(s)?", backups.len());
+        if !confirm(&question, "pruning backups")? {
+            println!("{} Pruning cancelled", "!".yellow().bold());
+            return Ok(());
+        }
+    }
+
+    for backup in &backups {
+        snapshot_store.delete(&backup.id)?;
+    }
+    println!(
+        "{} Deleted {} backup snapshot(s)",
+        "✓".green().bold(),
+        backups.len()
+    );
+
+    if opts.gc {
+        cmd_gc(ctx, &GcOptions::default())
+    } else {
+        maybe_auto_gc(ctx, &location, backups.len(), false)
+    }
+}
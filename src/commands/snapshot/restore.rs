@@ -1,81 +1,351 @@
-use std::path::Path;
+use std::collections::{HashMap, HashSet};
+use std::io::{self, ErrorKind, Write};
+use std::path::{Path, PathBuf};
 
 use colored::*;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
 
-use super::collect::collect_files;
+use super::collect::{collect_files, under_prefix, CollectOptions};
+use super::{
+    generate_unified_diff_with_content, show_progress, stdin_is_tty, DiffOutput, Whitespace,
+};
 use crate::commands::CommandContext;
-use crate::error::Result;
-use crate::storage::{Index, ObjectStore, Snapshot, SnapshotStore};
-
-pub fn cmd_restore(
-    ctx: &CommandContext,
-    snapshot_id: &str,
-    file: Option<String>,
-    force: bool,
-    dry_run: bool,
-) -> Result<()> {
+use crate::error::{MoteError, Result};
+use crate::ignore::IgnoreFilter;
+use crate::path_resolver::{join_project_path, join_within, snapshot_relative_path};
+use crate::progress::Progress;
+use crate::size_format::format_size;
+use crate::storage::{
+    FileEntry, Index, ObjectStore, Snapshot, SnapshotStore, StorageLock, LOCK_WAIT,
+};
+
+/// Context lines in the diff `--interactive` shows for `d`
+const PICK_DIFF_CONTEXT: usize = 3;
+
+/// Objects each restore worker may have retrieved ahead of the writer
+const PREFETCH_DEPTH: usize = 4;
+
+/// Trigger recorded on the snapshot taken before a restore overwrites files
+pub(super) const BACKUP_TRIGGER: &str = "auto-backup";
+
+/// Options for `mote snap restore`
+#[derive(Debug, Default)]
+pub struct RestoreOptions {
+    /// Files or glob patterns to restore; the whole snapshot when empty
+    pub files: Vec<String>,
+    /// Skip the backup snapshot and overwrite locally modified files
+    pub force: bool,
+    /// Show what would be restored without writing
+    pub dry_run: bool,
+    /// Delete working files that aren't in the snapshot
+    pub clean: bool,
+    /// Confirm `clean` when no backup snapshot will be taken
+    pub yes: bool,
+    /// Write into this directory instead of the project
+    pub target: Option<PathBuf>,
+    /// Ask which of the changed files to restore
+    pub interactive: bool,
+    /// Retrieve and write one file at a time, ignoring `restore.threads`
+    pub serial: bool,
+    /// Write the single selected file to stdout instead of restoring it
+    pub stdout: bool,
+    /// With `dry_run`, also list files that are already identical
+    pub verbose: bool,
+    /// Gitignore-style patterns for files to leave alone, also when cleaning
+    pub exclude: Vec<String>,
+}
+
+/// Where restored files are written
+#[derive(Clone, Copy)]
+enum Destination<'a> {
+    /// The project's working tree; files already identical are left alone
+    WorkingTree(&'a Path),
+    /// A `--target` directory, written without looking at the working tree
+    Target(&'a Path),
+}
+
+impl Destination<'_> {
+    fn root(&self) -> &Path {
+        match self {
+            Destination::WorkingTree(root) | Destination::Target(root) => root,
+        }
+    }
+
+    /// Path for a snapshot file; in a target directory it must stay inside
+    fn join(&self, path: &str) -> Result<PathBuf> {
+        match self {
+            Destination::WorkingTree(root) => Ok(join_project_path(root, path)),
+            Destination::Target(root) => {
+                join_within(root, path).ok_or_else(|| MoteError::PathOutsideTarget {
+                    path: path.to_string(),
+                    target: root.display().to_string(),
+                })
+            }
+        }
+    }
+}
+
+pub fn cmd_restore(ctx: &CommandContext, snapshot_id: &str, opts: &RestoreOptions) -> Result<()> {
     let location = ctx.resolve_location()?;
-    let snapshot_store = SnapshotStore::new(location.snapshots_dir());
-    let object_store = ObjectStore::new(location.objects_dir());
-    let snapshot = snapshot_store.find_by_id(snapshot_id)?;
+    let snapshot_store =
+        SnapshotStore::new(location.snapshots_dir()).fsync(ctx.config.storage.fsync);
+    let object_store = ctx
+        .object_store(&location)
+        .compression_level(ctx.compression_level(None)?);
+    let snapshot = snapshot_store.resolve_ref(snapshot_id)?;
+    if opts.stdout {
+        return write_to_stdout(ctx.project_root, &snapshot, &object_store, &opts.files);
+    }
+    let (force, dry_run) = (opts.force, opts.dry_run);
+    if opts.interactive && !stdin_is_tty() {
+        return Err(MoteError::InvalidArguments(
+            "--interactive needs a terminal to ask on; drop --interactive to restore without \
+             prompts"
+                .to_string(),
+        ));
+    }
+
+    let destination = match &opts.target {
+        Some(target) => {
+            let destination = Destination::Target(target);
+            // Check every entry before anything is written
+            for file in &snapshot.files {
+                destination.join(&file.path)?;
+            }
+            if !dry_run {
+                std::fs::create_dir_all(target)?;
+            }
+            destination
+        }
+        None => Destination::WorkingTree(ctx.project_root),
+    };
 
-    if let Some(ref file_path) = file {
-        restore_single_file(
+    if !opts.files.is_empty() {
+        restore_selected_files(
             ctx.project_root,
+            destination,
             &snapshot,
             &object_store,
-            file_path,
+            &opts.files,
             dry_run,
+            ctx.config.display.exact_sizes,
         )
     } else {
+        let to_target = opts.target.is_some();
+        // Restoring into the working tree saves the index and may take a backup
+        let _lock = (!ctx.archived && !to_target)
+            .then(|| StorageLock::acquire(&location, LOCK_WAIT))
+            .transpose()?;
         let mut index = Index::load(&location.index_path())?;
-        let result = restore_all_files(
-            ctx.project_root,
-            &ctx.ignore_file_path,
-            &snapshot,
-            &object_store,
-            &snapshot_store,
-            &mut index,
-            force,
-            dry_run,
-        );
-        if result.is_ok() {
-            index.save(&location.index_path())?;
+        let skip_backup = force || ctx.archived || to_target;
+        // The backup snapshot is what makes --clean reversible
+        if opts.clean && skip_backup && !dry_run && !opts.yes {
+            return Err(MoteError::InvalidArguments(
+                "--clean without a backup snapshot (--force or an archived context) deletes \
+                 files for good; pass --yes to confirm"
+                    .to_string(),
+            ));
+        }
+        if ctx.archived && !force && !dry_run && !to_target {
+            println!(
+                "{} Context '{}' is archived; skipping backup snapshot",
+                "!".yellow().bold(),
+                ctx.context_name
+            );
+        }
+        let ignore_filter = ctx.ignore_filter();
+        let threads = if opts.serial {
+            1
+        } else {
+            ctx.config.restore.threads
+        };
+        let result = if opts.interactive {
+            restore_interactively(
+                ctx.project_root,
+                &ignore_filter,
+                &snapshot,
+                &object_store,
+                &snapshot_store,
+                &mut index,
+                skip_backup,
+            )
+        } else {
+            restore_all_files(
+                destination,
+                &ignore_filter,
+                &snapshot,
+                &object_store,
+                &snapshot_store,
+                &mut index,
+                skip_backup,
+                opts,
+                threads,
+                ctx.config.display.exact_sizes,
+                show_progress(ctx, false),
+            )
+        };
+        if result.is_ok() && !ctx.archived && !to_target {
+            index.save(&location.index_path(), ctx.config.storage.fsync)?;
         }
         result
     }
 }
 
+/// `--file` arguments with `*`, `?` or `[` are glob patterns
+fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '['])
+}
+
+/// Restore each `--file` path, expanding glob patterns against the files in
+/// `snapshot`
+fn restore_selected_files(
+    project_root: &Path,
+    destination: Destination,
+    snapshot: &Snapshot,
+    object_store: &ObjectStore,
+    patterns: &[String],
+    dry_run: bool,
+    exact_sizes: bool,
+) -> Result<()> {
+    let paths = expand_patterns(project_root, snapshot, patterns)?;
+
+    let mut restored = 0;
+    for path in &paths {
+        if restore_single_file(
+            project_root,
+            destination,
+            snapshot,
+            object_store,
+            path,
+            dry_run,
+            exact_sizes,
+        )? {
+            restored += 1;
+        }
+    }
+
+    // A single plain path keeps its one-line output
+    if patterns.len() > 1 || patterns.iter().any(|p| is_glob(p)) {
+        if dry_run {
+            println!("Would restore {} file(s)", restored);
+        } else {
+            println!("{} Restored {} file(s)", "✓".green().bold(), restored);
+        }
+    }
+    Ok(())
+}
+
+/// Gitignore-style matcher for `--file` globs and `--exclude` patterns
+fn pattern_matcher(patterns: &[String]) -> Result<Gitignore> {
+    let mut builder = GitignoreBuilder::new("");
+    for pattern in patterns {
+        builder.add_line(None, pattern).map_err(|e| {
+            MoteError::InvalidArguments(format!("Invalid pattern '{}': {}", pattern, e))
+        })?;
+    }
+    builder
+        .build()
+        .map_err(|e| MoteError::InvalidArguments(format!("Invalid pattern: {}", e)))
+}
+
+/// Whether a project-relative path, or a directory above it, matches
+fn matches_pattern(matcher: &Gitignore, path: &str) -> bool {
+    matcher.matched_path_or_any_parents(path, false).is_ignore()
+}
+
+/// Snapshot-relative paths for `--file` arguments, in order and without
+/// duplicates. Glob patterns (gitignore-style) are expanded against the files
+/// in `snapshot`; one that matches nothing is warned about and skipped.
+fn expand_patterns(
+    project_root: &Path,
+    snapshot: &Snapshot,
+    patterns: &[String],
+) -> Result<Vec<String>> {
+    let mut paths: Vec<String> = Vec::new();
+    for pattern in patterns {
+        let relative = snapshot_relative_path(project_root, pattern);
+        let matches = if is_glob(&relative) {
+            let matcher = pattern_matcher(&[relative])?;
+            let matches: Vec<String> = snapshot
+                .files
+                .iter()
+                .filter(|f| matches_pattern(&matcher, &f.path))
+                .map(|f| f.path.clone())
+                .collect();
+            if matches.is_empty() {
+                eprintln!(
+                    "{}: pattern '{}' did not match any files in snapshot {}",
+                    "warning".yellow(),
+                    pattern,
+                    snapshot.short_id()
+                );
+            }
+            matches
+        } else {
+            vec![relative]
+        };
+        for path in matches {
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+    }
+    Ok(paths)
+}
+
+/// `--stdout`: write the one file `patterns` select to stdout, byte for byte,
+/// without touching the working tree
+fn write_to_stdout(
+    project_root: &Path,
+    snapshot: &Snapshot,
+    object_store: &ObjectStore,
+    patterns: &[String],
+) -> Result<()> {
+    let paths = expand_patterns(project_root, snapshot, patterns)?;
+    let [path] = paths.as_slice() else {
+        return Err(MoteError::InvalidArguments(format!(
+            "--stdout writes a single file, but --file selected {}",
+            paths.len()
+        )));
+    };
+    let file = snapshot
+        .find_file(path)
+        .ok_or_else(|| MoteError::FileNotFoundInSnapshot {
+            snapshot: snapshot.short_id().to_string(),
+            path: path.clone(),
+        })?;
+    let content = object_store.retrieve(&file.hash)?;
+    let mut stdout = io::stdout().lock();
+    stdout.write_all(&content)?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Restore (or delete, when absent from the snapshot) one file; returns
+/// whether anything was, or would be, changed
 fn restore_single_file(
     project_root: &Path,
+    destination: Destination,
     snapshot: &Snapshot,
     object_store: &ObjectStore,
     file_path: &str,
     dry_run: bool,
-) -> Result<()> {
+    exact_sizes: bool,
+) -> Result<bool> {
     // Convert absolute path to relative path if necessary
-    let file_path_buf = Path::new(file_path);
-    let relative_path = if file_path_buf.is_absolute() {
-        file_path_buf
-            .strip_prefix(project_root)
-            .unwrap_or(file_path_buf)
-            .to_string_lossy()
-            .to_string()
-    } else {
-        file_path.to_string()
-    };
+    let relative_path = snapshot_relative_path(project_root, file_path);
 
-    let dest = project_root.join(&relative_path);
+    let dest = destination.join(&relative_path)?;
 
     match snapshot.find_file(&relative_path) {
         Some(file_entry) => {
             // File exists in snapshot - restore it
             if dry_run {
                 println!(
-                    "{} Would restore: {} ({} bytes)",
+                    "{} Would restore: {} ({})",
                     "dry-run".cyan().bold(),
                     file_entry.path,
-                    file_entry.size
+                    format_size(file_entry.size, exact_sizes)
                 );
             } else {
                 object_store.restore_file(&file_entry.hash, &dest)?;
@@ -86,6 +356,10 @@ fn restore_single_file(
                 );
             }
         }
+        None if matches!(destination, Destination::Target(_)) => {
+            println!("{} Not in snapshot: {}", "info".blue().bold(), file_path);
+            return Ok(false);
+        }
         None => {
             // File doesn't exist in snapshot - delete it if it exists
             if dest.exists() {
@@ -109,33 +383,48 @@ fn restore_single_file(
                     "info".blue().bold(),
                     file_path
                 );
+                return Ok(false);
             }
         }
     }
-    Ok(())
+    Ok(true)
 }
 
 fn create_backup_snapshot(
     project_root: &Path,
-    ignore_file_path: &Path,
+    ignore_filter: &IgnoreFilter,
     object_store: &ObjectStore,
     snapshot_store: &SnapshotStore,
     target_snapshot: &Snapshot,
     index: &mut Index,
 ) -> Result<()> {
-    let files = collect_files(project_root, ignore_file_path, object_store, index, true);
+    let previous = snapshot_store.latest()?;
+    let (files, new_bytes, skipped) = collect_files(
+        project_root,
+        ignore_filter,
+        object_store,
+        index,
+        previous.as_ref(),
+        &CollectOptions {
+            quiet: true,
+            ..CollectOptions::default()
+        },
+    )?;
     if files.is_empty() {
         return Ok(());
     }
 
-    let backup = Snapshot::new(
+    let mut backup = Snapshot::new(
         files,
         Some(format!(
             "Backup before restore to {}",
             target_snapshot.short_id()
         )),
-        Some("auto-backup".to_string()),
+        Some(BACKUP_TRIGGER.to_string()),
     );
+    backup.new_bytes = Some(new_bytes);
+    backup.mark_partial(skipped);
+    backup.seq = snapshot_store.next_seq()?;
     snapshot_store.save(&backup)?;
     println!(
         "{} Created backup snapshot: {}",
@@ -147,19 +436,28 @@ fn create_backup_snapshot(
 
 #[allow(clippy::too_many_arguments)]
 fn restore_all_files(
-    project_root: &Path,
-    ignore_file_path: &Path,
+    destination: Destination,
+    ignore_filter: &IgnoreFilter,
     snapshot: &Snapshot,
     object_store: &ObjectStore,
     snapshot_store: &SnapshotStore,
     index: &mut Index,
-    force: bool,
-    dry_run: bool,
+    skip_backup: bool,
+    opts: &RestoreOptions,
+    threads: usize,
+    exact_sizes: bool,
+    progress: bool,
 ) -> Result<()> {
-    if !force && !dry_run {
+    let dry_run = opts.dry_run;
+    // Backups and --clean only ever apply to the working tree
+    let project_root = destination.root();
+    let exclude = pattern_matcher(&opts.exclude)?;
+    // Taken before the backup, which would otherwise hide local edits
+    let latest = snapshot_store.latest()?;
+    if !skip_backup && !dry_run {
         create_backup_snapshot(
             project_root,
-            ignore_file_path,
+            ignore_filter,
             object_store,
             snapshot_store,
             snapshot,
@@ -167,64 +465,505 @@ fn restore_all_files(
         )?;
     }
 
-    let (restored, skipped) = restore_files(project_root, snapshot, object_store, dry_run)?;
+    let outcome = restore_files(
+        destination,
+        ignore_filter,
+        snapshot,
+        object_store,
+        latest.as_ref(),
+        &exclude,
+        opts,
+        threads,
+        exact_sizes,
+        progress,
+    )?;
+    let (restored, skipped) = (outcome.changed + outcome.created, &outcome.skipped);
 
-    if dry_run {
+    if !dry_run {
+        println!("\n{} Restored {} file(s)", "✓".green().bold(), restored);
+    } else if matches!(destination, Destination::WorkingTree(_)) {
+        println!(
+            "\n{} Would restore {} file(s): {} changed, {} created, {} unchanged",
+            "dry-run".cyan().bold(),
+            restored,
+            outcome.changed,
+            outcome.created,
+            outcome.unchanged
+        );
+    } else {
         println!(
             "\n{} Would restore {} file(s)",
             "dry-run".cyan().bold(),
             restored
         );
-    } else {
-        println!("\n{} Restored {} file(s)", "✓".green().bold(), restored);
-        if skipped > 0 {
-            println!("  Skipped {} modified file(s)", skipped);
+    }
+    if outcome.excluded > 0 {
+        println!(
+            "  {} {} file(s) matching --exclude",
+            if dry_run { "Would leave" } else { "Left" },
+            outcome.excluded
+        );
+    }
+    if !skipped.is_empty() {
+        println!(
+            "  {} {} locally modified file(s); pass --force to overwrite:",
+            if dry_run { "Would skip" } else { "Skipped" },
+            skipped.len()
+        );
+        for path in skipped {
+            println!("    {}", path.yellow());
+        }
+    }
+
+    if let Some(ref missing) = snapshot.skipped_paths {
+        println!(
+            "{} Snapshot {} is partial; these files were not captured and cannot be restored:",
+            "!".yellow().bold(),
+            snapshot.short_id()
+        );
+        for path in missing {
+            println!("    {}", path.yellow());
+        }
+    }
+
+    if opts.clean {
+        let (deleted, kept) =
+            clean_untracked_files(project_root, ignore_filter, &exclude, snapshot, dry_run)?;
+        let kept = if kept > 0 {
+            format!(" ({} kept by --exclude)", kept)
+        } else {
+            String::new()
+        };
+        if dry_run {
+            println!(
+                "{} Would delete {} file(s) not in the snapshot{}",
+                "dry-run".cyan().bold(),
+                deleted,
+                kept
+            );
+        } else {
+            println!(
+                "{} Deleted {} file(s) not in the snapshot{}",
+                "✓".green().bold(),
+                deleted,
+                kept
+            );
         }
     }
     Ok(())
 }
 
-fn restore_files(
+/// Delete files the walk finds (through the same ignore rules as snapshots)
+/// that have no entry in `snapshot`, then the directories this leaves empty.
+/// The ignore file itself is always kept, as are files a partial snapshot
+/// failed to capture. Returns the deleted count and the count kept because
+/// they match `exclude`.
+fn clean_untracked_files(
     project_root: &Path,
+    ignore_filter: &IgnoreFilter,
+    exclude: &Gitignore,
     snapshot: &Snapshot,
-    object_store: &ObjectStore,
     dry_run: bool,
 ) -> Result<(u32, u32)> {
-    let mut restored = 0;
-    let skipped = 0;
+    let tracked: HashSet<&str> = snapshot
+        .files
+        .iter()
+        .map(|f| f.path.as_str())
+        .chain(snapshot.skipped_paths.iter().flatten().map(String::as_str))
+        .collect();
+    let (mut deleted, mut kept) = (0, 0);
+    let mut dirs: Vec<PathBuf> = Vec::new();
 
-    for file in &snapshot.files {
-        let dest = project_root.join(&file.path);
+    for entry in ignore_filter.walk_files(project_root)? {
+        let relative = entry
+            .path()
+            .strip_prefix(project_root)
+            .unwrap_or(entry.path());
+        let relative_str = relative.to_string_lossy();
+        if tracked.contains(relative_str.as_ref())
+            || ignore_filter.is_ignore_file(project_root, relative)
+        {
+            continue;
+        }
+        if matches_pattern(exclude, &relative_str) {
+            kept += 1;
+            continue;
+        }
 
         if dry_run {
             println!(
-                "{} Would restore: {} ({} bytes)",
+                "{} Would delete: {} (not in snapshot)",
                 "dry-run".cyan().bold(),
-                file.path,
-                file.size
+                relative_str
             );
-            restored += 1;
+        } else {
+            std::fs::remove_file(entry.path())?;
+            println!(
+                "{} Deleted: {} (not in snapshot)",
+                "✓".green().bold(),
+                relative_str.cyan()
+            );
+            dirs.extend(
+                relative
+                    .ancestors()
+                    .skip(1)
+                    .map(|dir| project_root.join(dir)),
+            );
+        }
+        deleted += 1;
+    }
+
+    // Deepest first, so a parent is tried once its children are gone;
+    // removal fails harmlessly for directories that still have entries
+    dirs.sort_by_key(|dir| std::cmp::Reverse(dir.components().count()));
+    dirs.dedup();
+    for dir in dirs {
+        if dir != project_root {
+            let _ = std::fs::remove_dir(&dir);
+        }
+    }
+    Ok((deleted, kept))
+}
+
+/// What a full restore did, or would do with `--dry-run`
+#[derive(Default)]
+struct RestoreOutcome<'s> {
+    /// Files written over different content
+    changed: u32,
+    /// Files written where none existed
+    created: u32,
+    /// Files already identical, left alone
+    unchanged: u32,
+    /// Files matching `--exclude`, left alone
+    excluded: u32,
+    /// Locally modified files left alone without `--force`
+    skipped: Vec<&'s str>,
+}
+
+/// A file `restore_files` decided to write
+struct PendingWrite<'s> {
+    file: &'s FileEntry,
+    dest: PathBuf,
+    created: bool,
+}
+
+/// Write every file of `snapshot` to `destination`. In the working tree,
+/// files that are already identical are left alone, and files whose content
+/// matches neither `snapshot` nor `latest` (local edits not in any snapshot)
+/// are skipped unless forced, as are files matching `exclude`. A dry run
+/// makes the same decisions and lists them. With `progress`, the writes are
+/// counted on a progress bar.
+#[allow(clippy::too_many_arguments)]
+fn restore_files<'s>(
+    destination: Destination,
+    ignore_filter: &IgnoreFilter,
+    snapshot: &'s Snapshot,
+    object_store: &ObjectStore,
+    latest: Option<&Snapshot>,
+    exclude: &Gitignore,
+    opts: &RestoreOptions,
+    threads: usize,
+    exact_sizes: bool,
+    progress: bool,
+) -> Result<RestoreOutcome<'s>> {
+    let mut outcome = RestoreOutcome::default();
+    let mut too_long = Vec::new();
+    let latest_files: HashMap<&str, &FileEntry> = latest
+        .map(|latest| latest.files.iter().map(|f| (f.path.as_str(), f)).collect())
+        .unwrap_or_default();
+
+    // Decide every file first; only the writes are pipelined
+    let mut to_write = Vec::new();
+    let working_tree = matches!(destination, Destination::WorkingTree(_));
+    for file in &snapshot.files {
+        if working_tree
+            && ignore_filter.is_excluded_ignore_file(destination.root(), Path::new(&file.path))
+        {
+            continue;
+        }
+        if matches_pattern(exclude, &file.path) {
+            if opts.dry_run && opts.verbose {
+                println!("{} Excluded: {}", "dry-run".cyan().bold(), file.path);
+            }
+            outcome.excluded += 1;
             continue;
         }
+        let dest = destination.join(&file.path)?;
 
-        if dest.exists() {
-            let current_hash = ObjectStore::compute_hash(&std::fs::read(&dest)?);
-            if current_hash == file.hash {
+        let state = if working_tree {
+            compare_working(&dest, file, latest_files.get(file.path.as_str()).copied())?
+        } else {
+            WorkingState::Missing
+        };
+        let created = match state {
+            WorkingState::Identical => {
+                if opts.dry_run && opts.verbose {
+                    println!("{} Unchanged: {}", "dry-run".cyan().bold(), file.path);
+                }
+                outcome.unchanged += 1;
+                continue;
+            }
+            WorkingState::Differs { in_latest: false } if !opts.force => {
+                outcome.skipped.push(file.path.as_str());
                 continue;
             }
+            WorkingState::Differs { .. } => false,
+            WorkingState::Missing => true,
+        };
+
+        if opts.dry_run {
+            println!(
+                "{} Would {}: {} ({})",
+                "dry-run".cyan().bold(),
+                if created && working_tree {
+                    "create"
+                } else {
+                    "restore"
+                },
+                file.path,
+                format_size(file.size, exact_sizes)
+            );
+            if created {
+                outcome.created += 1;
+            } else {
+                outcome.changed += 1;
+            }
+            continue;
         }
+        to_write.push(PendingWrite {
+            file,
+            dest,
+            created,
+        });
+    }
 
-        match object_store.restore_file(&file.hash, &dest) {
-            Ok(_) => restored += 1,
-            Err(e) => {
-                eprintln!(
-                    "{}: Failed to restore {}: {}",
-                    "warning".yellow(),
-                    file.path,
-                    e
-                );
+    let progress = Progress::new("Restoring", to_write.len(), progress);
+    for_each_retrieved(object_store, &to_write, threads, |pending, content| {
+        let file = pending.file;
+        match content.and_then(|content| ObjectStore::write_restored(&content, &pending.dest)) {
+            Ok(_) if pending.created => outcome.created += 1,
+            Ok(_) => outcome.changed += 1,
+            // ENAMETOOLONG tends to hit whole generated trees, so summarize below
+            Err(MoteError::Io(e)) if e.kind() == ErrorKind::InvalidFilename => {
+                too_long.push(file.path.as_str());
             }
+            Err(e) => progress.warn(format!("Failed to restore {}: {}", file.path, e)),
+        }
+        progress.inc(None);
+    });
+    progress.finish();
+    if !too_long.is_empty() {
+        eprintln!(
+            "{}: Could not restore {} file(s) whose paths are too long for this filesystem{}",
+            "warning".yellow(),
+            too_long.len(),
+            under_prefix(too_long.iter().copied())
+        );
+    }
+    Ok(outcome)
+}
+
+/// Hand each pending file's retrieved (decompressed and verified) content to
+/// `write`, in order. With more than one thread, workers retrieve ahead of
+/// the writer: file `i` goes to worker `i % threads`, whose bounded channel
+/// caps how much content is held in memory.
+fn for_each_retrieved<'s>(
+    object_store: &ObjectStore,
+    pending: &[PendingWrite<'s>],
+    threads: usize,
+    mut write: impl FnMut(&PendingWrite<'s>, Result<Vec<u8>>),
+) {
+    let threads = threads.clamp(1, pending.len().max(1));
+    if threads == 1 {
+        for item in pending {
+            write(item, object_store.retrieve(&item.file.hash));
         }
+        return;
     }
-    Ok((restored, skipped))
+
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|worker| {
+                let (sender, receiver) = std::sync::mpsc::sync_channel(PREFETCH_DEPTH);
+                scope.spawn(move || {
+                    for item in pending.iter().skip(worker).step_by(threads) {
+                        // Nothing left to do once the writer has gone
+                        if sender.send(object_store.retrieve(&item.file.hash)).is_err() {
+                            break;
+                        }
+                    }
+                });
+                receiver
+            })
+            .collect();
+        for (i, item) in pending.iter().enumerate() {
+            // A worker only disconnects early by panicking, which the scope re-raises
+            let Ok(content) = workers[i % threads].recv() else {
+                break;
+            };
+            write(item, content);
+        }
+    });
+}
+
+/// How the file at a restore destination compares with a snapshot entry
+enum WorkingState {
+    Missing,
+    Identical,
+    /// `in_latest` when the latest snapshot holds the current content
+    Differs {
+        in_latest: bool,
+    },
+}
+
+/// Compare the file at `dest` with `file` (and the latest snapshot's entry
+/// for the same path). Sizes are compared first; the file is only read and
+/// hashed when its size matches one of the entries.
+fn compare_working(
+    dest: &Path,
+    file: &FileEntry,
+    latest: Option<&FileEntry>,
+) -> Result<WorkingState> {
+    if !dest.exists() {
+        return Ok(WorkingState::Missing);
+    }
+    let size = std::fs::metadata(dest)?.len();
+    let latest = latest.filter(|latest| latest.size == size);
+    if size != file.size && latest.is_none() {
+        return Ok(WorkingState::Differs { in_latest: false });
+    }
+    let hash = ObjectStore::compute_hash(&std::fs::read(dest)?);
+    if hash == file.hash {
+        return Ok(WorkingState::Identical);
+    }
+    Ok(WorkingState::Differs {
+        in_latest: latest.is_some_and(|latest| latest.hash == hash),
+    })
+}
+
+/// `--interactive`: ask about each file that differs from the snapshot, then
+/// back up and restore the chosen ones in one pass
+fn restore_interactively(
+    project_root: &Path,
+    ignore_filter: &IgnoreFilter,
+    snapshot: &Snapshot,
+    object_store: &ObjectStore,
+    snapshot_store: &SnapshotStore,
+    index: &mut Index,
+    skip_backup: bool,
+) -> Result<()> {
+    let mut changed = Vec::new();
+    for file in &snapshot.files {
+        if ignore_filter.is_excluded_ignore_file(project_root, Path::new(&file.path)) {
+            continue;
+        }
+        let dest = join_project_path(project_root, &file.path);
+        if !matches!(compare_working(&dest, file, None)?, WorkingState::Identical) {
+            changed.push(file);
+        }
+    }
+    if changed.is_empty() {
+        println!(
+            "{} Working tree already matches snapshot {}",
+            "✓".green().bold(),
+            snapshot.short_id()
+        );
+        return Ok(());
+    }
+
+    let selected = pick_files(project_root, object_store, &changed)?;
+    if selected.is_empty() {
+        println!("{} No files selected", "!".yellow().bold());
+        return Ok(());
+    }
+
+    if !skip_backup {
+        create_backup_snapshot(
+            project_root,
+            ignore_filter,
+            object_store,
+            snapshot_store,
+            snapshot,
+            index,
+        )?;
+    }
+    for file in &selected {
+        let dest = join_project_path(project_root, &file.path);
+        object_store.restore_file(&file.hash, &dest)?;
+        println!("{} Restored: {}", "✓".green().bold(), file.path.cyan());
+    }
+    println!(
+        "\n{} Restored {} file(s)",
+        "✓".green().bold(),
+        selected.len()
+    );
+    Ok(())
+}
+
+/// Ask `[y/n/a/q/d]` for each file: restore it, skip it, restore it and all
+/// the rest, stop asking (keeping the answers so far), or show its diff
+/// first. End of input stops asking too.
+fn pick_files<'s>(
+    project_root: &Path,
+    object_store: &ObjectStore,
+    changed: &[&'s FileEntry],
+) -> Result<Vec<&'s FileEntry>> {
+    let mut selected = Vec::new();
+    for (i, file) in changed.iter().enumerate() {
+        let dest = join_project_path(project_root, &file.path);
+        let state = if dest.exists() { "modified" } else { "deleted" };
+        loop {
+            print!("Restore {} ({})? [y/n/a/q/d] ", file.path.cyan(), state);
+            io::stdout().flush()?;
+            let mut input = String::new();
+            if io::stdin().read_line(&mut input)? == 0 {
+                println!();
+                return Ok(selected);
+            }
+            match input.trim().to_lowercase().as_str() {
+                "y" | "yes" => {
+                    selected.push(*file);
+                    break;
+                }
+                "n" | "no" => break,
+                "a" => {
+                    selected.extend_from_slice(&changed[i..]);
+                    return Ok(selected);
+                }
+                "q" => return Ok(selected),
+                "d" => print_file_diff(object_store, file, &dest)?,
+                _ => println!(
+                    "  y: restore, n: skip, a: restore this and the rest, q: stop asking, \
+                     d: show diff"
+                ),
+            }
+        }
+    }
+    Ok(selected)
+}
+
+/// Diff from the snapshot's version of `file` to the working tree, as
+/// `snap diff` shows it
+fn print_file_diff(object_store: &ObjectStore, file: &FileEntry, dest: &Path) -> Result<()> {
+    let current = match std::fs::read(dest) {
+        Ok(content) => Some(content),
+        Err(e) if e.kind() == ErrorKind::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+    let mut output = DiffOutput::default();
+    generate_unified_diff_with_content(
+        object_store,
+        &file.path,
+        Some(&file.hash),
+        current.as_deref(),
+        PICK_DIFF_CONTEXT,
+        Whitespace::default(),
+        &mut output,
+    )?;
+    // Follows the global `--color` choice
+    print!(
+        "{}",
+        output.render(colored::control::SHOULD_COLORIZE.should_colorize())
+    );
+    Ok(())
 }
@@ -0,0 +1,289 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use chrono::{Datelike, Duration, NaiveDate};
+use colored::*;
+use serde::Serialize;
+
+use crate::cli::StatsPeriod;
+use crate::commands::CommandContext;
+use crate::error::Result;
+use crate::size_format::format_size;
+use crate::storage::{list_all_objects, object_first_seen, ObjectStore, SnapshotStore};
+
+/// Options for `mote stats`
+#[derive(Debug, Default)]
+pub struct StatsOptions {
+    /// Objects and bytes added per period instead of totals
+    pub history: bool,
+    pub period: StatsPeriod,
+    pub json: bool,
+}
+
+/// Object count and compressed bytes on disk
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize)]
+struct Usage {
+    objects: usize,
+    bytes: u64,
+}
+
+impl Usage {
+    fn add(&mut self, bytes: u64) {
+        self.objects += 1;
+        self.bytes += bytes;
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct Bucket {
+    /// First day of the week (Monday) or month, UTC
+    start: NaiveDate,
+    #[serde(flatten)]
+    usage: Usage,
+}
+
+/// Objects on disk bucketed by when a snapshot first referenced them
+#[derive(Debug, Serialize)]
+struct ObjectHistory {
+    period: &'static str,
+    /// Oldest first
+    buckets: Vec<Bucket>,
+    /// Objects no snapshot references (what `mote snap gc` would delete)
+    reclaimable: Usage,
+}
+
+impl ObjectHistory {
+    fn referenced(&self) -> Usage {
+        let mut total = Usage::default();
+        for bucket in &self.buckets {
+            total.objects += bucket.usage.objects;
+            total.bytes += bucket.usage.bytes;
+        }
+        total
+    }
+}
+
+pub fn cmd_stats(ctx: &CommandContext, opts: &StatsOptions) -> Result<()> {
+    let exact_sizes = ctx.config.display.exact_sizes;
+    let location = ctx.resolve_location()?;
+    let snapshot_store = SnapshotStore::new(location.snapshots_dir());
+    let history = object_history(&snapshot_store, &location.objects_dir(), opts.period)?;
+
+    if !opts.history {
+        let referenced = history.referenced();
+        if opts.json {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&serde_json::json!({
+                    "snapshots": snapshot_store.search_entries()?.len(),
+                    "objects": referenced.objects,
+                    "bytes": referenced.bytes,
+                    "reclaimable": history.reclaimable,
+                }))?
+            );
+        } else {
+            println!("Snapshots:   {}", snapshot_store.search_entries()?.len());
+            print_usage_line("Objects:", referenced, exact_sizes);
+            print_usage_line("Reclaimable:", history.reclaimable, exact_sizes);
+        }
+        return Ok(());
+    }
+
+    if opts.json {
+        println!("{}", serde_json::to_string_pretty(&history)?);
+        return Ok(());
+    }
+
+    let header = match opts.period {
+        StatsPeriod::Week => "Week of",
+        StatsPeriod::Month => "Month",
+    };
+    println!(
+        "{:<12} {:>8} {:>12}",
+        header.bold(),
+        "Objects".bold(),
+        "Size".bold()
+    );
+    for bucket in &history.buckets {
+        let label = match opts.period {
+            StatsPeriod::Week => bucket.start.format("%Y-%m-%d").to_string(),
+            StatsPeriod::Month => bucket.start.format("%Y-%m").to_string(),
+        };
+        println!(
+            "{:<12} {:>8} {:>12}",
+            label,
+            bucket.usage.objects,
+            format_size(bucket.usage.bytes, exact_sizes)
+        );
+    }
+    println!(
+        "{:<12} {:>8} {:>12}",
+        "reclaimable".dimmed(),
+        history.reclaimable.objects,
+        format_size(history.reclaimable.bytes, exact_sizes)
+    );
+    Ok(())
+}
+
+fn print_usage_line(label: &str, usage: Usage, exact_sizes: bool) {
+    println!(
+        "{:<12} {} ({})",
+        label,
+        usage.objects,
+        format_size(usage.bytes, exact_sizes)
+    );
+}
+
+/// Attribute every object on disk to the period of the earliest snapshot
+/// referencing it; sizes are the compressed object files
+fn object_history(
+    snapshot_store: &SnapshotStore,
+    objects_dir: &Path,
+    period: StatsPeriod,
+) -> Result<ObjectHistory> {
+    let first_seen = object_first_seen(snapshot_store)?;
+    let mut buckets: BTreeMap<NaiveDate, Usage> = BTreeMap::new();
+    let mut reclaimable = Usage::default();
+
+    for hash in list_all_objects(objects_dir)? {
+        let bytes = fs::metadata(objects_dir.join(ObjectStore::relative_path(&hash)))
+            .map(|m| m.len())
+            .unwrap_or(0);
+        match first_seen.get(&hash) {
+            Some(timestamp) => buckets
+                .entry(period_start(timestamp.date_naive(), period))
+                .or_default()
+                .add(bytes),
+            None => reclaimable.add(bytes),
+        }
+    }
+
+    Ok(ObjectHistory {
+        period: match period {
+            StatsPeriod::Week => "week",
+            StatsPeriod::Month => "month",
+        },
+        buckets: buckets
+            .into_iter()
+            .map(|(start, usage)| Bucket { start, usage })
+            .collect(),
+        reclaimable,
+    })
+}
+
+fn period_start(date: NaiveDate, period: StatsPeriod) -> NaiveDate {
+    match period {
+        StatsPeriod::Week => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+        StatsPeriod::Month => date.with_day(1).unwrap(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{FileEntry, Snapshot};
+    use chrono::{TimeZone, Utc};
+    use tempfile::TempDir;
+
+    struct Fixture {
+        _temp: TempDir,
+        snapshots: SnapshotStore,
+        objects: ObjectStore,
+        objects_dir: std::path::PathBuf,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            let temp = TempDir::new().unwrap();
+            let objects_dir = temp.path().join("objects");
+            Self {
+                snapshots: SnapshotStore::new(temp.path().join("snapshots")),
+                objects: ObjectStore::new(objects_dir.clone()),
+                objects_dir,
+                _temp: temp,
+            }
+        }
+
+        /// Store `contents` and save a snapshot of them taken at the given UTC date
+        fn snapshot(&self, (y, m, d): (i32, u32, u32), contents: &[&str]) {
+            let files = contents
+                .iter()
+                .map(|content| {
+                    let (hash, _) = self.objects.store(content.as_bytes()).unwrap();
+                    FileEntry {
+                        path: format!("{}.txt", content),
+                        hash,
+                        size: content.len() as u64,
+                        mode: None,
+                        kind: None,
+                    }
+                })
+                .collect();
+            let mut snapshot = Snapshot::new(files, None, None);
+            snapshot.timestamp = Utc.with_ymd_and_hms(y, m, d, 12, 0, 0).unwrap();
+            snapshot.id = format!("{:016x}", snapshot.timestamp.timestamp());
+            snapshot.seq = self.snapshots.next_seq().unwrap();
+            self.snapshots.save(&snapshot).unwrap();
+        }
+
+        fn history(&self, period: StatsPeriod) -> ObjectHistory {
+            object_history(&self.snapshots, &self.objects_dir, period).unwrap()
+        }
+    }
+
+    fn counts(history: &ObjectHistory) -> Vec<(String, usize)> {
+        history
+            .buckets
+            .iter()
+            .map(|b| (b.start.to_string(), b.usage.objects))
+            .collect()
+    }
+
+    #[test]
+    fn test_objects_bucket_by_first_referencing_snapshot() {
+        let fixture = Fixture::new();
+        // Wednesday and Sunday of the same ISO week, then the next Monday
+        fixture.snapshot((2026, 3, 4), &["a", "b"]);
+        fixture.snapshot((2026, 3, 8), &["a", "b", "c"]);
+        fixture.snapshot((2026, 3, 9), &["a", "d"]);
+        fixture.snapshot((2026, 4, 2), &["e"]);
+
+        let history = fixture.history(StatsPeriod::Week);
+        assert_eq!(
+            counts(&history),
+            vec![
+                ("2026-03-02".to_string(), 3),
+                ("2026-03-09".to_string(), 1),
+                ("2026-03-30".to_string(), 1),
+            ]
+        );
+        assert_eq!(history.reclaimable, Usage::default());
+        assert!(history.buckets.iter().all(|b| b.usage.bytes > 0));
+
+        let history = fixture.history(StatsPeriod::Month);
+        assert_eq!(
+            counts(&history),
+            vec![("2026-03-01".to_string(), 4), ("2026-04-01".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn test_unreferenced_objects_are_reclaimable() {
+        let fixture = Fixture::new();
+        fixture.snapshot((2026, 3, 4), &["kept"]);
+        let (orphan, _) = fixture.objects.store(b"orphan").unwrap();
+
+        let history = fixture.history(StatsPeriod::Week);
+        assert_eq!(counts(&history), vec![("2026-03-02".to_string(), 1)]);
+        assert_eq!(history.reclaimable.objects, 1);
+        let orphan_size = fs::metadata(
+            fixture
+                .objects_dir
+                .join(ObjectStore::relative_path(&orphan)),
+        )
+        .unwrap()
+        .len();
+        assert_eq!(history.reclaimable.bytes, orphan_size);
+    }
+}
@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use colored::*;
+
+use super::scan_files;
+use crate::commands::CommandContext;
+use crate::error::Result;
+use crate::storage::{Index, SnapshotStore};
+use crate::time_format::format_timestamp;
+
+/// Options for `mote status`
+#[derive(Debug, Default)]
+pub struct StatusOptions {
+    /// `A`/`M`/`D`, a tab and the path per line, uncolored, for scripts
+    pub porcelain: bool,
+}
+
+/// How a working-tree file differs from the latest snapshot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Change {
+    Added,
+    Modified,
+    Deleted,
+}
+
+impl Change {
+    fn code(self) -> char {
+        match self {
+            Change::Added => 'A',
+            Change::Modified => 'M',
+            Change::Deleted => 'D',
+        }
+    }
+
+    fn label(self) -> ColoredString {
+        match self {
+            Change::Added => "added:   ".green(),
+            Change::Modified => "modified:".yellow(),
+            Change::Deleted => "deleted: ".red(),
+        }
+    }
+}
+
+/// List working-tree changes since the latest snapshot, hashing only files
+/// whose index entry is stale. Returns whether anything changed, so the
+/// caller can exit non-zero for a dirty tree.
+pub fn cmd_status(ctx: &CommandContext, opts: &StatusOptions) -> Result<bool> {
+    let location = ctx.resolve_location()?;
+    let snapshot_store = SnapshotStore::new(location.snapshots_dir());
+    let ignore_filter = ctx.ignore_filter();
+    let current = scan_files(
+        ctx.project_root,
+        &ignore_filter,
+        &Index::load(&location.index_path())?,
+        ctx.config.snapshot.status_threads,
+    )?;
+
+    let Some(latest) = snapshot_store.latest()? else {
+        if opts.porcelain {
+            let mut paths: Vec<&str> = current.iter().map(|f| f.path.as_str()).collect();
+            paths.sort_unstable();
+            for path in paths {
+                println!("A\t{}", path);
+            }
+        } else {
+            println!(
+                "{} No snapshots yet; {} file(s) would be tracked",
+                "!".yellow().bold(),
+                current.len()
+            );
+        }
+        return Ok(!current.is_empty());
+    };
+
+    let stored: HashMap<&str, &str> = latest
+        .files
+        .iter()
+        .filter(|f| !ignore_filter.is_excluded_ignore_file(ctx.project_root, Path::new(&f.path)))
+        .map(|f| (f.path.as_str(), f.hash.as_str()))
+        .collect();
+    let working: HashMap<&str, &str> = current
+        .iter()
+        .map(|f| (f.path.as_str(), f.hash.as_str()))
+        .collect();
+
+    let mut changes: Vec<(&str, Change)> = working
+        .iter()
+        .filter_map(|(path, hash)| match stored.get(path) {
+            None => Some((*path, Change::Added)),
+            Some(stored_hash) if stored_hash != hash => Some((*path, Change::Modified)),
+            Some(_) => None,
+        })
+        .chain(
+            stored
+                .keys()
+                .filter(|path| !working.contains_key(*path))
+                .map(|path| (*path, Change::Deleted)),
+        )
+        .collect();
+    changes.sort_unstable_by(|a, b| a.0.cmp(b.0));
+
+    if opts.porcelain {
+        for (path, change) in &changes {
+            println!("{}\t{}", change.code(), path);
+        }
+        return Ok(!changes.is_empty());
+    }
+
+    let since = format!(
+        "snapshot {} ({})",
+        latest.short_id().cyan(),
+        format_timestamp(&latest.timestamp, ctx.config.display.timezone, false)
+    );
+    if changes.is_empty() {
+        println!("{} No changes since {}", "✓".green().bold(), since);
+        return Ok(false);
+    }
+
+    println!("Changes since {}:", since);
+    for (path, change) in &changes {
+        println!("  {} {}", change.label(), path);
+    }
+    let count = |kind: Change| changes.iter().filter(|(_, c)| *c == kind).count();
+    println!(
+        "{} added, {} modified, {} deleted",
+        count(Change::Added),
+        count(Change::Modified),
+        count(Change::Deleted)
+    );
+    Ok(true)
+}
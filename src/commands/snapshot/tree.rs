@@ -0,0 +1,186 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use super::plural;
+use crate::size_format::format_size;
+use crate::storage::FileEntry;
+
+/// Directory in a snapshot's file tree; counts and sizes include everything below it
+#[derive(Debug, Default)]
+struct DirNode {
+    dirs: BTreeMap<String, DirNode>,
+    files: BTreeMap<String, u64>,
+    file_count: usize,
+    size: u64,
+}
+
+impl DirNode {
+    fn build(files: &[FileEntry]) -> Self {
+        let mut root = DirNode::default();
+        for file in files {
+            root.insert(&file.path, file.size);
+        }
+        root
+    }
+
+    fn insert(&mut self, path: &str, size: u64) {
+        let mut node = self;
+        let mut components = path.split('/').filter(|c| !c.is_empty()).peekable();
+        while let Some(component) = components.next() {
+            node.file_count += 1;
+            node.size += size;
+            if components.peek().is_none() {
+                node.files.insert(component.to_string(), size);
+                return;
+            }
+            node = node.dirs.entry(component.to_string()).or_default();
+        }
+    }
+}
+
+/// Render `files` as an indented tree, directories first, then files, each
+/// sorted by name. Below `max_depth` levels a directory's contents collapse
+/// into a single `… N files` line. `exact` prints sizes as byte counts.
+pub(super) fn render_tree(files: &[FileEntry], max_depth: Option<usize>, exact: bool) -> String {
+    let root = DirNode::build(files);
+    let mut output = String::new();
+    writeln!(output, "./ ({})", dir_summary(&root, exact)).unwrap();
+    render_children(&root, "", 1, max_depth, exact, &mut output);
+    output
+}
+
+fn render_children(
+    node: &DirNode,
+    prefix: &str,
+    depth: usize,
+    max_depth: Option<usize>,
+    exact: bool,
+    output: &mut String,
+) {
+    if max_depth.is_some_and(|max| depth > max) {
+        if node.file_count > 0 {
+            writeln!(
+                output,
+                "{}└── … {} file{}",
+                prefix,
+                node.file_count,
+                plural(node.file_count)
+            )
+            .unwrap();
+        }
+        return;
+    }
+
+    let total = node.dirs.len() + node.files.len();
+    let entries = node
+        .dirs
+        .iter()
+        .map(|(name, dir)| (name, Some(dir), dir.size))
+        .chain(node.files.iter().map(|(name, size)| (name, None, *size)));
+
+    for (i, (name, dir, size)) in entries.enumerate() {
+        let last = i + 1 == total;
+        let branch = if last { "└── " } else { "├── " };
+        match dir {
+            Some(dir) => {
+                writeln!(
+                    output,
+                    "{}{}{}/ ({})",
+                    prefix,
+                    branch,
+                    name,
+                    dir_summary(dir, exact)
+                )
+                .unwrap();
+                let child_prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+                render_children(dir, &child_prefix, depth + 1, max_depth, exact, output);
+            }
+            None => writeln!(
+                output,
+                "{}{}{} ({})",
+                prefix,
+                branch,
+                name,
+                format_size(size, exact)
+            )
+            .unwrap(),
+        }
+    }
+}
+
+fn dir_summary(node: &DirNode, exact: bool) -> String {
+    format!(
+        "{} file{}, {}",
+        node.file_count,
+        plural(node.file_count),
+        format_size(node.size, exact)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &str, size: u64) -> FileEntry {
+        FileEntry {
+            path: path.to_string(),
+            hash: String::new(),
+            size,
+            mode: None,
+            kind: None,
+        }
+    }
+
+    #[test]
+    fn test_render_tree_sorts_directories_first() {
+        let files = vec![
+            entry("z.txt", 1),
+            entry("src/main.rs", 10),
+            entry("a.txt", 2),
+            entry("src/cmd/b.rs", 20),
+            entry("docs/x.md", 4),
+        ];
+        let expected = "\
+./ (5 files, 37 B)
+├── docs/ (1 file, 4 B)
+│   └── x.md (4 B)
+├── src/ (2 files, 30 B)
+│   ├── cmd/ (1 file, 20 B)
+│   │   └── b.rs (20 B)
+│   └── main.rs (10 B)
+├── a.txt (2 B)
+└── z.txt (1 B)
+";
+        assert_eq!(render_tree(&files, None, false), expected);
+    }
+
+    #[test]
+    fn test_render_tree_collapses_below_depth() {
+        let files = vec![
+            entry("src/main.rs", 1),
+            entry("src/cmd/a.rs", 1),
+            entry("src/cmd/b.rs", 1),
+            entry("README.md", 1),
+        ];
+        let output = render_tree(&files, Some(1), true);
+        assert!(output.contains("├── src/ (3 files, 3 bytes)\n│   └── … 3 files\n"));
+        assert!(output.contains("└── README.md (1 bytes)\n"));
+        assert!(!output.contains("main.rs"));
+
+        let output = render_tree(&files, Some(0), false);
+        assert_eq!(output, "./ (4 files, 4 B)\n└── … 4 files\n");
+    }
+
+    #[test]
+    fn test_render_tree_deep_paths() {
+        let components: Vec<String> = (0..200).map(|i| format!("d{}", i)).collect();
+        let path = format!("{}/leaf.txt", components.join("/"));
+        let output = render_tree(&[entry(&path, 3)], None, false);
+        assert_eq!(output.lines().count(), 202);
+        assert!(output
+            .lines()
+            .last()
+            .unwrap()
+            .ends_with("└── leaf.txt (3 B)"));
+    }
+}
@@ -0,0 +1,137 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+use colored::*;
+
+use super::confirm;
+use crate::commands::CommandContext;
+use crate::error::{MoteError, Result};
+use crate::storage::{list_all_objects, object_disk_size, ObjectStore, SnapshotStore};
+
+/// Options for `mote snap verify`
+#[derive(Debug, Default)]
+pub struct VerifyOptions {
+    /// Decompress every referenced object and recompute its hash
+    pub full: bool,
+    /// Remove snapshot files that fail to parse
+    pub delete_corrupt: bool,
+    /// Remove them without asking
+    pub force: bool,
+}
+
+/// First snapshot and path referencing an object, and how many snapshots do
+struct Reference {
+    snapshot: String,
+    path: String,
+    snapshots: usize,
+}
+
+/// Check that every snapshot loads and every object it references is stored
+/// (and, with `--full`, intact). Orphaned objects are reported but are not a
+/// problem: deleting snapshots leaves them until the next GC.
+pub fn cmd_verify(ctx: &CommandContext, opts: &VerifyOptions) -> Result<()> {
+    if opts.delete_corrupt {
+        ctx.ensure_writable()?;
+    }
+    let location = ctx.resolve_location()?;
+    let snapshot_store = SnapshotStore::new(location.snapshots_dir());
+    let objects_dir = location.objects_dir();
+
+    let mut unreadable = Vec::new();
+    let mut references: BTreeMap<String, Reference> = BTreeMap::new();
+    let mut checked = 0;
+    for (path, snapshot) in snapshot_store.load_each()? {
+        let snapshot = match snapshot {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                println!("  {}  {}: {}", "unreadable".red().bold(), path.display(), e);
+                unreadable.push(path);
+                continue;
+            }
+        };
+        checked += 1;
+        for file in &snapshot.files {
+            let reference = references
+                .entry(file.hash.clone())
+                .or_insert_with(|| Reference {
+                    snapshot: snapshot.short_id().to_string(),
+                    path: file.path.clone(),
+                    snapshots: 0,
+                });
+            reference.snapshots += 1;
+        }
+    }
+
+    let object_store = ObjectStore::new(objects_dir.clone());
+    let mut missing = 0;
+    let mut corrupt = 0;
+    for (hash, reference) in &references {
+        let problem = if object_disk_size(&objects_dir, hash)?.is_none() {
+            missing += 1;
+            "missing".red().bold()
+        } else if opts.full && object_store.verify(hash).is_err() {
+            corrupt += 1;
+            "corrupt".red().bold()
+        } else {
+            continue;
+        };
+        let others = match reference.snapshots {
+            1 => String::new(),
+            n => format!(" and {} other snapshot(s)", n - 1),
+        };
+        println!(
+            "  {}  {}  {} in {}{}",
+            problem,
+            hash.get(..12).unwrap_or(hash),
+            reference.path,
+            reference.snapshot.cyan(),
+            others
+        );
+    }
+
+    let orphaned = list_all_objects(&objects_dir)?
+        .iter()
+        .filter(|hash| !references.contains_key(*hash))
+        .count();
+
+    println!(
+        "Checked {} snapshot(s) referencing {} object(s)",
+        checked,
+        references.len()
+    );
+    println!("  Missing objects:           {}", missing);
+    if opts.full {
+        println!("  Corrupt objects:           {}", corrupt);
+    } else {
+        println!("  Corrupt objects:           not checked (pass --full)");
+    }
+    println!("  Unreadable snapshot files: {}", unreadable.len());
+    println!(
+        "  Orphaned objects:          {} (reclaim with 'mote snap gc')",
+        orphaned
+    );
+
+    if opts.delete_corrupt && !unreadable.is_empty() {
+        let question = format!("Delete {} unreadable snapshot file(s)?", unreadable.len());
+        if opts.force || confirm(&question, "deleting unreadable snapshot files")? {
+            for path in &unreadable {
+                fs::remove_file(path)?;
+            }
+            println!(
+                "{} Deleted {} unreadable snapshot file(s)",
+                "✓".green().bold(),
+                unreadable.len()
+            );
+            unreadable.clear();
+        } else {
+            println!("{} Deletion cancelled", "!".yellow().bold());
+        }
+    }
+
+    let problems = missing + corrupt + unreadable.len();
+    if problems > 0 {
+        return Err(MoteError::VerifyFailed { problems });
+    }
+    println!("{} Storage is consistent", "✓".green().bold());
+    Ok(())
+}
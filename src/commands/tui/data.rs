@@ -0,0 +1,198 @@
+use crate::commands::snapshot::{diff_snapshots, DiffOutput, TextDiffOptions};
+use crate::config::DisplayTimezone;
+use crate::error::Result;
+use crate::storage::{FileEntry, FileKind, ObjectStore, Snapshot, SnapshotStore};
+use crate::time_format::format_timestamp;
+
+/// Content of a stored file, ready for display
+#[derive(Debug, PartialEq)]
+pub enum FileContent {
+    Text(String),
+    Binary { size: usize },
+}
+
+/// Read-only view over a store that feeds the TUI widgets.
+/// Snapshots are loaded once, newest first.
+pub struct HistoryData {
+    snapshots: Vec<Snapshot>,
+    object_store: ObjectStore,
+    timezone: DisplayTimezone,
+    ignore_mode: bool,
+}
+
+impl HistoryData {
+    pub fn load(
+        snapshot_store: &SnapshotStore,
+        object_store: ObjectStore,
+        timezone: DisplayTimezone,
+        ignore_mode: bool,
+    ) -> Result<Self> {
+        Ok(Self {
+            snapshots: snapshot_store.list()?,
+            object_store,
+            timezone,
+            ignore_mode,
+        })
+    }
+
+    pub fn snapshots(&self) -> &[Snapshot] {
+        &self.snapshots
+    }
+
+    /// One-line summary of a snapshot: short id, date, message, trigger
+    pub fn summary(&self, index: usize) -> Option<String> {
+        let snapshot = self.snapshots.get(index)?;
+        let mut line = format!(
+            "{}  {}  {}",
+            snapshot.short_id(),
+            format_timestamp(&snapshot.timestamp, self.timezone, false),
+            snapshot.message.as_deref().unwrap_or("-")
+        );
+        if let Some(ref trigger) = snapshot.trigger {
+            line.push_str(&format!("  [{}]", trigger));
+        }
+        Some(line)
+    }
+
+    /// Files of a snapshot, sorted by path
+    pub fn files(&self, index: usize) -> Vec<&FileEntry> {
+        let mut files: Vec<&FileEntry> = self
+            .snapshots
+            .get(index)
+            .map(|s| s.files.iter().collect())
+            .unwrap_or_default();
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        files
+    }
+
+    pub fn file_content(&self, file: &FileEntry) -> Result<FileContent> {
+        if file.is_known_binary() {
+            return Ok(FileContent::Binary {
+                size: file.size as usize,
+            });
+        }
+        let content = self.object_store.retrieve(&file.hash)?;
+        if FileKind::detect(&content) == FileKind::Binary {
+            return Ok(FileContent::Binary {
+                size: content.len(),
+            });
+        }
+        Ok(FileContent::Text(
+            String::from_utf8_lossy(&content).into_owned(),
+        ))
+    }
+
+    /// Unified diff of a snapshot against the one before it.
+    /// The oldest snapshot is compared against an empty tree.
+    pub fn diff_with_previous(&self, index: usize) -> Result<String> {
+        let mut output = DiffOutput::default();
+        let Some(snapshot) = self.snapshots.get(index) else {
+            return Ok(String::new());
+        };
+
+        let empty;
+        let previous = match self.snapshots.get(index + 1) {
+            Some(previous) => previous,
+            None => {
+                empty = Snapshot::new(Vec::new(), None, None);
+                &empty
+            }
+        };
+
+        diff_snapshots(
+            previous,
+            snapshot,
+            &self.object_store,
+            &TextDiffOptions {
+                ignore_mode: self.ignore_mode,
+                tz: self.timezone,
+                ..TextDiffOptions::default()
+            },
+            &mut output,
+        )?;
+        Ok(output.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn setup() -> (TempDir, HistoryData) {
+        let temp = TempDir::new().unwrap();
+        let object_store = ObjectStore::new(temp.path().join("objects"));
+        let snapshot_store = SnapshotStore::new(temp.path().join("snapshots"));
+        fs::create_dir_all(temp.path().join("snapshots")).unwrap();
+
+        let entry = |path: &str, content: &[u8]| {
+            let (hash, _) = object_store.store(content).unwrap();
+            FileEntry {
+                path: path.to_string(),
+                hash,
+                size: content.len() as u64,
+                mode: None,
+                kind: None,
+            }
+        };
+
+        let mut first = Snapshot::new(
+            vec![entry("b.txt", b"hello\n"), entry("a.bin", b"\x00\x01\x02")],
+            Some("first".to_string()),
+            None,
+        );
+        first.seq = snapshot_store.next_seq().unwrap();
+        snapshot_store.save(&first).unwrap();
+
+        let mut second = Snapshot::new(
+            vec![entry("b.txt", b"hello world\n")],
+            Some("second".to_string()),
+            Some("manual".to_string()),
+        );
+        second.seq = snapshot_store.next_seq().unwrap();
+        snapshot_store.save(&second).unwrap();
+
+        let data =
+            HistoryData::load(&snapshot_store, object_store, DisplayTimezone::Utc, false).unwrap();
+        (temp, data)
+    }
+
+    #[test]
+    fn test_snapshots_are_newest_first_with_summaries() {
+        let (_temp, data) = setup();
+        assert_eq!(data.snapshots().len(), 2);
+        let summary = data.summary(0).unwrap();
+        assert!(summary.contains("second"));
+        assert!(summary.ends_with("[manual]"));
+        assert!(data.summary(2).is_none());
+    }
+
+    #[test]
+    fn test_files_sorted_and_content_detects_binary() {
+        let (_temp, data) = setup();
+        let files = data.files(1);
+        let paths: Vec<_> = files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, ["a.bin", "b.txt"]);
+
+        assert_eq!(
+            data.file_content(files[0]).unwrap(),
+            FileContent::Binary { size: 3 }
+        );
+        assert_eq!(
+            data.file_content(files[1]).unwrap(),
+            FileContent::Text("hello\n".to_string())
+        );
+    }
+
+    #[test]
+    fn test_diff_with_previous() {
+        let (_temp, data) = setup();
+        let diff = data.diff_with_previous(0).unwrap();
+        assert!(diff.contains("-hello\n"));
+        assert!(diff.contains("+hello world\n"));
+
+        let initial = data.diff_with_previous(1).unwrap();
+        assert!(initial.contains("+hello\n"));
+    }
+}
@@ -0,0 +1,214 @@
+//! Read-only terminal browser for snapshot history (`mote tui`)
+
+mod data;
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, List, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+
+use crate::commands::CommandContext;
+use crate::error::Result;
+use crate::storage::SnapshotStore;
+use data::{FileContent, HistoryData};
+
+enum View {
+    Snapshots,
+    Files,
+    /// Scrollable text pane (file content or diff)
+    Text {
+        title: String,
+        body: String,
+        scroll: u16,
+    },
+}
+
+struct App {
+    data: HistoryData,
+    view: View,
+    snapshot_state: ListState,
+    file_state: ListState,
+}
+
+pub fn cmd_tui(ctx: &CommandContext) -> Result<()> {
+    let location = ctx.resolve_location()?;
+    let snapshot_store = SnapshotStore::new(location.snapshots_dir());
+    let data = HistoryData::load(
+        &snapshot_store,
+        ctx.object_store(&location),
+        ctx.config.display.timezone,
+        ctx.config.diff.ignore_mode_changes,
+    )?;
+
+    let mut app = App {
+        data,
+        view: View::Snapshots,
+        snapshot_state: ListState::default().with_selected(Some(0)),
+        file_state: ListState::default(),
+    };
+
+    let mut terminal = ratatui::init();
+    let result = app.run(&mut terminal);
+    ratatui::restore();
+    result
+}
+
+impl App {
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            match key.code {
+                KeyCode::Char('q') => return Ok(()),
+                KeyCode::Esc | KeyCode::Backspace | KeyCode::Left | KeyCode::Char('h') => {
+                    self.back()
+                }
+                KeyCode::Down | KeyCode::Char('j') => self.move_by(1),
+                KeyCode::Up | KeyCode::Char('k') => self.move_by(-1),
+                KeyCode::PageDown => self.move_by(20),
+                KeyCode::PageUp => self.move_by(-20),
+                KeyCode::Enter | KeyCode::Right | KeyCode::Char('l') => self.open(),
+                KeyCode::Char('d') => self.show_diff(),
+                _ => {}
+            }
+        }
+    }
+
+    fn selected_snapshot(&self) -> usize {
+        self.snapshot_state.selected().unwrap_or(0)
+    }
+
+    fn move_by(&mut self, delta: i32) {
+        let (state, len) = match self.view {
+            View::Snapshots => (&mut self.snapshot_state, self.data.snapshots().len()),
+            View::Files => {
+                let len = self
+                    .data
+                    .files(self.snapshot_state.selected().unwrap_or(0))
+                    .len();
+                (&mut self.file_state, len)
+            }
+            View::Text { ref mut scroll, .. } => {
+                *scroll = scroll.saturating_add_signed(delta as i16);
+                return;
+            }
+        };
+        if len == 0 {
+            return;
+        }
+        let current = state.selected().unwrap_or(0) as i64;
+        let next = (current + delta as i64).clamp(0, len as i64 - 1);
+        state.select(Some(next as usize));
+    }
+
+    fn open(&mut self) {
+        match self.view {
+            View::Snapshots if !self.data.snapshots().is_empty() => {
+                self.file_state.select(Some(0));
+                self.view = View::Files;
+            }
+            View::Files => {
+                let files = self.data.files(self.selected_snapshot());
+                let Some(file) = self.file_state.selected().and_then(|i| files.get(i)) else {
+                    return;
+                };
+                let body = match self.data.file_content(file) {
+                    Ok(FileContent::Text(text)) => text,
+                    Ok(FileContent::Binary { size }) => {
+                        format!("(binary file, {} bytes)", size)
+                    }
+                    Err(e) => format!("error: {}", e),
+                };
+                self.view = View::Text {
+                    title: file.path.clone(),
+                    body,
+                    scroll: 0,
+                };
+            }
+            _ => {}
+        }
+    }
+
+    fn show_diff(&mut self) {
+        if matches!(self.view, View::Text { .. }) || self.data.snapshots().is_empty() {
+            return;
+        }
+        let index = self.selected_snapshot();
+        let body = self
+            .data
+            .diff_with_previous(index)
+            .unwrap_or_else(|e| format!("error: {}", e));
+        let title = format!("diff {}", self.data.snapshots()[index].short_id());
+        self.view = View::Text {
+            title,
+            body,
+            scroll: 0,
+        };
+    }
+
+    fn back(&mut self) {
+        self.view = match self.view {
+            View::Text { .. } if self.file_state.selected().is_some() => View::Files,
+            _ => {
+                self.file_state.select(None);
+                View::Snapshots
+            }
+        };
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, help] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+        let highlight = Style::default().add_modifier(Modifier::REVERSED);
+
+        match self.view {
+            View::Snapshots => {
+                let items: Vec<String> = (0..self.data.snapshots().len())
+                    .filter_map(|i| self.data.summary(i))
+                    .collect();
+                let list = List::new(items)
+                    .block(Block::bordered().title("Snapshots"))
+                    .highlight_style(highlight);
+                frame.render_stateful_widget(list, main, &mut self.snapshot_state);
+            }
+            View::Files => {
+                let index = self.selected_snapshot();
+                let items: Vec<String> = self
+                    .data
+                    .files(index)
+                    .iter()
+                    .map(|f| format!("{}  ({} bytes)", f.path, f.size))
+                    .collect();
+                let title = self.data.summary(index).unwrap_or_default();
+                let list = List::new(items)
+                    .block(Block::bordered().title(title))
+                    .highlight_style(highlight);
+                frame.render_stateful_widget(list, main, &mut self.file_state);
+            }
+            View::Text {
+                ref title,
+                ref body,
+                scroll,
+            } => {
+                let paragraph = Paragraph::new(body.as_str())
+                    .block(Block::bordered().title(title.as_str()))
+                    .scroll((scroll, 0));
+                frame.render_widget(paragraph, main);
+            }
+        }
+
+        frame.render_widget(
+            Line::from(" ↑/↓ move  enter open  d diff vs previous  esc back  q quit"),
+            help,
+        );
+    }
+}
@@ -0,0 +1,157 @@
+//! Automatic snapshots of a project while it changes (`mote watch`)
+//!
+//! The project tree is polled: every `watch.poll_interval_ms` the files the
+//! ignore filter keeps are listed with their size and modification time.
+//! Once a change is seen, the watcher waits until the tree has been quiet for
+//! `watch.debounce_secs` and at least `watch.min_interval_secs` have passed
+//! since its previous snapshot, then takes an auto snapshot with trigger
+//! `watch`. Ctrl-C (or SIGTERM) stops it between snapshots, never during one.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use colored::*;
+
+use super::snapshot::{cmd_snapshot, SnapshotOptions};
+use crate::commands::CommandContext;
+use crate::error::Result;
+use crate::ignore::IgnoreFilter;
+use crate::storage::SnapshotStore;
+use crate::time_format::format_timestamp;
+
+/// Trigger recorded on snapshots taken by `mote watch`
+const WATCH_TRIGGER: &str = "watch";
+
+/// Longest stretch a wait sleeps before checking for Ctrl-C
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Options for `mote watch`
+#[derive(Debug, Default)]
+pub struct WatchOptions {
+    /// Exit after the first snapshot
+    pub once: bool,
+}
+
+/// Size and modification time of every file the ignore filter keeps
+type Fingerprint = BTreeMap<PathBuf, (Option<SystemTime>, u64)>;
+
+pub fn cmd_watch(ctx: &CommandContext, opts: &WatchOptions) -> Result<()> {
+    ctx.ensure_writable()?;
+    let location = ctx.resolve_location()?;
+    let snapshot_store = SnapshotStore::new(location.snapshots_dir());
+    let watch = &ctx.config.watch;
+    let poll = Duration::from_millis(watch.poll_interval_ms.max(1));
+    let debounce = Duration::from_secs(watch.debounce_secs);
+    let min_interval = Duration::from_secs(watch.min_interval_secs);
+
+    let stop = Arc::new(AtomicBool::new(false));
+    for signal in [signal_hook::consts::SIGINT, signal_hook::consts::SIGTERM] {
+        signal_hook::flag::register(signal, Arc::clone(&stop))?;
+    }
+
+    let ignore_filter = ctx.ignore_filter();
+    let mut seen = fingerprint(ctx.project_root, &ignore_filter)?;
+    let mut last_snapshot: Option<Instant> = None;
+    println!(
+        "{} Watching {} for changes (Ctrl-C to stop)",
+        "->".cyan().bold(),
+        ctx.project_root.display()
+    );
+
+    loop {
+        if !sleep_unless_stopped(poll, &stop) {
+            break;
+        }
+        let mut current = fingerprint(ctx.project_root, &ignore_filter)?;
+        if current == seen {
+            continue;
+        }
+
+        // Let a burst of saves settle, then respect the minimum interval
+        loop {
+            if !sleep_unless_stopped(debounce.max(poll), &stop) {
+                return stopped();
+            }
+            let next = fingerprint(ctx.project_root, &ignore_filter)?;
+            if next == current {
+                break;
+            }
+            current = next;
+        }
+        if let Some(wait) = last_snapshot.and_then(|at| min_interval.checked_sub(at.elapsed())) {
+            if !sleep_unless_stopped(wait, &stop) {
+                return stopped();
+            }
+            current = fingerprint(ctx.project_root, &ignore_filter)?;
+        }
+        seen = current;
+
+        let before = snapshot_store.latest()?.map(|s| s.id);
+        let snapshot_opts = SnapshotOptions {
+            trigger: Some(WATCH_TRIGGER.to_string()),
+            auto: true,
+            ..Default::default()
+        };
+        if let Err(e) = cmd_snapshot(ctx, &snapshot_opts) {
+            eprintln!("{}: watch snapshot failed: {}", "warning".yellow(), e);
+            continue;
+        }
+        let Some(latest) = snapshot_store
+            .latest()?
+            .filter(|s| Some(&s.id) != before.as_ref())
+        else {
+            // Nothing new: only metadata changed, or a concurrent run held
+            // the storage lock and is recording these changes itself
+            continue;
+        };
+        last_snapshot = Some(Instant::now());
+        println!(
+            "{} Created snapshot {} ({} files)",
+            format_timestamp(&latest.timestamp, ctx.config.display.timezone, false).dimmed(),
+            latest.short_id().cyan(),
+            latest.file_count()
+        );
+        if opts.once {
+            return Ok(());
+        }
+    }
+    stopped()
+}
+
+fn stopped() -> Result<()> {
+    println!("{} Stopped watching", "✓".green().bold());
+    Ok(())
+}
+
+fn fingerprint(project_root: &Path, ignore_filter: &IgnoreFilter) -> Result<Fingerprint> {
+    Ok(ignore_filter
+        .walk_files(project_root)?
+        .into_iter()
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            Some((
+                entry.into_path(),
+                (metadata.modified().ok(), metadata.len()),
+            ))
+        })
+        .collect())
+}
+
+/// Sleep for `duration` in short steps; `false` if `stop` was set meanwhile
+fn sleep_unless_stopped(duration: Duration, stop: &AtomicBool) -> bool {
+    let deadline = Instant::now() + duration;
+    loop {
+        if stop.load(Ordering::Relaxed) {
+            return false;
+        }
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return true;
+        }
+        thread::sleep(left.min(STOP_CHECK_INTERVAL));
+    }
+}
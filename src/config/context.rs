@@ -12,6 +12,9 @@ pub struct ContextConfig {
     /// Custom context directory (if specified, context is stored here instead of default location)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub context_dir: Option<PathBuf>,
+    /// Archived contexts are read-only: snapshots can be browsed but not created or deleted
+    #[serde(default)]
+    pub archived: bool,
     #[serde(flatten)]
     pub config: Config,
 }
@@ -78,6 +81,15 @@ impl ContextConfig {
         Ok(())
     }
 
+    /// Overwrite the configuration of an existing context in `context_dir`
+    pub fn update(&self, context_dir: &Path) -> Result<()> {
+        let config_path = context_dir.join("config.toml");
+        let content = toml::to_string_pretty(self)
+            .map_err(|e| MoteError::ConfigParse(e.to_string()))?;
+
+        fs::write(&config_path, content)?;
+        Ok(())
+    }
 
     /// Get storage directory path for this context
     /// Storage is always at context_dir/storage/
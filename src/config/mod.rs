@@ -103,6 +103,7 @@ use std::fs;
 use std::path::PathBuf;
 
 use crate::error::{MoteError, Result};
+use crate::storage::DEFAULT_COMPRESSION_LEVEL;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
 #[serde(rename_all = "lowercase")]
@@ -117,12 +118,49 @@ pub enum LocationStrategy {
 pub struct StorageConfig {
     #[serde(default)]
     pub location_strategy: LocationStrategy,
+    /// zstd level for new objects, 1 (fastest) to 22 (smallest)
+    #[serde(default = "default_compression_level")]
+    pub compression_level: i32,
+    /// Megabytes of decompressed objects a command keeps in memory, so
+    /// content shared between files or snapshots is read once (0 = no cache)
+    #[serde(default = "default_object_cache_mb")]
+    pub object_cache_mb: usize,
+    /// Sync objects, snapshots and the index to disk before renaming them
+    /// into place, trading write speed for durability across power loss
+    #[serde(default)]
+    pub fsync: bool,
+    /// Extensions (without the dot) of already-compressed formats, stored
+    /// as-is instead of being run through zstd
+    #[serde(default = "default_no_compress_extensions")]
+    pub no_compress_extensions: Vec<String>,
+}
+
+fn default_no_compress_extensions() -> Vec<String> {
+    [
+        "png", "jpg", "jpeg", "gif", "webp", "avif", "heic", "zip", "gz", "tgz", "bz2", "xz",
+        "zst", "7z", "rar", "jar", "mp3", "mp4", "m4a", "mov", "mkv", "webm", "woff2",
+    ]
+    .iter()
+    .map(|ext| ext.to_string())
+    .collect()
+}
+
+fn default_compression_level() -> i32 {
+    DEFAULT_COMPRESSION_LEVEL
+}
+
+fn default_object_cache_mb() -> usize {
+    64
 }
 
 impl Default for StorageConfig {
     fn default() -> Self {
         Self {
             location_strategy: LocationStrategy::default(),
+            compression_level: default_compression_level(),
+            object_cache_mb: default_object_cache_mb(),
+            fsync: false,
+            no_compress_extensions: default_no_compress_extensions(),
         }
     }
 }
@@ -131,14 +169,32 @@ impl Default for StorageConfig {
 pub struct SnapshotConfig {
     #[serde(default = "default_true")]
     pub auto_cleanup: bool,
+    /// Maximum number of snapshots to keep (0 = unlimited)
     #[serde(default = "default_max_snapshots")]
     pub max_snapshots: u32,
+    /// Maximum snapshot age in days (0 = unlimited)
     #[serde(default = "default_max_age_days")]
     pub max_age_days: u32,
     #[serde(default)]
     pub gc_auto_enabled: bool,
     #[serde(default = "default_gc_auto")]
     pub gc_auto: usize,
+    /// Worker threads for the read-only change scan before auto snapshots
+    #[serde(default = "default_worker_threads")]
+    pub status_threads: usize,
+    /// Worker threads that hash, compress and store changed files (1 = serial)
+    #[serde(default = "default_worker_threads")]
+    pub threads: usize,
+    /// Abort a snapshot once the walk finds more files than this (0 = unlimited)
+    #[serde(default = "default_max_files")]
+    pub max_files: usize,
+    /// Abort instead of skipping files that can't be read (ignored by --auto)
+    #[serde(default)]
+    pub fail_fast: bool,
+    /// Skip files whose project-relative path is longer than this many bytes
+    /// (0 = unlimited)
+    #[serde(default)]
+    pub max_path_len: usize,
 }
 
 fn default_true() -> bool {
@@ -157,6 +213,16 @@ fn default_gc_auto() -> usize {
     100
 }
 
+fn default_max_files() -> usize {
+    200_000
+}
+
+fn default_worker_threads() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get().min(4))
+        .unwrap_or(1)
+}
+
 impl Default for SnapshotConfig {
     fn default() -> Self {
         Self {
@@ -165,6 +231,89 @@ impl Default for SnapshotConfig {
             max_age_days: default_max_age_days(),
             gc_auto_enabled: false,
             gc_auto: default_gc_auto(),
+            status_threads: default_worker_threads(),
+            threads: default_worker_threads(),
+            max_files: default_max_files(),
+            fail_fast: false,
+            max_path_len: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestoreConfig {
+    /// Worker threads that decompress and verify objects ahead of the writer
+    /// during a full restore (1 = serial)
+    #[serde(default = "default_restore_threads")]
+    pub threads: usize,
+}
+
+fn default_restore_threads() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get().clamp(2, 4))
+        .unwrap_or(2)
+}
+
+impl Default for RestoreConfig {
+    fn default() -> Self {
+        Self {
+            threads: default_restore_threads(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GcConfig {
+    /// Unreferenced objects written more recently than this are kept, since a
+    /// snapshot being taken concurrently may not have saved its JSON yet
+    #[serde(default = "default_grace_period_secs")]
+    pub grace_period_secs: u64,
+}
+
+fn default_grace_period_secs() -> u64 {
+    600
+}
+
+impl Default for GcConfig {
+    fn default() -> Self {
+        Self {
+            grace_period_secs: default_grace_period_secs(),
+        }
+    }
+}
+
+/// `mote watch` timing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchConfig {
+    /// How often the project tree is checked for changes
+    #[serde(default = "default_poll_interval_ms")]
+    pub poll_interval_ms: u64,
+    /// Seconds the tree must stay unchanged before a snapshot is taken
+    #[serde(default = "default_debounce_secs")]
+    pub debounce_secs: u64,
+    /// Minimum seconds between two watch snapshots
+    #[serde(default = "default_min_interval_secs")]
+    pub min_interval_secs: u64,
+}
+
+fn default_poll_interval_ms() -> u64 {
+    1000
+}
+
+fn default_debounce_secs() -> u64 {
+    2
+}
+
+fn default_min_interval_secs() -> u64 {
+    60
+}
+
+impl Default for WatchConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval_ms: default_poll_interval_ms(),
+            debounce_secs: default_debounce_secs(),
+            min_interval_secs: default_min_interval_secs(),
         }
     }
 }
@@ -173,6 +322,15 @@ impl Default for SnapshotConfig {
 pub struct IgnoreConfig {
     #[serde(default = "default_ignore_file")]
     pub ignore_file: String,
+    /// Fail instead of warning when an explicitly configured ignore file is missing
+    #[serde(default)]
+    pub require_ignore_file: bool,
+    /// Include the ignore file in snapshots when it lives inside the project root
+    #[serde(default = "default_true")]
+    pub snapshot_ignore_file: bool,
+    /// Also apply the project's top-level `.gitignore`, before the ignore file
+    #[serde(default)]
+    pub use_gitignore: bool,
 }
 
 fn default_ignore_file() -> String {
@@ -183,10 +341,56 @@ impl Default for IgnoreConfig {
     fn default() -> Self {
         Self {
             ignore_file: default_ignore_file(),
+            require_ignore_file: false,
+            snapshot_ignore_file: true,
+            use_gitignore: false,
         }
     }
 }
 
+/// Shell commands run around snapshot creation
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct HooksConfig {
+    /// Commands run before a snapshot; a non-zero exit aborts the snapshot
+    #[serde(default)]
+    pub pre_snapshot: Vec<String>,
+    /// Commands run after a snapshot has been saved
+    #[serde(default)]
+    pub post_snapshot: Vec<String>,
+}
+
+/// Time zone used when printing snapshot timestamps (storage is always UTC)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DisplayTimezone {
+    #[default]
+    Local,
+    Utc,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct DisplayConfig {
+    #[serde(default)]
+    pub timezone: DisplayTimezone,
+    /// Print sizes as exact byte counts instead of KB/MB/GB
+    #[serde(default)]
+    pub exact_sizes: bool,
+    /// Never draw progress bars for long snapshots and restores
+    #[serde(default)]
+    pub no_progress: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct DiffConfig {
+    /// Hide files whose only change is their mode (e.g. `chmod +x`)
+    #[serde(default)]
+    pub ignore_mode_changes: bool,
+    /// Command that shows each changed file instead of the built-in diff;
+    /// it gets the old and new file paths as its last two arguments
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Config {
     #[serde(default)]
@@ -194,7 +398,19 @@ pub struct Config {
     #[serde(default)]
     pub snapshot: SnapshotConfig,
     #[serde(default)]
+    pub restore: RestoreConfig,
+    #[serde(default)]
+    pub gc: GcConfig,
+    #[serde(default)]
+    pub watch: WatchConfig,
+    #[serde(default)]
     pub ignore: IgnoreConfig,
+    #[serde(default)]
+    pub hooks: HooksConfig,
+    #[serde(default)]
+    pub display: DisplayConfig,
+    #[serde(default)]
+    pub diff: DiffConfig,
 }
 
 impl Config {
@@ -202,19 +418,6 @@ impl Config {
         dirs::config_dir().map(|p| p.join("mote").join("config.toml"))
     }
 
-    /// Load global configuration from default path
-    ///
-    /// Note: Prefer using `load_from_path` with an explicit config_dir
-    #[allow(dead_code)]
-    pub fn load() -> Result<Self> {
-        let config_path = match Self::global_config_path() {
-            Some(p) => p,
-            None => return Ok(Self::default()),
-        };
-
-        Self::load_from_path(&config_path)
-    }
-
     /// Load configuration from a specific path
     pub fn load_from_path(config_path: &std::path::Path) -> Result<Self> {
         if !config_path.exists() {
@@ -228,12 +431,9 @@ impl Config {
         Ok(config)
     }
 
-    pub fn save_default() -> Result<()> {
-        let config_path = match Self::global_config_path() {
-            Some(p) => p,
-            None => return Ok(()),
-        };
-
+    /// Write the default global config into `config_dir` unless one exists
+    pub fn save_default(config_dir: &std::path::Path) -> Result<()> {
+        let config_path = config_dir.join("config.toml");
         if config_path.exists() {
             return Ok(());
         }
@@ -1,7 +1,7 @@
 use std::path::{Path, PathBuf};
 
 use crate::config::{Config, ContextConfig, ProjectConfig};
-use crate::error::Result;
+use crate::error::{MoteError, Result};
 
 /// Options for resolving configuration from the 3-layer hierarchy
 #[derive(Debug, Clone)]
@@ -16,7 +16,7 @@ pub struct ResolveOptions {
     pub context_dir: Option<PathBuf>,
     /// Current project root directory for auto-detection
     pub project_root: PathBuf,
-    /// Allow missing project (for commands like context new that can create the project)
+    /// Allow missing project or context (for commands like context new that create them)
     pub allow_missing_project: bool,
 }
 
@@ -65,17 +65,15 @@ pub struct ConfigResolver {
     context_config: Option<ContextConfig>,
     project_name: Option<String>,
     context_name: String,
+    /// Where the context lives: `--context-dir`, the project's `contexts`
+    /// map, or the default under the project directory
+    context_dir: Option<PathBuf>,
 }
 
 impl ConfigResolver {
     /// Load and resolve configuration based on options
     pub fn load(opts: &ResolveOptions) -> Result<Self> {
-        // Determine config directory
-        let config_dir = opts
-            .config_dir
-            .clone()
-            .or_else(|| Config::global_config_path().map(|p| p.parent().unwrap().to_path_buf()))
-            .unwrap_or_else(|| PathBuf::from(".config/mote"));
+        let config_dir = Self::config_dir_or_default(opts.config_dir.clone());
 
         // Load global config from the determined config_dir
         let global_config_path = config_dir.join("config.toml");
@@ -85,7 +83,7 @@ impl ConfigResolver {
         let (project_name, project_config) = if let Some(ref name) = opts.project {
             match ProjectConfig::load(&config_dir, name) {
                 Ok(config) => (Some(name.clone()), Some(config)),
-                Err(e) if opts.allow_missing_project => (Some(name.clone()), None),
+                Err(_) if opts.allow_missing_project => (Some(name.clone()), None),
                 Err(e) => return Err(e),
             }
         } else {
@@ -99,42 +97,47 @@ impl ConfigResolver {
         };
 
         // Resolve context
-        let context_name = opts.context.clone().unwrap_or_else(|| "default".to_string());
+        let context_name = opts
+            .context
+            .clone()
+            .unwrap_or_else(|| "default".to_string());
 
-        let context_config = if let (Some(ref proj_name), Some(ref proj_config)) =
+        let context_dir = if let (Some(ref proj_name), Some(ref proj_config)) =
             (&project_name, &project_config)
         {
             let project_dir = config_dir.join("projects").join(proj_name);
-
-            // Get context directory: CLI option > project config map
-            let context_dir_override = opts
-                .context_dir
-                .as_ref()
-                .or_else(|| {
-                    proj_config
-                        .contexts
-                        .as_ref()
-                        .and_then(|contexts| contexts.get(&context_name))
-                });
-
-            // If context was explicitly specified, propagate errors
-            // If using default context, allow it to be missing
-            match ContextConfig::load(&project_dir, &context_name, context_dir_override) {
-                Ok(config) => Some(config),
-                Err(e) => {
-                    if opts.context.is_some() {
-                        // Explicit context requested but failed to load - propagate error
-                        return Err(e);
-                    } else {
-                        // Default context doesn't exist yet - that's ok
-                        None
-                    }
-                }
-            }
+            // CLI option > project config map > default location
+            Some(
+                opts.context_dir
+                    .clone()
+                    .unwrap_or_else(|| proj_config.get_context_dir(&project_dir, &context_name)),
+            )
         } else {
             None
         };
 
+        let context_config =
+            if let (Some(ref proj_name), Some(ref dir)) = (&project_name, &context_dir) {
+                let project_dir = config_dir.join("projects").join(proj_name);
+
+                // If context was explicitly specified, propagate errors
+                // If using default context, allow it to be missing
+                match ContextConfig::load(&project_dir, &context_name, Some(dir)) {
+                    Ok(config) => Some(config),
+                    Err(e) => {
+                        if opts.context.is_some() && !opts.allow_missing_project {
+                            // Explicit context requested but failed to load - propagate error
+                            return Err(e);
+                        } else {
+                            // Default context doesn't exist yet - that's ok
+                            None
+                        }
+                    }
+                }
+            } else {
+                None
+            };
+
         Ok(Self {
             config_dir,
             global_config,
@@ -142,9 +145,54 @@ impl ConfigResolver {
             context_config,
             project_name,
             context_name,
+            context_dir,
         })
     }
 
+    /// Load only the global layer, without detecting a project or context
+    /// (for commands that operate on a storage directory directly)
+    pub fn load_global(config_dir: Option<PathBuf>) -> Result<Self> {
+        let config_dir = Self::config_dir_or_default(config_dir);
+        let global_config = Config::load_from_path(&config_dir.join("config.toml"))?;
+        Ok(Self {
+            config_dir,
+            global_config,
+            project_config: None,
+            context_config: None,
+            project_name: None,
+            context_name: "default".to_string(),
+            context_dir: None,
+        })
+    }
+
+    /// Resolve a standalone `--context-dir` without touching any config
+    /// directory: built-in defaults plus the context's own `config.toml`.
+    /// `config_dir()` is then the context directory itself.
+    pub fn standalone(context_dir: &Path) -> Result<Self> {
+        let context_dir_override = context_dir.to_path_buf();
+        let context_config =
+            match ContextConfig::load(context_dir, "default", Some(&context_dir_override)) {
+                Ok(config) => Some(config),
+                Err(MoteError::ContextNotFound(_)) => None,
+                Err(e) => return Err(e),
+            };
+        Ok(Self {
+            config_dir: context_dir_override,
+            global_config: Config::default(),
+            project_config: None,
+            context_config,
+            project_name: None,
+            context_name: "default".to_string(),
+            context_dir: None,
+        })
+    }
+
+    pub fn config_dir_or_default(config_dir: Option<PathBuf>) -> PathBuf {
+        config_dir
+            .or_else(|| Config::global_config_path().map(|p| p.parent().unwrap().to_path_buf()))
+            .unwrap_or_else(|| PathBuf::from(".config/mote"))
+    }
+
     /// Resolve merged configuration (context > project > global)
     pub fn resolve(&self) -> Config {
         let mut result = self.global_config.clone();
@@ -164,14 +212,7 @@ impl ConfigResolver {
 
     /// Get context directory path
     pub fn context_dir(&self) -> Option<PathBuf> {
-        if let (Some(ref project_name), Some(ref project_config)) =
-            (&self.project_name, &self.project_config)
-        {
-            let project_dir = self.config_dir.join("projects").join(project_name);
-            Some(project_config.get_context_dir(&project_dir, &self.context_name))
-        } else {
-            None
-        }
+        self.context_dir.clone()
     }
 
     /// Get context storage directory (if context is configured)
@@ -198,6 +239,11 @@ impl ConfigResolver {
         }
     }
 
+    /// Get global ignore file path, layered before the context's
+    pub fn global_ignore_path(&self) -> PathBuf {
+        self.config_dir.join("ignore")
+    }
+
     /// Get config directory
     pub fn config_dir(&self) -> &Path {
         &self.config_dir
@@ -208,12 +254,28 @@ impl ConfigResolver {
         self.project_name.as_deref()
     }
 
+    /// Recorded root of the resolved project, if it is not `cwd`
+    ///
+    /// Set when `-c other/ctx` selects a project other than the one the
+    /// current directory belongs to, so commands operate on that project's tree.
+    pub fn foreign_project_root(&self, cwd: &Path) -> Option<&Path> {
+        let path = &self.project_config.as_ref()?.path;
+        let canonical = |p: &Path| p.canonicalize().unwrap_or_else(|_| p.to_path_buf());
+        (canonical(path) != canonical(cwd)).then_some(path.as_path())
+    }
+
     /// Get context name
-    #[allow(dead_code)]
     pub fn context_name(&self) -> &str {
         &self.context_name
     }
 
+    /// Whether the resolved context is archived (read-only)
+    pub fn is_archived(&self) -> bool {
+        self.context_config
+            .as_ref()
+            .is_some_and(|context| context.archived)
+    }
+
     /// Merge source config into target (source takes precedence)
     ///
     /// Since Config fields all have defaults, we perform a simple override:
@@ -227,6 +289,18 @@ impl ConfigResolver {
         if source.storage.location_strategy != default_storage.location_strategy {
             target.storage.location_strategy = source.storage.location_strategy.clone();
         }
+        if source.storage.compression_level != default_storage.compression_level {
+            target.storage.compression_level = source.storage.compression_level;
+        }
+        if source.storage.object_cache_mb != default_storage.object_cache_mb {
+            target.storage.object_cache_mb = source.storage.object_cache_mb;
+        }
+        if source.storage.fsync != default_storage.fsync {
+            target.storage.fsync = source.storage.fsync;
+        }
+        if source.storage.no_compress_extensions != default_storage.no_compress_extensions {
+            target.storage.no_compress_extensions = source.storage.no_compress_extensions.clone();
+        }
 
         // For snapshot config, override each field if different from default
         let default_snapshot = crate::config::SnapshotConfig::default();
@@ -239,11 +313,79 @@ impl ConfigResolver {
         if source.snapshot.max_age_days != default_snapshot.max_age_days {
             target.snapshot.max_age_days = source.snapshot.max_age_days;
         }
+        if source.snapshot.status_threads != default_snapshot.status_threads {
+            target.snapshot.status_threads = source.snapshot.status_threads;
+        }
+        if source.snapshot.threads != default_snapshot.threads {
+            target.snapshot.threads = source.snapshot.threads;
+        }
+        if source.snapshot.max_files != default_snapshot.max_files {
+            target.snapshot.max_files = source.snapshot.max_files;
+        }
+        if source.snapshot.fail_fast != default_snapshot.fail_fast {
+            target.snapshot.fail_fast = source.snapshot.fail_fast;
+        }
+        if source.snapshot.max_path_len != default_snapshot.max_path_len {
+            target.snapshot.max_path_len = source.snapshot.max_path_len;
+        }
+
+        if source.restore.threads != crate::config::RestoreConfig::default().threads {
+            target.restore.threads = source.restore.threads;
+        }
+        if source.gc.grace_period_secs != crate::config::GcConfig::default().grace_period_secs {
+            target.gc.grace_period_secs = source.gc.grace_period_secs;
+        }
+
+        let default_watch = crate::config::WatchConfig::default();
+        if source.watch.poll_interval_ms != default_watch.poll_interval_ms {
+            target.watch.poll_interval_ms = source.watch.poll_interval_ms;
+        }
+        if source.watch.debounce_secs != default_watch.debounce_secs {
+            target.watch.debounce_secs = source.watch.debounce_secs;
+        }
+        if source.watch.min_interval_secs != default_watch.min_interval_secs {
+            target.watch.min_interval_secs = source.watch.min_interval_secs;
+        }
 
         // For ignore config, override if different from default
         let default_ignore = crate::config::IgnoreConfig::default();
         if source.ignore.ignore_file != default_ignore.ignore_file {
             target.ignore.ignore_file = source.ignore.ignore_file.clone();
         }
+        if source.ignore.require_ignore_file != default_ignore.require_ignore_file {
+            target.ignore.require_ignore_file = source.ignore.require_ignore_file;
+        }
+        if source.ignore.snapshot_ignore_file != default_ignore.snapshot_ignore_file {
+            target.ignore.snapshot_ignore_file = source.ignore.snapshot_ignore_file;
+        }
+        if source.ignore.use_gitignore != default_ignore.use_gitignore {
+            target.ignore.use_gitignore = source.ignore.use_gitignore;
+        }
+
+        // For hooks, a non-empty list replaces the inherited one
+        if !source.hooks.pre_snapshot.is_empty() {
+            target.hooks.pre_snapshot = source.hooks.pre_snapshot.clone();
+        }
+        if !source.hooks.post_snapshot.is_empty() {
+            target.hooks.post_snapshot = source.hooks.post_snapshot.clone();
+        }
+
+        // For display config, override if different from default
+        if source.display.timezone != crate::config::DisplayTimezone::default() {
+            target.display.timezone = source.display.timezone;
+        }
+        if source.display.exact_sizes {
+            target.display.exact_sizes = true;
+        }
+        if source.display.no_progress {
+            target.display.no_progress = true;
+        }
+
+        if source.diff.ignore_mode_changes {
+            target.diff.ignore_mode_changes = true;
+        }
+        if source.diff.external.is_some() {
+            target.diff.external = source.diff.external.clone();
+        }
     }
 }
@@ -191,6 +191,7 @@ mod context_validation_tests {
         ContextConfig {
             cwd: Some(PathBuf::from("/tmp/test")),
             context_dir: None,
+            archived: false,
             config: Config::default(),
         }
     }
@@ -303,6 +304,7 @@ mod config_merge_tests {
         let config = ContextConfig {
             cwd: Some(PathBuf::from("/path/to/context")),
             context_dir: Some(PathBuf::from("/custom/context")),
+            archived: false,
             config: Config::default(),
         };
 
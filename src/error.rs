@@ -11,14 +11,31 @@ pub enum MoteError {
     #[error("No VCS directory found (.git or .jj). Required for location_strategy = 'vcs'.")]
     NoVcsDirectory,
 
-    #[error("Snapshot not found: {0}")]
-    SnapshotNotFound(String),
+    #[error("Snapshot not found: {id}{}", did_you_mean(.suggestions))]
+    SnapshotNotFound {
+        id: String,
+        /// Close IDs, described as `abc1234 (date 'message')`
+        suggestions: Vec<String>,
+    },
 
     #[error("No snapshots available")]
     NoSnapshotsAvailable,
 
-    #[error("Ambiguous snapshot ID: {0}. Multiple matches found.")]
-    AmbiguousSnapshotId(String),
+    #[error("Snapshot reference {reference} is out of range: there are only {available} snapshot(s), @ through @~{}", .available - 1)]
+    SnapshotRefOutOfRange { reference: String, available: usize },
+
+    #[error("No snapshot at or before {time}; the earliest is from {earliest}")]
+    NoSnapshotAtTime { time: String, earliest: String },
+
+    #[error("Ambiguous snapshot ID: {id}. Multiple matches found:{}", indented(.matches))]
+    AmbiguousSnapshotId {
+        id: String,
+        /// Matching IDs, described as `abc1234 (date 'message')`
+        matches: Vec<String>,
+    },
+
+    #[error("File not found in snapshot {snapshot}: {path}")]
+    FileNotFoundInSnapshot { snapshot: String, path: String },
 
     #[error("Object not found: {0}")]
     ObjectNotFound(String),
@@ -50,11 +67,96 @@ pub enum MoteError {
     #[error("Context already exists: {0}")]
     ContextAlreadyExists(String),
 
+    #[error(
+        "Context '{0}' is archived and read-only. Run 'mote context unarchive {0}' to modify it."
+    )]
+    ContextArchived(String),
+
+    #[error("Hook '{command}' failed: {status}")]
+    HookFailed { command: String, status: String },
+
+    #[error("Ignore file not found: {0}. Create it or set ignore.require_ignore_file = false.")]
+    IgnoreFileMissing(String),
+
+    #[error(
+        "Refusing to use {0} as project root: it is the filesystem root or your home directory. Pass --project-root, or --force-large-root to proceed anyway."
+    )]
+    UnsafeProjectRoot(String),
+
+    #[error(
+        "{root} contains more than {limit} files. Add ignore patterns, raise snapshot.max_files, or pass --force-large-root."
+    )]
+    TooManyFiles { root: String, limit: usize },
+
+    #[error(
+        "Snapshot aborted at {path} after {processed} files ({reason}); no snapshot was written."
+    )]
+    SnapshotFileFailed {
+        path: String,
+        processed: usize,
+        reason: String,
+    },
+
+    #[error("A worker hashing files panicked")]
+    HashWorkerPanicked,
+
+    #[error("No mote storage at {0} (expected objects/ and snapshots/ directories)")]
+    StorageNotFound(String),
+
+    #[error("Invalid patch at line {line}: {reason}")]
+    PatchParse { line: usize, reason: String },
+
+    #[error("{failed} file(s) in the patch did not apply")]
+    PatchFailed { failed: usize },
+
+    #[error("Storage check found {problems} problem(s)")]
+    VerifyFailed { problems: usize },
+
+    #[error("Another mote process is running{}; try again once it finishes", holder(.pid))]
+    StorageLocked { pid: Option<u32> },
+
+    #[error("Output file {0} already exists; pass --force-output to overwrite it")]
+    OutputExists(String),
+
+    #[error("Snapshot path {path} would be restored outside {target}")]
+    PathOutsideTarget { path: String, target: String },
+
     #[error("Invalid name: {0}")]
     InvalidName(String),
 
     #[error("Invalid arguments: {0}")]
     InvalidArguments(String),
+
+    #[error("Invalid context specifier '{0}'. Use [project/]context")]
+    InvalidContextSpec(String),
+
+    #[error("Unsupported shell: {0}. Use bash, zsh, or fish.")]
+    UnsupportedShell(String),
+
+    #[error("No git repository at {0}; jj runs no hooks, use `mote setup` there instead")]
+    NoGitRepository(String),
+
+    #[error("Editor '{editor}' failed: {status}")]
+    EditorFailed { editor: String, status: String },
 }
 
 pub type Result<T> = std::result::Result<T, MoteError>;
+
+/// ` (pid N)` when the process holding the storage lock is known
+fn holder(pid: &Option<u32>) -> String {
+    pid.map(|pid| format!(" (pid {})", pid)).unwrap_or_default()
+}
+
+/// `; did you mean a, b or c?` for a non-empty list of suggestions
+fn did_you_mean(suggestions: &[String]) -> String {
+    match suggestions.split_last() {
+        None => String::new(),
+        Some((last, [])) => format!("; did you mean {}?", last),
+        Some((last, rest)) => format!("; did you mean {} or {}?", rest.join(", "), last),
+    }
+}
+
+/// One indented line per item
+fn indented(items: &[String]) -> String {
+    items.iter().map(|item| format!("\n  {}", item)).collect()
+}
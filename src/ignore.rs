@@ -1,33 +1,113 @@
-use ignore::gitignore::{Gitignore, GitignoreBuilder};
-use std::path::{Path, PathBuf};
+use ignore::gitignore::{Gitignore, GitignoreBuilder, Glob};
+use ignore::Match;
+use std::path::{Component, Path, PathBuf};
 use walkdir::WalkDir;
 
-use crate::error::Result;
+use crate::error::{MoteError, Result};
+
+/// Directories under the project root that are never walked, whatever the
+/// ignore file says; the templates don't need to list them
+pub const HARD_EXCLUDED_DIRS: &[&str] = &[".mote", ".git", ".jj"];
 
 pub struct IgnoreFilter {
     gitignore: Option<Gitignore>,
+    ignore_files: Vec<PathBuf>,
+    local_ignore_paths: Vec<PathBuf>,
+    exclude_ignore_file: bool,
+    max_files: usize,
+    max_path_len: usize,
+}
+
+/// Personal patterns layered after `ignore_file_path` (`.moteignore.local`,
+/// `<context_dir>/ignore.local`); never snapshotted
+pub fn local_ignore_path(ignore_file_path: &Path) -> PathBuf {
+    let mut path = ignore_file_path.as_os_str().to_owned();
+    path.push(".local");
+    PathBuf::from(path)
 }
 
 impl IgnoreFilter {
-    /// Creates a new IgnoreFilter for the given ignore file path.
+    /// Creates a new IgnoreFilter from `ignore_files`, in order of
+    /// precedence: later files can re-include what earlier ones ignore with
+    /// `!pattern`. Each file's local file (if any) is layered right after it
+    /// so local patterns win; files that don't exist are skipped.
     ///
     /// # Arguments
-    /// * `ignore_file_path` - Full path to the ignore file
-    pub fn new(ignore_file_path: &Path) -> Self {
-        let gitignore = if ignore_file_path.exists() {
-            // Use parent directory as project root for gitignore rules
-            let project_root = ignore_file_path
-                .parent()
-                .unwrap_or_else(|| Path::new("."));
-
-            let mut builder = GitignoreBuilder::new(project_root);
-            let _ = builder.add(ignore_file_path);
-            builder.build().ok()
-        } else {
-            None
+    /// * `ignore_files` - Full paths to the ignore files, lowest precedence first
+    pub fn new(ignore_files: &[PathBuf]) -> Self {
+        let local_ignore_paths = ignore_files
+            .iter()
+            .map(|path| local_ignore_path(path))
+            .collect();
+        let mut filter = Self {
+            gitignore: None,
+            ignore_files: ignore_files.to_vec(),
+            local_ignore_paths,
+            exclude_ignore_file: false,
+            max_files: 0,
+            max_path_len: 0,
         };
+        filter.gitignore = build_layers(&filter.layers(None));
+        filter
+    }
+
+    /// Also apply `<project_root>/.gitignore`, layered first so the mote
+    /// ignore files can re-include what git ignores with `!pattern`
+    /// (`ignore.use_gitignore`). Nested `.gitignore` files are not read.
+    pub fn respecting_gitignore(mut self, project_root: &Path, respect: bool) -> Self {
+        if respect {
+            let git_ignore = project_root.join(".gitignore");
+            self.gitignore = build_layers(&self.layers(Some(&git_ignore)));
+        }
+        self
+    }
+
+    /// Ignore files in order of precedence, each followed by its local file
+    fn layers<'a>(&'a self, first: Option<&'a Path>) -> Vec<&'a Path> {
+        first
+            .into_iter()
+            .chain(
+                self.ignore_files
+                    .iter()
+                    .zip(&self.local_ignore_paths)
+                    .flat_map(|(file, local)| [file.as_path(), local.as_path()]),
+            )
+            .collect()
+    }
+
+    /// Make `walk_files` fail with `TooManyFiles` as soon as it finds more
+    /// than `max_files` files (0 = unlimited)
+    pub fn limit_files(mut self, max_files: usize) -> Self {
+        self.max_files = max_files;
+        self
+    }
+
+    /// Make `walk_files` skip files whose project-relative path is longer
+    /// than `max_path_len` bytes (0 = unlimited)
+    pub fn limit_path_len(mut self, max_path_len: usize) -> Self {
+        self.max_path_len = max_path_len;
+        self
+    }
+
+    /// Treat the ignore file itself as ignored when it lives inside the project
+    /// (`ignore.snapshot_ignore_file = false`)
+    pub fn excluding_ignore_file(mut self, exclude: bool) -> Self {
+        self.exclude_ignore_file = exclude;
+        self
+    }
+
+    /// Whether `relative_path` is a local ignore file, or an ignore file
+    /// and those are excluded
+    pub fn is_excluded_ignore_file(&self, project_root: &Path, relative_path: &Path) -> bool {
+        let path = project_root.join(relative_path);
+        self.local_ignore_paths.contains(&path)
+            || (self.exclude_ignore_file && self.ignore_files.contains(&path))
+    }
 
-        Self { gitignore }
+    /// Whether `relative_path` is one of the ignore files or their local files
+    pub fn is_ignore_file(&self, project_root: &Path, relative_path: &Path) -> bool {
+        let path = project_root.join(relative_path);
+        self.ignore_files.contains(&path) || self.local_ignore_paths.contains(&path)
     }
 
     pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
@@ -38,43 +118,450 @@ impl IgnoreFilter {
         }
     }
 
-    pub fn walk_files(&self, project_root: &Path) -> Vec<walkdir::DirEntry> {
-        let mote_dir = project_root.join(".mote");
-        let git_dir = project_root.join(".git");
-        let jj_dir = project_root.join(".jj");
+    pub fn walk_files(&self, project_root: &Path) -> Result<Vec<walkdir::DirEntry>> {
+        Ok(self.walk_files_with_skipped(project_root)?.0)
+    }
+
+    /// Like `walk_files`, also returning the relative paths of the files it
+    /// left out
+    pub fn walk_files_with_skipped(
+        &self,
+        project_root: &Path,
+    ) -> Result<(Vec<walkdir::DirEntry>, SkippedFiles)> {
+        let excluded_dirs: Vec<PathBuf> = HARD_EXCLUDED_DIRS
+            .iter()
+            .map(|dir| project_root.join(dir))
+            .collect();
 
-        WalkDir::new(project_root)
+        let walker = WalkDir::new(project_root)
             .into_iter()
             .filter_entry(|entry| {
                 let path = entry.path();
 
-                if path.starts_with(&mote_dir)
-                    || path.starts_with(&git_dir)
-                    || path.starts_with(&jj_dir)
-                {
+                if excluded_dirs.iter().any(|dir| path.starts_with(dir)) {
                     return false;
                 }
 
                 let relative_path = path.strip_prefix(project_root).unwrap_or(path);
-                !self.is_ignored(relative_path, entry.file_type().is_dir())
+                !self.is_excluded_ignore_file(project_root, relative_path)
+                    && !self.is_ignored(relative_path, entry.file_type().is_dir())
             })
             .filter_map(|e| e.ok())
-            .filter(|e| e.file_type().is_file())
-            .collect()
+            .filter(|e| e.file_type().is_file());
+
+        let mut files = Vec::new();
+        let mut skipped = SkippedFiles::default();
+        for entry in walker {
+            let relative_path = entry
+                .path()
+                .strip_prefix(project_root)
+                .unwrap_or(entry.path());
+            // Snapshot paths are strings; a lossy name would restore as a
+            // different file, so such files are never walked
+            let Some(relative_path) = relative_path.to_str() else {
+                skipped
+                    .non_utf8
+                    .push(relative_path.to_string_lossy().into_owned());
+                continue;
+            };
+            if self.max_path_len > 0 && relative_path.len() > self.max_path_len {
+                skipped.too_long.push(relative_path.to_string());
+                continue;
+            }
+            files.push(entry);
+            if self.max_files > 0 && files.len() > self.max_files {
+                return Err(MoteError::TooManyFiles {
+                    root: project_root.display().to_string(),
+                    limit: self.max_files,
+                });
+            }
+        }
+        Ok((files, skipped))
+    }
+
+    /// Why walking the project would keep or leave out `relative_path`,
+    /// checking its parent directories first as the walk prunes them
+    pub fn explain(&self, project_root: &Path, relative_path: &Path, is_dir: bool) -> Verdict {
+        if let Some(Component::Normal(first)) = relative_path.components().next() {
+            if let Some(dir) = HARD_EXCLUDED_DIRS.iter().find(|dir| first == **dir) {
+                return Verdict::ignored(Reason::HardExcluded(dir));
+            }
+        }
+
+        let mut ancestor = PathBuf::new();
+        let components: Vec<Component> = relative_path.components().collect();
+        for (i, component) in components.iter().enumerate() {
+            ancestor.push(component);
+            let is_last = i + 1 == components.len();
+            if self.is_excluded_ignore_file(project_root, &ancestor) {
+                let path = project_root.join(&ancestor);
+                let reason = if self.local_ignore_paths.contains(&path) {
+                    Reason::LocalIgnoreFile
+                } else {
+                    Reason::ExcludedIgnoreFile
+                };
+                return Verdict::ignored(reason);
+            }
+            let Some(ref gitignore) = self.gitignore else {
+                continue;
+            };
+            match gitignore.matched(&ancestor, !is_last || is_dir) {
+                Match::Ignore(glob) => {
+                    return Verdict::ignored(Reason::Pattern {
+                        rule: IgnoreRule::from_glob(glob),
+                        dir: (!is_last).then(|| ancestor.clone()),
+                    });
+                }
+                Match::Whitelist(glob) if is_last => {
+                    return Verdict {
+                        ignored: false,
+                        reason: Reason::Pattern {
+                            rule: IgnoreRule::from_glob(glob),
+                            dir: None,
+                        },
+                    };
+                }
+                _ => {}
+            }
+        }
+
+        let len = relative_path.as_os_str().len();
+        if !is_dir && self.max_path_len > 0 && len > self.max_path_len {
+            return Verdict::ignored(Reason::TooLong(self.max_path_len));
+        }
+        Verdict {
+            ignored: false,
+            reason: Reason::NoMatch,
+        }
+    }
+}
+
+/// Whether a path is left out of snapshots, and why
+#[derive(Debug, PartialEq)]
+pub struct Verdict {
+    pub ignored: bool,
+    pub reason: Reason,
+}
+
+impl Verdict {
+    fn ignored(reason: Reason) -> Self {
+        Self {
+            ignored: true,
+            reason,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Reason {
+    /// No pattern matches the path or its directories
+    NoMatch,
+    /// Under a directory that is never walked
+    HardExcluded(&'static str),
+    /// The personal local ignore file, never snapshotted
+    LocalIgnoreFile,
+    /// The ignore file, with `ignore.snapshot_ignore_file = false`
+    ExcludedIgnoreFile,
+    /// Longer than `snapshot.max_path_len` bytes
+    TooLong(usize),
+    /// The last pattern matching the path (a negated one re-includes it),
+    /// or the pattern ignoring the directory `dir` it is in
+    Pattern {
+        rule: IgnoreRule,
+        dir: Option<PathBuf>,
+    },
+}
+
+/// An ignore file line
+#[derive(Debug, PartialEq)]
+pub struct IgnoreRule {
+    /// The pattern as written, `!` included
+    pub pattern: String,
+    /// The ignore file it comes from
+    pub source: Option<PathBuf>,
+    /// Its 1-based line in `source`, when it can be found there
+    pub line: Option<usize>,
+}
+
+impl IgnoreRule {
+    fn from_glob(glob: &Glob) -> Self {
+        let pattern = glob.original().to_string();
+        let line = glob.from().and_then(|source| {
+            let content = std::fs::read_to_string(source).ok()?;
+            // The last match wins, so a repeated pattern counts from its last line
+            let lines: Vec<&str> = content.lines().collect();
+            let index = lines.iter().rposition(|line| line.trim() == pattern)?;
+            Some(index + 1)
+        });
+        Self {
+            source: glob.from().map(Path::to_path_buf),
+            pattern,
+            line,
+        }
+    }
+}
+
+/// Gitignore rules from the existing files among `layers`, later layers
+/// taking precedence; `None` when there are none
+fn build_layers(layers: &[&Path]) -> Option<Gitignore> {
+    let layers: Vec<&Path> = layers
+        .iter()
+        .copied()
+        .filter(|path| path.exists())
+        .collect();
+    // Use the first file's directory as root for gitignore rules; walked
+    // paths are relative, so it doesn't anchor them
+    let project_root = layers.first()?.parent().unwrap_or_else(|| Path::new("."));
+
+    let mut builder = GitignoreBuilder::new(project_root);
+    for layer in layers {
+        let _ = builder.add(layer);
+    }
+    builder.build().ok()
+}
+
+/// Files `walk_files_with_skipped` left out, as relative paths
+#[derive(Debug, Default)]
+pub struct SkippedFiles {
+    /// Paths over `snapshot.max_path_len`
+    pub too_long: Vec<String>,
+    /// Paths that aren't valid UTF-8, shown lossily
+    pub non_utf8: Vec<String>,
+}
+
+/// A project ecosystem recognised by marker files in the project root,
+/// with the ignore patterns it contributes to the default template
+#[derive(Debug, PartialEq)]
+pub struct Ecosystem {
+    pub name: &'static str,
+    markers: &'static [&'static str],
+    pub patterns: &'static [&'static str],
+}
+
+pub const RUST: Ecosystem = Ecosystem {
+    name: "Rust",
+    markers: &["Cargo.toml"],
+    patterns: &["target/"],
+};
+
+pub const NODE: Ecosystem = Ecosystem {
+    name: "Node.js",
+    markers: &["package.json"],
+    patterns: &["node_modules/", "dist/", "build/", ".next/", "coverage/"],
+};
+
+pub const PYTHON: Ecosystem = Ecosystem {
+    name: "Python",
+    markers: &["pyproject.toml", "requirements.txt", "setup.py"],
+    patterns: &[
+        "__pycache__/",
+        "*.pyc",
+        ".venv/",
+        "venv/",
+        ".tox/",
+        ".pytest_cache/",
+        ".mypy_cache/",
+        "*.egg-info/",
+    ],
+};
+
+pub const GO: Ecosystem = Ecosystem {
+    name: "Go",
+    markers: &["go.mod"],
+    patterns: &["vendor/"],
+};
+
+pub const TERRAFORM: Ecosystem = Ecosystem {
+    name: "Terraform",
+    markers: &[".terraform", ".terraform.lock.hcl"],
+    patterns: &[".terraform/"],
+};
+
+pub const JAVA: Ecosystem = Ecosystem {
+    name: "Java",
+    markers: &["pom.xml", "build.gradle", "build.gradle.kts"],
+    patterns: &["target/", "build/", ".gradle/", "*.class"],
+};
+
+pub const RUBY: Ecosystem = Ecosystem {
+    name: "Ruby",
+    markers: &["Gemfile"],
+    patterns: &[".bundle/", "vendor/bundle/"],
+};
+
+pub const PHP: Ecosystem = Ecosystem {
+    name: "PHP",
+    markers: &["composer.json"],
+    patterns: &["vendor/"],
+};
+
+pub const C_CPP: Ecosystem = Ecosystem {
+    name: "C/C++",
+    markers: &["CMakeLists.txt", "Makefile"],
+    patterns: &["build/", "*.o", "*.a", "*.so", "*.dylib"],
+};
+
+/// All known ecosystems, in template order
+pub const ECOSYSTEMS: &[&Ecosystem] = &[
+    &RUST, &NODE, &PYTHON, &GO, &TERRAFORM, &JAVA, &RUBY, &PHP, &C_CPP,
+];
+
+const TEMPLATE_HEADER: &str = "# Mote ignore file
+# Uses gitignore syntax
+";
+
+/// A titled group of patterns in the default template
+#[derive(Debug, PartialEq)]
+pub struct TemplateSection {
+    pub title: &'static str,
+    pub patterns: &'static [&'static str],
+}
+
+pub const DEPS: TemplateSection = TemplateSection {
+    title: "Dependencies",
+    patterns: &[
+        "node_modules/",
+        "vendor/",
+        ".venv/",
+        "venv/",
+        "__pycache__/",
+    ],
+};
+
+pub const BUILD: TemplateSection = TemplateSection {
+    title: "Build outputs",
+    patterns: &[
+        "target/", "dist/", "build/", "*.o", "*.a", "*.so", "*.dylib",
+    ],
+};
+
+pub const EDITOR: TemplateSection = TemplateSection {
+    title: "IDE and editor",
+    patterns: &[".idea/", ".vscode/", "*.swp", "*.swo", "*~"],
+};
+
+pub const OS: TemplateSection = TemplateSection {
+    title: "OS files",
+    patterns: &[".DS_Store", "Thumbs.db"],
+};
+
+pub const LOGS: TemplateSection = TemplateSection {
+    title: "Logs",
+    patterns: &["*.log", "logs/"],
+};
+
+pub const TEMP: TemplateSection = TemplateSection {
+    title: "Temporary files",
+    patterns: &["*.tmp", "*.temp", ".cache/"],
+};
+
+/// Used when no ecosystem is detected
+pub const GENERIC_SECTIONS: &[&TemplateSection] = &[&DEPS, &BUILD];
+
+/// Added to every default template, after the ecosystem patterns
+pub const COMMON_SECTIONS: &[&TemplateSection] = &[&EDITOR, &OS, &LOGS, &TEMP];
+
+/// Content written by [`create_ignore_file`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum IgnoreTemplate {
+    /// Patterns for the detected ecosystems plus the common sections
+    Default,
+    /// Only a header comment; nothing is ignored beyond [`HARD_EXCLUDED_DIRS`]
+    Minimal,
+    /// Given content, written as is
+    Custom(String),
+}
+
+fn push_section(content: &mut String, title: &str, patterns: &[&str]) {
+    content.push_str(&format!("\n# {}\n", title));
+    for pattern in patterns {
+        content.push_str(pattern);
+        content.push('\n');
+    }
+}
+
+/// Ecosystems whose marker files exist directly under `project_root`
+pub fn detect_ecosystems(project_root: &Path) -> Vec<&'static Ecosystem> {
+    ECOSYSTEMS
+        .iter()
+        .copied()
+        .filter(|eco| eco.markers.iter().any(|m| project_root.join(m).exists()))
+        .collect()
+}
+
+/// Compose the default ignore file content from the detected ecosystems.
+/// Patterns shared by several ecosystems are listed once.
+pub fn ignore_template(ecosystems: &[&Ecosystem]) -> String {
+    let mut content = TEMPLATE_HEADER.to_string();
+    if ecosystems.is_empty() {
+        for section in GENERIC_SECTIONS {
+            push_section(&mut content, section.title, section.patterns);
+        }
+    }
+
+    let mut seen = Vec::new();
+    for eco in ecosystems {
+        let patterns: Vec<&str> = eco
+            .patterns
+            .iter()
+            .copied()
+            .filter(|p| !seen.contains(p))
+            .collect();
+        if patterns.is_empty() {
+            continue;
+        }
+        push_section(&mut content, eco.name, &patterns);
+        seen.extend(patterns);
+    }
+
+    for section in COMMON_SECTIONS {
+        push_section(&mut content, section.title, section.patterns);
+    }
+    content
+}
+
+/// Header-only content for [`IgnoreTemplate::Minimal`]
+pub fn minimal_template() -> String {
+    format!(
+        "{}# {} are always skipped; add patterns below\n",
+        TEMPLATE_HEADER,
+        HARD_EXCLUDED_DIRS.join(", ")
+    )
+}
+
+/// Patterns of `ecosystems` not already present as a line in `content`
+pub fn missing_patterns(content: &str, ecosystems: &[&Ecosystem]) -> Vec<&'static str> {
+    let existing: Vec<&str> = content.lines().map(str::trim).collect();
+    let mut missing = Vec::new();
+    for pattern in ecosystems
+        .iter()
+        .flat_map(|eco| eco.patterns.iter().copied())
+    {
+        if !existing.contains(&pattern) && !missing.contains(&pattern) {
+            missing.push(pattern);
+        }
     }
+    missing
 }
 
-/// Creates an ignore file at the specified path with default content.
+/// Creates an ignore file at the specified path from `template`; the default
+/// template is composed for the given ecosystems (see [`ignore_template`]).
 /// Returns the path of the created file (or existing file if already present).
 ///
 /// # Arguments
 /// * `ignore_path` - The full path where the ignore file should be created
+/// * `ecosystems` - Ecosystems detected in the project root
+/// * `template` - Default, minimal or custom content
 ///
 /// # Behavior
 /// - Does not overwrite existing files (idempotent)
 /// - Automatically creates parent directories if they don't exist
 /// - Returns Ok even if file already exists
-pub fn create_ignore_file(ignore_path: &Path) -> Result<PathBuf> {
+pub fn create_ignore_file(
+    ignore_path: &Path,
+    ecosystems: &[&Ecosystem],
+    template: IgnoreTemplate,
+) -> Result<PathBuf> {
     // Don't overwrite existing files
     if ignore_path.exists() {
         return Ok(ignore_path.to_path_buf());
@@ -87,47 +574,289 @@ pub fn create_ignore_file(ignore_path: &Path) -> Result<PathBuf> {
         }
     }
 
-    let default_content = r#"# Mote ignore file
-# Uses gitignore syntax
-
-# Dependencies
-node_modules/
-vendor/
-.venv/
-venv/
-__pycache__/
-
-# Build outputs
-target/
-dist/
-build/
-*.o
-*.a
-*.so
-*.dylib
-
-# IDE and editor
-.idea/
-.vscode/
-*.swp
-*.swo
-*~
-
-# OS files
-.DS_Store
-Thumbs.db
-
-# Logs
-*.log
-logs/
-
-# Temporary files
-*.tmp
-*.temp
-.cache/
-"#;
-
-    std::fs::write(ignore_path, default_content)?;
+    let content = match template {
+        IgnoreTemplate::Default => ignore_template(ecosystems),
+        IgnoreTemplate::Minimal => minimal_template(),
+        IgnoreTemplate::Custom(content) => content,
+    };
+    std::fs::write(ignore_path, content)?;
     Ok(ignore_path.to_path_buf())
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn fixture(markers: &[&str]) -> TempDir {
+        let temp = TempDir::new().unwrap();
+        for marker in markers {
+            fs::write(temp.path().join(marker), "").unwrap();
+        }
+        temp
+    }
+
+    #[test]
+    fn test_local_ignore_file_layers_after_shared() {
+        let temp = TempDir::new().unwrap();
+        let ignore_file = temp.path().join(".moteignore");
+        fs::write(&ignore_file, "*.log\nbuild/\n").unwrap();
+        fs::write(local_ignore_path(&ignore_file), "!keep.log\ntmp/\n").unwrap();
+
+        let filter = IgnoreFilter::new(std::slice::from_ref(&ignore_file));
+        assert!(filter.is_ignored(Path::new("app.log"), false));
+        assert!(!filter.is_ignored(Path::new("keep.log"), false));
+        assert!(filter.is_ignored(Path::new("build"), true));
+        assert!(filter.is_ignored(Path::new("tmp"), true));
+        assert!(!filter.is_ignored(Path::new("src.rs"), false));
+
+        // The local file is excluded even when the shared one is snapshotted
+        assert!(filter.is_excluded_ignore_file(temp.path(), Path::new(".moteignore.local")));
+        assert!(!filter.is_excluded_ignore_file(temp.path(), Path::new(".moteignore")));
+    }
+
+    #[test]
+    fn test_explain_reports_deciding_rule() {
+        let temp = TempDir::new().unwrap();
+        let ignore_file = temp.path().join(".moteignore");
+        fs::write(&ignore_file, "*.log\nbuild/\n!keep.log\n").unwrap();
+        let filter = IgnoreFilter::new(std::slice::from_ref(&ignore_file)).limit_path_len(20);
+        let explain =
+            |path: &str, is_dir: bool| filter.explain(temp.path(), Path::new(path), is_dir);
+
+        let verdict = explain("app.log", false);
+        assert!(verdict.ignored);
+        match verdict.reason {
+            Reason::Pattern { rule, dir: None } => {
+                assert_eq!(rule.pattern, "*.log");
+                assert_eq!(rule.line, Some(1));
+                assert_eq!(rule.source.as_deref(), Some(ignore_file.as_path()));
+            }
+            other => panic!("{:?}", other),
+        }
+
+        let verdict = explain("keep.log", false);
+        assert!(!verdict.ignored);
+        assert!(matches!(verdict.reason, Reason::Pattern { ref rule, .. } if rule.line == Some(3)));
+
+        // A pruned directory decides for everything under it
+        let verdict = explain("build/out/keep.log", false);
+        assert!(verdict.ignored);
+        assert!(
+            matches!(verdict.reason, Reason::Pattern { dir: Some(ref dir), .. } if dir == Path::new("build"))
+        );
+
+        assert_eq!(
+            explain(".git/config", false).reason,
+            Reason::HardExcluded(".git")
+        );
+        assert_eq!(
+            explain(".moteignore.local", false).reason,
+            Reason::LocalIgnoreFile
+        );
+        assert_eq!(
+            explain("src/some/long/name.rs", false).reason,
+            Reason::TooLong(20)
+        );
+        assert_eq!(
+            explain("src/main.rs", false),
+            Verdict {
+                ignored: false,
+                reason: Reason::NoMatch
+            }
+        );
+    }
+
+    #[test]
+    fn test_gitignore_layers_before_ignore_file() {
+        let temp = TempDir::new().unwrap();
+        let ignore_file = temp.path().join(".moteignore");
+        fs::write(temp.path().join(".gitignore"), "*.env\ndist/\n").unwrap();
+        fs::write(&ignore_file, "!dev.env\n").unwrap();
+
+        let filter = IgnoreFilter::new(std::slice::from_ref(&ignore_file));
+        assert!(!filter.is_ignored(Path::new("prod.env"), false));
+
+        let filter = IgnoreFilter::new(std::slice::from_ref(&ignore_file))
+            .respecting_gitignore(temp.path(), true);
+        assert!(filter.is_ignored(Path::new("prod.env"), false));
+        assert!(filter.is_ignored(Path::new("dist"), true));
+        assert!(!filter.is_ignored(Path::new("dev.env"), false));
+
+        // Works without a mote ignore file, and is a no-op when off
+        let missing = temp.path().join("missing");
+        let filter = IgnoreFilter::new(&[missing.join(".moteignore")]);
+        assert!(!filter.is_ignored(Path::new("prod.env"), false));
+        let filter = IgnoreFilter::new(std::slice::from_ref(&ignore_file))
+            .respecting_gitignore(temp.path(), false);
+        assert!(!filter.is_ignored(Path::new("prod.env"), false));
+    }
+
+    #[test]
+    fn test_local_ignore_file_without_shared_file() {
+        let temp = TempDir::new().unwrap();
+        let ignore_file = temp.path().join("ignore");
+        assert_eq!(
+            local_ignore_path(&ignore_file),
+            temp.path().join("ignore.local")
+        );
+        fs::write(local_ignore_path(&ignore_file), "*.tmp\n").unwrap();
+
+        let filter = IgnoreFilter::new(std::slice::from_ref(&ignore_file));
+        assert!(filter.is_ignored(Path::new("a.tmp"), false));
+    }
+
+    #[test]
+    fn test_later_ignore_files_take_precedence() {
+        let temp = TempDir::new().unwrap();
+        let global = temp.path().join("global");
+        let context = temp.path().join("context");
+        fs::write(&global, "*.log\n!notes.txt\n").unwrap();
+        fs::write(&context, "!keep.log\n*.txt\n").unwrap();
+
+        let filter = IgnoreFilter::new(&[global.clone(), context.clone()]);
+        assert!(filter.is_ignored(Path::new("app.log"), false));
+        assert!(!filter.is_ignored(Path::new("keep.log"), false));
+        assert!(filter.is_ignored(Path::new("notes.txt"), false));
+
+        // Reversed, the global file's rules win the conflicts
+        let filter = IgnoreFilter::new(&[context.clone(), global.clone()]);
+        assert!(filter.is_ignored(Path::new("keep.log"), false));
+        assert!(!filter.is_ignored(Path::new("notes.txt"), false));
+
+        // The deciding rule names the file it comes from
+        let filter = IgnoreFilter::new(&[global.clone(), context.clone()]);
+        match filter
+            .explain(temp.path(), Path::new("keep.log"), false)
+            .reason
+        {
+            Reason::Pattern { rule, .. } => {
+                assert_eq!(rule.source.as_deref(), Some(context.as_path()));
+                assert_eq!(rule.line, Some(1));
+            }
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_missing_ignore_files_are_skipped() {
+        let temp = TempDir::new().unwrap();
+        let context = temp.path().join("context");
+        fs::write(&context, "*.log\n").unwrap();
+        fs::write(local_ignore_path(&context), "!keep.log\n").unwrap();
+
+        let filter = IgnoreFilter::new(&[
+            temp.path().join("missing"),
+            context.clone(),
+            temp.path().join("missing-too"),
+        ]);
+        assert!(filter.is_ignored(Path::new("app.log"), false));
+        assert!(!filter.is_ignored(Path::new("keep.log"), false));
+        assert!(filter.is_ignore_file(temp.path(), Path::new("context.local")));
+
+        let filter = IgnoreFilter::new(&[temp.path().join("missing")]);
+        assert!(!filter.is_ignored(Path::new("app.log"), false));
+        assert!(!IgnoreFilter::new(&[]).is_ignored(Path::new("app.log"), false));
+    }
+
+    #[test]
+    fn test_detect_each_ecosystem() {
+        for eco in ECOSYSTEMS {
+            for marker in eco.markers {
+                let temp = fixture(&[marker]);
+                let detected = detect_ecosystems(temp.path());
+                assert!(
+                    detected.contains(eco),
+                    "{} not detected from {}",
+                    eco.name,
+                    marker
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_detect_terraform_directory() {
+        let temp = TempDir::new().unwrap();
+        fs::create_dir(temp.path().join(".terraform")).unwrap();
+        assert_eq!(detect_ecosystems(temp.path()), vec![&TERRAFORM]);
+    }
+
+    #[test]
+    fn test_python_template_excludes_other_ecosystems() {
+        let temp = fixture(&["pyproject.toml"]);
+        let template = ignore_template(&detect_ecosystems(temp.path()));
+        assert!(template.contains("# Python\n"));
+        assert!(template.contains(".tox/\n"));
+        assert!(!template.contains("target/"));
+        assert!(!template.contains("node_modules/"));
+        assert!(template.contains(".DS_Store"));
+    }
+
+    #[test]
+    fn test_template_without_ecosystems_uses_generic_patterns() {
+        let temp = fixture(&[]);
+        let template = ignore_template(&detect_ecosystems(temp.path()));
+        assert!(template.contains("node_modules/"));
+        assert!(template.contains("target/"));
+    }
+
+    #[test]
+    fn test_template_lists_shared_patterns_once() {
+        let template = ignore_template(&[&RUST, &JAVA]);
+        assert_eq!(template.matches("target/").count(), 1);
+        assert!(template.contains("# Java\nbuild/\n"));
+    }
+
+    #[test]
+    fn test_template_sections_skip_hard_excluded_dirs() {
+        let sections = GENERIC_SECTIONS.iter().chain(COMMON_SECTIONS);
+        let patterns = sections
+            .flat_map(|section| section.patterns.iter())
+            .chain(ECOSYSTEMS.iter().flat_map(|eco| eco.patterns.iter()));
+        for pattern in patterns {
+            let name = pattern.trim_end_matches('/');
+            assert!(!HARD_EXCLUDED_DIRS.contains(&name), "{}", pattern);
+        }
+
+        let template = ignore_template(&[]);
+        for section in GENERIC_SECTIONS.iter().chain(COMMON_SECTIONS) {
+            assert!(template.contains(&format!("# {}\n", section.title)));
+        }
+    }
+
+    #[test]
+    fn test_create_minimal_ignore_file() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("ctx").join("ignore");
+        create_ignore_file(&path, &[&NODE], IgnoreTemplate::Minimal).unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.lines().all(|line| line.starts_with('#')));
+        let filter = IgnoreFilter::new(std::slice::from_ref(&path));
+        assert!(!filter.is_ignored(Path::new("node_modules"), true));
+        assert!(!filter.is_ignored(Path::new("app.log"), false));
+
+        // Never overwritten
+        create_ignore_file(&path, &[], IgnoreTemplate::Custom("*.log\n".to_string())).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), content);
+    }
+
+    #[test]
+    fn test_missing_patterns() {
+        let content = "# existing\ntarget/\n  node_modules/  \n";
+        let missing = missing_patterns(content, &[&RUST, &NODE, &JAVA]);
+        assert_eq!(
+            missing,
+            [
+                "dist/",
+                "build/",
+                ".next/",
+                "coverage/",
+                ".gradle/",
+                "*.class"
+            ]
+        );
+    }
+}
@@ -3,45 +3,316 @@ mod commands;
 mod config;
 mod error;
 mod ignore;
+mod patch;
 mod path_resolver;
+mod progress;
+mod size_format;
 mod storage;
+mod time_format;
+mod time_spec;
 
-use clap::Parser;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{CommandFactory, Parser};
 use colored::*;
 
 use cli::{Cli, Commands};
-use commands::CommandContext;
+use commands::{CommandContext, LogOptions};
 use config::{ConfigResolver, ResolveOptions};
 use error::Result;
+use ignore::IgnoreTemplate;
 use path_resolver::resolve_ignore_file_path;
+use storage::StorageLocation;
 
-fn main() {
-    if let Err(e) = run() {
-        eprintln!("{}: {}", "error".red().bold(), e);
-        std::process::exit(1);
+fn main() -> ExitCode {
+    let mut resolution = Resolution::default();
+    match run(&mut resolution) {
+        Ok(status) => status,
+        Err(e) => {
+            eprintln!("{}: {}", "error".red().bold(), e);
+            if let Some(footer) = resolution.footer() {
+                eprintln!("{}", footer.dimmed());
+            }
+            ExitCode::FAILURE
+        }
     }
 }
 
-fn run() -> Result<()> {
+/// What `run` had resolved when it failed, so that error output (and bug
+/// reports quoting it) says which context, storage and config were in use
+#[derive(Debug, Default)]
+struct Resolution {
+    /// `project/context`
+    context: Option<String>,
+    project_root: Option<PathBuf>,
+    storage_dir: Option<PathBuf>,
+    config_dir: Option<PathBuf>,
+    /// The command asked for no output (`snap diff --quiet`, `--auto`)
+    quiet: bool,
+}
+
+impl Resolution {
+    /// `context: p/c  storage: ...  config: ...` with whatever is known;
+    /// `None` when quiet or nothing was resolved
+    fn footer(&self) -> Option<String> {
+        if self.quiet {
+            return None;
+        }
+        // Legacy storage is only known once found on disk
+        let storage = self
+            .project_root
+            .as_deref()
+            .and_then(|root| StorageLocation::find_existing(root, self.storage_dir.as_deref()).ok())
+            .map(|location| location.root().to_path_buf())
+            .or_else(|| self.storage_dir.clone());
+
+        let mut parts = Vec::new();
+        if let Some(ref context) = self.context {
+            parts.push(format!("context: {}", context));
+        }
+        if let Some(storage) = storage {
+            parts.push(format!("storage: {}", home_relative(&storage)));
+        }
+        if let Some(ref config_dir) = self.config_dir {
+            parts.push(format!("config: {}", home_relative(config_dir)));
+        }
+        (!parts.is_empty()).then(|| parts.join("  "))
+    }
+}
+
+/// `path` with the home directory shortened to `~`
+fn home_relative(path: &Path) -> String {
+    match dirs::home_dir().and_then(|home| path.strip_prefix(home).ok().map(Path::to_path_buf)) {
+        Some(rest) if rest.as_os_str().is_empty() => "~".to_string(),
+        Some(rest) => format!("~/{}", rest.display()),
+        None => path.display().to_string(),
+    }
+}
+
+/// Commands whose output is suppressed, errors aside
+fn is_quiet(command: &Commands) -> bool {
+    is_auto_snapshot(command)
+        || matches!(
+            command,
+            Commands::Snap {
+                command: Some(cli::SnapCommands::Diff { quiet: true, .. }),
+                ..
+            }
+        )
+}
+
+/// Commands that read the ignore file while walking the project tree
+fn uses_ignore_file(command: &Commands) -> bool {
+    matches!(
+        command,
+        Commands::Snap {
+            command: None
+                | Some(
+                    cli::SnapCommands::Create { .. }
+                        | cli::SnapCommands::Diff { .. }
+                        | cli::SnapCommands::Restore { .. }
+                ),
+            ..
+        } | Commands::Ignore {
+            command: cli::IgnoreCommands::Check { .. }
+        } | Commands::Serve { .. }
+            | Commands::Status { .. }
+            | Commands::Watch { .. }
+            | Commands::Snapshot { .. }
+            | Commands::Diff { .. }
+            | Commands::Restore { .. }
+    )
+}
+
+/// Snapshots taken by shell hooks (`--auto`) stay quiet
+fn is_auto_snapshot(command: &Commands) -> bool {
+    matches!(
+        command,
+        Commands::Snap {
+            command: Some(cli::SnapCommands::Create { auto: true, .. }),
+            ..
+        } | Commands::Snap {
+            command: None,
+            auto: true,
+            ..
+        } | Commands::Snapshot { auto: true, .. }
+    )
+}
+
+/// `--utc`, `--bytes`, `--no-progress` and `--use-gitignore` override the
+/// configured settings
+fn apply_flag_overrides(cli: &Cli, config: &mut config::Config) {
+    if cli.utc {
+        config.display.timezone = config::DisplayTimezone::Utc;
+    }
+    if cli.bytes {
+        config.display.exact_sizes = true;
+    }
+    if cli.no_progress {
+        config.display.no_progress = true;
+    }
+    if cli.use_gitignore {
+        config.ignore.use_gitignore = true;
+    }
+}
+
+/// Storage directory for `snap gc`, `snap verify` and `stats` given with
+/// `--storage-dir` or as a standalone `--context-dir`. These commands never
+/// touch a working tree, so no project or context is detected for them.
+fn maintenance_storage_dir(cli: &Cli) -> Option<PathBuf> {
+    let storage_dir = match &cli.command {
+        Commands::Snap {
+            command:
+                Some(
+                    cli::SnapCommands::Gc { storage_dir, .. }
+                    | cli::SnapCommands::Verify { storage_dir, .. },
+                ),
+            ..
+        }
+        | Commands::Stats { storage_dir, .. } => storage_dir,
+        _ => return None,
+    };
+    storage_dir
+        .clone()
+        .or_else(|| cli.context_dir.as_ref().map(|dir| dir.join("storage")))
+}
+
+/// Run a maintenance command against `storage_dir` with only the global
+/// config layer
+fn run_on_storage(cli: Cli, storage_dir: &Path, resolution: &mut Resolution) -> Result<ExitCode> {
+    // Never initialize here: a mistyped path must not become an empty store
+    if !storage_dir.join("objects").is_dir() || !storage_dir.join("snapshots").is_dir() {
+        return Err(error::MoteError::StorageNotFound(
+            storage_dir.display().to_string(),
+        ));
+    }
+
+    resolution.storage_dir = Some(storage_dir.to_path_buf());
+    let config_resolver = match (&cli.context_dir, &cli.config_dir) {
+        (Some(context_dir), None) => {
+            resolution.config_dir = None;
+            ConfigResolver::standalone(context_dir)?
+        }
+        _ => {
+            let config_resolver = ConfigResolver::load_global(cli.config_dir.clone())?;
+            resolution.config_dir = Some(config_resolver.config_dir().to_path_buf());
+            config_resolver
+        }
+    };
+    let mut config = config_resolver.resolve();
+    apply_flag_overrides(&cli, &mut config);
+    let ctx = CommandContext {
+        project_root: storage_dir,
+        config: &config,
+        storage_dir: Some(storage_dir),
+        ignore_file_path: PathBuf::new(),
+        global_ignore_path: None,
+        extra_ignore_path: None,
+        context_name: config_resolver.context_name(),
+        archived: false,
+    };
+
+    match cli.command {
+        Commands::Snap {
+            command:
+                Some(cli::SnapCommands::Gc {
+                    dry_run,
+                    verbose,
+                    no_grace,
+                    ..
+                }),
+            ..
+        } => commands::cmd_gc(
+            &ctx,
+            &commands::GcOptions {
+                dry_run,
+                verbose,
+                no_grace,
+            },
+        )?,
+        Commands::Stats {
+            history, by, json, ..
+        } => commands::cmd_stats(
+            &ctx,
+            &commands::StatsOptions {
+                history,
+                period: by,
+                json,
+            },
+        )?,
+        Commands::Snap {
+            command:
+                Some(cli::SnapCommands::Verify {
+                    full,
+                    delete_corrupt,
+                    force,
+                    ..
+                }),
+            ..
+        } => commands::cmd_verify(
+            &ctx,
+            &commands::VerifyOptions {
+                full,
+                delete_corrupt,
+                force,
+            },
+        )?,
+        _ => unreachable!("maintenance_storage_dir only selects gc, verify and stats"),
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Set to `1` to silence warnings about deprecated flags while migrating scripts
+const SUPPRESS_DEPRECATIONS_ENV: &str = "MOTE_SUPPRESS_DEPRECATIONS";
+
+/// Exit status on success: 0, or 1 for `snap diff --exit-code/--quiet` with
+/// differences and for `status` with changes
+fn run(resolution: &mut Resolution) -> Result<ExitCode> {
     let cli = Cli::parse();
+    // `colored` checks the terminal itself; the choice made here wins
+    colored::control::set_override(commands::use_color(cli.color));
+    resolution.quiet = is_quiet(&cli.command);
+    resolution.config_dir = Some(ConfigResolver::config_dir_or_default(
+        cli.config_dir.clone(),
+    ));
+
+    // Completion scripts are the same everywhere, even outside a project
+    if let Commands::Completions { shell } = cli.command {
+        commands::cmd_completions(Cli::command(), shell);
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    if let Some(storage_dir) = maintenance_storage_dir(&cli) {
+        return run_on_storage(cli, &storage_dir, resolution);
+    }
 
     // Parse context specifier and validate options
     let (project, context) = cli.parse_context_spec()?;
+    resolution.context = project
+        .as_ref()
+        .map(|project| format!("{}/{}", project, context.as_deref().unwrap_or("default")));
+    if let Some(warning) = cli.deprecation_warning() {
+        if std::env::var(SUPPRESS_DEPRECATIONS_ENV).as_deref() != Ok("1") {
+            eprintln!("{}: {}", "warning".yellow(), warning);
+        }
+    }
 
-    let project_root = cli
+    let cwd_root = cli
         .project_root
         .clone()
         .unwrap_or_else(|| std::env::current_dir().expect("Failed to get current directory"));
 
     // Standalone mode detection: --context-dir without -c/--context
-    let is_standalone_mode = cli.context_dir.is_some()
-        && !matches!(&cli.command, Commands::Context { .. });
+    let is_standalone_mode =
+        cli.context_dir.is_some() && !matches!(&cli.command, Commands::Context { .. });
 
     let allow_missing_project = matches!(
         &cli.command,
         Commands::Context {
             command: cli::ContextCommands::New { .. }
         } | Commands::Migrate { .. }
+            | Commands::Quickstart { .. }
     ) || is_standalone_mode;
 
     let resolve_opts = ResolveOptions {
@@ -49,41 +320,89 @@ fn run() -> Result<()> {
         project,
         context,
         context_dir: cli.context_dir.clone(),
-        project_root: project_root.clone(),
+        project_root: cwd_root.clone(),
         allow_missing_project,
     };
 
-    let config_resolver = ConfigResolver::load(&resolve_opts)?;
-    let config = config_resolver.resolve();
+    // Standalone mode reads and writes nothing outside the context directory
+    // and the project; in particular nothing under the home config directory
+    let manages_projects = matches!(
+        &cli.command,
+        Commands::Migrate { .. }
+            | Commands::Quickstart { .. }
+            | Commands::Project {
+                command: cli::ProjectCommands::List
+            }
+    );
+    if is_standalone_mode && manages_projects {
+        return Err(error::MoteError::InvalidArguments(
+            "-d/--context-dir has no config directory for this command to manage projects in"
+                .to_string(),
+        ));
+    }
+    let config_resolver = if is_standalone_mode {
+        ConfigResolver::standalone(cli.context_dir.as_deref().unwrap())?
+    } else {
+        ConfigResolver::load(&resolve_opts)?
+    };
+    resolution.config_dir =
+        (!is_standalone_mode).then(|| config_resolver.config_dir().to_path_buf());
+    let global_config_dir = (!is_standalone_mode).then(|| config_resolver.config_dir());
+    resolution.context = config_resolver
+        .project_name()
+        .map(|project| format!("{}/{}", project, config_resolver.context_name()));
+    let mut config = config_resolver.resolve();
+    apply_flag_overrides(&cli, &mut config);
+
+    // A project selected with -c that lives elsewhere operates on its own
+    // recorded root unless --project-root says otherwise
+    let project_root = match config_resolver.foreign_project_root(&cwd_root) {
+        Some(root) if cli.project_root.is_none() && !is_standalone_mode => {
+            eprintln!(
+                "{} Using project root {}",
+                "!".yellow().bold(),
+                root.display()
+            );
+            root.to_path_buf()
+        }
+        _ => cwd_root,
+    };
 
     // Auto-initialize context directory if in standalone mode
     if let Some(ref ctx_dir) = cli.context_dir {
-        if is_standalone_mode {
-            if !ctx_dir.exists() {
-                std::fs::create_dir_all(ctx_dir)?;
-                std::fs::create_dir_all(ctx_dir.join("storage"))?;
-                std::fs::create_dir_all(ctx_dir.join("storage/objects"))?;
-                std::fs::create_dir_all(ctx_dir.join("storage/snapshots"))?;
-
-                // Create default ignore file
-                let ignore_path = ctx_dir.join("ignore");
-                if !ignore_path.exists() {
-                    crate::ignore::create_ignore_file(&ignore_path)?;
-                }
+        if is_standalone_mode && !ctx_dir.exists() {
+            std::fs::create_dir_all(ctx_dir)?;
+            std::fs::create_dir_all(ctx_dir.join("storage"))?;
+            std::fs::create_dir_all(ctx_dir.join("storage/objects"))?;
+            std::fs::create_dir_all(ctx_dir.join("storage/snapshots"))?;
+
+            // Create default ignore file
+            let ignore_path = ctx_dir.join("ignore");
+            if !ignore_path.exists() {
+                crate::ignore::create_ignore_file(
+                    &ignore_path,
+                    &crate::ignore::detect_ecosystems(&project_root),
+                    IgnoreTemplate::Default,
+                )?;
             }
         }
     }
 
+    // The default .moteignore is optional; a path that came from context or
+    // config is expected to exist
+    let context_ignore_path = config_resolver.context_ignore_path();
+    let ignore_file_explicit = !is_standalone_mode
+        && (context_ignore_path.is_some()
+            || config.ignore.ignore_file != config::IgnoreConfig::default().ignore_file);
+
     let ignore_file_path = if is_standalone_mode {
         // Standalone mode: use context_dir/ignore
         cli.context_dir.as_ref().unwrap().join("ignore")
     } else {
         // Normal mode: use context ignore path or project default
-        config_resolver
-            .context_ignore_path()
-            .unwrap_or_else(|| {
-                resolve_ignore_file_path(&project_root, None, &config.ignore.ignore_file)
-            })
+        context_ignore_path.unwrap_or_else(|| {
+            resolve_ignore_file_path(&project_root, None, &config.ignore.ignore_file)
+        })
     };
 
     let ignore_file_path = if ignore_file_path.is_absolute() {
@@ -92,6 +411,34 @@ fn run() -> Result<()> {
         project_root.join(ignore_file_path)
     };
 
+    // Layered after the context's ignore file, so it can re-include with `!`
+    let extra_ignore_path = cli
+        .ignore_file
+        .as_deref()
+        .map(|path| path_resolver::resolve_path(&project_root, path));
+
+    let explicit_ignore_paths = ignore_file_explicit
+        .then_some(&ignore_file_path)
+        .into_iter()
+        .chain(&extra_ignore_path);
+    for path in explicit_ignore_paths {
+        if !uses_ignore_file(&cli.command) || path.exists() {
+            continue;
+        }
+        if config.ignore.require_ignore_file {
+            return Err(error::MoteError::IgnoreFileMissing(
+                path.display().to_string(),
+            ));
+        }
+        if !is_auto_snapshot(&cli.command) {
+            eprintln!(
+                "{}: Ignore file {} does not exist; its patterns won't apply",
+                "warning".yellow().bold(),
+                path.display()
+            );
+        }
+    }
+
     let resolved_storage_dir = if is_standalone_mode {
         // Standalone mode: use context_dir/storage
         Some(cli.context_dir.as_ref().unwrap().join("storage"))
@@ -106,52 +453,271 @@ fn run() -> Result<()> {
         })
     };
 
+    resolution.project_root = Some(project_root.clone());
+    resolution.storage_dir = resolved_storage_dir.clone();
+
     let ctx = CommandContext {
         project_root: &project_root,
         config: &config,
         storage_dir: resolved_storage_dir.as_deref(),
         ignore_file_path: ignore_file_path.clone(),
+        global_ignore_path: Some(config_resolver.global_ignore_path()),
+        extra_ignore_path,
+        context_name: config_resolver.context_name(),
+        archived: config_resolver.is_archived(),
     };
 
-    match cli.command {
-        Commands::Snap { command } => match command {
-            None | Some(cli::SnapCommands::Create { .. }) => {
-                let (message, trigger, auto) = if let Some(cli::SnapCommands::Create {
+    let result = match cli.command {
+        Commands::Snap {
+            command,
+            message,
+            trigger,
+            auto,
+        } => match command {
+            None => commands::cmd_snapshot(
+                &ctx,
+                &commands::SnapshotOptions {
                     message,
                     trigger,
                     auto,
-                }) = command
-                {
-                    (message, trigger, auto)
-                } else {
-                    (None, None, false)
-                };
-                commands::cmd_snapshot(&ctx, message, trigger, auto)
-            }
-            Some(cli::SnapCommands::List { limit, oneline }) => {
-                commands::cmd_log(&ctx, limit, oneline)
-            }
-            Some(cli::SnapCommands::Show { snapshot_id }) => {
-                commands::cmd_show(&ctx, &snapshot_id)
-            }
+                    ..Default::default()
+                },
+            ),
+            Some(cli::SnapCommands::Create {
+                message,
+                trigger,
+                auto,
+                metadata,
+                checkpoint,
+                force_large_root,
+                fail_fast,
+                compression,
+            }) => commands::cmd_snapshot(
+                &ctx,
+                &commands::SnapshotOptions {
+                    message,
+                    trigger,
+                    auto,
+                    metadata,
+                    checkpoint,
+                    force_large_root,
+                    fail_fast,
+                    compression,
+                },
+            ),
+            Some(cli::SnapCommands::List {
+                limit,
+                all,
+                oneline,
+                metadata,
+                json,
+                verbose,
+                since,
+                until,
+                grep,
+                regex,
+                trigger,
+                stat,
+                format,
+                follow,
+                reverse,
+                offset,
+                skip_empty,
+            }) => commands::cmd_log(
+                &ctx,
+                &LogOptions {
+                    limit,
+                    all,
+                    oneline,
+                    metadata,
+                    json,
+                    verbose,
+                    since,
+                    until,
+                    grep,
+                    regex,
+                    trigger,
+                    stat,
+                    format,
+                    follow,
+                    reverse,
+                    offset,
+                    skip_empty,
+                },
+            ),
+            Some(cli::SnapCommands::Show {
+                snapshot_id,
+                stat,
+                json,
+                path,
+                tree,
+                depth,
+            }) => commands::cmd_show(
+                &ctx,
+                snapshot_id.as_deref(),
+                &commands::ShowOptions {
+                    stat,
+                    json,
+                    path,
+                    tree,
+                    depth,
+                },
+            ),
             Some(cli::SnapCommands::Diff {
                 snapshot_id,
                 snapshot_id2,
+                range,
+                at,
                 name_only,
                 output,
+                force_output,
                 unified,
-            }) => commands::cmd_diff(&ctx, snapshot_id, snapshot_id2, name_only, output, unified),
+                summary_only,
+                stat,
+                json,
+                with_hunks,
+                exit_code,
+                quiet,
+                ignore_mode,
+                ignore_all_space,
+                ignore_blank_lines,
+                git_format,
+                word_diff,
+                tool,
+                paths,
+            }) => {
+                let snapshot_id = match at {
+                    Some(at) => Some(commands::snapshot_id_at(&ctx, &at, quiet)?),
+                    None => snapshot_id,
+                };
+                let changed = commands::cmd_diff(
+                    &ctx,
+                    snapshot_id,
+                    snapshot_id2,
+                    &commands::DiffOptions {
+                        range,
+                        name_only,
+                        output,
+                        force_output,
+                        unified,
+                        summary_only,
+                        stat,
+                        json,
+                        with_hunks,
+                        quiet,
+                        ignore_mode,
+                        ignore_all_space,
+                        ignore_blank_lines,
+                        git_format,
+                        word_diff,
+                        tool,
+                        color: cli.color,
+                        paths,
+                    },
+                )?;
+                if (exit_code || quiet) && changed {
+                    return Ok(ExitCode::from(1));
+                }
+                Ok(())
+            }
             Some(cli::SnapCommands::Restore {
                 snapshot_id,
+                at,
                 file,
                 force,
                 dry_run,
-            }) => commands::cmd_restore(&ctx, &snapshot_id, file, force, dry_run),
+                clean,
+                yes,
+                target,
+                interactive,
+                serial,
+                stdout,
+                verbose,
+                exclude,
+            }) => {
+                let opts = commands::RestoreOptions {
+                    files: file,
+                    force,
+                    dry_run,
+                    clean,
+                    yes,
+                    target,
+                    interactive,
+                    serial,
+                    stdout,
+                    verbose,
+                    exclude,
+                };
+                let snapshot_id = match at {
+                    Some(at) => commands::snapshot_id_at(&ctx, &at, stdout)?,
+                    None => snapshot_id.expect("clap requires a snapshot ID without --at"),
+                };
+                commands::cmd_restore(&ctx, &snapshot_id, &opts)
+            }
+            Some(cli::SnapCommands::FixPerms {
+                snapshot_id,
+                dry_run,
+            }) => commands::cmd_fix_perms(&ctx, snapshot_id.as_deref(), dry_run),
+            Some(cli::SnapCommands::Apply {
+                patch_file,
+                dry_run,
+                reverse,
+                atomic,
+            }) => {
+                let opts = commands::ApplyOptions {
+                    dry_run,
+                    reverse,
+                    atomic,
+                };
+                commands::cmd_apply(&ctx, &patch_file, &opts)
+            }
             Some(cli::SnapCommands::Delete { snapshot_id, force }) => {
                 commands::cmd_delete(&ctx, &snapshot_id, force)
             }
-            Some(cli::SnapCommands::Gc { dry_run, verbose }) => {
-                commands::cmd_gc(&ctx, dry_run, verbose)
+            Some(cli::SnapCommands::ExportStore { keep, ids, output }) => {
+                commands::cmd_export_store(&ctx, keep, ids, &output)
+            }
+            Some(cli::SnapCommands::PruneBackups {
+                older_than,
+                keep,
+                force,
+                dry_run,
+                gc,
+            }) => {
+                let opts = commands::PruneBackupsOptions {
+                    older_than,
+                    keep,
+                    force,
+                    dry_run,
+                    gc,
+                };
+                commands::cmd_prune_backups(&ctx, &opts)
+            }
+            Some(cli::SnapCommands::Verify {
+                full,
+                delete_corrupt,
+                force,
+                ..
+            }) => {
+                let opts = commands::VerifyOptions {
+                    full,
+                    delete_corrupt,
+                    force,
+                };
+                commands::cmd_verify(&ctx, &opts)
+            }
+            Some(cli::SnapCommands::Gc {
+                dry_run,
+                verbose,
+                no_grace,
+                ..
+            }) => {
+                let opts = commands::GcOptions {
+                    dry_run,
+                    verbose,
+                    no_grace,
+                };
+                commands::cmd_gc(&ctx, &opts)
             }
         },
         Commands::Project { command } => match command {
@@ -169,14 +735,49 @@ fn run() -> Result<()> {
             }
             cli::ProjectCommands::Init { name: _ } => {
                 // TODO: Implement proper project init with custom name
-                commands::cmd_init(&ctx)
+                commands::cmd_init(&ctx, IgnoreTemplate::Default, global_config_dir)
             }
         },
         Commands::Context { command } => {
             commands::cmd_context(&config_resolver, command, cli.context_dir.as_ref())
         }
-        Commands::Ignore { command } => commands::cmd_ignore(&ignore_file_path, command),
+        Commands::Ignore { command } => {
+            if commands::cmd_ignore(&ctx, command)? {
+                return Ok(ExitCode::from(1));
+            }
+            Ok(())
+        }
+        Commands::Hook { command } => commands::cmd_hook(&project_root, command),
         Commands::Setup { shell } => commands::cmd_setup_shell(&shell),
+        Commands::Completions { .. } => {
+            unreachable!("completions are printed before project setup")
+        }
+        Commands::Serve { stdio: _ } => commands::cmd_serve(&ctx),
+        Commands::Watch { once } => commands::cmd_watch(&ctx, &commands::WatchOptions { once }),
+        Commands::Status { porcelain } => {
+            let dirty = commands::cmd_status(&ctx, &commands::StatusOptions { porcelain })?;
+            if dirty {
+                return Ok(ExitCode::from(1));
+            }
+            Ok(())
+        }
+        Commands::Stats {
+            history, by, json, ..
+        } => commands::cmd_stats(
+            &ctx,
+            &commands::StatsOptions {
+                history,
+                period: by,
+                json,
+            },
+        ),
+        #[cfg(feature = "tui")]
+        Commands::Tui => commands::cmd_tui(&ctx),
+        Commands::Quickstart { yes, no_snapshot } => commands::cmd_quickstart(
+            &project_root,
+            &config_resolver,
+            &commands::QuickstartOptions { yes, no_snapshot },
+        ),
         Commands::Migrate { dry_run } => {
             commands::cmd_migrate(&project_root, &config_resolver, dry_run)
         }
@@ -185,23 +786,72 @@ fn run() -> Result<()> {
             message,
             trigger,
             auto,
-        } => commands::cmd_snapshot(&ctx, message, trigger, auto),
-        Commands::Log { limit, oneline } => commands::cmd_log(&ctx, limit, oneline),
-        Commands::Show { snapshot_id } => commands::cmd_show(&ctx, &snapshot_id),
+        } => commands::cmd_snapshot(
+            &ctx,
+            &commands::SnapshotOptions {
+                message,
+                trigger,
+                auto,
+                ..Default::default()
+            },
+        ),
+        Commands::Log {
+            limit,
+            oneline,
+            trigger,
+        } => commands::cmd_log(
+            &ctx,
+            &LogOptions {
+                limit: Some(limit),
+                oneline,
+                trigger,
+                ..Default::default()
+            },
+        ),
+        Commands::Show { snapshot_id } => {
+            commands::cmd_show(&ctx, Some(&snapshot_id), &commands::ShowOptions::default())
+        }
         Commands::Diff {
             snapshot_id,
             snapshot_id2,
             name_only,
             output,
             unified,
-        } => commands::cmd_diff(&ctx, snapshot_id, snapshot_id2, name_only, output, unified),
+        } => commands::cmd_diff(
+            &ctx,
+            snapshot_id,
+            snapshot_id2,
+            &commands::DiffOptions {
+                name_only,
+                output,
+                unified,
+                ..Default::default()
+            },
+        )
+        .map(|_| ()),
         Commands::Restore {
             snapshot_id,
             file,
             force,
             dry_run,
-        } => commands::cmd_restore(&ctx, &snapshot_id, file, force, dry_run),
+        } => {
+            let opts = commands::RestoreOptions {
+                files: file.into_iter().collect(),
+                force,
+                dry_run,
+                ..Default::default()
+            };
+            commands::cmd_restore(&ctx, &snapshot_id, &opts)
+        }
         Commands::SetupShell { shell } => commands::cmd_setup_shell(&shell),
-        Commands::Init => commands::cmd_init(&ctx),
-    }
+        Commands::Init { minimal } => {
+            let template = if minimal {
+                IgnoreTemplate::Minimal
+            } else {
+                IgnoreTemplate::Default
+            };
+            commands::cmd_init(&ctx, template, global_config_dir)
+        }
+    };
+    result.map(|()| ExitCode::SUCCESS)
 }
@@ -0,0 +1,440 @@
+//! Parsing and applying the unified diffs written by `mote snap diff`
+//!
+//! Files start at a `diff --mote` (or `diff --git`) header; `--- /dev/null`
+//! and `+++ /dev/null` mark created and deleted files. Anything before the
+//! first header, such as the `Comparing ...` line, is ignored.
+
+use crate::error::{MoteError, Result};
+
+/// How a patch changes a file's existence
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchKind {
+    Modify,
+    Create,
+    Delete,
+}
+
+/// A run of changed lines. Lines keep their line endings; a line without one
+/// was followed by `\ No newline at end of file`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hunk {
+    /// 1-based first line, or the line before an empty side
+    pub old_start: usize,
+    pub new_start: usize,
+    pub old: Vec<String>,
+    pub new: Vec<String>,
+}
+
+impl Hunk {
+    /// Index in the old file where `old` is expected to begin
+    fn expected_index(&self) -> usize {
+        if self.old.is_empty() {
+            self.old_start
+        } else {
+            self.old_start.saturating_sub(1)
+        }
+    }
+
+    fn header(&self) -> String {
+        format!(
+            "@@ -{},{} +{},{} @@",
+            self.old_start,
+            self.old.len(),
+            self.new_start,
+            self.new.len()
+        )
+    }
+}
+
+/// One file's changes
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilePatch {
+    /// Project-relative path
+    pub path: String,
+    pub kind: PatchKind,
+    pub hunks: Vec<Hunk>,
+    /// Binary changes are listed without content and can't be applied
+    pub binary: bool,
+}
+
+impl FilePatch {
+    fn new(path: String) -> Self {
+        Self {
+            path,
+            kind: PatchKind::Modify,
+            hunks: Vec::new(),
+            binary: false,
+        }
+    }
+
+    /// The patch that undoes this one
+    pub fn reversed(&self) -> Self {
+        Self {
+            path: self.path.clone(),
+            kind: match self.kind {
+                PatchKind::Modify => PatchKind::Modify,
+                PatchKind::Create => PatchKind::Delete,
+                PatchKind::Delete => PatchKind::Create,
+            },
+            hunks: self
+                .hunks
+                .iter()
+                .map(|hunk| Hunk {
+                    old_start: hunk.new_start,
+                    new_start: hunk.old_start,
+                    old: hunk.new.clone(),
+                    new: hunk.old.clone(),
+                })
+                .collect(),
+            binary: self.binary,
+        }
+    }
+
+    /// Apply to the file's current content (`None` if it doesn't exist).
+    /// Returns the new content, or `None` if the file is to be deleted; the
+    /// error describes the conflict.
+    pub fn apply(&self, original: Option<&str>) -> std::result::Result<Option<String>, String> {
+        if self.binary {
+            return Err("binary changes can't be applied from a patch".to_string());
+        }
+        // Patches written before /dev/null headers create files with hunks
+        // that have nothing on the old side
+        let creates = self.kind == PatchKind::Create
+            || (original.is_none() && self.hunks.iter().all(|h| h.old.is_empty()));
+        match (original, creates) {
+            (Some(_), true) => Err("already exists".to_string()),
+            (None, false) => Err("does not exist".to_string()),
+            (original, _) => {
+                let patched = apply_hunks(original.unwrap_or_default(), &self.hunks)?;
+                match self.kind {
+                    PatchKind::Delete if !patched.is_empty() => {
+                        Err("content differs from the deleted file".to_string())
+                    }
+                    PatchKind::Delete => Ok(None),
+                    _ => Ok(Some(patched)),
+                }
+            }
+        }
+    }
+}
+
+/// Apply `hunks` in order, looking for each one's old lines nearest to where
+/// the header puts them (shifted by how far earlier hunks moved)
+fn apply_hunks(original: &str, hunks: &[Hunk]) -> std::result::Result<String, String> {
+    let lines: Vec<&str> = original.split_inclusive('\n').collect();
+    let mut result = String::with_capacity(original.len());
+    let mut pos = 0;
+    let mut offset: isize = 0;
+
+    for (n, hunk) in hunks.iter().enumerate() {
+        let expected = (hunk.expected_index() as isize + offset).max(pos as isize) as usize;
+        let found = find_lines(&lines, &hunk.old, pos, expected)
+            .ok_or_else(|| format!("hunk {} ({}) does not match", n + 1, hunk.header()))?;
+        for line in &lines[pos..found] {
+            result.push_str(line);
+        }
+        for line in &hunk.new {
+            result.push_str(line);
+        }
+        pos = found + hunk.old.len();
+        offset = found as isize - hunk.expected_index() as isize;
+    }
+    for line in &lines[pos..] {
+        result.push_str(line);
+    }
+    Ok(result)
+}
+
+/// Start of the occurrence of `needle` at or after `from` closest to `near`
+fn find_lines(lines: &[&str], needle: &[String], from: usize, near: usize) -> Option<usize> {
+    let last_start = lines.len().checked_sub(needle.len())?;
+    if needle.is_empty() {
+        return Some(near.min(lines.len()));
+    }
+    if last_start < from {
+        return None;
+    }
+    let matches_at = |i: usize| {
+        lines[i..i + needle.len()]
+            .iter()
+            .zip(needle)
+            .all(|(line, expected)| line == expected)
+    };
+    let near = near.min(last_start);
+    for distance in 0..=last_start {
+        if let Some(after) = near.checked_add(distance).filter(|&i| i <= last_start) {
+            if matches_at(after) {
+                return Some(after);
+            }
+        }
+        if let Some(before) = near.checked_sub(distance).filter(|&i| i >= from) {
+            if distance > 0 && matches_at(before) {
+                return Some(before);
+            }
+        }
+        if near + distance > last_start && near.saturating_sub(distance) <= from {
+            break;
+        }
+    }
+    None
+}
+
+fn parse_error(line: usize, reason: impl Into<String>) -> MoteError {
+    MoteError::PatchParse {
+        line,
+        reason: reason.into(),
+    }
+}
+
+/// Path in `diff --mote a/<path> b/<path>`; both sides are the same path
+fn header_path(rest: &str) -> Option<String> {
+    let path_len = rest.len().checked_sub(5)? / 2;
+    let path = rest.strip_prefix("a/")?.get(..path_len)?;
+    (rest.get(2 + path_len..)? == format!(" b/{}", path)).then(|| path.to_string())
+}
+
+/// `@@ -a[,b] +c[,d] @@` into (start, count) for each side
+fn parse_hunk_header(header: &str) -> Option<((usize, usize), (usize, usize))> {
+    let ranges = header.strip_prefix("@@ -")?;
+    let (ranges, _) = ranges.split_once(" @@")?;
+    let (old, new) = ranges.split_once(" +")?;
+    let range = |range: &str| -> Option<(usize, usize)> {
+        match range.split_once(',') {
+            Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+            None => Some((range.parse().ok()?, 1)),
+        }
+    };
+    Some((range(old)?, range(new)?))
+}
+
+pub fn parse_patch(text: &str) -> Result<Vec<FilePatch>> {
+    let mut lines = text.split_inclusive('\n').enumerate().peekable();
+    let mut patches: Vec<FilePatch> = Vec::new();
+
+    while let Some((index, line)) = lines.next() {
+        let line_number = index + 1;
+        let header = line.trim_end_matches(['\n', '\r']);
+        if let Some(rest) = header
+            .strip_prefix("diff --mote ")
+            .or_else(|| header.strip_prefix("diff --git "))
+        {
+            let path = header_path(rest)
+                .ok_or_else(|| parse_error(line_number, "unrecognized file header"))?;
+            patches.push(FilePatch::new(path));
+            continue;
+        }
+        // Preamble before the first file
+        let Some(patch) = patches.last_mut() else {
+            continue;
+        };
+
+        if let Some(old) = header.strip_prefix("--- ") {
+            match old {
+                "/dev/null" => patch.kind = PatchKind::Create,
+                old => patch.path = old.strip_prefix("a/").unwrap_or(old).to_string(),
+            }
+        } else if let Some(new) = header.strip_prefix("+++ ") {
+            match new {
+                "/dev/null" => patch.kind = PatchKind::Delete,
+                new => patch.path = new.strip_prefix("b/").unwrap_or(new).to_string(),
+            }
+        } else if header.starts_with("new file mode ") {
+            patch.kind = PatchKind::Create;
+        } else if header.starts_with("deleted file mode ") {
+            patch.kind = PatchKind::Delete;
+        } else if header.starts_with("Binary files ") {
+            patch.binary = true;
+        } else if header.starts_with("@@ ") {
+            let ((old_start, old_count), (new_start, new_count)) = parse_hunk_header(header)
+                .ok_or_else(|| parse_error(line_number, "malformed hunk header"))?;
+            let mut hunk = Hunk {
+                old_start,
+                new_start,
+                old: Vec::new(),
+                new: Vec::new(),
+            };
+            while hunk.old.len() < old_count || hunk.new.len() < new_count {
+                let Some((_, body)) = lines.next() else {
+                    return Err(parse_error(line_number, "hunk ends before its line counts"));
+                };
+                // Editors may strip the space from empty context lines
+                let (tag, content) = match body.split_at_checked(1) {
+                    Some(("\n" | "\r", _)) | None => (' ', body),
+                    Some((tag, content)) => (tag.chars().next().unwrap_or(' '), content),
+                };
+                match tag {
+                    ' ' => {
+                        hunk.old.push(content.to_string());
+                        hunk.new.push(content.to_string());
+                    }
+                    '-' => hunk.old.push(content.to_string()),
+                    '+' => hunk.new.push(content.to_string()),
+                    '\\' => strip_last_newline(&mut hunk, None),
+                    _ => {
+                        return Err(parse_error(
+                            line_number,
+                            format!("unexpected line in hunk: {}", body.trim_end()),
+                        ))
+                    }
+                }
+                if let Some((_, next)) = lines.peek() {
+                    if next.starts_with('\\') {
+                        let tag = match tag {
+                            '-' => Some(true),
+                            '+' => Some(false),
+                            _ => None,
+                        };
+                        strip_last_newline(&mut hunk, tag);
+                        lines.next();
+                    }
+                }
+            }
+            patch.hunks.push(hunk);
+        }
+        // Anything else (mode lines, blank separators) carries no content
+    }
+    Ok(patches)
+}
+
+/// Apply `\ No newline at end of file` to the line just read: the old side
+/// (`Some(true)`), the new side (`Some(false)`) or both for context
+fn strip_last_newline(hunk: &mut Hunk, old_side: Option<bool>) {
+    let strip = |lines: &mut Vec<String>| {
+        if let Some(last) = lines.last_mut() {
+            let trimmed = last.trim_end_matches('\n').trim_end_matches('\r').len();
+            last.truncate(trimmed);
+        }
+    };
+    match old_side {
+        Some(true) => strip(&mut hunk.old),
+        Some(false) => strip(&mut hunk.new),
+        None => {
+            strip(&mut hunk.old);
+            strip(&mut hunk.new);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PATCH: &str = "Comparing abc1234 (2026-01-01) -> def5678 (2026-01-02)
+
+diff --mote a/src/main.rs b/src/main.rs
+--- a/src/main.rs
++++ b/src/main.rs
+@@ -1,3 +1,3 @@
+ fn main() {
+-    old();
++    new();
+ }
+
+diff --mote a/added.txt b/added.txt
+--- /dev/null
++++ b/added.txt
+@@ -0,0 +1,2 @@
++one
++two
+\\ No newline at end of file
+
+diff --git a/gone.txt b/gone.txt
+deleted file mode 100644
+--- a/gone.txt
++++ /dev/null
+@@ -1 +0,0 @@
+-bye
+
+diff --mote a/img.png b/img.png
+Binary files a/img.png and b/img.png differ (3 -> 4 bytes)
+
+";
+
+    #[test]
+    fn test_parse_patch() {
+        let patches = parse_patch(PATCH).unwrap();
+        assert_eq!(patches.len(), 4);
+
+        assert_eq!(patches[0].path, "src/main.rs");
+        assert_eq!(patches[0].kind, PatchKind::Modify);
+        assert_eq!(
+            patches[0].hunks,
+            vec![Hunk {
+                old_start: 1,
+                new_start: 1,
+                old: vec!["fn main() {\n", "    old();\n", "}\n"]
+                    .into_iter()
+                    .map(String::from)
+                    .collect(),
+                new: vec!["fn main() {\n", "    new();\n", "}\n"]
+                    .into_iter()
+                    .map(String::from)
+                    .collect(),
+            }]
+        );
+
+        assert_eq!(patches[1].kind, PatchKind::Create);
+        assert_eq!(patches[1].hunks[0].new, vec!["one\n", "two"]);
+        assert_eq!(patches[2].kind, PatchKind::Delete);
+        assert!(patches[3].binary);
+    }
+
+    #[test]
+    fn test_header_path() {
+        assert_eq!(header_path("a/x.rs b/x.rs").as_deref(), Some("x.rs"));
+        assert_eq!(
+            header_path("a/my dir/b/c b/my dir/b/c").as_deref(),
+            Some("my dir/b/c")
+        );
+        assert_eq!(header_path("x.rs y.rs"), None);
+    }
+
+    #[test]
+    fn test_apply_and_reverse() {
+        let patches = parse_patch(PATCH).unwrap();
+        let original = "fn main() {\n    old();\n}\n";
+        let patched = "fn main() {\n    new();\n}\n";
+        assert_eq!(
+            patches[0].apply(Some(original)).unwrap().as_deref(),
+            Some(patched)
+        );
+        assert_eq!(
+            patches[0]
+                .reversed()
+                .apply(Some(patched))
+                .unwrap()
+                .as_deref(),
+            Some(original)
+        );
+
+        assert_eq!(patches[1].apply(None).unwrap().as_deref(), Some("one\ntwo"));
+        assert_eq!(patches[1].reversed().apply(Some("one\ntwo")).unwrap(), None);
+        assert!(patches[1].apply(Some("one\ntwo")).is_err());
+
+        assert_eq!(patches[2].apply(Some("bye\n")).unwrap(), None);
+        assert!(patches[2].apply(Some("changed\n")).is_err());
+        assert!(patches[3].apply(Some("png")).is_err());
+    }
+
+    #[test]
+    fn test_apply_finds_shifted_hunks() {
+        let patches = parse_patch(PATCH).unwrap();
+        let shifted = "// header\n\nfn main() {\n    old();\n}\n";
+        assert_eq!(
+            patches[0].apply(Some(shifted)).unwrap().as_deref(),
+            Some("// header\n\nfn main() {\n    new();\n}\n")
+        );
+
+        let err = patches[0]
+            .apply(Some("fn main() {\n    other();\n}\n"))
+            .unwrap_err();
+        assert_eq!(err, "hunk 1 (@@ -1,3 +1,3 @@) does not match");
+    }
+
+    #[test]
+    fn test_parse_rejects_truncated_hunk() {
+        let err = parse_patch("diff --mote a/x b/x\n--- a/x\n+++ b/x\n@@ -1,2 +1,2 @@\n a\n")
+            .unwrap_err();
+        assert!(err.to_string().contains("line 4"), "{}", err);
+    }
+}
@@ -1,4 +1,4 @@
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 
 /// Resolves a path relative to a base directory.
 /// If the path is absolute, returns it as-is.
@@ -25,6 +25,116 @@ pub fn resolve_ignore_file_path(
     }
 }
 
+/// Converts a user-supplied file path into the project-relative form stored
+/// in snapshots. Absolute paths under project_root are stripped of it;
+/// anything else is returned unchanged.
+pub fn snapshot_relative_path(project_root: &Path, file_path: &str) -> String {
+    let path = Path::new(file_path);
+    if path.is_absolute() {
+        path.strip_prefix(project_root)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .to_string()
+    } else {
+        file_path.to_string()
+    }
+}
+
+/// Whether `path` is the filesystem root or the user's home directory,
+/// which are almost never meant to be snapshotted as a whole
+pub fn is_unsafe_project_root(path: &Path) -> bool {
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    if path.parent().is_none() {
+        return true;
+    }
+    dirs::home_dir()
+        .map(|home| home.canonicalize().unwrap_or(home))
+        .is_some_and(|home| home == path)
+}
+
+/// Directory prefix shared by all `paths` (`a/b` for `a/b/c.txt` and
+/// `a/b/d/e.txt`), cut to `max_len` bytes with a trailing `...`
+pub fn common_dir_prefix<'a>(paths: impl IntoIterator<Item = &'a str>, max_len: usize) -> String {
+    let mut paths = paths.into_iter();
+    let Some(first) = paths.next() else {
+        return String::new();
+    };
+    let mut prefix: Vec<&str> = first.split('/').collect();
+    // The last component of a lone path is the file name
+    prefix.pop();
+    for path in paths {
+        let shared = prefix
+            .iter()
+            .zip(path.split('/'))
+            .take_while(|(a, b)| *a == b)
+            .count();
+        prefix.truncate(shared.min(path.split('/').count() - 1));
+    }
+
+    let prefix = prefix.join("/");
+    if prefix.len() <= max_len {
+        return prefix;
+    }
+    let mut end = max_len;
+    while !prefix.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}...", &prefix[..end])
+}
+
+/// Join a snapshot path onto the project root. On Windows, paths past
+/// `MAX_PATH` get the `\\?\` extended-length prefix so they can still be
+/// created.
+#[cfg(windows)]
+pub fn join_project_path(project_root: &Path, relative_path: &str) -> PathBuf {
+    const MAX_PATH: usize = 260;
+
+    let joined = project_root.join(relative_path);
+    if joined.as_os_str().len() < MAX_PATH {
+        return joined;
+    }
+    let Ok(root) = std::path::absolute(project_root) else {
+        return joined;
+    };
+    let root = root.to_string_lossy();
+    // Already extended-length, or a UNC share that would need `\\?\UNC\`
+    if root.starts_with(r"\\") {
+        return joined;
+    }
+    // Verbatim paths skip normalization, so separators must be backslashes
+    let mut extended = PathBuf::from(format!(r"\\?\{}", root));
+    extended.extend(Path::new(relative_path).components());
+    extended
+}
+
+#[cfg(not(windows))]
+pub fn join_project_path(project_root: &Path, relative_path: &str) -> PathBuf {
+    project_root.join(relative_path)
+}
+
+/// Join a snapshot path under `root` after resolving `.` and `..`
+/// lexically; `None` when the result would not be a file inside `root`
+/// (an absolute path, or more `..` than directories)
+pub fn join_within(root: &Path, relative_path: &str) -> Option<PathBuf> {
+    let mut normalized = PathBuf::new();
+    for component in Path::new(relative_path).components() {
+        match component {
+            Component::Normal(part) => normalized.push(part),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    return None;
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    if normalized.as_os_str().is_empty() {
+        return None;
+    }
+    Some(join_project_path(root, &normalized.to_string_lossy()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -69,4 +179,60 @@ mod tests {
         let result = resolve_ignore_file_path(project_root, cli_path, ".moteignore");
         assert_eq!(result, PathBuf::from("/tmp/my.ignore"));
     }
+
+    #[test]
+    fn test_snapshot_relative_path() {
+        let project_root = Path::new("/project");
+        assert_eq!(
+            snapshot_relative_path(project_root, "/project/src/main.rs"),
+            "src/main.rs"
+        );
+        assert_eq!(
+            snapshot_relative_path(project_root, "src/main.rs"),
+            "src/main.rs"
+        );
+        assert_eq!(
+            snapshot_relative_path(project_root, "/elsewhere/a.rs"),
+            "/elsewhere/a.rs"
+        );
+    }
+
+    #[test]
+    fn test_is_unsafe_project_root() {
+        assert!(is_unsafe_project_root(Path::new("/")));
+        if let Some(home) = dirs::home_dir() {
+            assert!(is_unsafe_project_root(&home));
+        }
+        let temp = tempfile::TempDir::new().unwrap();
+        assert!(!is_unsafe_project_root(temp.path()));
+    }
+
+    #[test]
+    fn test_common_dir_prefix() {
+        assert_eq!(common_dir_prefix(["a/b/c.txt", "a/b/d/e.txt"], 100), "a/b");
+        assert_eq!(common_dir_prefix(["a/b/c.txt"], 100), "a/b");
+        assert_eq!(common_dir_prefix(["a/b/c.txt", "a/b"], 100), "a");
+        assert_eq!(common_dir_prefix(["a/x.txt", "b/y.txt"], 100), "");
+        assert_eq!(common_dir_prefix(["top.txt"], 100), "");
+        assert_eq!(common_dir_prefix([], 100), "");
+        assert_eq!(common_dir_prefix(["abcdef/g/h.txt"], 4), "abcd...");
+        assert_eq!(common_dir_prefix(["ééé/x.txt"], 3), "é...");
+    }
+
+    #[test]
+    fn test_join_within() {
+        let root = Path::new("/target");
+        assert_eq!(
+            join_within(root, "src/main.rs"),
+            Some(PathBuf::from("/target/src/main.rs"))
+        );
+        assert_eq!(
+            join_within(root, "./src/../README.md"),
+            Some(PathBuf::from("/target/README.md"))
+        );
+        assert_eq!(join_within(root, "../outside.txt"), None);
+        assert_eq!(join_within(root, "src/../../outside.txt"), None);
+        assert_eq!(join_within(root, "/etc/passwd"), None);
+        assert_eq!(join_within(root, "."), None);
+    }
 }
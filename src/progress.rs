@@ -0,0 +1,131 @@
+use std::fmt::Display;
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use colored::*;
+
+use crate::size_format::format_bytes;
+
+/// Setting this (to anything non-empty) hides progress bars, like `--no-progress`
+pub const NO_PROGRESS_ENV: &str = "MOTE_NO_PROGRESS";
+
+const BAR_WIDTH: usize = 30;
+
+/// Redraws closer together than this are skipped
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A one-line progress bar on stderr, counting files (and optionally bytes
+/// stored) towards a known total. Safe to advance from several threads. A
+/// hidden bar draws nothing, and its warnings go straight to stderr.
+pub struct Progress {
+    bar: Option<Mutex<Bar>>,
+}
+
+struct Bar {
+    label: &'static str,
+    total: usize,
+    done: usize,
+    bytes: Option<u64>,
+    last_draw: Option<Instant>,
+}
+
+impl Progress {
+    /// Shown only when `enabled` and `MOTE_NO_PROGRESS` is unset
+    pub fn new(label: &'static str, total: usize, enabled: bool) -> Self {
+        let hidden_by_env = std::env::var_os(NO_PROGRESS_ENV).is_some_and(|v| !v.is_empty());
+        let bar = (enabled && !hidden_by_env).then(|| {
+            Mutex::new(Bar {
+                label,
+                total,
+                done: 0,
+                bytes: None,
+                last_draw: None,
+            })
+        });
+        Self { bar }
+    }
+
+    /// Count one file done; `bytes` adds to the bytes-stored count
+    pub fn inc(&self, bytes: Option<u64>) {
+        self.with_bar(|bar| {
+            bar.done += 1;
+            if let Some(bytes) = bytes {
+                *bar.bytes.get_or_insert(0) += bytes;
+            }
+            let due = bar
+                .last_draw
+                .is_none_or(|last| last.elapsed() >= REDRAW_INTERVAL);
+            if due || bar.done == bar.total {
+                bar.draw();
+            }
+        });
+    }
+
+    /// Print a `warning:` line above the bar
+    pub fn warn(&self, message: impl Display) {
+        let print = || eprintln!("{}: {}", "warning".yellow(), message);
+        if self.bar.is_none() {
+            print();
+            return;
+        }
+        self.with_bar(|bar| {
+            clear_line();
+            print();
+            bar.draw();
+        });
+    }
+
+    /// Remove the bar, leaving the line free for the summary
+    pub fn finish(&self) {
+        self.with_bar(|bar| {
+            if bar.last_draw.take().is_some() {
+                clear_line();
+            }
+        });
+    }
+
+    fn with_bar(&self, f: impl FnOnce(&mut Bar)) {
+        if let Some(bar) = &self.bar {
+            if let Ok(mut bar) = bar.lock() {
+                f(&mut bar);
+            }
+        }
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+impl Bar {
+    fn draw(&mut self) {
+        let filled = (self.done * BAR_WIDTH)
+            .checked_div(self.total)
+            .unwrap_or(BAR_WIDTH)
+            .min(BAR_WIDTH);
+        let bytes = self
+            .bytes
+            .map(|bytes| format!(", {} stored", format_bytes(bytes)))
+            .unwrap_or_default();
+        let mut stderr = std::io::stderr().lock();
+        let _ = write!(
+            stderr,
+            "\r\x1b[2K{} [{}{}] {}/{} files{}",
+            self.label,
+            "#".repeat(filled),
+            "-".repeat(BAR_WIDTH - filled),
+            self.done,
+            self.total,
+            bytes
+        );
+        let _ = stderr.flush();
+        self.last_draw = Some(Instant::now());
+    }
+}
+
+fn clear_line() {
+    eprint!("\r\x1b[2K");
+}
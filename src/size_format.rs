@@ -0,0 +1,68 @@
+const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+
+/// Formats a byte count in binary units with one decimal (`512 B`, `1.5 KB`,
+/// `2.0 GB`). Sizes past the largest unit stay in TB.
+pub fn format_bytes(bytes: u64) -> String {
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    // Move up while the value would round to 1024.0 or more in this unit
+    while value >= 1023.95 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// Formats a size for display: human-readable, or the exact byte count when
+/// `exact` is set (`--bytes` or `display.exact_sizes`).
+pub fn format_size(bytes: u64, exact: bool) -> String {
+    if exact {
+        format!("{} bytes", bytes)
+    } else {
+        format_bytes(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_bytes_below_one_kilobyte() {
+        assert_eq!(format_bytes(0), "0 B");
+        assert_eq!(format_bytes(1), "1 B");
+        assert_eq!(format_bytes(1023), "1023 B");
+    }
+
+    #[test]
+    fn test_format_bytes_units() {
+        assert_eq!(format_bytes(1024), "1.0 KB");
+        assert_eq!(format_bytes(1536), "1.5 KB");
+        assert_eq!(format_bytes(10 * 1024 * 1024), "10.0 MB");
+        assert_eq!(format_bytes(3 * 1024 * 1024 * 1024 / 2), "1.5 GB");
+        assert_eq!(format_bytes(2 * 1024_u64.pow(4)), "2.0 TB");
+    }
+
+    #[test]
+    fn test_format_bytes_rounding_moves_to_next_unit() {
+        // 1023.99 KB would print as "1024.0 KB"
+        assert_eq!(format_bytes(1024 * 1024 - 10), "1.0 MB");
+        assert_eq!(format_bytes(1024 * 1024 - 100), "1023.9 KB");
+    }
+
+    #[test]
+    fn test_format_bytes_caps_at_terabytes() {
+        assert_eq!(format_bytes(2048 * 1024_u64.pow(4)), "2048.0 TB");
+        assert_eq!(format_bytes(u64::MAX), "16777216.0 TB");
+    }
+
+    #[test]
+    fn test_format_size_exact() {
+        assert_eq!(format_size(1536, true), "1536 bytes");
+        assert_eq!(format_size(1536, false), "1.5 KB");
+    }
+}
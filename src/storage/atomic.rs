@@ -0,0 +1,103 @@
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime};
+
+/// Makes temp file names unique across threads writing at once
+static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Marker between a file's name and the suffix of its temp file
+const TEMP_MARKER: &str = ".tmp";
+
+/// Write `bytes` to `path` through a temp file in the same directory that is
+/// renamed into place, so readers (and a crash) see either the old content or
+/// the new, never a truncated file. With `fsync` the data reaches the disk
+/// before the rename.
+pub fn write_atomic(path: &Path, bytes: &[u8], fsync: bool) -> std::io::Result<()> {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    let temp_path = path.with_file_name(format!(
+        "{}{}-{}-{}",
+        file_name,
+        TEMP_MARKER,
+        std::process::id(),
+        TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let written = File::create(&temp_path).and_then(|mut file| {
+        file.write_all(bytes)?;
+        if fsync {
+            file.sync_all()?;
+        }
+        Ok(())
+    });
+    if let Err(e) = written.and_then(|()| fs::rename(&temp_path, path)) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Whether `name` is a temp file left by [`write_atomic`] (or the plain
+/// `*.tmp` files older versions wrote)
+pub fn is_temp_file(name: &str) -> bool {
+    name.ends_with(TEMP_MARKER) || name.contains(&format!("{}-", TEMP_MARKER))
+}
+
+/// Remove temp files in `dir` (not recursively) older than `grace`, left
+/// behind when a write was interrupted. Returns how many were removed; files
+/// that can't be inspected or removed are skipped.
+pub fn remove_stale_temp_files(dir: &Path, grace: Duration) -> usize {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return 0;
+    };
+    let now = SystemTime::now();
+    entries
+        .flatten()
+        .filter(|entry| is_temp_file(&entry.file_name().to_string_lossy()))
+        .filter(|entry| {
+            entry
+                .metadata()
+                .and_then(|m| m.modified())
+                .is_ok_and(|modified| now.duration_since(modified).unwrap_or_default() >= grace)
+        })
+        .filter(|entry| fs::remove_file(entry.path()).is_ok())
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_write_atomic_replaces_content_without_leftovers() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("index");
+        write_atomic(&path, b"first", false).unwrap();
+        write_atomic(&path, b"second", true).unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), b"second");
+        assert_eq!(fs::read_dir(temp.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_remove_stale_temp_files() {
+        let temp = TempDir::new().unwrap();
+        fs::write(temp.path().join("snap.json"), "{}").unwrap();
+        fs::write(temp.path().join("snap.json.tmp-1-0"), "{").unwrap();
+        fs::write(temp.path().join("index.tmp"), "").unwrap();
+
+        assert_eq!(
+            remove_stale_temp_files(temp.path(), Duration::from_secs(600)),
+            0
+        );
+        assert_eq!(remove_stale_temp_files(temp.path(), Duration::ZERO), 2);
+        assert!(temp.path().join("snap.json").exists());
+        assert!(!is_temp_file("snap.json"));
+        assert!(is_temp_file("abcd.tmp-12-3"));
+    }
+}
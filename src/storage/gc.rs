@@ -1,9 +1,14 @@
 use std::collections::HashSet;
-use std::fs;
-use std::path::Path;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 
+use serde::{Deserialize, Serialize};
+
+use super::objects::is_raw_object;
 use crate::error::Result;
-use crate::storage::{Snapshot, SnapshotStore};
+use crate::storage::{write_atomic, Snapshot, SnapshotStore, StorageLocation};
 
 pub struct ObjectReferences {
     refs: HashSet<String>,
@@ -29,6 +34,10 @@ impl ObjectReferences {
     pub fn referenced_count(&self) -> usize {
         self.refs.len()
     }
+
+    pub fn iter(&self) -> impl Iterator<Item = &String> {
+        self.refs.iter()
+    }
 }
 
 pub struct GcStats {
@@ -40,6 +49,28 @@ pub struct AutoGcInfo {
     pub should_run: bool,
 }
 
+/// Snapshot removals since the last GC, kept next to the index so auto GC
+/// can count churn across runs
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct GcState {
+    pub ops_since_gc: usize,
+}
+
+impl GcState {
+    /// A missing or unreadable state file counts as no operations
+    pub fn load(path: &Path) -> Self {
+        fs::read(path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        write_atomic(path, &serde_json::to_vec(self)?, false)?;
+        Ok(())
+    }
+}
+
 pub fn list_all_objects(objects_dir: &Path) -> Result<Vec<String>> {
     let mut objects = Vec::new();
 
@@ -75,6 +106,79 @@ pub fn list_all_objects(objects_dir: &Path) -> Result<Vec<String>> {
     Ok(objects)
 }
 
+/// Where object `hash` is stored; `None` for a hash too short to have a
+/// prefix directory
+fn object_path(objects_dir: &Path, hash: &str) -> Option<PathBuf> {
+    if hash.len() < 2 || !hash.is_char_boundary(2) {
+        return None;
+    }
+    let (prefix, rest) = hash.split_at(2);
+    Some(objects_dir.join(prefix).join(rest))
+}
+
+/// Compressed size of object `hash` on disk, or `None` if it isn't stored
+pub fn object_disk_size(objects_dir: &Path, hash: &str) -> Result<Option<u64>> {
+    let Some(path) = object_path(objects_dir, hash) else {
+        return Ok(None);
+    };
+    match fs::metadata(&path) {
+        Ok(metadata) => Ok(Some(metadata.len())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Whether object `hash` is stored uncompressed, or `None` if it isn't stored
+pub fn object_is_raw(objects_dir: &Path, hash: &str) -> Result<Option<bool>> {
+    let Some(path) = object_path(objects_dir, hash) else {
+        return Ok(None);
+    };
+    let mut first = [0u8; 1];
+    match File::open(&path) {
+        Ok(mut file) => {
+            let read = file.read(&mut first)?;
+            Ok(Some(is_raw_object(&first[..read])))
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Drop objects written within the last `grace_period_secs` from `hashes`,
+/// returning how many were kept: an unreferenced object that new may belong
+/// to a snapshot still being taken, whose JSON isn't saved yet
+pub fn skip_recent_objects(
+    objects_dir: &Path,
+    hashes: &mut Vec<String>,
+    grace_period_secs: u64,
+) -> Result<usize> {
+    if grace_period_secs == 0 {
+        return Ok(0);
+    }
+    let grace = Duration::from_secs(grace_period_secs);
+    let now = SystemTime::now();
+    let before = hashes.len();
+    let mut error = None;
+    hashes.retain(|hash| {
+        let Some(path) = object_path(objects_dir, hash) else {
+            return true;
+        };
+        match fs::metadata(&path).and_then(|m| m.modified()) {
+            // A clock set back leaves mtimes in the future; keep those too
+            Ok(modified) => now.duration_since(modified).is_ok_and(|age| age >= grace),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => true,
+            Err(e) => {
+                error.get_or_insert(e);
+                true
+            }
+        }
+    });
+    match error {
+        Some(e) => Err(e.into()),
+        None => Ok(before - hashes.len()),
+    }
+}
+
 pub fn delete_objects(
     objects_dir: &Path,
     hashes_to_delete: &[String],
@@ -84,19 +188,13 @@ pub fn delete_objects(
     let mut deleted_bytes = 0;
 
     for hash in hashes_to_delete {
-        if hash.len() < 2 {
+        let Some(object_path) = object_path(objects_dir, hash) else {
             eprintln!("Warning: Skipping invalid hash: {}", hash);
             continue;
-        }
-
-        let (prefix, rest) = hash.split_at(2);
-        let object_path = objects_dir.join(prefix).join(rest);
-
-        if !object_path.exists() {
+        };
+        let Some(size) = object_disk_size(objects_dir, hash)? else {
             continue;
-        }
-
-        let size = fs::metadata(&object_path)?.len();
+        };
 
         if verbose {
             println!("  Deleting object: {}", hash);
@@ -106,7 +204,7 @@ pub fn delete_objects(
         deleted_objects += 1;
         deleted_bytes += size;
 
-        let prefix_dir = objects_dir.join(prefix);
+        let prefix_dir = objects_dir.join(&hash[..2]);
         if let Ok(mut entries) = fs::read_dir(&prefix_dir) {
             if entries.next().is_none() {
                 let _ = fs::remove_dir(&prefix_dir);
@@ -148,6 +246,7 @@ pub fn check_auto_gc(
 pub fn run_auto_gc(
     snapshots_dir: &Path,
     objects_dir: &Path,
+    grace_period_secs: u64,
 ) -> Result<Option<GcStats>> {
     let snapshot_store = SnapshotStore::new(snapshots_dir.to_path_buf());
     let snapshots = snapshot_store.list()?;
@@ -158,10 +257,11 @@ pub fn run_auto_gc(
     }
 
     let all_objects = list_all_objects(objects_dir)?;
-    let unreferenced: Vec<String> = all_objects
+    let mut unreferenced: Vec<String> = all_objects
         .into_iter()
         .filter(|hash| !refs.is_referenced(hash))
         .collect();
+    skip_recent_objects(objects_dir, &mut unreferenced, grace_period_secs)?;
 
     if unreferenced.is_empty() {
         return Ok(None);
@@ -170,3 +270,89 @@ pub fn run_auto_gc(
     let stats = delete_objects(objects_dir, &unreferenced, false)?;
     Ok(Some(stats))
 }
+
+/// Count `removed` snapshots toward auto GC, then collect unreferenced
+/// objects older than `grace_period_secs` once the operations since the last
+/// GC or the unreferenced objects reach `threshold`
+pub fn auto_gc(
+    location: &StorageLocation,
+    threshold: usize,
+    removed: usize,
+    grace_period_secs: u64,
+) -> Result<Option<GcStats>> {
+    let state_path = location.gc_state_path();
+    let mut state = GcState::load(&state_path);
+    state.ops_since_gc += removed;
+
+    let snapshots_dir = location.snapshots_dir();
+    let objects_dir = location.objects_dir();
+    let due = state.ops_since_gc >= threshold
+        || check_auto_gc(&snapshots_dir, &objects_dir, threshold)?.should_run;
+    if !due {
+        if removed > 0 {
+            state.save(&state_path)?;
+        }
+        return Ok(None);
+    }
+
+    let stats = run_auto_gc(&snapshots_dir, &objects_dir, grace_period_secs)?;
+    reset_gc_state(location)?;
+    Ok(stats)
+}
+
+/// Start counting operations afresh after a GC
+pub fn reset_gc_state(location: &StorageLocation) -> Result<()> {
+    GcState::default().save(&location.gc_state_path())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_object_disk_size() {
+        let temp = TempDir::new().unwrap();
+        let objects_dir = temp.path();
+        fs::create_dir_all(objects_dir.join("ab")).unwrap();
+        fs::write(objects_dir.join("ab").join("cdef"), b"12345").unwrap();
+
+        assert_eq!(object_disk_size(objects_dir, "abcdef").unwrap(), Some(5));
+        assert_eq!(object_disk_size(objects_dir, "abffff").unwrap(), None);
+        assert_eq!(object_disk_size(objects_dir, "a").unwrap(), None);
+    }
+
+    #[test]
+    fn test_skip_recent_objects() {
+        let temp = TempDir::new().unwrap();
+        let objects_dir = temp.path();
+        fs::create_dir_all(objects_dir.join("ab")).unwrap();
+        fs::write(objects_dir.join("ab").join("cdef"), b"fresh").unwrap();
+        let old = fs::File::create(objects_dir.join("ab").join("0000")).unwrap();
+        old.set_modified(SystemTime::now() - Duration::from_secs(3600))
+            .unwrap();
+
+        let mut hashes = vec!["abcdef".to_string(), "ab0000".to_string()];
+        let kept = skip_recent_objects(objects_dir, &mut hashes, 600).unwrap();
+        assert_eq!(kept, 1);
+        assert_eq!(hashes, ["ab0000"]);
+
+        let mut hashes = vec!["abcdef".to_string(), "ab0000".to_string()];
+        assert_eq!(skip_recent_objects(objects_dir, &mut hashes, 0).unwrap(), 0);
+        assert_eq!(hashes.len(), 2);
+    }
+
+    #[test]
+    fn test_delete_objects_counts_disk_size() {
+        let temp = TempDir::new().unwrap();
+        let objects_dir = temp.path();
+        fs::create_dir_all(objects_dir.join("ab")).unwrap();
+        fs::write(objects_dir.join("ab").join("cdef"), b"12345").unwrap();
+
+        let hashes = ["abcdef".to_string(), "abffff".to_string()];
+        let stats = delete_objects(objects_dir, &hashes, false).unwrap();
+        assert_eq!(stats.deleted_objects, 1);
+        assert_eq!(stats.deleted_bytes, 5);
+        assert!(!objects_dir.join("ab").exists());
+    }
+}
@@ -4,6 +4,7 @@ use std::fs;
 use std::path::Path;
 use std::time::SystemTime;
 
+use super::atomic::write_atomic;
 use crate::error::Result;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -71,14 +72,15 @@ impl Index {
         Ok(index)
     }
 
-    pub fn save(&self, index_path: &Path) -> Result<()> {
+    /// Replace the index file atomically, syncing it first if `fsync`
+    pub fn save(&self, index_path: &Path, fsync: bool) -> Result<()> {
         if let Some(parent) = index_path.parent() {
             fs::create_dir_all(parent)?;
         }
 
         let encoded = bincode::serialize(self)
             .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
-        fs::write(index_path, encoded)?;
+        write_atomic(index_path, &encoded, fsync)?;
         Ok(())
     }
 
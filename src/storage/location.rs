@@ -80,6 +80,16 @@ impl StorageLocation {
         self.root.join("index")
     }
 
+    /// Advisory lock serializing commands that write the storage
+    pub fn lock_path(&self) -> PathBuf {
+        self.root.join("index.lock")
+    }
+
+    /// Auto GC's operation counter
+    pub fn gc_state_path(&self) -> PathBuf {
+        self.root.join("gc-state")
+    }
+
     pub fn find_existing(project_root: &Path, custom_storage_dir: Option<&Path>) -> Result<Self> {
         if let Some(custom_dir) = custom_storage_dir {
             if custom_dir.exists() {
@@ -0,0 +1,92 @@
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{ErrorKind, Write};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::StorageLocation;
+use crate::error::{MoteError, Result};
+
+/// How long interactive commands wait for another run to finish
+pub const LOCK_WAIT: Duration = Duration::from_secs(3);
+
+/// How long auto snapshots (shell hooks, editor plugins) wait before giving up
+pub const AUTO_LOCK_WAIT: Duration = Duration::from_secs(1);
+
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Exclusive advisory lock on a storage location, held while a command
+/// rewrites its index, snapshots or objects so concurrent runs don't
+/// interleave. The lock is an OS file lock on `index.lock`: it is released
+/// when dropped, and by the OS when the holder dies, so a crashed run never
+/// leaves a stale lock behind. The file itself records the holder's PID.
+pub struct StorageLock {
+    _file: File,
+}
+
+impl StorageLock {
+    /// Lock `location`, retrying for up to `wait` before failing with
+    /// `StorageLocked`. Filesystems without lock support are used unlocked.
+    pub fn acquire(location: &StorageLocation, wait: Duration) -> Result<Self> {
+        let path = location.lock_path();
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+
+        let start = Instant::now();
+        loop {
+            match file.try_lock() {
+                Ok(()) => break,
+                Err(TryLockError::WouldBlock) if start.elapsed() < wait => {
+                    thread::sleep(POLL_INTERVAL);
+                }
+                Err(TryLockError::WouldBlock) => {
+                    return Err(MoteError::StorageLocked {
+                        pid: lock_holder(&path),
+                    });
+                }
+                Err(TryLockError::Error(e)) if e.kind() == ErrorKind::Unsupported => {
+                    return Ok(Self { _file: file });
+                }
+                Err(TryLockError::Error(e)) => return Err(e.into()),
+            }
+        }
+
+        // Best effort: the PID only makes the "locked" error more helpful
+        let _ = file
+            .set_len(0)
+            .and_then(|()| write!(file, "{}", std::process::id()));
+        Ok(Self { _file: file })
+    }
+}
+
+/// PID recorded by the process holding the lock at `path`
+fn lock_holder(path: &Path) -> Option<u32> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_lock_excludes_second_holder_until_dropped() {
+        let temp = TempDir::new().unwrap();
+        let location = StorageLocation::find_existing(temp.path(), Some(temp.path())).unwrap();
+
+        let lock = StorageLock::acquire(&location, Duration::ZERO).unwrap();
+        match StorageLock::acquire(&location, Duration::from_millis(100)) {
+            Err(MoteError::StorageLocked { pid }) => {
+                assert_eq!(pid, Some(std::process::id()))
+            }
+            other => panic!("expected StorageLocked, got {:?}", other.err()),
+        }
+
+        drop(lock);
+        assert!(StorageLock::acquire(&location, Duration::ZERO).is_ok());
+    }
+}
@@ -1,11 +1,23 @@
+pub mod atomic;
 pub mod gc;
 pub mod index;
 pub mod location;
+pub mod lock;
 pub mod objects;
+pub mod search_index;
 pub mod snapshots;
+pub mod stats;
 
-pub use gc::{check_auto_gc, delete_objects, list_all_objects, run_auto_gc, ObjectReferences};
+pub use atomic::{remove_stale_temp_files, write_atomic};
+pub use gc::{
+    auto_gc, delete_objects, list_all_objects, object_disk_size, object_is_raw, reset_gc_state,
+    skip_recent_objects, ObjectReferences,
+};
 pub use index::{Index, IndexEntry};
 pub use location::StorageLocation;
-pub use objects::ObjectStore;
-pub use snapshots::{FileEntry, Snapshot, SnapshotStore};
+pub use lock::{StorageLock, AUTO_LOCK_WAIT, LOCK_WAIT};
+pub use objects::{ObjectStore, COMPRESSION_LEVELS, DEFAULT_COMPRESSION_LEVEL};
+pub use search_index::SearchEntry;
+pub(crate) use snapshots::{ambiguous_error, not_found_error, nth_newest};
+pub use snapshots::{relative_offset, FileEntry, FileKind, Snapshot, SnapshotStore, LATEST_ALIAS};
+pub use stats::object_first_seen;
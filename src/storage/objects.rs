@@ -1,40 +1,198 @@
 use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::fs::{self, File};
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
+#[cfg(test)]
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
 
+use super::{write_atomic, FileKind};
 use crate::error::{MoteError, Result};
 
-const COMPRESSION_LEVEL: i32 = 3;
+/// zstd level used unless configured with `storage.compression_level`
+pub const DEFAULT_COMPRESSION_LEVEL: i32 = 3;
+
+/// Levels accepted for `storage.compression_level` and `--compression`
+pub const COMPRESSION_LEVELS: std::ops::RangeInclusive<i32> = 1..=22;
+
+/// First byte of an object stored uncompressed. zstd frames start with their
+/// magic number (`28 b5 2f fd`), so objects written before raw storage
+/// existed are never mistaken for raw ones.
+const RAW_MARKER: u8 = 0;
+
+/// Leading bytes compressed to judge whether compressing the rest pays off
+const SAMPLE_BYTES: usize = 64 * 1024;
+
+/// Content whose sample compresses to more than this fraction of its size
+/// is stored raw
+const MAX_COMPRESSED_RATIO: f64 = 0.95;
+
+/// Bytes of decompressed content kept in memory, unless configured with
+/// `storage.object_cache_mb`
+const DEFAULT_CACHE_BYTES: usize = 64 * 1024 * 1024;
 
 pub struct ObjectStore {
     objects_dir: PathBuf,
+    /// Decompressed objects, so a blob shared by many files or snapshots is
+    /// read once per command
+    cache: Mutex<ObjectCache>,
+    /// Flush each new object to disk before renaming it into place
+    fsync: bool,
+    /// Lowercase extensions of files stored without trying compression
+    no_compress_extensions: Vec<String>,
+    compression_level: i32,
+    #[cfg(test)]
+    decompressions: AtomicUsize,
+}
+
+/// Least recently used objects are evicted once `budget` bytes are exceeded
+#[derive(Default)]
+struct ObjectCache {
+    entries: HashMap<String, (Vec<u8>, u64)>,
+    bytes: usize,
+    budget: usize,
+    /// Bumped on every access; an entry's stamp records its last use
+    clock: u64,
+}
+
+impl ObjectCache {
+    fn get(&mut self, hash: &str) -> Option<Vec<u8>> {
+        self.clock += 1;
+        let (content, used) = self.entries.get_mut(hash)?;
+        *used = self.clock;
+        Some(content.clone())
+    }
+
+    fn insert(&mut self, hash: &str, content: &[u8]) {
+        // Content larger than the whole budget would only evict everything else
+        if content.len() > self.budget || self.entries.contains_key(hash) {
+            return;
+        }
+        while self.bytes + content.len() > self.budget {
+            let Some(oldest) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(hash, _)| hash.clone())
+            else {
+                break;
+            };
+            if let Some((evicted, _)) = self.entries.remove(&oldest) {
+                self.bytes -= evicted.len();
+            }
+        }
+        self.clock += 1;
+        self.bytes += content.len();
+        self.entries
+            .insert(hash.to_string(), (content.to_vec(), self.clock));
+    }
 }
 
 impl ObjectStore {
     pub fn new(objects_dir: PathBuf) -> Self {
-        Self { objects_dir }
+        Self {
+            objects_dir,
+            cache: Mutex::new(ObjectCache {
+                budget: DEFAULT_CACHE_BYTES,
+                ..ObjectCache::default()
+            }),
+            fsync: false,
+            no_compress_extensions: Vec::new(),
+            compression_level: DEFAULT_COMPRESSION_LEVEL,
+            #[cfg(test)]
+            decompressions: Default::default(),
+        }
+    }
+
+    /// Keep at most `bytes` of retrieved content in memory (0 disables the cache)
+    pub fn cache_limit(self, bytes: usize) -> Self {
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.budget = bytes;
+        }
+        self
+    }
+
+    /// Sync new objects to disk before they become visible (`storage.fsync`)
+    pub fn fsync(mut self, fsync: bool) -> Self {
+        self.fsync = fsync;
+        self
+    }
+
+    /// zstd level for newly written objects
+    pub fn compression_level(mut self, level: i32) -> Self {
+        self.compression_level = level;
+        self
     }
 
-    pub fn store(&self, content: &[u8]) -> Result<String> {
+    /// Store files with these extensions (case-insensitive, no dot) raw,
+    /// without sampling them (`storage.no_compress_extensions`)
+    pub fn no_compress_extensions(mut self, extensions: &[String]) -> Self {
+        self.no_compress_extensions = extensions
+            .iter()
+            .map(|ext| ext.trim_start_matches('.').to_lowercase())
+            .collect();
+        self
+    }
+
+    /// Store content and return its hash, plus whether a new object was written
+    /// (`false` if identical content was already stored)
+    pub fn store(&self, content: &[u8]) -> Result<(String, bool)> {
+        self.store_encoded(content, true)
+    }
+
+    /// `try_compress = false` stores new content raw without sampling it
+    fn store_encoded(&self, content: &[u8], try_compress: bool) -> Result<(String, bool)> {
         let hash = Self::compute_hash(content);
         let object_path = self.object_path(&hash);
 
-        if object_path.exists() {
-            return Ok(hash);
+        // An existing object that is incomplete (e.g. cut short by a crash in
+        // a version that wrote in place) is rewritten from this content
+        if self.is_complete_object(&hash, content.len()) {
+            return Ok((hash, false));
         }
 
         if let Some(parent) = object_path.parent() {
             fs::create_dir_all(parent)?;
         }
 
-        let compressed = zstd::encode_all(content, COMPRESSION_LEVEL)?;
-        fs::write(&object_path, compressed)?;
+        // Written aside and renamed into place, so a concurrent store of the
+        // same content (or a reader) never sees a partial object. A temp file
+        // left by a crash looks like an unreferenced object and GC removes it.
+        let encoded = if try_compress {
+            encode(content, self.compression_level)?
+        } else {
+            encode_raw(content)
+        };
+        write_atomic(&object_path, &encoded, self.fsync)?;
 
-        Ok(hash)
+        Ok((hash, true))
     }
 
     pub fn retrieve(&self, hash: &str) -> Result<Vec<u8>> {
+        if let Some(content) = self.cache.lock().ok().and_then(|mut c| c.get(hash)) {
+            return Ok(content);
+        }
+        let (_, content) = self.load_verified(hash)?;
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.insert(hash, &content);
+        }
+        Ok(content)
+    }
+
+    /// Returns the stored (compressed or raw) bytes of an object after
+    /// verifying its hash
+    pub fn retrieve_compressed(&self, hash: &str) -> Result<Vec<u8>> {
+        let (compressed, _) = self.load_verified(hash)?;
+        Ok(compressed)
+    }
+
+    /// Decompress an object and check its hash, bypassing the cache
+    pub fn verify(&self, hash: &str) -> Result<()> {
+        self.load_verified(hash).map(|_| ())
+    }
+
+    fn load_verified(&self, hash: &str) -> Result<(Vec<u8>, Vec<u8>)> {
         let object_path = self.object_path(hash);
 
         if !object_path.exists() {
@@ -42,7 +200,9 @@ impl ObjectStore {
         }
 
         let compressed = fs::read(&object_path)?;
-        let content = zstd::decode_all(compressed.as_slice())?;
+        let content = decode(&compressed)?;
+        #[cfg(test)]
+        self.decompressions.fetch_add(1, Ordering::Relaxed);
 
         let actual_hash = Self::compute_hash(&content);
         if actual_hash != hash {
@@ -52,12 +212,42 @@ impl ObjectStore {
             });
         }
 
-        Ok(content)
+        Ok((compressed, content))
+    }
+
+    /// Whether the stored object for `hash` fully encodes `content_len` bytes.
+    /// Raw objects and zstd frames that record their content size are judged
+    /// by their lengths; other frames are decompressed and hashed.
+    fn is_complete_object(&self, hash: &str, content_len: usize) -> bool {
+        let Ok(stored) = fs::read(self.object_path(hash)) else {
+            return false;
+        };
+        if is_raw_object(&stored) {
+            return stored.len() == content_len + 1;
+        }
+        if zstd::zstd_safe::find_frame_compressed_size(&stored) != Ok(stored.len()) {
+            return false;
+        }
+        match zstd::zstd_safe::get_frame_content_size(&stored) {
+            Ok(Some(size)) => size == content_len as u64,
+            _ => decode(&stored).is_ok_and(|content| Self::compute_hash(&content) == hash),
+        }
+    }
+
+    /// Objects decompressed so far, cache hits excluded
+    #[cfg(test)]
+    pub(crate) fn decompressions(&self) -> usize {
+        self.decompressions.load(Ordering::Relaxed)
     }
 
     fn object_path(&self, hash: &str) -> PathBuf {
+        self.objects_dir.join(Self::relative_path(hash))
+    }
+
+    /// Path of an object relative to the objects directory (`ab/cdef...`)
+    pub fn relative_path(hash: &str) -> PathBuf {
         let (prefix, rest) = hash.split_at(2);
-        self.objects_dir.join(prefix).join(rest)
+        Path::new(prefix).join(rest)
     }
 
     pub fn compute_hash(content: &[u8]) -> String {
@@ -66,27 +256,217 @@ impl ObjectStore {
         hex::encode(hasher.finalize())
     }
 
-    pub fn store_file(&self, path: &Path) -> Result<(String, u64)> {
+    /// Store a file's content, returning `(hash, size, newly_written, kind)`
+    pub fn store_file(&self, path: &Path) -> Result<(String, u64, bool, FileKind)> {
         let mut file = File::open(path)?;
         let mut content = Vec::new();
         file.read_to_end(&mut content)?;
 
         let size = content.len() as u64;
-        let hash = self.store(&content)?;
+        let extension = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase());
+        let (hash, written) =
+            if extension.is_some_and(|ext| self.no_compress_extensions.contains(&ext)) {
+                self.store_encoded(&content, false)?
+            } else {
+                self.store(&content)?
+            };
 
-        Ok((hash, size))
+        Ok((hash, size, written, FileKind::detect(&content)))
     }
 
     pub fn restore_file(&self, hash: &str, dest: &Path) -> Result<()> {
-        let content = self.retrieve(hash)?;
+        Self::write_restored(&self.retrieve(hash)?, dest)
+    }
 
+    /// Write already retrieved content to `dest`, creating parent directories
+    pub fn write_restored(content: &[u8], dest: &Path) -> Result<()> {
         if let Some(parent) = dest.parent() {
             fs::create_dir_all(parent)?;
         }
 
         let mut file = File::create(dest)?;
-        file.write_all(&content)?;
+        file.write_all(content)?;
 
         Ok(())
     }
 }
+
+/// Compress `content` unless a sample of it shows zstd gains next to nothing,
+/// and never store more than the raw encoding would take
+fn encode(content: &[u8], level: i32) -> Result<Vec<u8>> {
+    let sample = &content[..content.len().min(SAMPLE_BYTES)];
+    let compressed_sample = zstd::encode_all(sample, level)?;
+    if compressed_sample.len() as f64 > sample.len() as f64 * MAX_COMPRESSED_RATIO {
+        return Ok(encode_raw(content));
+    }
+    let compressed = if sample.len() == content.len() {
+        compressed_sample
+    } else {
+        zstd::encode_all(content, level)?
+    };
+    if compressed.len() > content.len() {
+        return Ok(encode_raw(content));
+    }
+    Ok(compressed)
+}
+
+fn encode_raw(content: &[u8]) -> Vec<u8> {
+    let mut raw = Vec::with_capacity(content.len() + 1);
+    raw.push(RAW_MARKER);
+    raw.extend_from_slice(content);
+    raw
+}
+
+/// Content of a stored object, raw or zstd-compressed
+fn decode(stored: &[u8]) -> Result<Vec<u8>> {
+    match stored.split_first() {
+        Some((&RAW_MARKER, content)) => Ok(content.to_vec()),
+        _ => Ok(zstd::decode_all(stored)?),
+    }
+}
+
+/// Whether a stored object's bytes hold raw rather than compressed content
+pub fn is_raw_object(stored: &[u8]) -> bool {
+    stored.first() == Some(&RAW_MARKER)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_retrieve_decompresses_each_object_once() {
+        let temp = TempDir::new().unwrap();
+        let store = ObjectStore::new(temp.path().join("objects"));
+        let (hash, _) = store.store(b"shared content\n").unwrap();
+
+        for _ in 0..5 {
+            assert_eq!(store.retrieve(&hash).unwrap(), b"shared content\n");
+        }
+        assert_eq!(store.decompressions(), 1);
+    }
+
+    #[test]
+    fn test_concurrent_stores_of_same_content() {
+        let temp = TempDir::new().unwrap();
+        let store = ObjectStore::new(temp.path().join("objects"));
+        let content = b"stored by every thread\n".repeat(1000);
+
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| store.store(&content).unwrap());
+            }
+        });
+        let hash = ObjectStore::compute_hash(&content);
+        assert_eq!(store.retrieve(&hash).unwrap(), content);
+        let dir = temp.path().join("objects").join(&hash[..2]);
+        assert_eq!(fs::read_dir(dir).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_cache_evicts_least_recently_used() {
+        let temp = TempDir::new().unwrap();
+        let store = ObjectStore::new(temp.path().join("objects")).cache_limit(8);
+        let (a, _) = store.store(b"aaaa").unwrap();
+        let (b, _) = store.store(b"bbbb").unwrap();
+        let (c, _) = store.store(b"cccc").unwrap();
+
+        store.retrieve(&a).unwrap();
+        store.retrieve(&b).unwrap();
+        // `a` is now more recent than `b`, so `c` evicts `b`
+        store.retrieve(&a).unwrap();
+        store.retrieve(&c).unwrap();
+        assert_eq!(store.decompressions(), 3);
+
+        store.retrieve(&a).unwrap();
+        assert_eq!(store.decompressions(), 3);
+        store.retrieve(&b).unwrap();
+        assert_eq!(store.decompressions(), 4);
+
+        let uncached = ObjectStore::new(temp.path().join("objects")).cache_limit(0);
+        uncached.retrieve(&a).unwrap();
+        uncached.retrieve(&a).unwrap();
+        assert_eq!(uncached.decompressions(), 2);
+    }
+
+    #[test]
+    fn test_store_repairs_partially_written_object() {
+        let temp = TempDir::new().unwrap();
+        let store = ObjectStore::new(temp.path().join("objects")).cache_limit(0);
+        let content = b"content cut short by a crash\n".repeat(100);
+        let hash = ObjectStore::compute_hash(&content);
+
+        // What an interrupted in-place write used to leave behind
+        let compressed = zstd::encode_all(content.as_slice(), DEFAULT_COMPRESSION_LEVEL).unwrap();
+        let raw = encode_raw(&content);
+        let object_path = store.object_path(&hash);
+        fs::create_dir_all(object_path.parent().unwrap()).unwrap();
+        for partial in [
+            &compressed[..compressed.len() / 2],
+            &raw[..raw.len() / 2],
+            &b""[..],
+            &b"garbage"[..],
+        ] {
+            fs::write(&object_path, partial).unwrap();
+            assert!(store.retrieve(&hash).is_err());
+
+            assert_eq!(store.store(&content).unwrap(), (hash.clone(), true));
+            assert_eq!(store.retrieve(&hash).unwrap(), content);
+            assert_eq!(store.store(&content).unwrap(), (hash.clone(), false));
+        }
+    }
+
+    /// Bytes that look random to zstd
+    fn incompressible(len: usize) -> Vec<u8> {
+        let mut block = ObjectStore::compute_hash(b"seed").into_bytes();
+        let mut content = Vec::with_capacity(len);
+        while content.len() < len {
+            block = Sha256::digest(&block).to_vec();
+            content.extend_from_slice(&block);
+        }
+        content.truncate(len);
+        content
+    }
+
+    #[test]
+    fn test_incompressible_content_is_stored_raw() {
+        let temp = TempDir::new().unwrap();
+        let store = ObjectStore::new(temp.path().join("objects")).cache_limit(0);
+
+        let random = incompressible(100 * 1024);
+        let (random_hash, _) = store.store(&random).unwrap();
+        let stored = fs::read(store.object_path(&random_hash)).unwrap();
+        assert!(is_raw_object(&stored));
+        assert_eq!(stored.len(), random.len() + 1);
+        assert_eq!(store.retrieve(&random_hash).unwrap(), random);
+
+        let text = b"compresses well\n".repeat(1000);
+        let (text_hash, _) = store.store(&text).unwrap();
+        let stored = fs::read(store.object_path(&text_hash)).unwrap();
+        assert!(stored.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]));
+        assert_eq!(store.retrieve(&text_hash).unwrap(), text);
+    }
+
+    #[test]
+    fn test_no_compress_extensions_skip_compression() {
+        let temp = TempDir::new().unwrap();
+        let store = ObjectStore::new(temp.path().join("objects"))
+            .no_compress_extensions(&["PNG".to_string(), ".zip".to_string()]);
+        let content = b"compressible, but stored as-is\n".repeat(100);
+        fs::write(temp.path().join("image.png"), &content).unwrap();
+        fs::write(temp.path().join("notes.txt"), b"other text\n".repeat(100)).unwrap();
+
+        let (png_hash, ..) = store.store_file(&temp.path().join("image.png")).unwrap();
+        let (txt_hash, ..) = store.store_file(&temp.path().join("notes.txt")).unwrap();
+        assert!(is_raw_object(
+            &fs::read(store.object_path(&png_hash)).unwrap()
+        ));
+        assert!(!is_raw_object(
+            &fs::read(store.object_path(&txt_hash)).unwrap()
+        ));
+        assert_eq!(store.retrieve(&png_hash).unwrap(), content);
+    }
+}
@@ -0,0 +1,81 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use super::atomic::write_atomic;
+use super::snapshots::{trigger_matches, Snapshot};
+use crate::error::Result;
+
+/// Sidecar file in the snapshots directory
+pub const SEARCH_INDEX_FILE: &str = "search.idx";
+
+/// Searchable fields of one snapshot, enough to filter listings without
+/// loading the snapshot's file list
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SearchEntry {
+    pub id: String,
+    /// Name of the snapshot's JSON file
+    pub file_name: String,
+    pub seq: u64,
+    pub timestamp: DateTime<Utc>,
+    #[serde(default)]
+    pub message: Option<String>,
+    #[serde(default)]
+    pub trigger: Option<String>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub metadata: HashMap<String, String>,
+}
+
+impl SearchEntry {
+    pub fn new(snapshot: &Snapshot, file_name: String) -> Self {
+        Self {
+            id: snapshot.id.clone(),
+            file_name,
+            seq: snapshot.seq,
+            timestamp: snapshot.timestamp,
+            message: snapshot.message.clone(),
+            trigger: snapshot.trigger.clone(),
+            metadata: snapshot.metadata.clone(),
+        }
+    }
+
+    /// Same rules as [`Snapshot::matches_metadata`]
+    pub fn matches_metadata(&self, filters: &[(String, String)]) -> bool {
+        filters
+            .iter()
+            .all(|(key, value)| self.metadata.get(key) == Some(value))
+    }
+
+    /// Same rules as [`Snapshot::matches_trigger`]
+    pub fn matches_trigger(&self, trigger: &str) -> bool {
+        trigger_matches(self.trigger.as_deref(), trigger)
+    }
+}
+
+/// Snapshot id → searchable fields, kept in step with the snapshot files by
+/// `SnapshotStore` and rebuilt from them when missing or stale
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SearchIndex {
+    pub entries: Vec<SearchEntry>,
+}
+
+impl SearchIndex {
+    /// `None` if the sidecar is missing or unreadable
+    pub fn load(path: &Path) -> Option<Self> {
+        let content = fs::read_to_string(path).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        write_atomic(path, serde_json::to_string(self)?.as_bytes(), false)?;
+        Ok(())
+    }
+
+    /// Insert or replace the entry for `entry.id`
+    pub fn upsert(&mut self, entry: SearchEntry) {
+        self.entries.retain(|e| e.id != entry.id);
+        self.entries.push(entry);
+    }
+}
@@ -1,10 +1,15 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 
+use super::atomic::write_atomic;
+use super::search_index::{SearchEntry, SearchIndex, SEARCH_INDEX_FILE};
+use crate::config::DisplayTimezone;
 use crate::error::{MoteError, Result};
+use crate::time_format::format_timestamp;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileEntry {
@@ -13,31 +18,100 @@ pub struct FileEntry {
     pub size: u64,
     #[serde(default)]
     pub mode: Option<String>,
+    /// Content type recorded at store time; `None` for older snapshots
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<FileKind>,
+}
+
+/// Bytes inspected when deciding whether content is binary
+const BINARY_SNIFF_LEN: usize = 8000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileKind {
+    Text,
+    Binary,
+}
+
+impl FileKind {
+    /// Content with a NUL byte near the start is treated as binary
+    pub fn detect(content: &[u8]) -> Self {
+        if content[..content.len().min(BINARY_SNIFF_LEN)].contains(&0) {
+            Self::Binary
+        } else {
+            Self::Text
+        }
+    }
+}
+
+impl FileEntry {
+    /// Known to be binary without reading the content
+    pub fn is_known_binary(&self) -> bool {
+        self.kind == Some(FileKind::Binary)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Snapshot {
     pub id: String,
     pub timestamp: DateTime<Utc>,
+    /// Monotonic per-store sequence number; 0 for snapshots created before it existed
+    #[serde(default)]
+    pub seq: u64,
     #[serde(default)]
     pub message: Option<String>,
     pub files: Vec<FileEntry>,
     #[serde(default)]
     pub trigger: Option<String>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub metadata: HashMap<String, String>,
+    /// Sum of all file sizes in the snapshot
+    #[serde(default)]
+    pub total_size: Option<u64>,
+    /// Bytes of content that were not already in the object store
+    #[serde(default)]
+    pub new_bytes: Option<u64>,
+    /// Some files could not be collected, so restoring this snapshot
+    /// cannot bring back the whole tree
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub partial: bool,
+    /// Paths left out of a partial snapshot, at most `MAX_SKIPPED_PATHS`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skipped_paths: Option<Vec<String>>,
 }
 
+/// How many skipped paths a partial snapshot records
+const MAX_SKIPPED_PATHS: usize = 100;
+
 impl Snapshot {
     pub fn new(files: Vec<FileEntry>, message: Option<String>, trigger: Option<String>) -> Self {
         let timestamp = Utc::now();
         let id = Self::generate_id(&timestamp, &files);
+        let total_size = files.iter().map(|f| f.size).sum();
 
         Self {
             id,
             timestamp,
+            seq: 0,
             message,
             files,
             trigger,
+            metadata: HashMap::new(),
+            total_size: Some(total_size),
+            new_bytes: None,
+            partial: false,
+            skipped_paths: None,
+        }
+    }
+
+    /// Record files the collection left out; no-op when there were none
+    pub fn mark_partial(&mut self, mut skipped: Vec<String>) {
+        if skipped.is_empty() {
+            return;
         }
+        skipped.truncate(MAX_SKIPPED_PATHS);
+        self.partial = true;
+        self.skipped_paths = Some(skipped);
     }
 
     fn generate_id(timestamp: &DateTime<Utc>, files: &[FileEntry]) -> String {
@@ -50,6 +124,11 @@ impl Snapshot {
         hex::encode(hasher.finalize())
     }
 
+    /// Ordering key: sequence number first, wall-clock time as tie-breaker
+    pub fn order_key(&self) -> (u64, DateTime<Utc>) {
+        (self.seq, self.timestamp)
+    }
+
     pub fn short_id(&self) -> &str {
         &self.id[..7.min(self.id.len())]
     }
@@ -58,87 +137,272 @@ impl Snapshot {
         self.files.len()
     }
 
+    /// `abc1234 (2024-05-01 10:00 'message')` for error messages
+    pub fn describe(&self) -> String {
+        let timestamp = format_timestamp(&self.timestamp, DisplayTimezone::default(), false);
+        match self.message.as_deref() {
+            Some(message) => format!("{} ({} '{}')", self.short_id(), timestamp, message),
+            None => format!("{} ({})", self.short_id(), timestamp),
+        }
+    }
+
+    /// Whether every `(key, value)` pair is present in this snapshot's metadata
+    pub fn matches_metadata(&self, filters: &[(String, String)]) -> bool {
+        filters
+            .iter()
+            .all(|(key, value)| self.metadata.get(key) == Some(value))
+    }
+
+    /// Whether this snapshot was recorded by `trigger`.
+    /// `none` and `manual` also match snapshots without a trigger.
+    pub fn matches_trigger(&self, trigger: &str) -> bool {
+        trigger_matches(self.trigger.as_deref(), trigger)
+    }
+
     pub fn find_file(&self, path: &str) -> Option<&FileEntry> {
         self.files.iter().find(|f| f.path == path)
     }
 }
 
+pub(super) fn trigger_matches(recorded: Option<&str>, trigger: &str) -> bool {
+    match recorded {
+        Some(t) => t == trigger,
+        None => trigger == "none" || trigger == "manual",
+    }
+}
+
+/// Sequence counter file, kept alongside the snapshot JSON files
+const SEQ_FILE: &str = "seq";
+
+/// Snapshot reference that resolves to the newest snapshot
+pub const LATEST_ALIAS: &str = "latest";
+
 pub struct SnapshotStore {
     snapshots_dir: PathBuf,
+    fsync: bool,
 }
 
 impl SnapshotStore {
     pub fn new(snapshots_dir: PathBuf) -> Self {
-        Self { snapshots_dir }
+        Self {
+            snapshots_dir,
+            fsync: false,
+        }
     }
 
-    pub fn save(&self, snapshot: &Snapshot) -> Result<()> {
-        let filename = format!(
+    /// Sync saved snapshot files and the sequence counter to disk before
+    /// renaming them into place (`storage.fsync`)
+    pub fn fsync(mut self, fsync: bool) -> Self {
+        self.fsync = fsync;
+        self
+    }
+
+    /// File name a snapshot is stored under (`<timestamp>_<id prefix>.json`)
+    pub fn file_name(snapshot: &Snapshot) -> String {
+        format!(
             "{}_{}.json",
             snapshot.timestamp.format("%Y%m%d_%H%M%S"),
             &snapshot.id[..8.min(snapshot.id.len())]
-        );
-        let path = self.snapshots_dir.join(filename);
+        )
+    }
+
+    pub fn save(&self, snapshot: &Snapshot) -> Result<()> {
+        let file_name = Self::file_name(snapshot);
+        let path = self.snapshots_dir.join(&file_name);
 
         let json = serde_json::to_string_pretty(snapshot)?;
-        fs::write(&path, json)?;
+        write_atomic(&path, json.as_bytes(), self.fsync)?;
 
+        self.update_search_index(|index| index.upsert(SearchEntry::new(snapshot, file_name)));
         Ok(())
     }
 
     pub fn list(&self) -> Result<Vec<Snapshot>> {
-        let mut snapshots = Vec::new();
+        let mut snapshots: Vec<Snapshot> = self
+            .load_all()?
+            .into_iter()
+            .map(|(_, snapshot)| snapshot)
+            .collect();
+        snapshots.sort_by_key(|s| std::cmp::Reverse(s.order_key()));
+        Ok(snapshots)
+    }
 
+    /// Paths of all snapshot JSON files
+    fn snapshot_paths(&self) -> Result<Vec<PathBuf>> {
         if !self.snapshots_dir.exists() {
-            return Ok(snapshots);
+            return Ok(Vec::new());
         }
 
+        let mut paths = Vec::new();
         for entry in fs::read_dir(&self.snapshots_dir)? {
-            let entry = entry?;
-            let path = entry.path();
-
+            let path = entry?.path();
             if path.extension().is_some_and(|e| e == "json") {
-                match self.load_snapshot(&path) {
-                    Ok(snapshot) => snapshots.push(snapshot),
-                    Err(e) => eprintln!("Warning: Failed to load snapshot {:?}: {}", path, e),
+                paths.push(path);
+            }
+        }
+        Ok(paths)
+    }
+
+    /// Every snapshot file with its snapshot, or the error that kept it from
+    /// loading, in directory order
+    pub fn load_each(&self) -> Result<Vec<(PathBuf, Result<Snapshot>)>> {
+        Ok(self
+            .snapshot_paths()?
+            .into_iter()
+            .map(|path| {
+                let snapshot = self.load_snapshot(&path);
+                (path, snapshot)
+            })
+            .collect())
+    }
+
+    /// Every loadable snapshot with its file name, in directory order
+    fn load_all(&self) -> Result<Vec<(String, Snapshot)>> {
+        let mut snapshots = Vec::new();
+        for (path, snapshot) in self.load_each()? {
+            match snapshot {
+                Ok(snapshot) => {
+                    let file_name = path.file_name().unwrap().to_string_lossy().to_string();
+                    snapshots.push((file_name, snapshot));
                 }
+                Err(e) => eprintln!("Warning: Failed to load snapshot {:?}: {}", path, e),
             }
         }
+        Ok(snapshots)
+    }
+
+    fn search_index_path(&self) -> PathBuf {
+        self.snapshots_dir.join(SEARCH_INDEX_FILE)
+    }
+
+    /// Apply `update` to the search sidecar if one exists. A failed write
+    /// leaves the entry count out of step, which forces a rebuild on the next
+    /// search, so errors are not reported.
+    fn update_search_index(&self, update: impl FnOnce(&mut SearchIndex)) {
+        let path = self.search_index_path();
+        if let Some(mut index) = SearchIndex::load(&path) {
+            update(&mut index);
+            let _ = index.save(&path);
+        }
+    }
+
+    /// Regenerate the search sidecar from the snapshot files
+    pub fn rebuild_search_index(&self) -> Result<SearchIndex> {
+        let index = SearchIndex {
+            entries: self
+                .load_all()?
+                .iter()
+                .map(|(file_name, snapshot)| SearchEntry::new(snapshot, file_name.clone()))
+                .collect(),
+        };
+        if self.snapshots_dir.exists() {
+            index.save(&self.search_index_path())?;
+        }
+        Ok(index)
+    }
+
+    /// Searchable fields of every snapshot, newest first, read from the
+    /// sidecar. A missing sidecar, or one whose entry count differs from the
+    /// number of snapshot files, is rebuilt first.
+    pub fn search_entries(&self) -> Result<Vec<SearchEntry>> {
+        let file_count = self.snapshot_paths()?.len();
+        let mut entries = match SearchIndex::load(&self.search_index_path()) {
+            Some(index) if index.entries.len() == file_count => index.entries,
+            _ => self.rebuild_search_index()?.entries,
+        };
+        entries.sort_by_key(|e| std::cmp::Reverse((e.seq, e.timestamp)));
+        Ok(entries)
+    }
 
-        snapshots.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+    /// Load the snapshots behind `entries`, keeping their order
+    pub fn load_entries(&self, entries: &[SearchEntry]) -> Result<Vec<Snapshot>> {
+        let mut snapshots = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let path = self.snapshots_dir.join(&entry.file_name);
+            match self.load_snapshot(&path) {
+                Ok(snapshot) => snapshots.push(snapshot),
+                Err(e) => eprintln!("Warning: Failed to load snapshot {:?}: {}", path, e),
+            }
+        }
         Ok(snapshots)
     }
 
+    /// Most recent snapshot by sequence number (not wall-clock time)
+    pub fn latest(&self) -> Result<Option<Snapshot>> {
+        Ok(self.list()?.into_iter().next())
+    }
+
+    /// Allocate the next sequence number and persist it in the counter file.
+    /// The counter never goes below the highest `seq` already stored.
+    pub fn next_seq(&self) -> Result<u64> {
+        let counter_path = self.snapshots_dir.join(SEQ_FILE);
+        let stored = fs::read_to_string(&counter_path)
+            .ok()
+            .and_then(|s| s.trim().parse::<u64>().ok())
+            .unwrap_or(0);
+        let highest = self.list()?.iter().map(|s| s.seq).max().unwrap_or(0);
+
+        let next = stored.max(highest) + 1;
+        fs::create_dir_all(&self.snapshots_dir)?;
+        write_atomic(&counter_path, next.to_string().as_bytes(), self.fsync)?;
+        Ok(next)
+    }
+
     fn load_snapshot(&self, path: &Path) -> Result<Snapshot> {
         let content = fs::read_to_string(path)?;
         let snapshot: Snapshot = serde_json::from_str(&content)?;
         Ok(snapshot)
     }
 
+    /// Resolve a relative reference (`latest`/`@` for the newest snapshot,
+    /// `@~N` or `@{N}` for the N-th before it, in `list` order) or an
+    /// abbreviated ID. IDs are hex, so the aliases never shadow a prefix.
+    pub fn resolve_ref(&self, reference: &str) -> Result<Snapshot> {
+        match relative_offset(reference) {
+            None => self.find_by_id(reference),
+            Some(offset) => Ok(nth_newest(&self.list()?, reference, offset?)?.clone()),
+        }
+    }
+
+    /// The snapshot with the newest timestamp at or before `time`; on a tie,
+    /// the one saved last
+    pub fn latest_at(&self, time: DateTime<Utc>) -> Result<Option<Snapshot>> {
+        // Oldest first, so max_by_key (last maximum wins) prefers the newer
+        Ok(self
+            .list()?
+            .into_iter()
+            .rev()
+            .filter(|s| s.timestamp <= time)
+            .max_by_key(|s| s.timestamp))
+    }
+
     pub fn find_by_id(&self, partial_id: &str) -> Result<Snapshot> {
         let snapshots = self.list()?;
-        let matches: Vec<_> = snapshots
-            .into_iter()
+        let matches: Vec<&Snapshot> = snapshots
+            .iter()
             .filter(|s| s.id.starts_with(partial_id))
             .collect();
 
         match matches.len() {
-            0 => Err(MoteError::SnapshotNotFound(partial_id.to_string())),
-            1 => Ok(matches.into_iter().next().unwrap()),
-            _ => Err(MoteError::AmbiguousSnapshotId(partial_id.to_string())),
+            0 => Err(not_found_error(&snapshots, partial_id)),
+            1 => Ok(matches[0].clone()),
+            _ => Err(ambiguous_error(&matches, partial_id)),
         }
     }
 
+    /// Remove snapshots beyond `max_snapshots` or older than `max_age_days`.
+    /// A limit of 0 disables that check.
     pub fn cleanup(&self, max_snapshots: u32, max_age_days: u32) -> Result<u32> {
-        let mut snapshots = self.list()?;
+        let snapshots = self.list()?;
         let now = Utc::now();
         let mut removed = 0;
 
-        snapshots.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
-
         for (i, snapshot) in snapshots.iter().enumerate() {
-            let age_days = (now - snapshot.timestamp).num_days();
-            let should_remove = i >= max_snapshots as usize || age_days > max_age_days as i64;
+            // A timestamp in the future (clock skew) is never considered expired
+            let age_days = (now - snapshot.timestamp).num_days().max(0);
+            let over_count = max_snapshots > 0 && i >= max_snapshots as usize;
+            let too_old = max_age_days > 0 && age_days > max_age_days as i64;
+            let should_remove = over_count || too_old;
 
             if should_remove {
                 if let Err(e) = self.delete(&snapshot.id) {
@@ -168,11 +432,491 @@ impl SnapshotStore {
                 {
                     if hash_part.starts_with(&id[..8.min(id.len())]) {
                         fs::remove_file(&path)?;
+                        self.update_search_index(|index| {
+                            index.entries.retain(|e| e.file_name != filename)
+                        });
                         return Ok(());
                     }
                 }
             }
         }
-        Err(MoteError::SnapshotNotFound(id.to_string()))
+        Err(MoteError::SnapshotNotFound {
+            id: id.to_string(),
+            suggestions: Vec::new(),
+        })
+    }
+}
+
+/// How many snapshots back a relative reference points (0 for `latest`,
+/// `@`, `@~0`), or `None` if `reference` isn't one and names an ID prefix
+pub fn relative_offset(reference: &str) -> Option<Result<usize>> {
+    if reference == LATEST_ALIAS || reference == "@" {
+        return Some(Ok(0));
+    }
+    let rest = reference.strip_prefix('@')?;
+    let count = rest
+        .strip_prefix('~')
+        .or_else(|| rest.strip_prefix('{').and_then(|r| r.strip_suffix('}')));
+    Some(
+        count
+            .filter(|n| n.bytes().all(|b| b.is_ascii_digit()))
+            .and_then(|n| n.parse().ok())
+            .ok_or_else(|| {
+                MoteError::InvalidArguments(format!(
+                    "Invalid snapshot reference '{}': expected @, @~N or @{{N}}",
+                    reference
+                ))
+            }),
+    )
+}
+
+/// The snapshot `offset` places before the newest in `snapshots` (newest
+/// first), which `reference` named
+pub(crate) fn nth_newest<'a>(
+    snapshots: &'a [Snapshot],
+    reference: &str,
+    offset: usize,
+) -> Result<&'a Snapshot> {
+    if snapshots.is_empty() {
+        return Err(MoteError::NoSnapshotsAvailable);
+    }
+    snapshots
+        .get(offset)
+        .ok_or_else(|| MoteError::SnapshotRefOutOfRange {
+            reference: reference.to_string(),
+            available: snapshots.len(),
+        })
+}
+
+/// Most close IDs a "not found" error suggests
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Most matches an "ambiguous" error lists
+const MAX_AMBIGUOUS_LISTED: usize = 10;
+
+/// `SnapshotNotFound` for `reference`, suggesting the snapshots (newest
+/// first in `snapshots`) whose ID starts within a few typos of it
+pub(crate) fn not_found_error(snapshots: &[Snapshot], reference: &str) -> MoteError {
+    let needle = reference.to_ascii_lowercase();
+    let max_distance = (needle.len() / 3).max(1);
+    let mut close: Vec<(usize, usize, &Snapshot)> = snapshots
+        .iter()
+        .map(|s| (prefix_distance(&needle, &s.id), s))
+        .filter(|(distance, _)| *distance <= max_distance && *distance < needle.len())
+        .map(|(distance, s)| (distance, common_prefix_len(&needle, &s.id), s))
+        .collect();
+    // Stable: among equally close IDs the newer snapshot comes first
+    close.sort_by_key(|(distance, shared, _)| (*distance, std::cmp::Reverse(*shared)));
+
+    MoteError::SnapshotNotFound {
+        id: reference.to_string(),
+        suggestions: close
+            .into_iter()
+            .take(MAX_SUGGESTIONS)
+            .map(|(_, _, s)| s.describe())
+            .collect(),
+    }
+}
+
+/// `AmbiguousSnapshotId` for `reference`, listing the snapshots it matches
+pub(crate) fn ambiguous_error(matches: &[&Snapshot], reference: &str) -> MoteError {
+    let mut listed: Vec<String> = matches
+        .iter()
+        .take(MAX_AMBIGUOUS_LISTED)
+        .map(|s| s.describe())
+        .collect();
+    if matches.len() > MAX_AMBIGUOUS_LISTED {
+        listed.push(format!(
+            "... and {} more",
+            matches.len() - MAX_AMBIGUOUS_LISTED
+        ));
+    }
+    MoteError::AmbiguousSnapshotId {
+        id: reference.to_string(),
+        matches: listed,
+    }
+}
+
+/// Edit distance from `reference` to the closest prefix of `id` that is one
+/// character shorter, as long, or one longer
+fn prefix_distance(reference: &str, id: &str) -> usize {
+    let len = reference.len();
+    (len.saturating_sub(1)..=len + 1)
+        .filter_map(|prefix_len| id.get(..prefix_len))
+        .map(|prefix| edit_distance(reference.as_bytes(), prefix.as_bytes()))
+        .min()
+        .unwrap_or(usize::MAX)
+}
+
+/// Levenshtein distance
+fn edit_distance(a: &[u8], b: &[u8]) -> usize {
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+fn common_prefix_len(a: &str, b: &str) -> usize {
+    a.bytes().zip(b.bytes()).take_while(|(x, y)| x == y).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use tempfile::TempDir;
+
+    fn snapshot_at(store: &SnapshotStore, offset_days: i64, message: &str) -> Snapshot {
+        let mut snapshot = Snapshot::new(Vec::new(), Some(message.to_string()), None);
+        snapshot.timestamp = Utc::now() + Duration::days(offset_days);
+        snapshot.id = Snapshot::generate_id(&snapshot.timestamp, &[]);
+        snapshot.seq = store.next_seq().unwrap();
+        store.save(&snapshot).unwrap();
+        snapshot
+    }
+
+    #[test]
+    fn test_latest_at_picks_newest_timestamp_not_after() {
+        let temp = TempDir::new().unwrap();
+        let store = SnapshotStore::new(temp.path().to_path_buf());
+
+        snapshot_at(&store, -10, "ten days ago");
+        snapshot_at(&store, -3, "three days ago");
+        // Saved last but timestamped earlier (clock skew)
+        snapshot_at(&store, -5, "five days ago");
+
+        let at = |days: i64| {
+            store
+                .latest_at(Utc::now() - Duration::days(days))
+                .unwrap()
+                .and_then(|s| s.message)
+        };
+        assert_eq!(at(0).as_deref(), Some("three days ago"));
+        assert_eq!(at(4).as_deref(), Some("five days ago"));
+        assert_eq!(at(7).as_deref(), Some("ten days ago"));
+        assert_eq!(at(11), None);
+    }
+
+    #[test]
+    fn test_list_orders_by_seq_despite_clock_skew() {
+        let temp = TempDir::new().unwrap();
+        let store = SnapshotStore::new(temp.path().to_path_buf());
+
+        snapshot_at(&store, 0, "first");
+        snapshot_at(&store, -5, "second, clock went backwards");
+
+        let snapshots = store.list().unwrap();
+        assert_eq!(
+            snapshots[0].message.as_deref(),
+            Some("second, clock went backwards")
+        );
+        assert_eq!(snapshots[1].message.as_deref(), Some("first"));
+        assert_eq!(
+            store.latest().unwrap().unwrap().message.as_deref(),
+            Some("second, clock went backwards")
+        );
+    }
+
+    #[test]
+    fn test_legacy_snapshots_without_seq_sort_by_timestamp() {
+        let temp = TempDir::new().unwrap();
+        let store = SnapshotStore::new(temp.path().to_path_buf());
+
+        for (offset, message) in [(-2, "older"), (-1, "newer")] {
+            let mut snapshot = Snapshot::new(Vec::new(), Some(message.to_string()), None);
+            snapshot.timestamp = Utc::now() + Duration::days(offset);
+            snapshot.id = Snapshot::generate_id(&snapshot.timestamp, &[]);
+            store.save(&snapshot).unwrap();
+        }
+        snapshot_at(&store, -10, "sequenced");
+
+        let messages: Vec<_> = store
+            .list()
+            .unwrap()
+            .into_iter()
+            .map(|s| s.message.unwrap())
+            .collect();
+        assert_eq!(messages, ["sequenced", "newer", "older"]);
+    }
+
+    #[test]
+    fn test_partial_flag_defaults_to_complete_and_caps_paths() {
+        let old: Snapshot = serde_json::from_str(
+            r#"{"id": "abc", "timestamp": "2024-05-01T09:00:00Z", "files": []}"#,
+        )
+        .unwrap();
+        assert!(!old.partial);
+        assert!(old.skipped_paths.is_none());
+        let json = serde_json::to_value(&old).unwrap();
+        assert!(json.get("partial").is_none());
+
+        let mut snapshot = Snapshot::new(Vec::new(), None, None);
+        snapshot.mark_partial(Vec::new());
+        assert!(!snapshot.partial);
+        snapshot.mark_partial((0..150).map(|i| format!("file{}", i)).collect());
+        assert!(snapshot.partial);
+        assert_eq!(snapshot.skipped_paths.unwrap().len(), MAX_SKIPPED_PATHS);
+    }
+
+    #[test]
+    fn test_next_seq_never_goes_backwards() {
+        let temp = TempDir::new().unwrap();
+        let store = SnapshotStore::new(temp.path().to_path_buf());
+
+        snapshot_at(&store, 0, "first");
+        snapshot_at(&store, 0, "second");
+
+        fs::remove_file(temp.path().join(SEQ_FILE)).unwrap();
+        assert_eq!(store.next_seq().unwrap(), 3);
+    }
+
+    #[test]
+    fn test_cleanup_keeps_future_timestamps() {
+        let temp = TempDir::new().unwrap();
+        let store = SnapshotStore::new(temp.path().to_path_buf());
+
+        snapshot_at(&store, 3650, "far future");
+        snapshot_at(&store, 0, "now");
+
+        assert_eq!(store.cleanup(1000, 30).unwrap(), 0);
+        assert_eq!(store.list().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_cleanup_zero_limits_are_disabled() {
+        let temp = TempDir::new().unwrap();
+        let store = SnapshotStore::new(temp.path().to_path_buf());
+
+        snapshot_at(&store, -400, "ancient");
+        snapshot_at(&store, -1, "yesterday");
+        snapshot_at(&store, 0, "today");
+
+        assert_eq!(store.cleanup(0, 0).unwrap(), 0);
+        assert_eq!(store.list().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_cleanup_age_boundary_is_kept() {
+        let temp = TempDir::new().unwrap();
+        let store = SnapshotStore::new(temp.path().to_path_buf());
+
+        snapshot_at(&store, -31, "past the limit");
+        snapshot_at(&store, -30, "exactly at the limit");
+
+        assert_eq!(store.cleanup(0, 30).unwrap(), 1);
+        let snapshots = store.list().unwrap();
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(
+            snapshots[0].message.as_deref(),
+            Some("exactly at the limit")
+        );
+    }
+
+    #[test]
+    fn test_cleanup_with_only_one_limit_disabled() {
+        let temp = TempDir::new().unwrap();
+        let store = SnapshotStore::new(temp.path().to_path_buf());
+
+        snapshot_at(&store, -100, "old");
+        snapshot_at(&store, -2, "recent");
+        snapshot_at(&store, 0, "newest");
+
+        // Count limit only: ages are ignored
+        assert_eq!(store.cleanup(2, 0).unwrap(), 1);
+        let messages: Vec<_> = store
+            .list()
+            .unwrap()
+            .into_iter()
+            .map(|s| s.message.unwrap())
+            .collect();
+        assert_eq!(messages, ["newest", "recent"]);
+
+        // Age limit only: count is ignored
+        snapshot_at(&store, 0, "another");
+        assert_eq!(store.cleanup(0, 1).unwrap(), 1);
+        assert_eq!(store.list().unwrap().len(), 2);
+    }
+
+    fn entry_messages(store: &SnapshotStore) -> Vec<String> {
+        store
+            .search_entries()
+            .unwrap()
+            .into_iter()
+            .map(|e| e.message.unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_search_index_follows_save_delete_and_cleanup() {
+        let temp = TempDir::new().unwrap();
+        let store = SnapshotStore::new(temp.path().to_path_buf());
+        let index_path = temp.path().join(SEARCH_INDEX_FILE);
+
+        let old = snapshot_at(&store, -100, "old");
+        assert!(!index_path.exists());
+        // The first search builds the sidecar
+        assert_eq!(entry_messages(&store), ["old"]);
+        assert!(index_path.exists());
+
+        let middle = snapshot_at(&store, -1, "middle");
+        snapshot_at(&store, 0, "new");
+        assert_eq!(
+            SearchIndex::load(&index_path).unwrap().entries.len(),
+            3,
+            "save updates the sidecar"
+        );
+        assert_eq!(entry_messages(&store), ["new", "middle", "old"]);
+
+        store.delete(&middle.id).unwrap();
+        assert_eq!(entry_messages(&store), ["new", "old"]);
+        assert_eq!(store.cleanup(0, 30).unwrap(), 1);
+        assert_eq!(entry_messages(&store), ["new"]);
+        assert!(!store
+            .search_entries()
+            .unwrap()
+            .iter()
+            .any(|e| e.id == old.id));
+
+        let entries = store.search_entries().unwrap();
+        let loaded = store.load_entries(&entries).unwrap();
+        assert_eq!(loaded[0].message.as_deref(), Some("new"));
+    }
+
+    #[test]
+    fn test_stale_search_index_is_rebuilt() {
+        let temp = TempDir::new().unwrap();
+        let store = SnapshotStore::new(temp.path().to_path_buf());
+        snapshot_at(&store, 0, "indexed");
+        store.search_entries().unwrap();
+
+        // Written behind the store's back (e.g. copied in from another store)
+        let mut snapshot = Snapshot::new(Vec::new(), Some("copied".to_string()), None);
+        snapshot.seq = 10;
+        fs::write(
+            temp.path().join(SnapshotStore::file_name(&snapshot)),
+            serde_json::to_string(&snapshot).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(entry_messages(&store), ["copied", "indexed"]);
+
+        // Corrupt sidecar
+        fs::write(temp.path().join(SEARCH_INDEX_FILE), "not json").unwrap();
+        assert_eq!(entry_messages(&store), ["copied", "indexed"]);
+    }
+
+    #[test]
+    fn test_load_each_reports_unparseable_files() {
+        let temp = TempDir::new().unwrap();
+        let store = SnapshotStore::new(temp.path().to_path_buf());
+        snapshot_at(&store, 0, "good");
+        fs::write(temp.path().join("broken.json"), "{").unwrap();
+
+        let mut loaded = store.load_each().unwrap();
+        loaded.sort_by_key(|(_, snapshot)| snapshot.is_ok());
+        assert_eq!(loaded.len(), 2);
+        assert!(loaded[0].0.ends_with("broken.json"));
+        assert!(loaded[0].1.is_err());
+        assert_eq!(
+            loaded[1].1.as_ref().unwrap().message.as_deref(),
+            Some("good")
+        );
+        assert_eq!(store.list().unwrap().len(), 1);
+    }
+
+    fn with_id(id: &str, message: &str) -> Snapshot {
+        let mut snapshot = Snapshot::new(Vec::new(), Some(message.to_string()), None);
+        snapshot.id = id.to_string();
+        snapshot
+    }
+
+    #[test]
+    fn test_not_found_suggests_close_ids() {
+        let snapshots = vec![
+            with_id("abd1234ffff", "before refactor"),
+            with_id("abc9999ffff", "other"),
+            with_id("0000000ffff", "unrelated"),
+        ];
+
+        match not_found_error(&snapshots, "abc1234") {
+            MoteError::SnapshotNotFound { id, suggestions } => {
+                assert_eq!(id, "abc1234");
+                assert_eq!(suggestions.len(), 1);
+                assert!(suggestions[0].starts_with("abd1234 ("));
+                assert!(suggestions[0].ends_with(" 'before refactor')"));
+            }
+            other => panic!("{:?}", other),
+        }
+        // A dropped character is a typo too
+        assert!(not_found_error(&snapshots, "ab1234")
+            .to_string()
+            .contains("did you mean abd1234"));
+        assert!(!not_found_error(&snapshots, "fff")
+            .to_string()
+            .contains("did you mean"));
+    }
+
+    #[test]
+    fn test_ambiguous_error_lists_matches() {
+        let snapshots = [with_id("abc1111", "one"), with_id("abc2222", "two")];
+        let matches: Vec<&Snapshot> = snapshots.iter().collect();
+        let message = ambiguous_error(&matches, "abc").to_string();
+        assert!(message.contains("\n  abc1111 ("), "{}", message);
+        assert!(message.contains("\n  abc2222 ("), "{}", message);
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance(b"abc", b"abc"), 0);
+        assert_eq!(edit_distance(b"abc", b"abd"), 1);
+        assert_eq!(edit_distance(b"abc", b"ab"), 1);
+        assert_eq!(edit_distance(b"", b"ab"), 2);
+        assert_eq!(prefix_distance("ab1234", "abd1234ff"), 1);
+    }
+
+    #[test]
+    fn test_resolve_ref() {
+        let temp = TempDir::new().unwrap();
+        let store = SnapshotStore::new(temp.path().to_path_buf());
+        assert!(matches!(
+            store.resolve_ref("@"),
+            Err(MoteError::NoSnapshotsAvailable)
+        ));
+        for days in [-4, -3, -2, -1] {
+            snapshot_at(&store, days, &format!("{} days", -days));
+        }
+        let message = |reference: &str| store.resolve_ref(reference).unwrap().message.unwrap();
+
+        assert_eq!(message("@"), "1 days");
+        assert_eq!(message("latest"), "1 days");
+        assert_eq!(message("@~0"), "1 days");
+        assert_eq!(message("@~3"), "4 days");
+        assert_eq!(message("@{2}"), "3 days");
+
+        match store.resolve_ref("@~4") {
+            Err(MoteError::SnapshotRefOutOfRange { available, .. }) => assert_eq!(available, 4),
+            other => panic!("{:?}", other.map(|s| s.id)),
+        }
+        for bad in ["@~", "@~x", "@~-1", "@{1", "@1", "@~+1"] {
+            assert!(
+                matches!(store.resolve_ref(bad), Err(MoteError::InvalidArguments(_))),
+                "{}",
+                bad
+            );
+        }
+
+        // Anything else is an ID prefix
+        let oldest = store.list().unwrap().pop().unwrap();
+        assert_eq!(store.resolve_ref(&oldest.id[..8]).unwrap().id, oldest.id);
+        assert!(matches!(
+            store.resolve_ref("zz"),
+            Err(MoteError::SnapshotNotFound { .. })
+        ));
     }
 }
@@ -0,0 +1,21 @@
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+use crate::error::Result;
+use crate::storage::SnapshotStore;
+
+/// Timestamp of the earliest snapshot referencing each object.
+///
+/// Snapshots are visited oldest first using the search sidecar for ordering
+/// and loaded one at a time, so only a single file list is held in memory.
+pub fn object_first_seen(store: &SnapshotStore) -> Result<HashMap<String, DateTime<Utc>>> {
+    let mut first_seen = HashMap::new();
+    for entry in store.search_entries()?.iter().rev() {
+        for snapshot in store.load_entries(std::slice::from_ref(entry))? {
+            for file in snapshot.files {
+                first_seen.entry(file.hash).or_insert(snapshot.timestamp);
+            }
+        }
+    }
+    Ok(first_seen)
+}
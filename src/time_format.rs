@@ -0,0 +1,77 @@
+use chrono::{DateTime, Local, TimeZone, Utc};
+
+use crate::config::DisplayTimezone;
+
+const DATE_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+const DATE_TIME_ZONE_FORMAT: &str = "%Y-%m-%d %H:%M:%S %Z";
+
+/// Formats a stored (UTC) timestamp for display in the configured time zone.
+/// `with_zone` appends the zone name or offset (e.g. `UTC`, `+09:00`).
+pub fn format_timestamp(
+    timestamp: &DateTime<Utc>,
+    timezone: DisplayTimezone,
+    with_zone: bool,
+) -> String {
+    match timezone {
+        DisplayTimezone::Local => format_in(timestamp, &Local, with_zone),
+        DisplayTimezone::Utc => format_in(timestamp, &Utc, with_zone),
+    }
+}
+
+fn format_in<Tz: TimeZone>(timestamp: &DateTime<Utc>, tz: &Tz, with_zone: bool) -> String
+where
+    Tz::Offset: std::fmt::Display,
+{
+    let format = if with_zone {
+        DATE_TIME_ZONE_FORMAT
+    } else {
+        DATE_TIME_FORMAT
+    };
+    timestamp.with_timezone(tz).format(format).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::FixedOffset;
+
+    fn fixed() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2024-05-01T14:30:05Z")
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_format_utc() {
+        assert_eq!(
+            format_timestamp(&fixed(), DisplayTimezone::Utc, true),
+            "2024-05-01 14:30:05 UTC"
+        );
+        assert_eq!(
+            format_timestamp(&fixed(), DisplayTimezone::Utc, false),
+            "2024-05-01 14:30:05"
+        );
+    }
+
+    #[test]
+    fn test_format_with_offset() {
+        let tokyo = FixedOffset::east_opt(9 * 3600).unwrap();
+        assert_eq!(
+            format_in(&fixed(), &tokyo, true),
+            "2024-05-01 23:30:05 +09:00"
+        );
+        assert_eq!(format_in(&fixed(), &tokyo, false), "2024-05-01 23:30:05");
+    }
+
+    #[test]
+    fn test_format_local_matches_chrono_local() {
+        let expected = fixed()
+            .with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string();
+        assert_eq!(
+            format_timestamp(&fixed(), DisplayTimezone::Local, false),
+            expected
+        );
+    }
+}
@@ -0,0 +1,170 @@
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+
+use crate::config::DisplayTimezone;
+use crate::error::{MoteError, Result};
+
+const ACCEPTED_FORMATS: &str =
+    "expected an RFC3339 timestamp (2024-05-01T14:00:00Z), a date and time (2024-05-01 14:00), a date (2024-05-01), or a relative duration like 30m, 2h, 3d, 1w";
+
+/// Parses a point in time for history filters.
+///
+/// Accepts an RFC3339 timestamp, a `YYYY-MM-DD HH:MM` time or a `YYYY-MM-DD`
+/// date (midnight) in `timezone`, the zone snapshot times are displayed in, or
+/// a relative duration (`30s`, `15m`, `2h`, `3d`, `1w`) counted back from
+/// `now`.
+pub fn parse_time_spec(
+    input: &str,
+    now: DateTime<Utc>,
+    timezone: DisplayTimezone,
+) -> Result<DateTime<Utc>> {
+    match timezone {
+        DisplayTimezone::Local => parse_time_spec_in(input, now, &Local),
+        DisplayTimezone::Utc => parse_time_spec_in(input, now, &Utc),
+    }
+}
+
+fn parse_time_spec_in<Tz: TimeZone>(
+    input: &str,
+    now: DateTime<Utc>,
+    tz: &Tz,
+) -> Result<DateTime<Utc>> {
+    let input = input.trim();
+
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(input) {
+        return Ok(timestamp.with_timezone(&Utc));
+    }
+
+    if let Ok(time) = NaiveDateTime::parse_from_str(input, "%Y-%m-%d %H:%M") {
+        return in_timezone(input, time, tz);
+    }
+
+    if let Ok(date) = NaiveDate::parse_from_str(input, "%Y-%m-%d") {
+        if let Some(midnight) = date.and_hms_opt(0, 0, 0) {
+            return in_timezone(input, midnight, tz);
+        }
+    }
+
+    match parse_relative_duration(input) {
+        Some(duration) => Ok(now - duration),
+        None => Err(MoteError::InvalidArguments(format!(
+            "Invalid time '{}': {}",
+            input, ACCEPTED_FORMATS
+        ))),
+    }
+}
+
+/// `time` read as a wall-clock time in `tz`; of a time repeated when clocks
+/// go back, the earlier one
+fn in_timezone<Tz: TimeZone>(input: &str, time: NaiveDateTime, tz: &Tz) -> Result<DateTime<Utc>> {
+    match tz.from_local_datetime(&time).earliest() {
+        Some(time) => Ok(time.with_timezone(&Utc)),
+        None => Err(MoteError::InvalidArguments(format!(
+            "Invalid time '{}': it does not exist in the display time zone",
+            input
+        ))),
+    }
+}
+
+/// Parses `<number><unit>` where unit is one of s, m, h, d, w
+pub fn parse_relative_duration(input: &str) -> Option<Duration> {
+    let unit_start = input.find(|c: char| !c.is_ascii_digit())?;
+    let (amount, unit) = input.split_at(unit_start);
+    let amount: i64 = amount.parse().ok()?;
+
+    match unit {
+        "s" => Duration::try_seconds(amount),
+        "m" => Duration::try_minutes(amount),
+        "h" => Duration::try_hours(amount),
+        "d" => Duration::try_days(amount),
+        "w" => Duration::try_weeks(amount),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::FixedOffset;
+
+    fn now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2024-05-10T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_parse_relative_duration_units() {
+        assert_eq!(parse_relative_duration("45s"), Some(Duration::seconds(45)));
+        assert_eq!(parse_relative_duration("30m"), Some(Duration::minutes(30)));
+        assert_eq!(parse_relative_duration("2h"), Some(Duration::hours(2)));
+        assert_eq!(parse_relative_duration("3d"), Some(Duration::days(3)));
+        assert_eq!(parse_relative_duration("1w"), Some(Duration::weeks(1)));
+        assert_eq!(parse_relative_duration("0h"), Some(Duration::zero()));
+    }
+
+    #[test]
+    fn test_parse_relative_duration_invalid() {
+        assert_eq!(parse_relative_duration(""), None);
+        assert_eq!(parse_relative_duration("h"), None);
+        assert_eq!(parse_relative_duration("12"), None);
+        assert_eq!(parse_relative_duration("2y"), None);
+        assert_eq!(parse_relative_duration("2 h"), None);
+        assert_eq!(parse_relative_duration("-2h"), None);
+        assert_eq!(parse_relative_duration("2hours"), None);
+        assert_eq!(parse_relative_duration("99999999999999999999d"), None);
+    }
+
+    #[test]
+    fn test_parse_time_spec_relative() {
+        assert_eq!(
+            parse_time_spec("2h", now(), DisplayTimezone::Local).unwrap(),
+            now() - Duration::hours(2)
+        );
+    }
+
+    #[test]
+    fn test_parse_time_spec_rfc3339_and_date() {
+        let expected = DateTime::parse_from_rfc3339("2024-05-01T14:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(
+            parse_time_spec("2024-05-01T16:00:00+02:00", now(), DisplayTimezone::Local).unwrap(),
+            expected
+        );
+        assert_eq!(
+            parse_time_spec("2024-05-01", now(), DisplayTimezone::Utc).unwrap(),
+            expected - Duration::hours(14)
+        );
+        assert_eq!(
+            parse_time_spec("2024-05-01 14:00", now(), DisplayTimezone::Utc).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_parse_time_spec_dates_use_display_timezone() {
+        let tokyo = FixedOffset::east_opt(9 * 3600).unwrap();
+        let expected = DateTime::parse_from_rfc3339("2024-05-01T05:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(
+            parse_time_spec_in("2024-05-01 14:00", now(), &tokyo).unwrap(),
+            expected
+        );
+        assert_eq!(
+            parse_time_spec_in("2024-05-01", now(), &tokyo).unwrap(),
+            expected - Duration::hours(14)
+        );
+    }
+
+    #[test]
+    fn test_parse_time_spec_invalid_mentions_formats() {
+        match parse_time_spec("yesterday", now(), DisplayTimezone::Local) {
+            Err(MoteError::InvalidArguments(msg)) => {
+                assert!(msg.contains("yesterday"));
+                assert!(msg.contains("2h"));
+            }
+            other => panic!("Expected InvalidArguments, got {:?}", other),
+        }
+    }
+}
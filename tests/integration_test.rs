@@ -1,11 +1,13 @@
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use tempfile::TempDir;
 
 struct TestContext {
     _temp_dir: TempDir,
+    _config_temp_dir: TempDir,
     project_dir: PathBuf,
+    config_dir: PathBuf,
     mote_bin: PathBuf,
 }
 
@@ -13,6 +15,8 @@ impl TestContext {
     fn new() -> Self {
         let temp_dir = TempDir::new().expect("Failed to create temp directory");
         let project_dir = temp_dir.path().to_path_buf();
+        let config_temp_dir = TempDir::new().expect("Failed to create config directory");
+        let config_dir = config_temp_dir.path().to_path_buf();
 
         let mote_bin = std::env::current_exe()
             .expect("Failed to get current executable path")
@@ -24,7 +28,9 @@ impl TestContext {
 
         Self {
             _temp_dir: temp_dir,
+            _config_temp_dir: config_temp_dir,
             project_dir,
+            config_dir,
             mote_bin,
         }
     }
@@ -37,6 +43,20 @@ impl TestContext {
             .expect("Failed to execute mote")
     }
 
+    /// Run mote against the isolated config directory (context-managed storage)
+    fn run_mote_ctx(&self, args: &[&str]) -> std::process::Output {
+        let config_dir = self.config_dir.to_string_lossy().to_string();
+        let mut full_args = vec!["--config-dir", config_dir.as_str()];
+        full_args.extend_from_slice(args);
+        self.run_mote(&full_args)
+    }
+
+    /// Create the `test/default` context rooted at the project directory
+    fn init_context(&self) {
+        let output = self.run_mote_ctx(&["-c", "test/default", "context", "new", "default"]);
+        assert!(output.status.success(), "{:?}", output);
+    }
+
     fn write_file(&self, path: &str, content: &str) {
         let file_path = self.project_dir.join(path);
         if let Some(parent) = file_path.parent() {
@@ -117,7 +137,7 @@ fn test_log_oneline_format() {
     let stdout = String::from_utf8_lossy(&output.stdout);
     let lines: Vec<&str> = stdout.lines().collect();
 
-    assert!(lines.len() > 0);
+    assert!(!lines.is_empty());
     assert!(lines[0].contains("Test message"));
     assert!(lines[0].contains("files"));
 }
@@ -199,6 +219,764 @@ fn test_restore_single_file() {
     assert_eq!(content, "original content");
 }
 
+#[test]
+fn test_restore_file_globs() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    for file in [
+        "src/handlers/a.rs",
+        "src/handlers/b.rs",
+        "src/main.rs",
+        "README.md",
+    ] {
+        ctx.write_file(file, "original");
+    }
+    let id = extract_snapshot_id(&ctx.run_mote(&["snap", "create"]));
+    for file in [
+        "src/handlers/a.rs",
+        "src/handlers/b.rs",
+        "src/main.rs",
+        "README.md",
+    ] {
+        ctx.write_file(file, "modified");
+    }
+
+    let restore = |extra: &[&str]| {
+        let mut args = vec!["snap", "restore", &id];
+        args.extend_from_slice(extra);
+        ctx.run_mote(&args)
+    };
+    let selection = [
+        "--file",
+        "src/handlers/*.rs",
+        "--file",
+        "README.md",
+        "--file",
+        "*.py",
+    ];
+
+    let output = restore(&[&selection[..], &["--dry-run"]].concat());
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Would restore: src/handlers/a.rs"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("Would restore: src/handlers/b.rs"));
+    assert!(stdout.contains("Would restore: README.md"));
+    assert!(!stdout.contains("src/main.rs"));
+    assert!(stdout.contains("Would restore 3 file(s)"));
+    assert_eq!(ctx.read_file("README.md"), "modified");
+
+    // The unmatched pattern warns without stopping the others
+    let output = restore(&selection);
+    assert!(output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("pattern '*.py' did not match any files")
+    );
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Restored 3 file(s)"));
+    assert_eq!(ctx.read_file("src/handlers/a.rs"), "original");
+    assert_eq!(ctx.read_file("src/handlers/b.rs"), "original");
+    assert_eq!(ctx.read_file("README.md"), "original");
+    assert_eq!(ctx.read_file("src/main.rs"), "modified");
+}
+
+#[test]
+fn test_restore_clean_removes_untracked_files() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file("keep.txt", "original");
+    let id = extract_snapshot_id(&ctx.run_mote(&["snap", "create"]));
+    fs::remove_file(ctx.project_dir.join("keep.txt")).unwrap();
+    ctx.write_file("extra/nested/new.txt", "untracked");
+    ctx.write_file("debug.log", "ignored");
+
+    let output = ctx.run_mote(&["snap", "restore", &id, "--clean", "--dry-run"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Would delete: extra/nested/new.txt"),
+        "{}",
+        stdout
+    );
+    assert!(!stdout.contains("debug.log"));
+    assert!(ctx.file_exists("extra/nested/new.txt"));
+
+    // Without a backup snapshot, deleting needs --yes
+    let output = ctx.run_mote(&["snap", "restore", &id, "--clean", "--force"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--yes"));
+    assert!(ctx.file_exists("extra/nested/new.txt"));
+
+    let output = ctx.run_mote(&["snap", "restore", &id, "--clean"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Deleted 1 file(s)"));
+    assert_eq!(ctx.read_file("keep.txt"), "original");
+    assert!(!ctx.file_exists("extra/nested/new.txt"));
+    assert!(!ctx.file_exists("extra"));
+    assert!(ctx.file_exists("debug.log"));
+    assert!(ctx.file_exists(".moteignore"));
+}
+
+#[test]
+fn test_restore_into_target_directory() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file("src/main.rs", "original");
+    ctx.write_file("README.md", "readme");
+    let id = extract_snapshot_id(&ctx.run_mote(&["snap", "create"]));
+    ctx.write_file("src/main.rs", "modified");
+
+    let target = ctx.config_dir.join("inspect");
+    let target_arg = target.to_string_lossy().to_string();
+    let output = ctx.run_mote(&["snap", "restore", &id, "--target", &target_arg]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("backup"));
+    assert_eq!(
+        fs::read_to_string(target.join("src/main.rs")).unwrap(),
+        "original"
+    );
+    assert_eq!(
+        fs::read_to_string(target.join("README.md")).unwrap(),
+        "readme"
+    );
+    assert_eq!(ctx.read_file("src/main.rs"), "modified");
+
+    let output = ctx.run_mote(&["snap", "list", "--oneline"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout).lines().count(), 1);
+
+    // --file selects within the snapshot as usual
+    let other = ctx.config_dir.join("other");
+    let other_arg = other.to_string_lossy().to_string();
+    let output = ctx.run_mote(&[
+        "snap",
+        "restore",
+        &id,
+        "--target",
+        &other_arg,
+        "--file",
+        "README.md",
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(other.join("README.md").exists());
+    assert!(!other.join("src").exists());
+
+    // Entries that would escape the target are refused before writing
+    let escaping = serde_json::json!({
+        "id": "e5c4e5c4",
+        "timestamp": "2024-05-01T09:00:00Z",
+        "files": [
+            {"path": "README.md", "hash": "0".repeat(64), "size": 1},
+            {"path": "../escaped.txt", "hash": "0".repeat(64), "size": 1},
+        ],
+    });
+    fs::write(
+        ctx.project_dir.join(".mote/snapshots/e5c4e5c4.json"),
+        escaping.to_string(),
+    )
+    .unwrap();
+    let unsafe_target = ctx.config_dir.join("unsafe");
+    let unsafe_arg = unsafe_target.to_string_lossy().to_string();
+    let output = ctx.run_mote(&["snap", "restore", "e5c4e5c4", "--target", &unsafe_arg]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("outside"));
+    assert!(!unsafe_target.exists());
+    assert!(!ctx.config_dir.join("escaped.txt").exists());
+}
+
+#[test]
+fn test_restore_pipelined_matches_serial() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    for i in 0..300 {
+        // Varied sizes, with some content shared between files
+        let content = format!("file {}\n", i % 250).repeat(i % 40 + 1);
+        ctx.write_file(&format!("dir{}/file{}.txt", i % 12, i), &content);
+    }
+    let id = extract_snapshot_id(&ctx.run_mote(&["snap", "create"]));
+
+    // A missing object must be reported the same way by both paths
+    let objects_dir = ctx.project_dir.join(".mote/objects");
+    let lost = fs::read_dir(
+        fs::read_dir(&objects_dir)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path(),
+    )
+    .unwrap()
+    .next()
+    .unwrap()
+    .unwrap()
+    .path();
+    fs::remove_file(lost).unwrap();
+
+    let mut runs = Vec::new();
+    for (name, extra) in [("pipelined", None), ("serial", Some("--serial"))] {
+        let target = ctx.config_dir.join(name);
+        let target_arg = target.to_string_lossy().to_string();
+        let mut args = vec!["snap", "restore", &id, "--target", &target_arg];
+        args.extend(extra);
+        let output = ctx.run_mote(&args);
+        assert!(output.status.success(), "{:?}", output);
+        let contents: Vec<_> = tree_contents(&target)
+            .into_iter()
+            .map(|(path, content)| (path.strip_prefix(&target).unwrap().to_path_buf(), content))
+            .collect();
+        runs.push((contents, output.stdout, output.stderr));
+    }
+
+    let (pipelined, serial) = (&runs[0], &runs[1]);
+    assert!(pipelined.0.len() >= 290);
+    assert_eq!(pipelined, serial);
+    assert!(String::from_utf8_lossy(&serial.2).contains("Failed to restore"));
+}
+
+#[test]
+fn test_restore_file_to_stdout() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    let binary: Vec<u8> = vec![0, 159, 146, 150, b'\n', 255, b'x'];
+    fs::write(ctx.project_dir.join("blob.bin"), &binary).unwrap();
+    ctx.write_file("src/a.rs", "a");
+    ctx.write_file("src/b.rs", "b");
+    let id = extract_snapshot_id(&ctx.run_mote(&["snap", "create"]));
+    fs::write(ctx.project_dir.join("blob.bin"), "changed").unwrap();
+
+    let output = ctx.run_mote(&["snap", "restore", &id, "--file", "blob.bin", "--stdout"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(output.stdout, binary);
+    // Nothing written and no backup taken
+    assert_eq!(ctx.read_file("blob.bin"), "changed");
+    let output = ctx.run_mote(&["snap", "list", "--oneline"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout).lines().count(), 1);
+
+    let output = ctx.run_mote(&["snap", "restore", &id, "-f", "src/*.rs", "--stdout"]);
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("selected 2"));
+
+    let output = ctx.run_mote(&["snap", "restore", &id, "--stdout"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--file"));
+
+    let output = ctx.run_mote(&["snap", "restore", &id, "-f", "missing.txt", "--stdout"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("File not found in snapshot"));
+}
+
+#[test]
+fn test_restore_dry_run_previews_changes() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file("same.txt", "same");
+    ctx.write_file("changed.txt", "v1");
+    ctx.write_file("missing.txt", "gone soon");
+    ctx.write_file("edited.txt", "aaaa");
+    let id = extract_snapshot_id(&ctx.run_mote(&["snap", "create"]));
+    ctx.write_file("changed.txt", "version 2");
+    ctx.run_mote(&["snap", "create"]);
+    fs::remove_file(ctx.project_dir.join("missing.txt")).unwrap();
+    // Same size as the snapshot's content, so only the hash tells them apart
+    ctx.write_file("edited.txt", "bbbb");
+    ctx.write_file("extra.txt", "untracked");
+
+    let output = ctx.run_mote(&["snap", "restore", &id, "--dry-run", "--clean"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Would restore: changed.txt"), "{}", stdout);
+    assert!(stdout.contains("Would create: missing.txt"), "{}", stdout);
+    assert!(stdout.contains("Would delete: extra.txt"), "{}", stdout);
+    assert!(
+        stdout.contains("Would restore 2 file(s): 1 changed, 1 created,"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("Would delete 1 file(s) not in the snapshot"));
+    assert!(stdout.contains("Would skip 1 locally modified file(s)"));
+    assert!(!stdout.contains("same.txt"));
+
+    let output = ctx.run_mote(&["snap", "restore", &id, "--dry-run", "--verbose"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Unchanged: same.txt"), "{}", stdout);
+    assert!(!stdout.contains("Unchanged: edited.txt"));
+
+    // Nothing was touched
+    assert!(!ctx.file_exists("missing.txt"));
+    assert_eq!(ctx.read_file("changed.txt"), "version 2");
+    assert_eq!(ctx.read_file("edited.txt"), "bbbb");
+    assert!(ctx.file_exists("extra.txt"));
+}
+
+#[test]
+fn test_restore_exclude_patterns() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file(".env.local", "SECRET=old");
+    ctx.write_file("config/app.toml", "port = 1");
+    ctx.write_file("src/main.rs", "original");
+    let id = extract_snapshot_id(&ctx.run_mote(&["snap", "create"]));
+    ctx.write_file(".env.local", "SECRET=new");
+    ctx.write_file("config/app.toml", "port = 2");
+    fs::remove_file(ctx.project_dir.join("src/main.rs")).unwrap();
+    ctx.write_file("config/local.toml", "untracked but wanted");
+    ctx.write_file("scratch.txt", "untracked");
+
+    let args = [
+        "snap",
+        "restore",
+        &id,
+        "--force",
+        "--clean",
+        "--yes",
+        "-x",
+        ".env.local",
+        "--exclude",
+        "config/",
+    ];
+    let output = ctx.run_mote(&[&args[..], &["--dry-run"]].concat());
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Would create: src/main.rs"), "{}", stdout);
+    assert!(!stdout.contains("Would restore: .env.local"));
+    assert!(stdout.contains("Would leave 2 file(s) matching --exclude"));
+    assert!(stdout.contains("Would delete: scratch.txt"));
+    assert!(stdout.contains("(1 kept by --exclude)"), "{}", stdout);
+    assert!(!ctx.file_exists("src/main.rs"));
+
+    let output = ctx.run_mote(&args);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Left 2 file(s) matching --exclude"));
+    assert_eq!(ctx.read_file("src/main.rs"), "original");
+    assert_eq!(ctx.read_file(".env.local"), "SECRET=new");
+    assert_eq!(ctx.read_file("config/app.toml"), "port = 2");
+    assert!(ctx.file_exists("config/local.toml"));
+    assert!(!ctx.file_exists("scratch.txt"));
+}
+
+#[test]
+fn test_restore_skips_local_edits() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file("edited.txt", "v1");
+    ctx.write_file("committed.txt", "v1");
+    let old_id = extract_snapshot_id(&ctx.run_mote(&["snap", "create"]));
+    ctx.write_file("committed.txt", "v2");
+    ctx.run_mote(&["snap", "create"]);
+    // Only edited.txt has changes that no snapshot holds
+    ctx.write_file("edited.txt", "local edit");
+
+    let output = ctx.run_mote(&["snap", "restore", &old_id, "--dry-run"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Would restore: committed.txt"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("Would skip 1 locally modified file(s)"));
+    assert!(!stdout.contains("Would restore: edited.txt"));
+
+    let output = ctx.run_mote(&["snap", "restore", &old_id]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Restored 1 file(s)"), "{}", stdout);
+    assert!(stdout.contains("Skipped 1 locally modified file(s)"));
+    assert!(stdout.contains("edited.txt"));
+    assert_eq!(ctx.read_file("committed.txt"), "v1");
+    assert_eq!(ctx.read_file("edited.txt"), "local edit");
+}
+
+#[test]
+fn test_restore_force_overwrites_local_edits() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file("edited.txt", "v1");
+    let id = extract_snapshot_id(&ctx.run_mote(&["snap", "create"]));
+    ctx.write_file("edited.txt", "local edit");
+
+    let output = ctx.run_mote(&["snap", "restore", &id, "--force"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Restored 1 file(s)"), "{}", stdout);
+    assert!(!stdout.contains("Skipped"));
+    assert_eq!(ctx.read_file("edited.txt"), "v1");
+}
+
+#[test]
+fn test_delete_without_terminal_fails_fast() {
+    use std::process::Stdio;
+
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file("a.txt", "a");
+    let id = extract_snapshot_id(&ctx.run_mote(&["snap", "create"]));
+
+    let output = Command::new(&ctx.mote_bin)
+        .args(["snap", "delete", &id])
+        .current_dir(&ctx.project_dir)
+        .env_remove("MOTE_FORCE_TTY")
+        .stdin(Stdio::null())
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("stdin is not a terminal"), "{}", stderr);
+    assert!(stderr.contains("--force"));
+
+    let output = ctx.run_mote(&["snap", "list", "--oneline"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout).lines().count(), 1);
+
+    let output = ctx.run_mote(&["snap", "delete", &id, "-y"]);
+    assert!(output.status.success(), "{:?}", output);
+    let id = extract_snapshot_id(&ctx.run_mote(&["snap", "create"]));
+    let output = ctx.run_mote(&["snap", "delete", &id, "--yes"]);
+    assert!(output.status.success(), "{:?}", output);
+}
+
+#[test]
+fn test_prune_backups_keeps_newest() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file("a.txt", "original");
+    let id = extract_snapshot_id(&ctx.run_mote(&["snap", "create"]));
+    for edit in ["one", "two", "three"] {
+        ctx.write_file("a.txt", edit);
+        ctx.run_mote(&["snap", "create", "-m", edit]);
+        let output = ctx.run_mote(&["snap", "restore", &id]);
+        assert!(output.status.success(), "{:?}", output);
+    }
+    let count_snapshots = || {
+        let output = ctx.run_mote(&["snap", "list", "--oneline"]);
+        String::from_utf8_lossy(&output.stdout).lines().count()
+    };
+    assert_eq!(count_snapshots(), 7);
+
+    let output = ctx.run_mote(&["snap", "prune-backups", "--dry-run"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Would delete 2 backup snapshot(s)"),
+        "{}",
+        stdout
+    );
+    assert_eq!(count_snapshots(), 7);
+
+    let output = ctx.run_mote(&["snap", "prune-backups", "--older-than", "1d", "--keep", "0"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("No backup snapshots to prune"),
+        "{}",
+        stdout
+    );
+
+    let output = ctx.run_mote(&["snap", "prune-backups", "--older-than", "soon"]);
+    assert!(!output.status.success());
+
+    let output = ctx.run_mote(&["snap", "prune-backups", "-y"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(count_snapshots(), 5);
+
+    let output = ctx.run_mote(&["snap", "prune-backups", "--keep", "0", "--force", "--gc"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Deleted 1 backup snapshot(s)"),
+        "{}",
+        stdout
+    );
+    assert_eq!(count_snapshots(), 4);
+
+    // The objects of the remaining snapshots survive --gc
+    ctx.write_file("a.txt", "four");
+    let output = ctx.run_mote(&["snap", "restore", &id, "--force"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(ctx.read_file("a.txt"), "original");
+}
+
+#[test]
+fn test_auto_gc_after_delete() {
+    let ctx = TestContext::new();
+    ctx.run_mote_ctx(&["init"]);
+    let config_path = ctx.config_dir.join("config.toml");
+    fs::write(
+        &config_path,
+        "[snapshot]\ngc_auto_enabled = true\ngc_auto = 2\n[gc]\ngrace_period_secs = 0\n",
+    )
+    .unwrap();
+    let ids: Vec<String> = (0..4)
+        .map(|i| {
+            ctx.write_file("a.txt", &format!("v{}", i));
+            extract_snapshot_id(&ctx.run_mote_ctx(&["snap", "create"]))
+        })
+        .collect();
+    let objects_dir = ctx.project_dir.join(".mote/objects");
+    let before = count_objects(&objects_dir);
+
+    // One removal and one unreferenced object are both below the threshold
+    let output = ctx.run_mote_ctx(&["snap", "delete", &ids[0], "-y"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(count_objects(&objects_dir), before);
+    assert!(ctx.file_exists(".mote/gc-state"));
+
+    let output = ctx.run_mote_ctx(&["snap", "delete", &ids[1], "-y"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Auto GC: cleaned 2 unreferenced object(s)"),
+        "{}",
+        stdout
+    );
+    assert_eq!(count_objects(&objects_dir), before - 2);
+
+    fs::write(&config_path, "[snapshot]\ngc_auto = 1\n").unwrap();
+    ctx.run_mote_ctx(&["snap", "delete", &ids[2], "-y"]);
+    assert_eq!(count_objects(&objects_dir), before - 2);
+}
+
+#[test]
+fn test_gc_grace_period_and_dry_run_size() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file("a.txt", &"old content\n".repeat(100));
+    let old_id = extract_snapshot_id(&ctx.run_mote(&["snap", "create"]));
+    ctx.write_file("a.txt", "new");
+    ctx.run_mote(&["snap", "create"]);
+    ctx.run_mote(&["snap", "delete", &old_id, "-y"]);
+    let objects_dir = ctx.project_dir.join(".mote/objects");
+    let before = count_objects(&objects_dir);
+
+    // Too new to tell from an object of a snapshot still being taken
+    let output = ctx.run_mote(&["snap", "gc"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Kept 1 unreferenced object(s)"),
+        "{}",
+        stdout
+    );
+    assert_eq!(count_objects(&objects_dir), before);
+
+    let output = ctx.run_mote(&["snap", "gc", "--dry-run", "--verbose", "--no-grace"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Would delete 1 object(s), reclaiming"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("(compressed)"));
+    assert!(stdout.contains("Would delete: "));
+    assert_eq!(count_objects(&objects_dir), before);
+}
+
+#[test]
+fn test_compressed_formats_are_stored_raw() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file("image.png", &"pretend pixels\n".repeat(200));
+    ctx.write_file("notes.txt", &"plain text\n".repeat(200));
+    let id = extract_snapshot_id(&ctx.run_mote(&["snap", "create"]));
+
+    let output = ctx.run_mote(&["snap", "gc", "--dry-run", "--verbose"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Compression: "), "{}", stdout);
+    // Only the PNG; the text file and .moteignore compress
+    assert!(stdout.contains("), 1 of "), "{}", stdout);
+
+    let output = ctx.run_mote(&["snap", "verify", "--full"]);
+    assert!(output.status.success(), "{:?}", output);
+    ctx.write_file("image.png", "changed");
+    ctx.run_mote(&["snap", "restore", &id, "--force"]);
+    assert_eq!(ctx.read_file("image.png"), "pretend pixels\n".repeat(200));
+}
+
+#[test]
+fn test_compression_level_override_and_config() {
+    use sha2::{Digest, Sha256};
+
+    let content: String = (0..20_000)
+        .map(|i| format!("row {} holds {}\n", i, i * 7919 % 1000))
+        .collect();
+    let hash = format!("{:x}", Sha256::digest(content.as_bytes()));
+    let object_size = |level: &str| {
+        let ctx = TestContext::new();
+        ctx.run_mote(&["init"]);
+        ctx.write_file("data.csv", &content);
+        let output = ctx.run_mote(&["snap", "create", "--compression", level]);
+        assert!(output.status.success(), "{:?}", output);
+        let object = ctx
+            .project_dir
+            .join(".mote/objects")
+            .join(&hash[..2])
+            .join(&hash[2..]);
+        fs::metadata(object).unwrap().len()
+    };
+    assert!(object_size("19") < object_size("1"));
+
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    let output = ctx.run_mote(&["snap", "create", "--compression", "23"]);
+    assert!(!output.status.success());
+
+    fs::write(
+        ctx.config_dir.join("config.toml"),
+        "[storage]\ncompression_level = 0\n",
+    )
+    .unwrap();
+    let output = ctx.run_mote_ctx(&["snap", "create"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("storage.compression_level must be between 1 and 22, got 0"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn test_verify_reports_storage_problems() {
+    use sha2::{Digest, Sha256};
+
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file("a.txt", "a");
+    ctx.write_file("b.txt", "b");
+    ctx.run_mote(&["snap", "create"]);
+
+    let output = ctx.run_mote(&["snap", "verify", "--full"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Storage is consistent"));
+
+    let objects_dir = ctx.project_dir.join(".mote/objects");
+    let object_path = |content: &str| {
+        let hash = format!("{:x}", Sha256::digest(content.as_bytes()));
+        objects_dir.join(&hash[..2]).join(&hash[2..])
+    };
+    fs::write(object_path("a"), b"not zstd").unwrap();
+    fs::remove_file(object_path("b")).unwrap();
+    fs::write(ctx.project_dir.join(".mote/snapshots/broken.json"), "{").unwrap();
+
+    // Without --full the damaged object still counts as present
+    let output = ctx.run_mote(&["snap", "verify"]);
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Missing objects:           1"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("not checked"));
+    assert!(stdout.contains("Unreadable snapshot files: 1"));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("2 problem(s)"));
+
+    let output = ctx.run_mote(&["snap", "fsck", "--full", "--delete-corrupt", "-y"]);
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("corrupt"), "{}", stdout);
+    assert!(stdout.contains("b.txt"));
+    assert!(stdout.contains("Deleted 1 unreadable snapshot file(s)"));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("2 problem(s)"));
+    assert!(!ctx.file_exists(".mote/snapshots/broken.json"));
+}
+
+#[test]
+fn test_progress_bar_only_on_terminals() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    let run = |args: &[&str], envs: &[(&str, &str)]| {
+        let mut command = Command::new(&ctx.mote_bin);
+        command
+            .args(args)
+            .current_dir(&ctx.project_dir)
+            .env("MOTE_FORCE_TTY", "1")
+            .env_remove("MOTE_NO_PROGRESS");
+        for (key, value) in envs {
+            command.env(key, value);
+        }
+        let output = command.output().unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8_lossy(&output.stderr).into_owned()
+    };
+
+    ctx.write_file("a.txt", "1");
+    let stderr = run(&["snap", "create"], &[]);
+    assert!(stderr.contains("Snapshotting ["), "{}", stderr);
+    assert!(stderr.contains("2/2"), "{}", stderr);
+
+    ctx.write_file("a.txt", "2");
+    assert!(!run(&["snap", "create", "--no-progress"], &[]).contains('['));
+    ctx.write_file("a.txt", "3");
+    assert!(!run(&["snap", "create"], &[("MOTE_NO_PROGRESS", "1")]).contains('['));
+    ctx.write_file("a.txt", "4");
+    assert!(!run(&["snap", "create"], &[("MOTE_FORCE_TTY", "0")]).contains('['));
+    ctx.write_file("a.txt", "5");
+    assert!(!run(&["snap", "create", "--auto"], &[]).contains('['));
+
+    let output = ctx.run_mote(&["snap", "list", "--oneline"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let first = stdout
+        .lines()
+        .last()
+        .unwrap()
+        .split_whitespace()
+        .next()
+        .unwrap();
+    let stderr = run(&["snap", "restore", first, "--force"], &[]);
+    assert!(stderr.contains("Restoring ["), "{}", stderr);
+}
+
+#[test]
+fn test_restore_interactive() {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file("a.txt", "original\n");
+    ctx.write_file("b.txt", "b\n");
+    let id = extract_snapshot_id(&ctx.run_mote(&["snap", "create"]));
+    let restore = |answers: &str, tty: &str| {
+        let mut child = Command::new(&ctx.mote_bin)
+            .args(["snap", "restore", &id, "--interactive"])
+            .current_dir(&ctx.project_dir)
+            .env("MOTE_FORCE_TTY", tty)
+            .env("NO_COLOR", "1")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("Failed to spawn mote");
+        // Without a terminal mote exits before reading
+        let _ = child.stdin.take().unwrap().write_all(answers.as_bytes());
+        child.wait_with_output().unwrap()
+    };
+
+    let output = restore("y\n", "0");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("drop --interactive"));
+
+    // Only the changed file is offered; `d` shows its diff before answering
+    ctx.write_file("a.txt", "modified\n");
+    let output = restore("x\nd\ny\n", "1");
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Restore a.txt (modified)?"), "{}", stdout);
+    assert!(!stdout.contains("Restore b.txt"));
+    assert!(stdout.contains("q: stop asking"));
+    assert!(stdout.contains("-original\n+modified"));
+    assert!(stdout.contains("Created backup snapshot"));
+    assert!(stdout.contains("Restored 1 file(s)"));
+    assert_eq!(ctx.read_file("a.txt"), "original\n");
+
+    ctx.write_file("a.txt", "modified again\n");
+    fs::remove_file(ctx.project_dir.join("b.txt")).unwrap();
+    let output = restore("q\n", "1");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("No files selected"));
+    assert_eq!(ctx.read_file("a.txt"), "modified again\n");
+
+    let output = restore("a\n", "1");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Restored 2 file(s)"));
+    assert_eq!(ctx.read_file("a.txt"), "original\n");
+    assert_eq!(ctx.read_file("b.txt"), "b\n");
+}
+
 #[test]
 fn test_restore_dry_run() {
     let ctx = TestContext::new();
@@ -285,6 +1063,33 @@ fn test_show_displays_snapshot_details() {
     assert!(stdout.contains("Files:"));
 }
 
+#[test]
+fn test_show_defaults_to_latest() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+
+    let output = ctx.run_mote(&["snap", "show"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("No snapshots available"));
+
+    ctx.write_file("test.txt", "v1");
+    ctx.run_mote(&["snap", "create", "-m", "older"]);
+    ctx.write_file("test.txt", "v2");
+    let output = ctx.run_mote(&["snap", "create", "-m", "newer"]);
+    let newest = extract_snapshot_id(&output);
+
+    for args in [&["snap", "show"][..], &["snap", "show", "latest"]] {
+        let output = ctx.run_mote(args);
+        assert!(output.status.success());
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains(&format!("snapshot {}", newest)));
+        assert!(stdout.contains("Message: newer"));
+    }
+
+    let output = ctx.run_mote(&["snap", "show", "latest:test.txt"]);
+    assert_eq!(output.stdout, b"v2");
+}
+
 #[test]
 fn test_snapshot_without_init_fails() {
     let ctx = TestContext::new();
@@ -308,3 +1113,3265 @@ fn test_empty_project_snapshot() {
     let stdout = String::from_utf8_lossy(&output.stdout);
     assert!(stdout.contains("No files to snapshot") || stdout.contains("Created snapshot"));
 }
+
+fn extract_snapshot_id(output: &std::process::Output) -> String {
+    String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .find(|s| s.len() == 7 && s.chars().all(|c| c.is_ascii_hexdigit()))
+        .expect("Could not find snapshot ID")
+        .to_string()
+}
+
+#[test]
+fn test_deprecated_context_flags() {
+    let ctx = TestContext::new();
+    ctx.init_context();
+    ctx.write_file("test.txt", "content");
+    ctx.run_mote_ctx(&["snap", "create", "-m", "First"]);
+
+    let config_dir = ctx.config_dir.to_string_lossy().to_string();
+    let run = |args: &[&str], suppress: bool| {
+        let mut command = Command::new(&ctx.mote_bin);
+        command
+            .args(["--config-dir", &config_dir])
+            .args(args)
+            .args(["snap", "list", "--oneline"])
+            .current_dir(&ctx.project_dir)
+            .env_remove("MOTE_SUPPRESS_DEPRECATIONS");
+        if suppress {
+            command.env("MOTE_SUPPRESS_DEPRECATIONS", "1");
+        }
+        command.output().unwrap()
+    };
+
+    let output = run(&["-p", "test", "--old-context", "default"], false);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("First"));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr
+            .contains("-p/--project and --old-context are deprecated; use -c test/default instead"),
+        "{}",
+        stderr
+    );
+
+    let output = run(&["-p", "test"], false);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("use -c test/default instead"));
+
+    let output = run(&["-p", "test", "--old-context", "default"], true);
+    assert!(output.status.success());
+    assert!(output.stderr.is_empty());
+
+    let output = run(&["-c", "test/default"], false);
+    assert!(output.status.success());
+    assert!(output.stderr.is_empty());
+
+    let output = run(&["-c", "test/default", "-p", "test"], true);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains(
+        "-c/--context cannot be combined with the deprecated -p/--project or --old-context"
+    ));
+}
+
+#[test]
+fn test_archived_context_blocks_writes() {
+    let ctx = TestContext::new();
+    ctx.init_context();
+
+    ctx.write_file("test.txt", "original");
+    let snapshot_id = extract_snapshot_id(&ctx.run_mote_ctx(&["snap", "create", "-m", "First"]));
+
+    let output = ctx.run_mote_ctx(&["context", "archive", "default"]);
+    assert!(output.status.success());
+
+    let output = ctx.run_mote_ctx(&["context", "list"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("[archived]"));
+
+    let output = ctx.run_mote_ctx(&["snap", "create", "-m", "Second"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("archived"));
+    assert!(stderr.contains("mote context unarchive default"));
+
+    let output = ctx.run_mote_ctx(&["snap", "create", "--auto"]);
+    assert!(output.status.success());
+
+    let output = ctx.run_mote_ctx(&["snap", "delete", &snapshot_id, "--force"]);
+    assert!(!output.status.success());
+
+    let output = ctx.run_mote_ctx(&["snap", "gc"]);
+    assert!(!output.status.success());
+
+    let output = ctx.run_mote_ctx(&["snap", "list", "--oneline"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.lines().count(), 1);
+}
+
+#[test]
+fn test_archived_context_allows_reads_and_restore() {
+    let ctx = TestContext::new();
+    ctx.init_context();
+
+    ctx.write_file("test.txt", "original");
+    let snapshot_id = extract_snapshot_id(&ctx.run_mote_ctx(&["snap", "create"]));
+    ctx.run_mote_ctx(&["context", "archive", "default"]);
+
+    assert!(ctx.run_mote_ctx(&["snap", "show", &snapshot_id]).status.success());
+    assert!(ctx.run_mote_ctx(&["snap", "diff", &snapshot_id]).status.success());
+
+    fs::remove_file(ctx.project_dir.join("test.txt")).unwrap();
+    let output = ctx.run_mote_ctx(&["snap", "restore", &snapshot_id]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("skipping backup snapshot"));
+    assert_eq!(ctx.read_file("test.txt"), "original");
+
+    let output = ctx.run_mote_ctx(&["snap", "list", "--oneline"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout).lines().count(), 1);
+
+    ctx.run_mote_ctx(&["context", "unarchive", "default"]);
+    let output = ctx.run_mote_ctx(&["snap", "create"]);
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_pre_snapshot_hook_failure_aborts() {
+    let ctx = TestContext::new();
+    ctx.init_context();
+    fs::write(
+        ctx.config_dir.join("config.toml"),
+        "[hooks]\npre_snapshot = [\"exit 3\"]\n",
+    )
+    .unwrap();
+
+    ctx.write_file("test.txt", "content");
+    let output = ctx.run_mote_ctx(&["snap", "create"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("exit 3"));
+
+    let output = ctx.run_mote_ctx(&["snap", "create", "--auto"]);
+    assert!(output.status.success());
+    assert!(output.stderr.is_empty());
+
+    let output = ctx.run_mote_ctx(&["snap", "list"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("No snapshots yet"));
+}
+
+#[test]
+fn test_post_snapshot_hook_receives_environment() {
+    let ctx = TestContext::new();
+    ctx.init_context();
+    let hook_output = ctx.config_dir.join("hook.out");
+    fs::write(
+        ctx.config_dir.join("config.toml"),
+        format!(
+            "[hooks]\npre_snapshot = [\"echo pre-hook-ran\"]\npost_snapshot = [\"echo $MOTE_SNAPSHOT_ID $MOTE_FILE_COUNT $MOTE_TRIGGER > '{}'\"]\n",
+            hook_output.display()
+        ),
+    )
+    .unwrap();
+
+    ctx.write_file("test.txt", "content");
+    let output = ctx.run_mote_ctx(&["snap", "create", "-t", "manual"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("pre-hook-ran"));
+
+    let snapshot_id = extract_snapshot_id(&output);
+    let recorded = fs::read_to_string(&hook_output).unwrap();
+    assert!(recorded.starts_with(&snapshot_id));
+    assert!(recorded.trim_end().ends_with("1 manual"));
+}
+
+#[test]
+fn test_snapshot_metadata_show_and_filter() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+
+    ctx.write_file("test.txt", "v1");
+    let output = ctx.run_mote(&[
+        "snap",
+        "create",
+        "-m",
+        "Tagged",
+        "--meta",
+        "agent=claude",
+        "--meta",
+        "prompt=fix the bug",
+    ]);
+    assert!(output.status.success());
+    let snapshot_id = extract_snapshot_id(&output);
+
+    ctx.write_file("test.txt", "v2");
+    ctx.run_mote(&["snap", "create", "-m", "Untagged"]);
+
+    let output = ctx.run_mote(&["snap", "show", &snapshot_id]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Metadata"));
+    assert!(stdout.contains("prompt = fix the bug"));
+
+    let output = ctx.run_mote(&["snap", "list", "--oneline", "--meta", "agent=claude"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.lines().count(), 1);
+    assert!(stdout.contains("Tagged"));
+
+    let output = ctx.run_mote(&["snap", "list", "--oneline", "--meta", "agent=other"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("No snapshots match"));
+}
+
+#[test]
+fn test_snapshot_metadata_rejects_invalid_keys() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file("test.txt", "content");
+
+    for bad in ["novalue", "=value", "bad\tkey=value"] {
+        let output = ctx.run_mote(&["snap", "create", "--meta", bad]);
+        assert!(!output.status.success(), "accepted {:?}", bad);
+    }
+}
+
+#[test]
+fn test_export_store_keeps_latest_snapshots() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+
+    ctx.write_file("old.txt", "only in the oldest snapshot");
+    ctx.run_mote(&["snap", "create", "-m", "Oldest"]);
+    fs::remove_file(ctx.project_dir.join("old.txt")).unwrap();
+    ctx.write_file("test.txt", "v1");
+    ctx.run_mote(&["snap", "create", "-m", "Middle"]);
+    ctx.write_file("test.txt", "v2");
+    let latest_id = extract_snapshot_id(&ctx.run_mote(&["snap", "create", "-m", "Latest"]));
+
+    let export_dir = ctx.config_dir.join("export");
+    let export_arg = export_dir.to_string_lossy().to_string();
+    let output = ctx.run_mote(&["snap", "export-store", "--keep", "2", "-o", &export_arg]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(export_dir.join("manifest.json").exists());
+
+    let output = ctx.run_mote(&["-d", &export_arg, "snap", "list", "--oneline"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.lines().count(), 2);
+    assert!(!stdout.contains("Oldest"));
+
+    let object_count = count_objects(&export_dir.join("storage/objects"));
+    assert_eq!(object_count, 3); // .moteignore, test.txt v1, test.txt v2
+
+    ctx.write_file("test.txt", "changed");
+    let output = ctx.run_mote(&["-d", &export_arg, "snap", "restore", &latest_id, "--force"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(ctx.read_file("test.txt"), "v2");
+
+    let output = ctx.run_mote(&["snap", "export-store", "--keep", "1", "-o", &export_arg]);
+    assert!(!output.status.success());
+}
+
+fn count_objects(dir: &std::path::Path) -> usize {
+    fs::read_dir(dir)
+        .unwrap()
+        .map(|e| fs::read_dir(e.unwrap().path()).unwrap().count())
+        .sum()
+}
+
+#[test]
+fn test_clock_skew_warning_and_ordering() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+
+    ctx.write_file("test.txt", "v1");
+    ctx.run_mote(&["snap", "create", "-m", "Skewed"]);
+
+    // Pretend the previous snapshot was taken a year in the future
+    let snapshots_dir = ctx.project_dir.join(".mote/snapshots");
+    for entry in fs::read_dir(&snapshots_dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_some_and(|e| e == "json") {
+            let mut json: serde_json::Value =
+                serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+            json["timestamp"] = serde_json::json!("2099-01-01T00:00:00Z");
+            fs::write(&path, json.to_string()).unwrap();
+        }
+    }
+
+    ctx.write_file("test.txt", "v2");
+    let output = ctx.run_mote(&["snap", "create", "-m", "After skew"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Clock skew detected"));
+
+    let output = ctx.run_mote(&["snap", "list", "--oneline"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert!(lines[0].contains("After skew"));
+    assert!(lines[1].contains("Skewed"));
+}
+
+#[test]
+fn test_snapshot_records_total_and_new_size() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    fs::write(ctx.project_dir.join(".moteignore"), ".moteignore\n").unwrap();
+
+    ctx.write_file("a.txt", &"a".repeat(2048));
+    let first_id = extract_snapshot_id(&ctx.run_mote(&["snap", "create"]));
+    ctx.write_file("b.txt", &"a".repeat(2048));
+    let second_id = extract_snapshot_id(&ctx.run_mote(&["snap", "create"]));
+
+    let output = ctx.run_mote(&["snap", "show", &first_id]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Size:    2.0 KB total, 2.0 KB new"));
+
+    // b.txt has identical content, so nothing new is stored
+    let output = ctx.run_mote(&["snap", "show", &second_id]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Size:    4.0 KB total, 0 B new"));
+
+    let output = ctx.run_mote(&["snap", "list", "--oneline"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("(2 files, 4.0 KB)"));
+
+    // --bytes prints exact counts
+    let output = ctx.run_mote(&["--bytes", "snap", "show", &second_id]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Size:    4096 bytes total, 0 bytes new"));
+    assert!(stdout.contains("a.txt (2048 bytes)"));
+}
+
+#[test]
+fn test_legacy_snapshot_without_sizes_displays_dash() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    let snapshot_path = ctx
+        .project_dir
+        .join(".mote/snapshots/20240101_000000_abcdef12.json");
+    fs::write(
+        snapshot_path,
+        r#"{"id":"abcdef1234","timestamp":"2024-01-01T00:00:00Z","files":[]}"#,
+    )
+    .unwrap();
+
+    let output = ctx.run_mote(&["snap", "list", "--oneline"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("(0 files, -)"));
+
+    let output = ctx.run_mote(&["snap", "show", "abcdef1"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Size:    - total, - new"));
+}
+
+#[test]
+fn test_list_marks_and_skips_empty_snapshots() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file("a.txt", "v1");
+    ctx.run_mote(&["snap", "create", "-m", "first"]);
+    ctx.run_mote(&["snap", "create", "-m", "just in case", "--checkpoint"]);
+    ctx.write_file("a.txt", "v2");
+    ctx.run_mote(&["snap", "create", "-m", "third"]);
+
+    let output = ctx.run_mote(&["snap", "list", "--oneline", "--stat"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let marked: Vec<&str> = stdout
+        .lines()
+        .filter(|l| l.contains("(no changes)"))
+        .collect();
+    assert_eq!(marked.len(), 1, "{}", stdout);
+    assert!(marked[0].contains("just in case"));
+
+    let output = ctx.run_mote(&["snap", "list", "--stat"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.matches("(no changes)").count(), 1);
+
+    // Plain listing doesn't compare snapshots
+    let output = ctx.run_mote(&["snap", "list", "--oneline"]);
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("(no changes)"));
+
+    let output = ctx.run_mote(&["snap", "list", "--oneline", "--skip-empty"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.lines().count(), 2);
+    assert!(!stdout.contains("just in case"));
+
+    let output = ctx.run_mote(&["snap", "list", "--json", "--skip-empty", "--grep", "i"]);
+    let entries: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let messages: Vec<&str> = entries
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["message"].as_str().unwrap())
+        .collect();
+    assert_eq!(messages, ["third", "first"]);
+}
+
+#[test]
+fn test_checkpoint_always_records_snapshot() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file("test.txt", "content");
+
+    for _ in 0..2 {
+        let output = ctx.run_mote(&["snap", "create", "--auto", "--checkpoint"]);
+        assert!(output.status.success());
+    }
+
+    let output = ctx.run_mote(&["snap", "list"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.matches("Trigger: checkpoint").count(), 2);
+
+    let ids: Vec<String> =
+        String::from_utf8_lossy(&ctx.run_mote(&["snap", "list", "--oneline"]).stdout)
+            .lines()
+            .map(|l| l.split_whitespace().next().unwrap().to_string())
+            .collect();
+    assert_eq!(ids.len(), 2);
+
+    let files: Vec<String> = ids
+        .iter()
+        .map(|id| {
+            let stdout =
+                String::from_utf8_lossy(&ctx.run_mote(&["snap", "show", id]).stdout).to_string();
+            stdout.split("Files:\n").nth(1).unwrap().to_string()
+        })
+        .collect();
+    assert_eq!(files[0], files[1]);
+
+    let output = ctx.run_mote(&["snap", "create", "--checkpoint", "-t", "release-tag"]);
+    assert!(output.status.success());
+    let output = ctx.run_mote(&["snap", "list", "--limit", "1"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Trigger: release-tag"));
+}
+
+#[test]
+fn test_list_json_output() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+
+    let output = ctx.run_mote(&["snap", "list", "--json"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "[]");
+
+    ctx.write_file("test.txt", "v1");
+    ctx.run_mote(&["snap", "create", "-m", "First", "-t", "manual"]);
+    ctx.write_file("test.txt", "v2");
+    ctx.run_mote(&["snap", "create", "-m", "Second"]);
+
+    let output = ctx.run_mote(&["snap", "list", "--json", "--limit", "1"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains('\x1b'));
+    let json: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    let entries = json.as_array().unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0]["message"], "Second");
+    assert_eq!(entries[0]["file_count"], 2);
+    assert_eq!(
+        entries[0]["short_id"].as_str().unwrap(),
+        &entries[0]["id"].as_str().unwrap()[..7]
+    );
+    let timestamp = entries[0]["timestamp"].as_str().unwrap();
+    assert!(chrono::DateTime::parse_from_rfc3339(timestamp).is_ok());
+    assert!(entries[0].get("files").is_none());
+
+    let output = ctx.run_mote(&["snap", "list", "--json", "--verbose"]);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json.as_array().unwrap().len(), 2);
+    assert_eq!(json[1]["trigger"], "manual");
+    assert_eq!(json[1]["files"].as_array().unwrap().len(), 2);
+}
+
+/// Write a minimal snapshot file directly into `.mote/snapshots`
+fn write_snapshot_fixture(ctx: &TestContext, id: &str, timestamp: &str, message: &str) {
+    let snapshot = serde_json::json!({
+        "id": id,
+        "timestamp": timestamp,
+        "message": message,
+        "files": [],
+    });
+    let path = ctx
+        .project_dir
+        .join(".mote/snapshots")
+        .join(format!("{}.json", id));
+    fs::write(path, snapshot.to_string()).unwrap();
+}
+
+#[test]
+fn test_restore_and_diff_at_time() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file("notes.txt", "good");
+    ctx.run_mote(&["snap", "create"]);
+    // Written after the snapshot, whose auto-cleanup would expire them
+    write_snapshot_fixture(&ctx, "aaaaaaa1", "2024-05-01T09:00:00Z", "morning");
+    write_snapshot_fixture(&ctx, "bbbbbbb2", "2024-05-01T14:00:00Z", "afternoon");
+    fs::remove_file(ctx.project_dir.join("notes.txt")).unwrap();
+
+    let output = ctx.run_mote(&["snap", "restore", "--at", "0s"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(ctx.read_file("notes.txt"), "good");
+
+    let output = ctx.run_mote(&[
+        "--utc",
+        "snap",
+        "restore",
+        "--at",
+        "2024-05-01 12:00",
+        "--dry-run",
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Using snapshot aaaaaaa"));
+
+    // A date and time without a zone is read in the display time zone:
+    // 20:00 at +09:00 is 11:00 UTC
+    let output = Command::new(&ctx.mote_bin)
+        .args(["snap", "restore", "--at", "2024-05-01 20:00", "--dry-run"])
+        .env("TZ", "JST-9")
+        .current_dir(&ctx.project_dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Using snapshot aaaaaaa"));
+
+    let output = ctx.run_mote(&[
+        "snap",
+        "diff",
+        "--at",
+        "2024-05-01T14:00:00Z",
+        "--name-only",
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Using snapshot bbbbbbb"));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("notes.txt"));
+
+    // Nothing old enough: the error names the earliest snapshot
+    let output = ctx.run_mote(&["--utc", "snap", "restore", "--at", "2024-05-01 08:00"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("the earliest is from 2024-05-01 09:00:00 UTC"),
+        "{}",
+        stderr
+    );
+
+    let output = ctx.run_mote(&["snap", "restore", "aaaaaaa1", "--at", "2h"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("cannot be used with"));
+}
+
+#[test]
+fn test_list_since_until_filters() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    write_snapshot_fixture(&ctx, "aaaaaaa1", "2024-05-01T09:00:00Z", "morning");
+    write_snapshot_fixture(&ctx, "bbbbbbb2", "2024-05-01T14:00:00Z", "afternoon");
+    write_snapshot_fixture(&ctx, "ccccccc3", "2024-05-02T09:00:00Z", "next day");
+
+    let list = |args: &[&str]| {
+        let mut full = vec!["snap", "list", "--oneline"];
+        full.extend_from_slice(args);
+        String::from_utf8_lossy(&ctx.run_mote(&full).stdout).to_string()
+    };
+
+    let stdout = list(&["--since", "2024-05-01T14:00:00Z"]);
+    assert!(stdout.contains("afternoon") && stdout.contains("next day"));
+    assert!(!stdout.contains("morning"));
+
+    let stdout = list(&["--since", "2024-05-01", "--until", "2024-05-01T14:00:00Z"]);
+    assert!(stdout.contains("morning") && stdout.contains("afternoon"));
+    assert!(!stdout.contains("next day"));
+
+    let stdout = list(&["--since", "2024-05-01", "--limit", "1"]);
+    assert_eq!(stdout.lines().count(), 1);
+    assert!(stdout.contains("next day"));
+
+    let stdout = list(&["--since", "1h"]);
+    assert!(stdout.contains("No snapshots match"));
+
+    // A date is midnight in the display time zone: 2024-05-02 starts at
+    // 2024-05-01 15:00 UTC at +09:00
+    write_snapshot_fixture(&ctx, "ddddddd4", "2024-05-01T20:00:00Z", "evening");
+    let output = Command::new(&ctx.mote_bin)
+        .args(["snap", "list", "--oneline", "--since", "2024-05-02"])
+        .env("TZ", "JST-9")
+        .current_dir(&ctx.project_dir)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("evening") && stdout.contains("next day"));
+    assert!(!stdout.contains("afternoon"));
+
+    let output = ctx.run_mote(&["snap", "list", "--since", "last tuesday"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid arguments"));
+}
+
+#[test]
+fn test_list_grep_filters_messages() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    // Created before the fixtures so auto cleanup doesn't prune them
+    ctx.write_file("test.txt", "content");
+    ctx.run_mote(&["snap", "create"]);
+    write_snapshot_fixture(&ctx, "aaaaaaa1", "2024-05-01T09:00:00Z", "Before refactor");
+    write_snapshot_fixture(&ctx, "bbbbbbb2", "2024-05-01T10:00:00Z", "working build");
+    write_snapshot_fixture(&ctx, "ccccccc3", "2024-05-01T11:00:00Z", "before release");
+
+    let list = |args: &[&str]| {
+        let mut full = vec!["snap", "list", "--oneline"];
+        full.extend_from_slice(args);
+        String::from_utf8_lossy(&ctx.run_mote(&full).stdout).to_string()
+    };
+
+    let stdout = list(&["--grep", "BEFORE"]);
+    assert_eq!(stdout.lines().count(), 2);
+    assert!(stdout.contains("Before refactor") && stdout.contains("before release"));
+
+    let stdout = list(&["--grep", "before", "--limit", "1"]);
+    assert_eq!(stdout.lines().count(), 1);
+    assert!(stdout.contains("before release"));
+
+    let stdout = list(&["--grep", "^(working|before rel)", "--regex"]);
+    assert_eq!(stdout.lines().count(), 2);
+    assert!(stdout.contains("working build") && stdout.contains("before release"));
+
+    // Snapshots without a message never match, even an empty pattern
+    let stdout = list(&["--grep", ""]);
+    assert_eq!(stdout.lines().count(), 3);
+
+    let output = ctx.run_mote(&["snap", "list", "--grep", "(", "--regex"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid arguments"));
+}
+
+#[test]
+fn test_foreign_project_uses_recorded_root() {
+    let ctx = TestContext::new();
+    ctx.init_context();
+
+    let other_dir = TempDir::new().unwrap();
+    let config_dir = ctx.config_dir.to_string_lossy().to_string();
+    let run_in = |dir: &std::path::Path, args: &[&str]| {
+        Command::new(&ctx.mote_bin)
+            .arg("--config-dir")
+            .arg(&config_dir)
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .unwrap()
+    };
+
+    let output = run_in(
+        other_dir.path(),
+        &["-c", "other/default", "context", "new", "default"],
+    );
+    assert!(output.status.success(), "{:?}", output);
+    fs::write(other_dir.path().join("other.txt"), "from other").unwrap();
+    let snapshot_id = extract_snapshot_id(&run_in(other_dir.path(), &["snap", "create"]));
+
+    let other_root = other_dir.path().canonicalize().unwrap();
+    // Identical files aren't restored, so remove it to see where it would go
+    fs::remove_file(other_dir.path().join("other.txt")).unwrap();
+    let output = ctx.run_mote_ctx(&[
+        "-c",
+        "other/default",
+        "snap",
+        "restore",
+        &snapshot_id,
+        "--dry-run",
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains(&format!("Using project root {}", other_root.display())));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("other.txt"));
+
+    let output = ctx.run_mote_ctx(&[
+        "-c",
+        "other/default",
+        "snap",
+        "restore",
+        &snapshot_id,
+        "--force",
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(other_dir.path().join("other.txt").exists());
+    assert!(!ctx.file_exists("other.txt"));
+
+    // --project-root deliberately overrides the recorded root
+    let project_root = ctx.project_dir.to_string_lossy().to_string();
+    let output = ctx.run_mote_ctx(&[
+        "--project-root",
+        &project_root,
+        "-c",
+        "other/default",
+        "snap",
+        "restore",
+        &snapshot_id,
+        "--force",
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("Using project root"));
+    assert!(ctx.file_exists("other.txt"));
+}
+
+#[test]
+fn test_list_trigger_filter() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+
+    for (i, trigger) in ["git-commit", "git-commit", "", "manual", "shell"]
+        .iter()
+        .enumerate()
+    {
+        ctx.write_file("test.txt", &format!("v{}", i));
+        let mut args = vec!["snap", "create", "-m", "snap"];
+        if !trigger.is_empty() {
+            args.extend(["-t", trigger]);
+        }
+        assert!(ctx.run_mote(&args).status.success());
+    }
+
+    let count = |args: &[&str]| {
+        let mut full = vec!["snap", "list", "--oneline"];
+        full.extend_from_slice(args);
+        let output = ctx.run_mote(&full);
+        String::from_utf8_lossy(&output.stdout).lines().count()
+    };
+
+    assert_eq!(count(&["--trigger", "git-commit"]), 2);
+    assert_eq!(count(&["--trigger", "git-commit", "--limit", "1"]), 1);
+    assert_eq!(count(&["--trigger", "none"]), 1);
+    assert_eq!(count(&["--trigger", "manual"]), 2);
+    assert_eq!(count(&["--trigger", "shell", "--since", "1h"]), 1);
+
+    let output = ctx.run_mote(&["log", "--oneline", "--trigger", "git-commit"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout).lines().count(), 2);
+}
+
+#[test]
+fn test_init_and_ignore_suggest_detect_ecosystems() {
+    let ctx = TestContext::new();
+    ctx.write_file("pyproject.toml", "[project]\nname = \"demo\"\n");
+
+    let output = ctx.run_mote(&["init"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Detected Python"));
+    let ignore = ctx.read_file(".moteignore");
+    assert!(ignore.contains(".tox/"));
+    assert!(!ignore.contains("target/"));
+
+    let output = ctx.run_mote(&["ignore", "suggest"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("All suggested patterns are present"));
+
+    ctx.write_file("Cargo.toml", "[package]\nname = \"demo\"\n");
+    let output = ctx.run_mote(&["ignore", "suggest"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("target/"));
+    assert!(!ctx.read_file(".moteignore").contains("target/"));
+
+    let output = ctx.run_mote(&["ignore", "suggest", "--apply"]);
+    assert!(output.status.success());
+    let applied = ctx.read_file(".moteignore");
+    assert_eq!(applied.matches("target/").count(), 1);
+
+    ctx.run_mote(&["ignore", "suggest", "--apply"]);
+    assert_eq!(ctx.read_file(".moteignore"), applied);
+}
+
+#[test]
+fn test_list_stat_counts_changes() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    fs::write(ctx.project_dir.join(".moteignore"), ".moteignore\n").unwrap();
+
+    ctx.write_file("a.txt", "a");
+    ctx.write_file("b.txt", "b");
+    ctx.run_mote(&["snap", "create", "-m", "first"]);
+
+    ctx.write_file("a.txt", "a2");
+    ctx.write_file("c.txt", "c");
+    fs::remove_file(ctx.project_dir.join("b.txt")).unwrap();
+    ctx.run_mote(&["snap", "create", "-m", "second"]);
+
+    let output = ctx.run_mote(&["snap", "list", "--oneline", "--stat"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].contains("second") && lines[0].ends_with("+1 ~1 -1"));
+    assert!(lines[1].contains("first") && lines[1].ends_with("+2 ~0 -0"));
+
+    // Filtering still compares against the chronologically previous snapshot
+    let output = ctx.run_mote(&["snap", "list", "--stat", "--grep", "second"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Changes: +1 ~1 -1"));
+}
+
+#[test]
+fn test_list_default_limit_depends_on_tty() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    for i in 0..25 {
+        write_snapshot_fixture(
+            &ctx,
+            &format!("{:08x}", i),
+            &format!("2024-05-01T10:{:02}:00Z", i),
+            &format!("snapshot {}", i),
+        );
+    }
+
+    let list = |tty: &str, args: &[&str]| {
+        let output = Command::new(&ctx.mote_bin)
+            .args(["snap", "list", "--oneline"])
+            .args(args)
+            .env("MOTE_FORCE_TTY", tty)
+            .env("NO_COLOR", "1")
+            .current_dir(&ctx.project_dir)
+            .output()
+            .unwrap();
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    let stdout = list("1", &[]);
+    assert_eq!(stdout.lines().count(), 21);
+    assert!(stdout.ends_with("(showing 20 of 25, use --all)\n"));
+
+    assert_eq!(list("1", &["--all"]).lines().count(), 25);
+    assert_eq!(list("1", &["--limit", "30"]).lines().count(), 25);
+    assert_eq!(list("0", &[]).lines().count(), 25);
+    assert_eq!(list("0", &["--limit", "3"]).lines().count(), 3);
+
+    let stdout = list("1", &["--grep", "snapshot 1"]);
+    assert!(!stdout.contains("use --all"));
+}
+
+#[test]
+fn test_list_follow_file_history() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+
+    ctx.write_file("other.txt", "x");
+    ctx.run_mote(&["snap", "create", "-m", "before"]);
+    ctx.write_file("src/parser.rs", "v1");
+    ctx.run_mote(&["snap", "create", "-m", "added"]);
+    ctx.write_file("other.txt", "y");
+    ctx.run_mote(&["snap", "create", "-m", "unrelated"]);
+    ctx.write_file("src/parser.rs", "v2");
+    ctx.run_mote(&["snap", "create", "-m", "modified"]);
+    fs::remove_file(ctx.project_dir.join("src/parser.rs")).unwrap();
+    ctx.run_mote(&["snap", "create", "-m", "deleted"]);
+
+    let output = ctx.run_mote(&["snap", "list", "--oneline", "--follow", "src/parser.rs"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 3, "{}", stdout);
+    assert!(lines[0].starts_with("D ") && lines[0].contains("deleted"));
+    assert!(lines[1].starts_with("M ") && lines[1].contains("modified"));
+    assert!(lines[2].starts_with("A ") && lines[2].contains("added"));
+
+    let absolute = ctx.project_dir.join("src/parser.rs");
+    let output = ctx.run_mote(&[
+        "snap",
+        "list",
+        "--oneline",
+        "--follow",
+        absolute.to_str().unwrap(),
+    ]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), stdout);
+}
+
+#[test]
+fn test_timestamps_display_in_local_time_or_utc() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    write_snapshot_fixture(&ctx, "aaaaaaa1", "2024-05-01T14:00:00Z", "fixture");
+
+    let show = |args: &[&str]| {
+        let output = Command::new(&ctx.mote_bin)
+            .args(args)
+            .args(["snap", "show", "aaaaaaa1"])
+            .env("TZ", "JST-9")
+            .current_dir(&ctx.project_dir)
+            .output()
+            .unwrap();
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    assert!(show(&[]).contains("Date:    2024-05-01 23:00:00 +09:00"));
+    assert!(show(&["--utc"]).contains("Date:    2024-05-01 14:00:00 UTC"));
+
+    fs::write(
+        ctx.config_dir.join("config.toml"),
+        "[display]\ntimezone = \"utc\"\n",
+    )
+    .unwrap();
+    let config_dir = ctx.config_dir.to_string_lossy().to_string();
+    assert!(show(&["--config-dir", &config_dir]).contains("Date:    2024-05-01 14:00:00 UTC"));
+}
+
+#[test]
+fn test_missing_explicit_ignore_file_warns_or_fails() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    fs::remove_file(ctx.project_dir.join(".moteignore")).unwrap();
+    ctx.write_file("test.txt", "content");
+
+    // The default .moteignore is optional
+    let output = ctx.run_mote_ctx(&["snap", "create", "-m", "default"]);
+    assert!(output.status.success());
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("Ignore file"));
+
+    let config_path = ctx.config_dir.join("config.toml");
+    fs::write(&config_path, "[ignore]\nignore_file = \".moteignroe\"\n").unwrap();
+    ctx.write_file("test.txt", "changed");
+    let output = ctx.run_mote_ctx(&["snap", "create", "-m", "typo"]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("warning"));
+    assert!(stderr.contains(".moteignroe does not exist"));
+
+    fs::write(
+        &config_path,
+        "[ignore]\nignore_file = \".moteignroe\"\nrequire_ignore_file = true\n",
+    )
+    .unwrap();
+    let output = ctx.run_mote_ctx(&["snap", "create", "-m", "strict"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Ignore file not found"));
+
+    ctx.write_file(".moteignroe", "*.log\n");
+    let output = ctx.run_mote_ctx(&["snap", "create", "-m", "fixed"]);
+    assert!(output.status.success());
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("Ignore file"));
+}
+
+#[test]
+fn test_list_custom_format() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    write_snapshot_fixture(&ctx, "aaaaaaa1", "2024-05-01T09:00:00Z", "first");
+    write_snapshot_fixture(&ctx, "bbbbbbb2", "2024-05-01T10:00:00Z", "second");
+
+    let output = ctx.run_mote(&["snap", "list", "--format", "{short_id}\t{message}"]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "bbbbbbb\tsecond\naaaaaaa\tfirst\n"
+    );
+
+    let output = ctx.run_mote(&["snap", "list", "--format", "{author}"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("unknown placeholder '{author}'"));
+    assert!(stderr.contains("{timestamp_iso}"));
+}
+
+#[test]
+fn test_snapshot_ignore_file_setting() {
+    let ctx = TestContext::new();
+    ctx.run_mote_ctx(&["init"]);
+    ctx.write_file(".moteignore", "*.log\n");
+    ctx.write_file("test.txt", "content");
+
+    // By default the ignore file is tracked like any other file
+    let output = ctx.run_mote_ctx(&["snap", "create", "-m", "with ignore"]);
+    let with_ignore = extract_snapshot_id(&output);
+    let output = ctx.run_mote_ctx(&["snap", "show", &with_ignore]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains(".moteignore"));
+
+    fs::write(
+        ctx.config_dir.join("config.toml"),
+        "[ignore]\nsnapshot_ignore_file = false\n",
+    )
+    .unwrap();
+    ctx.write_file("test.txt", "changed");
+    let output = ctx.run_mote_ctx(&["snap", "create", "-m", "without ignore"]);
+    let without_ignore = extract_snapshot_id(&output);
+    let output = ctx.run_mote_ctx(&["snap", "show", &without_ignore]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("test.txt"));
+    assert!(!stdout.contains(".moteignore"));
+
+    // Neither added nor deleted in working-directory diffs
+    ctx.write_file(".moteignore", "*.log\n*.tmp\n");
+    for id in [&with_ignore, &without_ignore] {
+        let output = ctx.run_mote_ctx(&["snap", "diff", id, "--name-only"]);
+        assert!(!String::from_utf8_lossy(&output.stdout).contains(".moteignore"));
+    }
+
+    // Restoring an older snapshot leaves the current ignore file alone
+    let output = ctx.run_mote_ctx(&["snap", "restore", &with_ignore, "--force"]);
+    assert!(output.status.success());
+    assert_eq!(ctx.read_file("test.txt"), "content");
+    assert_eq!(ctx.read_file(".moteignore"), "*.log\n*.tmp\n");
+}
+
+#[test]
+fn test_minimal_ignore_file() {
+    let files = [
+        "node_modules/dep.js",
+        "app.log",
+        "target/out.o",
+        ".git/HEAD",
+    ];
+
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init", "--minimal"]);
+    assert!(ctx
+        .read_file(".moteignore")
+        .lines()
+        .all(|line| line.starts_with('#')));
+    for file in files {
+        ctx.write_file(file, "content");
+    }
+    let id = extract_snapshot_id(&ctx.run_mote(&["snap", "create"]));
+    let output = ctx.run_mote(&["snap", "show", &id]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    for file in &files[..3] {
+        assert!(stdout.contains(file), "{}", stdout);
+    }
+    assert!(!stdout.contains(".git/HEAD"));
+
+    // The default template would have left these out
+    let ctx = TestContext::new();
+    ctx.run_mote_ctx(&[
+        "-c",
+        "test/default",
+        "context",
+        "new",
+        "default",
+        "--minimal",
+    ]);
+    ctx.run_mote_ctx(&["-c", "test/default", "context", "new", "full"]);
+    for file in files {
+        ctx.write_file(file, "content");
+    }
+    ctx.write_file("src.txt", "content");
+    for (context, included) in [("test/default", true), ("test/full", false)] {
+        let output = ctx.run_mote_ctx(&["-c", context, "snap", "create"]);
+        let id = extract_snapshot_id(&output);
+        let output = ctx.run_mote_ctx(&["-c", context, "snap", "show", &id]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert_eq!(
+            stdout.contains("node_modules/dep.js"),
+            included,
+            "{}",
+            stdout
+        );
+        assert_eq!(stdout.contains("app.log"), included, "{}", stdout);
+    }
+}
+
+#[test]
+fn test_error_footer_names_resolved_context() {
+    let ctx = TestContext::new();
+    ctx.init_context();
+    ctx.run_mote_ctx(&["-c", "test/default", "context", "new", "feature"]);
+
+    let output = ctx.run_mote_ctx(&["-c", "test/feature", "snap", "show", "deadbee"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let footer = stderr.lines().last().unwrap();
+    assert!(stderr.contains("Snapshot not found"), "{}", stderr);
+    assert!(
+        footer.starts_with("context: test/feature  storage: "),
+        "{}",
+        footer
+    );
+    assert!(footer.contains("feature"));
+    assert!(footer.ends_with(&format!("config: {}", ctx.config_dir.display())));
+
+    // A project that doesn't exist is still named
+    let output = ctx.run_mote_ctx(&["-c", "nope/x", "snap", "show", "deadbee"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("context: nope/x  config: "), "{}", stderr);
+
+    // Nothing but the error under --quiet
+    let output = ctx.run_mote_ctx(&["-c", "test/feature", "snap", "diff", "deadbee", "--quiet"]);
+    assert_eq!(String::from_utf8_lossy(&output.stderr).lines().count(), 1);
+}
+
+#[test]
+fn test_local_ignore_file() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file(".moteignore", "*.log\n");
+    ctx.write_file("app.log", "log");
+    ctx.write_file("keep.log", "keep");
+    ctx.write_file("scratch/notes.txt", "notes");
+    ctx.write_file("src.txt", "src");
+
+    let output = ctx.run_mote(&["ignore", "add", "--local", "scratch/"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains(".moteignore.local"));
+    ctx.run_mote(&["ignore", "add", "--local", "!keep.log"]);
+    assert_eq!(ctx.read_file(".moteignore"), "*.log\n");
+    assert_eq!(ctx.read_file(".moteignore.local"), "scratch/\n!keep.log\n");
+
+    let output = ctx.run_mote(&["ignore", "list"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let shared = stdout.find(".moteignore:").unwrap();
+    let local = stdout.find(".moteignore.local (local):").unwrap();
+    assert!(shared < local, "{}", stdout);
+
+    let output = ctx.run_mote(&["snap", "create"]);
+    let id = extract_snapshot_id(&output);
+    let output = ctx.run_mote(&["snap", "show", &id]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("keep.log"), "{}", stdout);
+    assert!(stdout.contains("src.txt"));
+    assert!(!stdout.contains("app.log"));
+    assert!(!stdout.contains("scratch/notes.txt"));
+    assert!(!stdout.contains(".moteignore.local"));
+
+    // Never reported as added or deleted either
+    ctx.write_file(".moteignore.local", "scratch/\n");
+    let output = ctx.run_mote(&["snap", "diff", &id, "--name-only"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains(".moteignore.local"), "{}", stdout);
+    assert!(stdout.contains("keep.log"));
+}
+
+#[test]
+fn test_list_reverse_and_offset() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    for (i, msg) in ["s1", "s2", "s3", "s4", "s5"].iter().enumerate() {
+        let id = format!("{}{}", "a".repeat(7), i);
+        let ts = format!("2024-05-01T0{}:00:00Z", i + 1);
+        write_snapshot_fixture(&ctx, &id, &ts, msg);
+    }
+
+    let list = |extra: &[&str]| {
+        let mut args = vec!["snap", "list", "--format", "{message}"];
+        args.extend_from_slice(extra);
+        let output = ctx.run_mote(&args);
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+
+    assert_eq!(list(&[]), "s5 s4 s3 s2 s1");
+    assert_eq!(list(&["--reverse"]), "s1 s2 s3 s4 s5");
+    assert_eq!(list(&["--offset", "0", "--limit", "2"]), "s5 s4");
+    assert_eq!(list(&["--offset", "1", "--limit", "2"]), "s4 s3");
+    assert_eq!(list(&["--offset", "3", "--limit", "5"]), "s2 s1");
+    assert_eq!(list(&["--offset", "4"]), "s1");
+    assert_eq!(list(&["--offset", "5"]), "");
+    assert_eq!(list(&["--offset", "9"]), "");
+    assert_eq!(
+        list(&["--reverse", "--offset", "2", "--limit", "2"]),
+        "s3 s4"
+    );
+    assert_eq!(list(&["--reverse", "--offset", "4", "--limit", "1"]), "s5");
+    assert_eq!(list(&["--limit", "0"]), "");
+
+    let output = ctx.run_mote(&[
+        "snap",
+        "list",
+        "--oneline",
+        "--reverse",
+        "--offset",
+        "1",
+        "--limit",
+        "1",
+    ]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.lines().count(), 1);
+    assert!(stdout.contains("s2"));
+
+    let output = ctx.run_mote(&["snap", "list", "--json", "--reverse", "--offset", "3"]);
+    let entries: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let messages: Vec<&str> = entries
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["message"].as_str().unwrap())
+        .collect();
+    assert_eq!(messages, ["s4", "s5"]);
+}
+
+#[test]
+fn test_serve_stdio_requests() {
+    use std::io::{BufRead, BufReader, Write};
+    use std::process::Stdio;
+
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file("src/main.rs", "fn main() {}\n");
+    ctx.write_file("notes.txt", "keep\n");
+    let output = ctx.run_mote(&["snap", "create", "-m", "first"]);
+    let first = extract_snapshot_id(&output);
+
+    let mut child = Command::new(&ctx.mote_bin)
+        .args(["serve", "--stdio"])
+        .current_dir(&ctx.project_dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn mote serve");
+    let mut stdin = child.stdin.take().unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut request = |line: &str| -> serde_json::Value {
+        writeln!(stdin, "{}", line).unwrap();
+        let mut response = String::new();
+        stdout.read_line(&mut response).unwrap();
+        serde_json::from_str(&response).unwrap()
+    };
+
+    let status = request(r#"{"id":1,"cmd":"status"}"#);
+    assert_eq!(status["id"], 1);
+    assert_eq!(status["ok"], true);
+    assert!(status["result"]["snapshot"]
+        .as_str()
+        .unwrap()
+        .starts_with(&first));
+    assert_eq!(status["result"]["modified"], serde_json::json!([]));
+
+    ctx.write_file("src/main.rs", "fn main() { run(); }\n");
+    ctx.write_file("new.txt", "new\n");
+    fs::remove_file(ctx.project_dir.join("notes.txt")).unwrap();
+    let status = request(r#"{"cmd":"status"}"#);
+    assert_eq!(status["id"], serde_json::Value::Null);
+    assert_eq!(status["result"]["added"], serde_json::json!(["new.txt"]));
+    assert_eq!(
+        status["result"]["modified"],
+        serde_json::json!(["src/main.rs"])
+    );
+    assert_eq!(
+        status["result"]["deleted"],
+        serde_json::json!(["notes.txt"])
+    );
+
+    let diff = request(r#"{"cmd":"diff","file":"src/main.rs"}"#);
+    assert_eq!(diff["result"]["status"], "modified");
+    let text = diff["result"]["diff"].as_str().unwrap();
+    assert!(text.contains("-fn main() {}"));
+    assert!(text.contains("+fn main() { run(); }"));
+
+    let cat = request(r#"{"cmd":"cat","snapshot":"latest","path":"src/main.rs"}"#);
+    assert_eq!(cat["result"]["content"], "fn main() {}\n");
+
+    // A snapshot taken by another process is picked up by the warm cache
+    let output = ctx.run_mote(&["snap", "create", "-m", "second"]);
+    let second = extract_snapshot_id(&output);
+    let list = request(r#"{"cmd":"list"}"#);
+    assert_eq!(list["result"][0]["short_id"], second.as_str());
+    assert_eq!(list["result"][1]["message"], "first");
+    let diff = request(r#"{"cmd":"diff","file":"src/main.rs"}"#);
+    assert_eq!(diff["result"]["status"], "unchanged");
+    let cat = request(&format!(
+        r#"{{"cmd":"cat","snapshot":"{}","path":"notes.txt"}}"#,
+        first
+    ));
+    assert_eq!(cat["result"]["content"], "keep\n");
+
+    // Malformed and unsupported requests get structured errors
+    let error = request("not json");
+    assert_eq!(error["ok"], false);
+    assert_eq!(error["error"]["code"], "parse_error");
+    let error = request(r#"{"id":"x","cmd":"restore"}"#);
+    assert_eq!(error["id"], "x");
+    assert_eq!(error["error"]["code"], "invalid_request");
+    let error = request(r#"{"cmd":"diff"}"#);
+    assert_eq!(error["error"]["code"], "invalid_request");
+    let error = request(r#"{"cmd":"cat","snapshot":"ffffffff","path":"notes.txt"}"#);
+    assert_eq!(error["error"]["code"], "not_found");
+    let error = request(r#"{"cmd":"cat","snapshot":"latest","path":"notes.txt"}"#);
+    assert_eq!(error["error"]["code"], "not_found");
+
+    // Exits cleanly on EOF
+    drop(stdin);
+    assert!(child.wait().unwrap().success());
+}
+
+#[test]
+fn test_show_stat() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file("a.txt", "one\ntwo\nthree\n");
+    ctx.write_file("b.txt", "gone\n");
+    fs::write(ctx.project_dir.join("bin.dat"), b"\x00\x01\x02").unwrap();
+    let output = ctx.run_mote(&["snap", "create", "-m", "first"]);
+    let first = extract_snapshot_id(&output);
+
+    ctx.write_file("a.txt", "one\nTWO\nthree\nfour\n");
+    fs::remove_file(ctx.project_dir.join("b.txt")).unwrap();
+    ctx.write_file("c.txt", "new\nfile\n");
+    fs::write(ctx.project_dir.join("bin.dat"), b"\x00\x03").unwrap();
+    let output = ctx.run_mote(&["snap", "create", "-m", "second"]);
+    let second = extract_snapshot_id(&output);
+
+    let output = ctx.run_mote(&["snap", "show", &second, "--stat"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(&format!("Changes (vs {}):", first)));
+    assert!(stdout.contains("  a.txt   | 3 ++-\n"));
+    assert!(stdout.contains("  b.txt   | 1 -\n"));
+    assert!(stdout.contains("  bin.dat | bin\n"));
+    assert!(stdout.contains("  c.txt   | 2 ++\n"));
+    assert!(stdout.contains("4 files changed, 4 insertions(+), 2 deletions(-)"));
+    assert!(!stdout.contains("bytes)"));
+
+    // The oldest snapshot counts every file as added
+    let output = ctx.run_mote(&["snap", "show", &first, "--stat"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Changes (initial snapshot):"));
+    let lines: Vec<String> = stdout
+        .lines()
+        .map(|l| l.split_whitespace().collect::<Vec<_>>().join(" "))
+        .collect();
+    assert!(lines.iter().any(|l| l == "a.txt | 3 +++"));
+    assert!(lines.iter().any(|l| l == "bin.dat | bin"));
+    assert!(stdout.contains("4 files changed"));
+    assert!(stdout.contains(" 0 deletions(-)"));
+}
+
+#[test]
+fn test_show_json() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file("test.txt", "content");
+    let output = ctx.run_mote(&["snap", "create", "-m", "json me"]);
+    let short_id = extract_snapshot_id(&output);
+
+    let output = ctx.run_mote(&["snap", "show", &short_id, "--json"]);
+    assert!(output.status.success());
+    let snapshot: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(snapshot["id"].as_str().unwrap().starts_with(&short_id));
+    assert_eq!(snapshot["message"], "json me");
+    let file = snapshot["files"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|f| f["path"] == "test.txt")
+        .unwrap();
+    assert_eq!(file["size"], 7);
+    assert_eq!(file["hash"].as_str().unwrap().len(), 64);
+
+    let output = ctx.run_mote(&["snap", "show", "ffffffff", "--json"]);
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Snapshot not found"));
+
+    let output = ctx.run_mote(&["snap", "show", &short_id, "--json", "--stat"]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_snapshot_aborts_on_large_tree() {
+    let ctx = TestContext::new();
+    ctx.run_mote_ctx(&["init"]);
+    for i in 0..5 {
+        ctx.write_file(&format!("file{}.txt", i), "content");
+    }
+    fs::write(
+        ctx.config_dir.join("config.toml"),
+        "[snapshot]\nmax_files = 3\n",
+    )
+    .unwrap();
+    let objects_dir = ctx.project_dir.join(".mote/objects");
+
+    let output = ctx.run_mote_ctx(&["snap", "create", "-m", "too big"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("more than 3 files"));
+    assert!(stderr.contains("--force-large-root"));
+    assert_eq!(count_objects(&objects_dir), 0);
+
+    // Shell hooks skip quietly
+    let output = ctx.run_mote_ctx(&["snap", "create", "--auto"]);
+    assert!(output.status.success());
+    assert!(output.stderr.is_empty());
+    assert_eq!(count_objects(&objects_dir), 0);
+
+    let output = ctx.run_mote_ctx(&["snap", "create", "--force-large-root"]);
+    assert!(output.status.success());
+    assert!(count_objects(&objects_dir) > 0);
+}
+
+#[test]
+fn test_snapshot_refuses_filesystem_root() {
+    let ctx = TestContext::new();
+    let output = ctx.run_mote_ctx(&["--project-root", "/", "snap", "create"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Refusing to use / as project root"));
+
+    let output = ctx.run_mote_ctx(&[
+        "-c",
+        "rootproj/default",
+        "context",
+        "new",
+        "default",
+        "--cwd",
+        "/",
+    ]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Refusing"));
+    assert!(!ctx.config_dir.join("projects/rootproj").exists());
+}
+
+#[test]
+fn test_show_file_content() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file("src/main.rs", "fn main() {}");
+    ctx.write_file("odd:name.txt", "colon");
+    fs::write(ctx.project_dir.join("data.bin"), b"\x00\xff\x01").unwrap();
+    let output = ctx.run_mote(&["snap", "create"]);
+    let id = extract_snapshot_id(&output);
+    ctx.write_file("src/main.rs", "changed");
+
+    let output = ctx.run_mote(&["snap", "show", &format!("{}:src/main.rs", id)]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"fn main() {}");
+
+    let output = ctx.run_mote(&["snap", "show", &format!("{}:data.bin", id)]);
+    assert_eq!(output.stdout, b"\x00\xff\x01");
+
+    let output = ctx.run_mote(&["snap", "show", &id, "--path", "odd:name.txt"]);
+    assert_eq!(output.stdout, b"colon");
+
+    let output = ctx.run_mote(&["snap", "show", &format!("{}:missing.rs", id)]);
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains(&format!("File not found in snapshot {}: missing.rs", id)));
+
+    let output = ctx.run_mote(&["snap", "show", &format!("{}:src/main.rs", id), "--json"]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_diff_summary_only() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file("a.txt", "hello");
+    ctx.write_file("b.txt", "bye!");
+    let output = ctx.run_mote(&["snap", "create", "-m", "first"]);
+    let first = extract_snapshot_id(&output);
+
+    ctx.write_file("a.txt", "hello world");
+    fs::remove_file(ctx.project_dir.join("b.txt")).unwrap();
+    ctx.write_file("c.txt", "new");
+    let output = ctx.run_mote(&["snap", "create", "-m", "second"]);
+    let second = extract_snapshot_id(&output);
+
+    // Metadata only: works with every object gone
+    let objects_dir = ctx.project_dir.join(".mote/objects");
+    fs::remove_dir_all(&objects_dir).unwrap();
+    fs::create_dir(&objects_dir).unwrap();
+
+    let output = ctx.run_mote(&["snap", "diff", &first, &second, "--summary-only"]);
+    assert!(output.status.success());
+    assert!(output.stderr.is_empty());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("1 added, 1 modified, 1 deleted"));
+
+    let output = ctx.run_mote(&["snap", "diff", &first, &second, "--summary-only", "--json"]);
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(summary["added"], 1);
+    assert_eq!(summary["modified"], 1);
+    assert_eq!(summary["deleted"], 1);
+    assert_eq!(summary["bytes_added"], 9);
+    assert_eq!(summary["bytes_removed"], 4);
+
+    // Against the working directory
+    ctx.write_file("a.txt", "hi");
+    let output = ctx.run_mote(&["snap", "diff", &second, "--summary-only", "--json"]);
+    assert!(output.stderr.is_empty());
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(summary["modified"], 1);
+    assert_eq!(summary["bytes_removed"], 9);
+
+    let output = ctx.run_mote(&["snap", "diff", &second, "--summary-only", "--exit-code"]);
+    assert_eq!(output.status.code(), Some(1));
+    ctx.write_file("a.txt", "hello world");
+    let output = ctx.run_mote(&["snap", "diff", &second, "--summary-only", "--exit-code"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("0 added, 0 modified, 0 deleted"));
+    let output = ctx.run_mote(&["snap", "diff", &second, "--name-only", "--exit-code"]);
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn test_diff_json() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file("a.txt", "one\ntwo\nthree\n");
+    ctx.write_file("b.txt", "gone\n");
+    let output = ctx.run_mote(&["snap", "create", "-m", "first"]);
+    let first = extract_snapshot_id(&output);
+
+    ctx.write_file("a.txt", "one\n2\nthree\n");
+    fs::remove_file(ctx.project_dir.join("b.txt")).unwrap();
+    ctx.write_file("c.txt", "new\n");
+    let output = ctx.run_mote(&["snap", "create", "-m", "second"]);
+    let second = extract_snapshot_id(&output);
+
+    let check = |changes: &serde_json::Value| {
+        let changes = changes.as_array().unwrap();
+        let summary: Vec<(&str, &str)> = changes
+            .iter()
+            .map(|c| (c["path"].as_str().unwrap(), c["status"].as_str().unwrap()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("a.txt", "modified"),
+                ("b.txt", "deleted"),
+                ("c.txt", "added")
+            ]
+        );
+        assert_eq!(changes[0]["old_size"], 14);
+        assert_eq!(changes[0]["new_size"], 12);
+        assert_ne!(changes[0]["old_hash"], changes[0]["new_hash"]);
+        assert!(changes[1]["new_hash"].is_null());
+        assert!(changes[2]["old_hash"].is_null());
+    };
+
+    let output = ctx.run_mote(&["snap", "diff", &first, &second, "--json"]);
+    assert!(output.status.success());
+    let changes: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    check(&changes);
+    assert!(changes[0].get("hunks").is_none());
+
+    // Same changes, seen from the working directory, with hunks
+    let output = ctx.run_mote(&["snap", "diff", &first, "--json", "--with-hunks", "-U", "0"]);
+    assert!(output.status.success());
+    let changes: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    check(&changes);
+    let hunk = &changes[0]["hunks"][0];
+    assert_eq!(hunk["old_start"], 2);
+    assert_eq!(hunk["old_lines"], 1);
+    assert_eq!(
+        hunk["lines"],
+        serde_json::json!([
+            {"tag": "delete", "old_line": 2, "new_line": null, "text": "two"},
+            {"tag": "insert", "old_line": null, "new_line": 2, "text": "2"},
+        ])
+    );
+    assert_eq!(changes[2]["hunks"][0]["new_lines"], 1);
+
+    // --output receives the JSON instead of a patch
+    let out_dir = TempDir::new().unwrap();
+    let out_file = out_dir.path().join("changes.json");
+    let out_arg = out_file.to_string_lossy().to_string();
+    let output = ctx.run_mote(&["snap", "diff", &first, &second, "--json", "-o", &out_arg]);
+    assert!(output.status.success());
+    let written: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&out_file).unwrap()).unwrap();
+    check(&written);
+
+    let output = ctx.run_mote(&["snap", "diff", &second, "--json"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "[]");
+
+    let output = ctx.run_mote(&["snap", "diff", &first, "--with-hunks"]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_diff_range_syntax() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file("a.txt", "one\n");
+    let first = extract_snapshot_id(&ctx.run_mote(&["snap", "create"]));
+    ctx.write_file("a.txt", "two\n");
+    let second = extract_snapshot_id(&ctx.run_mote(&["snap", "create"]));
+    ctx.write_file("a.txt", "three\n");
+
+    let diff = |args: &[&str]| {
+        let mut full_args = vec!["snap", "diff"];
+        full_args.extend_from_slice(args);
+        ctx.run_mote(&full_args)
+    };
+
+    let range = format!("{}..{}", first, second);
+    let output = diff(&[&range]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, diff(&[&first, &second]).stdout);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("-one\n+two\n"));
+
+    let open_range = format!("{}..", second);
+    let output = diff(&[&open_range]);
+    assert_eq!(output.stdout, diff(&[&second]).stdout);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("-two\n+three\n"));
+
+    let output = diff(&[&format!("..{}", second)]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("a starting snapshot is required"));
+
+    let output = diff(&[&range, &second]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_diff_range_last_changed() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file("a.txt", "a1\n");
+    ctx.write_file("b.txt", "b1\n");
+    let base = extract_snapshot_id(&ctx.run_mote(&["snap", "create"]));
+    let mut ids = Vec::new();
+    for (file, content) in [
+        ("a.txt", "a2\n"),
+        ("b.txt", "b2\n"),
+        ("c.txt", "c1\n"),
+        ("a.txt", "a3\n"),
+    ] {
+        ctx.write_file(file, content);
+        ids.push(extract_snapshot_id(&ctx.run_mote(&["snap", "create"])));
+    }
+    let latest = &ids[3];
+
+    let output = ctx.run_mote(&["snap", "diff", "--range", "4", "--name-only"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains(&format!("M\ta.txt\t{} (", latest)),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains(&format!("M\tb.txt\t{} (", ids[1])),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains(&format!("A\tc.txt\t{} (", ids[2])),
+        "{}",
+        stdout
+    );
+
+    // The same endpoints given by ID, here with the patch and JSON forms
+    let range = format!("{}..{}", base, latest);
+    let output = ctx.run_mote(&["snap", "diff", "--range", &range]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(&format!("# b.txt: last changed in {} (", ids[1])));
+    assert!(stdout.contains("-a1\n+a3\n"));
+
+    let output = ctx.run_mote(&["snap", "diff", "--range", &range, "--json"]);
+    let changes: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let last_changed: Vec<&str> = changes
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| c["last_changed"]["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(last_changed, [latest.as_str(), &ids[1], &ids[2]]);
+
+    // Only the last two snapshots: b.txt didn't change between them
+    let output = ctx.run_mote(&["snap", "diff", "--range", "2", "--name-only"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("b.txt"));
+    assert!(stdout.contains(&format!("A\tc.txt\t{} (", ids[2])));
+
+    let output = ctx.run_mote(&["snap", "diff", "--range", "0"]);
+    assert!(!output.status.success());
+    let backwards = format!("{}..{}", latest, base);
+    let output = ctx.run_mote(&["snap", "diff", "--range", &backwards]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("is newer than"));
+}
+
+#[test]
+fn test_diff_output_file_handling() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file("a.txt", "one\n");
+    let id = extract_snapshot_id(&ctx.run_mote(&["snap", "create"]));
+    ctx.write_file("a.txt", "two\n");
+
+    // Parent directories are created
+    let output = ctx.run_mote(&["snap", "diff", &id, "-o", "review/changes.patch"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(ctx.read_file("review/changes.patch").contains("+two\n"));
+    assert!(String::from_utf8_lossy(&output.stderr).is_empty());
+
+    // An existing file is left alone without --force-output
+    ctx.write_file("notes.diff", "keep me\n");
+    let output = ctx.run_mote(&["snap", "diff", &id, "-o", "notes.diff"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--force-output"));
+    assert_eq!(ctx.read_file("notes.diff"), "keep me\n");
+
+    let output = ctx.run_mote(&["snap", "diff", &id, "-o", "notes.diff", "--force-output"]);
+    assert!(output.status.success());
+    assert!(ctx.read_file("notes.diff").contains("+two\n"));
+
+    let output = ctx.run_mote(&["snap", "diff", &id, "-o", "changes.txt"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("doesn't end in .diff or .patch"));
+    let entries: Vec<_> = fs::read_dir(&ctx.project_dir)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+        .filter(|name| name.ends_with(".tmp"))
+        .collect();
+    assert!(entries.is_empty(), "{:?}", entries);
+}
+
+#[test]
+fn test_diff_word_diff() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file(
+        "lock.json",
+        "{\"name\": \"mote\", \"version\": \"0.2.3\"}\n",
+    );
+    let first = extract_snapshot_id(&ctx.run_mote(&["snap", "create"]));
+    ctx.write_file(
+        "lock.json",
+        "{\"name\": \"mote\", \"version\": \"0.2.4\"}\n",
+    );
+    let second = extract_snapshot_id(&ctx.run_mote(&["snap", "create"]));
+
+    let output = ctx.run_mote(&["snap", "diff", &first, &second, "--word-diff"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains(
+            "@@ -1 +1 @@\n {\"name\": \"mote\", \"version\": [-\"0.2.3\"}-]{+\"0.2.4\"}+}\n"
+        ),
+        "{}",
+        stdout
+    );
+    assert!(!stdout.contains("\n-{"));
+
+    let output = ctx.run_mote(&[
+        "snap",
+        "diff",
+        &first,
+        &second,
+        "--word-diff",
+        "--git-format",
+    ]);
+    assert!(!output.status.success());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_diff_external_tool() {
+    let ctx = TestContext::new();
+    ctx.run_mote_ctx(&["init"]);
+    ctx.write_file("notes.md", "old\n");
+    ctx.write_file("gone.txt", "bye\n");
+    let first = extract_snapshot_id(&ctx.run_mote_ctx(&["snap", "create"]));
+    ctx.write_file("notes.md", "new\n");
+    fs::remove_file(ctx.project_dir.join("gone.txt")).unwrap();
+
+    // Records where its inputs lived so the test can check they're removed
+    let record = TempDir::new().unwrap();
+    let dirs = record.path().join("dirs");
+    let tool = format!(
+        "sh -c 'echo \"$(basename \"$0\"): $(cat \"$0\") -> $(cat \"$1\")\"; dirname \"$0\" >> {}; exit 1'",
+        dirs.display()
+    );
+
+    let output = ctx.run_mote_ctx(&["snap", "diff", &first, "--tool", &tool]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("notes.md: old -> new"), "{}", stdout);
+    assert!(stdout.contains("gone.txt: bye -> \n"));
+    assert!(!stdout.contains("diff --mote"));
+    for dir in fs::read_to_string(&dirs).unwrap().lines() {
+        assert!(!Path::new(dir).exists(), "{} was left behind", dir);
+    }
+
+    // The configured tool is used without --tool; --name-only bypasses it
+    fs::write(
+        ctx.config_dir.join("config.toml"),
+        format!(
+            "[diff]\nexternal = \"{}\"\n",
+            tool.replace('\\', "\\\\").replace('"', "\\\"")
+        ),
+    )
+    .unwrap();
+    let output = ctx.run_mote_ctx(&["snap", "diff", &first]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("notes.md: old -> new"));
+    let output = ctx.run_mote_ctx(&["snap", "diff", &first, "--name-only"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("old -> new"));
+    assert!(stdout.contains("notes.md"));
+
+    // A tool that fails falls back to the built-in diff
+    let output = ctx.run_mote_ctx(&["snap", "diff", &first, "--tool", "sh -c 'exit 3'"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("diff --mote a/notes.md b/notes.md"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("-old\n+new\n"));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Diff tool 'sh' failed for notes.md"));
+}
+
+#[test]
+fn test_diff_ignore_whitespace() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file("a.py", "def f():\n    return 1\n");
+    ctx.write_file("b.py", "x = 1\n");
+    let output = ctx.run_mote(&["snap", "create", "-m", "first"]);
+    let first = extract_snapshot_id(&output);
+
+    // A formatter run: reindent a.py, add blank lines to b.py
+    ctx.write_file("a.py", "def f():\n  return 1\n");
+    ctx.write_file("b.py", "\nx = 1\n\n");
+
+    let output = ctx.run_mote(&["snap", "diff", &first, "--name-only"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("M\ta.py") && stdout.contains("M\tb.py"));
+
+    let output = ctx.run_mote(&["snap", "diff", &first, "--name-only", "-w"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("a.py"), "{}", stdout);
+    assert!(stdout.contains("M\tb.py"));
+
+    let output = ctx.run_mote(&[
+        "snap",
+        "diff",
+        &first,
+        "-w",
+        "--ignore-blank-lines",
+        "--exit-code",
+    ]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("@@"));
+
+    let output = ctx.run_mote(&["snap", "create", "-m", "formatted"]);
+    let formatted = extract_snapshot_id(&output);
+    let output = ctx.run_mote(&["snap", "diff", &first, &formatted, "-w", "--quiet"]);
+    assert_eq!(output.status.code(), Some(1));
+    let output = ctx.run_mote(&[
+        "snap",
+        "diff",
+        &first,
+        &formatted,
+        "-w",
+        "--ignore-blank-lines",
+        "--quiet",
+    ]);
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn test_diff_output_applies_with_git_apply() {
+    if Command::new("git").arg("--version").output().is_err() {
+        eprintln!("git not found; skipping");
+        return;
+    }
+    let before = [
+        ("a.txt", "one\ntwo\n"),
+        ("b.txt", "bye\n"),
+        ("d.txt", "end\n"),
+    ];
+    let after = [
+        ("a.txt", "one\n2\n"),
+        ("c.txt", "no newline"),
+        ("d.txt", "end"),
+        ("src/e.txt", "nested\n"),
+    ];
+
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    for (path, content) in before {
+        ctx.write_file(path, content);
+    }
+    let output = ctx.run_mote(&["snap", "create", "-m", "before"]);
+    let first = extract_snapshot_id(&output);
+    fs::remove_file(ctx.project_dir.join("b.txt")).unwrap();
+    fs::create_dir_all(ctx.project_dir.join("src")).unwrap();
+    for (path, content) in after {
+        ctx.write_file(path, content);
+    }
+    let output = ctx.run_mote(&["snap", "create", "-m", "after"]);
+    let second = extract_snapshot_id(&output);
+
+    let patches = TempDir::new().unwrap();
+    let cases: [&[&str]; 3] = [
+        &[&first, &second],
+        &[&first, &second, "--git-format"],
+        &[&first, "--git-format"],
+    ];
+    for (i, args) in cases.iter().enumerate() {
+        let patch = patches.path().join(format!("{}.patch", i));
+        let patch_arg = patch.to_string_lossy().to_string();
+        let mut diff_args = vec!["snap", "diff", "-o", &patch_arg];
+        diff_args.extend_from_slice(args);
+        assert!(ctx.run_mote(&diff_args).status.success());
+
+        let repo = TempDir::new().unwrap();
+        for (path, content) in before {
+            fs::write(repo.path().join(path), content).unwrap();
+        }
+        let git = |git_args: &[&str]| {
+            Command::new("git")
+                .args(git_args)
+                .current_dir(repo.path())
+                .output()
+                .unwrap()
+        };
+        git(&["init", "-q"]);
+        let check = git(&["apply", "--check", &patch_arg]);
+        assert!(
+            check.status.success(),
+            "{:?}: {}\n{}",
+            args,
+            String::from_utf8_lossy(&check.stderr),
+            fs::read_to_string(&patch).unwrap()
+        );
+        assert!(git(&["apply", &patch_arg]).status.success());
+        for (path, content) in after {
+            assert_eq!(fs::read_to_string(repo.path().join(path)).unwrap(), content);
+        }
+        assert!(!repo.path().join("b.txt").exists());
+    }
+
+    let patch = fs::read_to_string(patches.path().join("1.patch")).unwrap();
+    assert!(patch.contains("diff --git a/c.txt b/c.txt\nnew file mode 100644\n--- /dev/null\n"));
+    assert!(patch.contains("deleted file mode 100644\n--- a/b.txt\n+++ /dev/null\n"));
+    assert!(patch.contains("+end\n\\ No newline at end of file\n"));
+}
+
+#[test]
+fn test_diff_stat() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file("a.txt", "one\ntwo\nthree\n");
+    ctx.write_file("b.txt", "gone\n");
+    let output = ctx.run_mote(&["snap", "create", "-m", "first"]);
+    let first = extract_snapshot_id(&output);
+
+    ctx.write_file("a.txt", "one\n2\nthree\nfour\n");
+    fs::remove_file(ctx.project_dir.join("b.txt")).unwrap();
+    ctx.write_file("c.txt", "new\n");
+    let output = ctx.run_mote(&["snap", "create", "-m", "second"]);
+    let second = extract_snapshot_id(&output);
+
+    let expected_lines = ["a.txt | 3 ++-", "b.txt | 1 -", "c.txt | 1 +"];
+    let expected_summary = "3 files changed, 3 insertions(+), 2 deletions(-)";
+
+    let output = ctx.run_mote(&["snap", "diff", &first, &second, "--stat"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    for line in expected_lines {
+        assert!(stdout.contains(line), "missing {:?} in:\n{}", line, stdout);
+    }
+    assert!(stdout.contains(expected_summary));
+    assert!(!stdout.contains("@@"));
+
+    // Same changes, seen from the working directory
+    let output = ctx.run_mote(&["snap", "diff", &first, "--stat"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    for line in expected_lines {
+        assert!(stdout.contains(line), "missing {:?} in:\n{}", line, stdout);
+    }
+    assert!(stdout.contains(expected_summary));
+
+    let output = ctx.run_mote(&["snap", "diff", &second, "--stat", "--exit-code"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("No changes"));
+
+    let output = ctx.run_mote(&["snap", "diff", &first, "--stat", "--name-only"]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_diff_paths() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file("src/main.rs", "fn main() {}\n");
+    ctx.write_file("src2/lib.rs", "lib\n");
+    ctx.write_file("docs/a.md", "a\n");
+    let output = ctx.run_mote(&["snap", "create", "-m", "first"]);
+    let first = extract_snapshot_id(&output);
+
+    ctx.write_file("src/main.rs", "fn main() { run() }\n");
+    ctx.write_file("src/new.rs", "new\n");
+    ctx.write_file("src2/lib.rs", "lib2\n");
+    ctx.write_file("docs/a.md", "b\n");
+    let output = ctx.run_mote(&["snap", "create", "-m", "second"]);
+    let second = extract_snapshot_id(&output);
+
+    let output = ctx.run_mote(&["snap", "diff", &first, &second, "--name-only", "--", "src/"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("M\tsrc/main.rs"));
+    assert!(stdout.contains("A\tsrc/new.rs"));
+    assert!(!stdout.contains("src2"));
+    assert!(!stdout.contains("docs"));
+
+    // Working directory, with the filter applied to --output as well
+    let output = ctx.run_mote(&[
+        "snap",
+        "diff",
+        &first,
+        "-o",
+        "out.diff",
+        "--",
+        "docs",
+        "src2/lib.rs",
+    ]);
+    assert!(output.status.success());
+    let diff = ctx.read_file("out.diff");
+    assert!(diff.contains("+b"));
+    assert!(diff.contains("+lib2"));
+    assert!(!diff.contains("main.rs"));
+
+    let output = ctx.run_mote(&["snap", "diff", &first, "--name-only", "--", "missing"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("'missing' did not match any files"));
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("\t"));
+}
+
+#[test]
+fn test_diff_output_is_sorted_and_deterministic() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    for name in ["m.txt", "b/x.txt", "z.txt", "a.txt", "k/y.txt", "q.txt"] {
+        ctx.write_file(name, "old\n");
+    }
+    let output = ctx.run_mote(&["snap", "create", "-m", "first"]);
+    let first = extract_snapshot_id(&output);
+
+    for name in ["m.txt", "b/x.txt", "z.txt"] {
+        ctx.write_file(name, "new\n");
+    }
+    fs::remove_file(ctx.project_dir.join("a.txt")).unwrap();
+    fs::remove_file(ctx.project_dir.join("q.txt")).unwrap();
+    ctx.write_file("c.txt", "added\n");
+    ctx.write_file("y.txt", "added\n");
+    let output = ctx.run_mote(&["snap", "create", "-m", "second"]);
+    let second = extract_snapshot_id(&output);
+
+    for args in [
+        vec!["snap", "diff", &first, &second],
+        vec!["snap", "diff", &first],
+    ] {
+        let runs: Vec<Vec<u8>> = (0..3).map(|_| ctx.run_mote(&args).stdout).collect();
+        assert!(runs.iter().all(|run| *run == runs[0]));
+    }
+
+    let output = ctx.run_mote(&["snap", "diff", &first, &second, "--name-only"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let names: Vec<&str> = stdout.lines().filter(|l| l.contains('\t')).collect();
+    assert_eq!(
+        names,
+        vec![
+            "D\ta.txt",
+            "M\tb/x.txt",
+            "A\tc.txt",
+            "M\tm.txt",
+            "D\tq.txt",
+            "A\ty.txt",
+            "M\tz.txt",
+        ]
+    );
+}
+
+#[test]
+fn test_status_lists_changes_since_latest_snapshot() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file("keep.txt", "same");
+    ctx.write_file("edit.txt", "before");
+    ctx.write_file("gone.txt", "bye");
+
+    let output = ctx.run_mote(&["status"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("No snapshots yet; 4 file(s) would be tracked"),
+        "{}",
+        stdout
+    );
+
+    ctx.run_mote(&["snap", "create"]);
+    let output = ctx.run_mote(&["status"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("No changes since snapshot"));
+
+    ctx.write_file("edit.txt", "after, and longer");
+    ctx.write_file("new.txt", "hello");
+    fs::remove_file(ctx.project_dir.join("gone.txt")).unwrap();
+    let output = ctx.run_mote(&["status", "--porcelain"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "M\tedit.txt\nD\tgone.txt\nA\tnew.txt\n"
+    );
+
+    let output = ctx.run_mote(&["status"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("1 added, 1 modified, 1 deleted"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn test_watch_once_snapshots_after_a_change() {
+    use std::io::{BufRead, BufReader};
+    use std::process::Stdio;
+    use std::time::{Duration, Instant};
+
+    let ctx = TestContext::new();
+    ctx.run_mote_ctx(&["init"]);
+    fs::write(
+        ctx.config_dir.join("config.toml"),
+        "[watch]\npoll_interval_ms = 50\ndebounce_secs = 0\nmin_interval_secs = 0\n",
+    )
+    .unwrap();
+    ctx.write_file("a.txt", "before");
+
+    let config_dir = ctx.config_dir.to_string_lossy().to_string();
+    let mut child = Command::new(&ctx.mote_bin)
+        .args(["--config-dir", &config_dir, "watch", "--once"])
+        .current_dir(&ctx.project_dir)
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut line = String::new();
+    stdout.read_line(&mut line).unwrap();
+    assert!(line.contains("Watching"), "{}", line);
+
+    ctx.write_file("a.txt", "after, edited by an editor");
+    let deadline = Instant::now() + Duration::from_secs(20);
+    while child.try_wait().unwrap().is_none() {
+        assert!(Instant::now() < deadline, "watch --once did not exit");
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    line.clear();
+    stdout.read_line(&mut line).unwrap();
+    assert!(line.contains("Created snapshot"), "{}", line);
+
+    let output = ctx.run_mote_ctx(&["snap", "list", "--trigger", "watch"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("watch"));
+}
+
+#[test]
+fn test_completions_for_every_shell() {
+    let ctx = TestContext::new();
+    for shell in ["bash", "zsh", "fish", "powershell"] {
+        let output = ctx.run_mote(&["completions", shell]);
+        assert!(output.status.success(), "{:?}", output);
+        let script = String::from_utf8_lossy(&output.stdout);
+        assert!(script.starts_with("#"), "{}", script);
+        assert!(script.contains("mote completions"), "{}", script);
+        assert!(script.contains("prune-backups"), "{}", script);
+        assert!(
+            script.contains("snap list --oneline --limit 50"),
+            "{}",
+            script
+        );
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_bash_completion_offers_subcommands_and_snapshot_ids() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file("a.txt", "a");
+    let id = extract_snapshot_id(&ctx.run_mote(&["snap", "create"]));
+    let script = ctx.project_dir.join("mote.bash");
+    fs::write(&script, ctx.run_mote(&["completions", "bash"]).stdout).unwrap();
+
+    let complete = |words: &str| {
+        let path = format!(
+            "{}:{}",
+            ctx.mote_bin.parent().unwrap().display(),
+            std::env::var("PATH").unwrap_or_default()
+        );
+        let output = Command::new("bash")
+            .arg("-c")
+            .arg(format!(
+                "source '{}'; COMP_WORDS=({}); COMP_CWORD=$((${{#COMP_WORDS[@]}} - 1)); \
+                 _mote; echo \"${{COMPREPLY[@]}}\"",
+                script.display(),
+                words
+            ))
+            .env("PATH", path)
+            .current_dir(&ctx.project_dir)
+            .output()
+            .unwrap();
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    };
+    assert_eq!(complete("mote snap res"), "restore");
+    assert_eq!(complete("mote snap fsck --fu"), "--full");
+    assert_eq!(complete(&format!("mote snap restore {}", &id[..4])), id);
+}
+
+#[test]
+fn test_concurrent_run_is_locked_out() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file("a.txt", "content");
+
+    // Stand in for another mote process holding the storage lock
+    let lock = fs::File::create(ctx.project_dir.join(".mote/index.lock")).unwrap();
+    lock.lock().unwrap();
+
+    let output = ctx.run_mote(&["snap", "create", "--auto"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(snapshot_file_count(&ctx), 0);
+
+    let output = ctx.run_mote(&["snap", "create"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Another mote process is running"),
+        "{}",
+        stderr
+    );
+
+    drop(lock);
+    let output = ctx.run_mote(&["snap", "create"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(snapshot_file_count(&ctx), 1);
+}
+
+fn snapshot_file_count(ctx: &TestContext) -> usize {
+    fs::read_dir(ctx.project_dir.join(".mote/snapshots"))
+        .map(|entries| {
+            entries
+                .filter(|e| {
+                    e.as_ref()
+                        .is_ok_and(|e| e.path().extension().is_some_and(|x| x == "json"))
+                })
+                .count()
+        })
+        .unwrap_or(0)
+}
+
+#[cfg(unix)]
+#[test]
+fn test_fix_perms() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file("run.sh", "#!/bin/sh\n");
+    ctx.write_file("notes.txt", "notes");
+    ctx.write_file("untracked.txt", "x");
+    // Modes aren't captured yet, so record them in a snapshot by hand
+    let file = |path: &str, mode: Option<&str>| serde_json::json!({"path": path, "hash": "0".repeat(64), "size": 1, "mode": mode});
+    let snapshot = serde_json::json!({
+        "id": "f1f1f1f1",
+        "timestamp": "2024-05-01T09:00:00Z",
+        "files": [
+            file("run.sh", Some("100755")),
+            file("notes.txt", Some("644")),
+            file("gone.txt", Some("644")),
+            file("legacy.txt", None),
+        ],
+    });
+    fs::write(
+        ctx.project_dir.join(".mote/snapshots/f1f1f1f1.json"),
+        snapshot.to_string(),
+    )
+    .unwrap();
+
+    let mode_of = |path: &str| {
+        fs::metadata(ctx.project_dir.join(path))
+            .unwrap()
+            .permissions()
+            .mode()
+            & 0o777
+    };
+    let set_mode = |path: &str, mode: u32| {
+        fs::set_permissions(ctx.project_dir.join(path), fs::Permissions::from_mode(mode)).unwrap()
+    };
+    set_mode("run.sh", 0o600);
+    set_mode("notes.txt", 0o666);
+    set_mode("untracked.txt", 0o600);
+
+    let output = ctx.run_mote(&["snap", "fix-perms", "--dry-run"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("600 -> 755  run.sh"), "{}", stdout);
+    assert!(stdout.contains("666 -> 644  notes.txt"));
+    assert!(stdout.contains("missing  gone.txt"));
+    assert!(stdout.contains("Would fix 2 file(s)"));
+    assert!(stdout.contains("1 file(s) have no recorded mode"));
+    assert_eq!(mode_of("run.sh"), 0o600);
+
+    let output = ctx.run_mote(&["snap", "fix-perms", "f1f1"]);
+    assert!(output.status.success());
+    assert_eq!(mode_of("run.sh"), 0o755);
+    assert_eq!(mode_of("notes.txt"), 0o644);
+    assert_eq!(mode_of("untracked.txt"), 0o600);
+    assert!(!ctx.project_dir.join("gone.txt").exists());
+    assert_eq!(ctx.read_file("run.sh"), "#!/bin/sh\n");
+
+    let output = ctx.run_mote(&["snap", "fix-perms"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Fixed 0 file(s)"));
+}
+
+#[test]
+fn test_apply_patch() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file("a.txt", "one\ntwo\nthree\n");
+    ctx.write_file("b.txt", "bye\n");
+    ctx.write_file("c.txt", "keep\n");
+    let output = ctx.run_mote(&["snap", "create", "-m", "before"]);
+    let first = extract_snapshot_id(&output);
+    ctx.write_file("a.txt", "one\n2\nthree\n");
+    fs::remove_file(ctx.project_dir.join("b.txt")).unwrap();
+    ctx.write_file("c.txt", "kept\n");
+    ctx.write_file("new.txt", "hello");
+    let output = ctx.run_mote(&["snap", "create", "-m", "after"]);
+    let second = extract_snapshot_id(&output);
+
+    let patches = TempDir::new().unwrap();
+    let patch = patches.path().join("changes.patch");
+    let patch = patch.to_str().unwrap();
+    ctx.run_mote(&["snap", "diff", &first, &second, "-o", patch]);
+
+    let assert_before = || {
+        assert_eq!(ctx.read_file("a.txt"), "one\ntwo\nthree\n");
+        assert_eq!(ctx.read_file("b.txt"), "bye\n");
+        assert!(!ctx.project_dir.join("new.txt").exists());
+    };
+    let assert_after = || {
+        assert_eq!(ctx.read_file("a.txt"), "one\n2\nthree\n");
+        assert!(!ctx.project_dir.join("b.txt").exists());
+        assert_eq!(ctx.read_file("new.txt"), "hello");
+    };
+
+    // Undo the working changes, then replay them from the patch
+    let output = ctx.run_mote(&["snap", "apply", "-R", patch]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_before();
+    assert_eq!(ctx.read_file("c.txt"), "keep\n");
+
+    let output = ctx.run_mote(&["snap", "apply", "--dry-run", patch]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("create  new.txt"), "{}", stdout);
+    assert!(stdout.contains("Would apply 4 file(s)"));
+    assert_before();
+
+    let output = ctx.run_mote(&["snap", "apply", patch]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Applied 4 file(s)"));
+    assert_after();
+
+    // A conflicting file fails the command but the others still apply
+    ctx.run_mote(&["snap", "apply", "-R", patch]);
+    ctx.write_file("c.txt", "edited\n");
+    let output = ctx.run_mote(&["snap", "apply", "--atomic", patch]);
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("conflict  c.txt"), "{}", stdout);
+    assert_before();
+
+    let output = ctx.run_mote(&["snap", "apply", patch]);
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("1 file(s) in the patch did not apply")
+    );
+    assert_after();
+    assert_eq!(ctx.read_file("c.txt"), "edited\n");
+}
+
+#[test]
+fn test_long_paths() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file("short.txt", "short");
+    // Nine 120-byte directories: over the 1024-byte warning length but well
+    // within what the temp dir's filesystem accepts
+    let deep: Vec<String> = (0..9)
+        .map(|i| format!("{}{}", i, "d".repeat(119)))
+        .collect();
+    let deep_dir = format!("fixtures/{}", deep.join("/"));
+    fs::create_dir_all(ctx.project_dir.join(&deep_dir)).unwrap();
+    ctx.write_file(&format!("{}/a.txt", deep_dir), "a");
+    ctx.write_file(&format!("{}/b.txt", deep_dir), "b");
+
+    let output = ctx.run_mote(&["snap", "create"]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("2 file(s) have paths over 1024 bytes under fixtures/0ddd"),
+        "{}",
+        stderr
+    );
+    assert!(String::from_utf8_lossy(&output.stdout).contains("(4 files)"));
+
+    fs::write(
+        ctx.config_dir.join("config.toml"),
+        "[snapshot]\nmax_path_len = 500\n",
+    )
+    .unwrap();
+    let output = ctx.run_mote_ctx(&["snap", "create", "-m", "limited"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Skipped 2 file(s) with paths over snapshot.max_path_len under fixtures/"),
+        "{}",
+        stderr
+    );
+    assert!(!stderr.contains("may not restore"));
+    let id = extract_snapshot_id(&output);
+    let show = ctx.run_mote(&["snap", "show", &id, "--json"]);
+    let snapshot: serde_json::Value = serde_json::from_slice(&show.stdout).unwrap();
+    assert_eq!(snapshot["files"].as_array().unwrap().len(), 2);
+
+    // A file name over NAME_MAX fails with ENAMETOOLONG on restore
+    let mut snapshot = snapshot;
+    let hash = snapshot["files"][0]["hash"].clone();
+    for name in ["x", "y"] {
+        snapshot["files"]
+            .as_array_mut()
+            .unwrap()
+            .push(serde_json::json!({
+                "path": format!("gen/long/{}.txt", name.repeat(300)),
+                "hash": hash,
+                "size": 5,
+            }));
+    }
+    snapshot["id"] = "e0e0e0e0".into();
+    fs::write(
+        ctx.project_dir.join(".mote/snapshots/e0e0e0e0.json"),
+        snapshot.to_string(),
+    )
+    .unwrap();
+    let output = ctx.run_mote(&["snap", "restore", "e0e0", "--force"]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Could not restore 2 file(s) whose paths are too long for this filesystem under gen/long/"),
+        "{}",
+        stderr
+    );
+    assert!(!stderr.contains("Failed to restore"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_snapshot_fail_fast() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file("a.txt", "a");
+    ctx.write_file("secret.txt", "secret");
+    let secret = ctx.project_dir.join("secret.txt");
+    fs::set_permissions(&secret, fs::Permissions::from_mode(0o000)).unwrap();
+
+    // Permissions don't stop root; the store failure below covers that case
+    if fs::read(&secret).is_err() {
+        let output = ctx.run_mote(&["snap", "create", "--fail-fast"]);
+        assert_eq!(output.status.code(), Some(1));
+        assert!(String::from_utf8_lossy(&output.stderr).contains("secret.txt"));
+        assert_eq!(snapshot_file_count(&ctx), 0);
+
+        let output = ctx.run_mote(&["snap", "create"]);
+        assert!(output.status.success());
+        assert_eq!(snapshot_file_count(&ctx), 1);
+    }
+    fs::set_permissions(&secret, fs::Permissions::from_mode(0o644)).unwrap();
+
+    // Every store fails once the objects directory is a plain file
+    let before = snapshot_file_count(&ctx);
+    let objects_dir = ctx.project_dir.join(".mote/objects");
+    fs::remove_dir_all(&objects_dir).unwrap();
+    fs::write(&objects_dir, "not a directory").unwrap();
+
+    let output = ctx.run_mote(&["snap", "create", "--fail-fast"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Snapshot aborted at"), "{}", stderr);
+    assert!(stderr.contains("after 0 files"), "{}", stderr);
+    assert_eq!(snapshot_file_count(&ctx), before);
+
+    // Shell hooks keep their quiet best-effort behavior
+    let output = ctx.run_mote(&["snap", "create", "--auto", "--fail-fast"]);
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_diff_exit_code_and_quiet() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file("a.txt", "a\n");
+    let output = ctx.run_mote(&["snap", "create", "-m", "first"]);
+    let first = extract_snapshot_id(&output);
+
+    // Unchanged working tree
+    for flag in ["--exit-code", "--quiet"] {
+        let output = ctx.run_mote(&["snap", "diff", flag]);
+        assert_eq!(output.status.code(), Some(0), "{}", flag);
+    }
+    let output = ctx.run_mote(&["snap", "diff", "--quiet"]);
+    assert!(output.stdout.is_empty());
+
+    // Changed working tree: status 1 without an error banner
+    ctx.write_file("a.txt", "b\n");
+    let output = ctx.run_mote(&["snap", "diff", "--exit-code"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("+b"));
+    assert!(output.stderr.is_empty());
+
+    let output = ctx.run_mote(&["snap", "diff", "-q"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    assert!(output.stderr.is_empty());
+
+    // Without either flag differences still exit 0
+    let output = ctx.run_mote(&["snap", "diff"]);
+    assert_eq!(output.status.code(), Some(0));
+
+    let output = ctx.run_mote(&["snap", "create", "-m", "second"]);
+    let second = extract_snapshot_id(&output);
+    let output = ctx.run_mote(&["snap", "diff", &first, &second, "--quiet"]);
+    assert_eq!(output.status.code(), Some(1));
+    let output = ctx.run_mote(&["snap", "diff", &second, &second, "--quiet"]);
+    assert_eq!(output.status.code(), Some(0));
+}
+
+fn copy_dir(from: &std::path::Path, to: &std::path::Path) {
+    fs::create_dir_all(to).unwrap();
+    for entry in fs::read_dir(from).unwrap() {
+        let path = entry.unwrap().path();
+        let target = to.join(path.file_name().unwrap());
+        if path.is_dir() {
+            copy_dir(&path, &target);
+        } else {
+            fs::copy(&path, &target).unwrap();
+        }
+    }
+}
+
+#[test]
+fn test_gc_verify_and_stats_on_bare_storage_dir() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file("a.txt", "kept");
+    ctx.run_mote(&["snap", "create", "-m", "first"]);
+    ctx.write_file("b.txt", "dropped");
+    let output = ctx.run_mote(&["snap", "create", "-m", "second"]);
+    let second = extract_snapshot_id(&output);
+    ctx.run_mote(&["snap", "delete", &second, "--force"]);
+
+    // A copied store on "another disk", used from a directory with no project
+    let backup = TempDir::new().unwrap();
+    let store = backup.path().join("store");
+    copy_dir(&ctx.project_dir.join(".mote"), &store);
+    let elsewhere = TempDir::new().unwrap();
+    let config_dir = ctx.config_dir.to_string_lossy().to_string();
+    let run = |args: &[&str]| {
+        Command::new(&ctx.mote_bin)
+            .args(["--config-dir", config_dir.as_str()])
+            .args(args)
+            .current_dir(elsewhere.path())
+            .output()
+            .unwrap()
+    };
+    let store_arg = store.to_string_lossy().to_string();
+
+    let output = run(&["stats", "--storage-dir", &store_arg, "--json"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stats: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(stats["snapshots"], 1);
+    assert_eq!(stats["reclaimable"]["objects"], 1);
+
+    let output = run(&["snap", "gc", "--no-grace", "--storage-dir", &store_arg]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Deleted 1 object(s)"));
+
+    let output = run(&["stats", "--storage-dir", &store_arg, "--json"]);
+    let stats: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(stats["reclaimable"]["objects"], 0);
+
+    let output = run(&["snap", "verify", "--full", "--storage-dir", &store_arg]);
+    assert!(output.status.success(), "{:?}", output);
+    // A missing object is reported against the store that was given
+    let objects = store.join("objects");
+    let object = fs::read_dir(&objects)
+        .unwrap()
+        .flat_map(|dir| fs::read_dir(dir.unwrap().path()).unwrap())
+        .map(|entry| entry.unwrap().path())
+        .next()
+        .unwrap();
+    let saved = fs::read(&object).unwrap();
+    fs::remove_file(&object).unwrap();
+    let output = run(&["snap", "verify", "--storage-dir", &store_arg]);
+    assert!(!output.status.success(), "{:?}", output);
+    fs::write(&object, saved).unwrap();
+
+    // The same store as a standalone context directory
+    let context_dir = backup.path().join("context");
+    fs::rename(&store, context_dir.join("storage")).unwrap_or_else(|_| {
+        fs::create_dir_all(&context_dir).unwrap();
+        fs::rename(&store, context_dir.join("storage")).unwrap();
+    });
+    let context_arg = context_dir.to_string_lossy().to_string();
+    let output = run(&["-d", &context_arg, "stats"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Snapshots:   1"));
+
+    // A mistyped path is an error, never a new empty store
+    let typo = backup.path().join("stroe");
+    let typo_arg = typo.to_string_lossy().to_string();
+    let output = run(&["snap", "gc", "--storage-dir", &typo_arg]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("No mote storage"));
+    let output = run(&["snap", "verify", "--storage-dir", &typo_arg]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("No mote storage"));
+    let output = run(&["-d", &typo_arg, "snap", "gc"]);
+    assert!(!output.status.success());
+    assert!(!typo.exists());
+}
+
+#[test]
+fn test_show_tree() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file("src/main.rs", "fn main() {}");
+    ctx.write_file("src/cmd/run.rs", "run");
+    ctx.write_file("src/cmd/stop.rs", "stop");
+    ctx.run_mote(&["snap", "create", "-m", "tree"]);
+
+    let output = ctx.run_mote(&["snap", "show", "--tree"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("├── src/ (3 files, 19 B)\n"));
+    assert!(stdout.contains("│   ├── cmd/ (2 files, 7 B)\n"));
+    assert!(stdout.contains("│   │   ├── run.rs (3 B)\n"));
+    assert!(stdout.contains("│   └── main.rs (12 B)\n"));
+
+    let output = ctx.run_mote(&["snap", "show", "--tree", "--depth", "1"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("│   └── … 3 files\n"));
+    assert!(!stdout.contains("main.rs"));
+
+    let output = ctx.run_mote(&["snap", "show", "--depth", "1"]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_list_filters_use_search_index() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    for (i, message) in ["fix parser", "add feature", "fix lexer"]
+        .iter()
+        .enumerate()
+    {
+        ctx.write_file("test.txt", &i.to_string());
+        ctx.run_mote(&["snap", "create", "-m", message]);
+    }
+    ctx.write_file("test.txt", "auto");
+    ctx.run_mote(&["snap", "create", "--trigger", "claude-hook"]);
+    let index_path = ctx.project_dir.join(".mote/snapshots/search.idx");
+
+    let list = |args: &[&str]| {
+        let mut full = vec!["snap", "list", "--oneline"];
+        full.extend_from_slice(args);
+        let output = ctx.run_mote(&full);
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    let without_sidecar = list(&["--grep", "fix"]);
+    assert!(index_path.exists(), "a filtered listing builds the sidecar");
+    let with_sidecar = list(&["--grep", "fix"]);
+    assert_eq!(with_sidecar, without_sidecar);
+    assert!(with_sidecar.contains("fix lexer"));
+    assert!(with_sidecar.contains("fix parser"));
+    assert!(!with_sidecar.contains("add feature"));
+
+    let by_trigger = list(&["--trigger", "claude-hook"]);
+    assert_eq!(by_trigger.lines().count(), 1);
+
+    // A snapshot the sidecar doesn't know about triggers a rebuild
+    ctx.write_file("test.txt", "more");
+    ctx.run_mote(&["snap", "create", "-m", "fix tests"]);
+    fs::write(&index_path, r#"{"entries":[]}"#).unwrap();
+    assert!(list(&["--grep", "fix"]).contains("fix tests"));
+
+    let output = ctx.run_mote(&["snap", "list", "--grep", "nothing"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("No snapshots match"));
+}
+
+#[test]
+fn test_diff_color() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file("test.txt", "old\n");
+    ctx.run_mote(&["snap", "create", "-m", "base"]);
+    ctx.write_file("test.txt", "new\n");
+
+    let diff = |args: &[&str], envs: &[(&str, &str)]| {
+        let mut full = vec!["snap", "diff"];
+        full.extend_from_slice(args);
+        let output = Command::new(&ctx.mote_bin)
+            .args(&full)
+            .env_remove("NO_COLOR")
+            .envs(envs.iter().copied())
+            .current_dir(&ctx.project_dir)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    let colored = diff(&["--color", "always"], &[]);
+    assert!(colored.contains("\x1b[32m+new\x1b[0m\n"));
+    assert!(colored.contains("\x1b[31m-old\x1b[0m\n"));
+    assert!(colored.contains("\x1b[36m@@ -1 +1 @@\x1b[0m\n"));
+
+    // Piped output is plain unless forced
+    assert!(!diff(&[], &[]).contains('\x1b'));
+    assert!(diff(&[], &[("MOTE_FORCE_TTY", "1")]).contains('\x1b'));
+    assert!(!diff(&[], &[("MOTE_FORCE_TTY", "1"), ("NO_COLOR", "1")]).contains('\x1b'));
+    assert!(!diff(&["--color", "never"], &[("MOTE_FORCE_TTY", "1")]).contains('\x1b'));
+
+    // Files are always plain
+    let patch = ctx.project_dir.join("out.patch");
+    diff(
+        &["--color", "always", "-o", patch.to_str().unwrap()],
+        &[("MOTE_FORCE_TTY", "1")],
+    );
+    let written = fs::read_to_string(&patch).unwrap();
+    assert!(written.contains("-old\n+new\n"));
+    assert!(!written.contains('\x1b'));
+}
+
+/// Every file under `dir` with its content, sorted by path
+fn tree_contents(dir: &std::path::Path) -> Vec<(PathBuf, Vec<u8>)> {
+    let mut contents = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        for entry in fs::read_dir(&current).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                pending.push(path);
+            } else {
+                let content = fs::read(&path).unwrap();
+                contents.push((path, content));
+            }
+        }
+    }
+    contents.sort();
+    contents
+}
+
+#[test]
+fn test_quickstart_is_idempotent() {
+    let ctx = TestContext::new();
+    ctx.write_file("Cargo.toml", "[package]\nname = \"demo\"\n");
+    ctx.write_file("src/main.rs", "fn main() {}");
+
+    let output = ctx.run_mote_ctx(&["quickstart", "--yes"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("created  project"));
+    assert!(stdout.contains("created  context 'default'"));
+    assert!(stdout.contains("created  ignore file"));
+    assert!(stdout.contains("Created snapshot"));
+    assert!(stdout.contains("mote setup"));
+
+    let output = ctx.run_mote_ctx(&["snap", "list", "--oneline"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout).lines().count(), 1);
+
+    let before = (
+        tree_contents(&ctx.config_dir),
+        tree_contents(&ctx.project_dir),
+    );
+    let output = ctx.run_mote_ctx(&["quickstart", "--yes"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("created"));
+    assert!(stdout.contains("found    project"));
+    assert!(stdout.contains("found    1 snapshot\n"));
+    let after = (
+        tree_contents(&ctx.config_dir),
+        tree_contents(&ctx.project_dir),
+    );
+    assert!(before == after, "second quickstart changed files");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_explicit_dirs_leave_home_untouched() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let ctx = TestContext::new();
+    ctx.write_file("src.txt", "content");
+    // A global config that fails to parse, so reading it would be an error
+    let home = TempDir::new().unwrap();
+    let home_config = home.path().join(".config/mote");
+    fs::create_dir_all(&home_config).unwrap();
+    fs::write(home_config.join("config.toml"), "not = [valid").unwrap();
+    let before = tree_contents(home.path());
+    let set_mode = |mode| {
+        for dir in [home.path(), &home.path().join(".config"), &home_config] {
+            fs::set_permissions(dir, fs::Permissions::from_mode(mode)).unwrap();
+        }
+    };
+    set_mode(0o555);
+
+    let run = |args: &[&str]| {
+        Command::new(&ctx.mote_bin)
+            .args(args)
+            .current_dir(&ctx.project_dir)
+            .env("HOME", home.path())
+            .env_remove("XDG_CONFIG_HOME")
+            .output()
+            .expect("Failed to execute mote")
+    };
+    let config_arg = ctx.config_dir.to_string_lossy().to_string();
+    let context_dir = ctx.config_dir.join("standalone");
+    let context_arg = context_dir.to_string_lossy().to_string();
+
+    let output = run(&["--config-dir", &config_arg, "init"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(ctx.config_dir.join("config.toml").exists());
+    let output = run(&["--config-dir", &config_arg, "project", "list"]);
+    assert!(output.status.success(), "{:?}", output);
+
+    for args in [
+        &["snap", "create", "-m", "standalone"][..],
+        &["snap", "list"],
+        &["stats"],
+        &["snap", "gc"],
+    ] {
+        let output = run(&[&["-d", &context_arg][..], args].concat());
+        assert!(output.status.success(), "{:?}: {:?}", args, output);
+    }
+    assert!(!context_dir.join("config.toml").exists());
+    let output = run(&["-d", &context_arg, "project", "list"]);
+    assert!(!output.status.success());
+
+    set_mode(0o755);
+    assert!(
+        tree_contents(home.path()) == before,
+        "home directory changed"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_partial_snapshot_marked_and_noted_on_restore() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file("a.txt", "a");
+    ctx.write_file("secret.txt", "secret");
+    // Paths over the limit are skipped too, which works even as root
+    ctx.write_file("deeply/nested/over_the_limit.txt", "long");
+    fs::write(
+        ctx.config_dir.join("config.toml"),
+        "[snapshot]\nmax_path_len = 20\n",
+    )
+    .unwrap();
+    let secret = ctx.project_dir.join("secret.txt");
+    fs::set_permissions(&secret, fs::Permissions::from_mode(0o000)).unwrap();
+    let unreadable = fs::read(&secret).is_err();
+
+    let output = ctx.run_mote_ctx(&["snap", "create"]);
+    fs::set_permissions(&secret, fs::Permissions::from_mode(0o644)).unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("(partial)"));
+    let id = extract_snapshot_id(&output);
+
+    let output = ctx.run_mote(&["snap", "list", "--oneline"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("(partial)"));
+    let output = ctx.run_mote(&["snap", "show", &id]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("(partial)"));
+    assert!(stdout.contains("deeply/nested/over_the_limit.txt"));
+
+    let show = ctx.run_mote(&["snap", "show", &id, "--json"]);
+    let snapshot: serde_json::Value = serde_json::from_slice(&show.stdout).unwrap();
+    assert_eq!(snapshot["partial"], true);
+    let skipped = snapshot["skipped_paths"].as_array().unwrap();
+    assert!(skipped.contains(&"deeply/nested/over_the_limit.txt".into()));
+    assert_eq!(skipped.contains(&"secret.txt".into()), unreadable);
+
+    let output = ctx.run_mote(&["snap", "restore", &id]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("is partial"), "{}", stdout);
+    assert!(stdout.contains("deeply/nested/over_the_limit.txt"));
+
+    // Complete snapshots carry no marker
+    fs::remove_file(ctx.config_dir.join("config.toml")).unwrap();
+    let output = ctx.run_mote(&["snap", "create"]);
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("(partial)"));
+}
+
+#[test]
+fn test_custom_context_dir_resolved_by_name() {
+    let ctx = TestContext::new();
+    ctx.init_context();
+    ctx.write_file("a.txt", "hello");
+
+    let custom_dir = ctx.config_dir.join("elsewhere");
+    let custom_arg = custom_dir.to_string_lossy().to_string();
+    let output = ctx.run_mote_ctx(&[
+        "-c",
+        "test/custom",
+        "-d",
+        &custom_arg,
+        "context",
+        "new",
+        "custom",
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(custom_dir.join("config.toml").exists());
+    assert!(!ctx
+        .config_dir
+        .join("projects/test/contexts/custom")
+        .exists());
+
+    let output = ctx.run_mote_ctx(&["-c", "test/custom", "snap", "create", "-m", "custom"]);
+    assert!(output.status.success(), "{:?}", output);
+    let id = extract_snapshot_id(&output);
+
+    // Objects land in the custom directory, not the default one
+    let objects = tree_contents(&custom_dir.join("storage/objects"));
+    assert!(!objects.is_empty());
+    assert!(!ctx
+        .config_dir
+        .join("projects/test/contexts/custom/storage")
+        .exists());
+
+    let output = ctx.run_mote_ctx(&["-c", "test/custom", "snap", "list", "--oneline"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains(&id));
+
+    // The default context keeps its own, empty store
+    let output = ctx.run_mote_ctx(&["-c", "test/default", "snap", "list", "--oneline"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(!String::from_utf8_lossy(&output.stdout).contains(&id));
+}
+
+#[test]
+fn test_editor_and_shell_errors_name_the_problem() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+
+    let output = Command::new(&ctx.mote_bin)
+        .args(["ignore", "edit"])
+        .env("EDITOR", "false")
+        .current_dir(&ctx.project_dir)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Editor 'false' failed: exit status: 1"),
+        "{}",
+        stderr
+    );
+    assert!(!stderr.contains("Failed to read config"), "{}", stderr);
+
+    let output = ctx.run_mote(&["setup", "tcsh"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Unsupported shell: tcsh"), "{}", stderr);
+    assert!(!stderr.contains("Failed to read config"), "{}", stderr);
+}
+
+#[test]
+#[cfg(unix)]
+fn test_hook_install_keeps_existing_hook_and_snapshots_on_run() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file(".git/hooks/pre-commit", "#!/bin/sh\necho lint\n");
+
+    let output = ctx.run_mote(&["hook", "install"]);
+    assert!(output.status.success(), "{:?}", output);
+    let pre_commit = ctx.read_file(".git/hooks/pre-commit");
+    assert!(pre_commit.contains("# >>> mote >>>"), "{}", pre_commit);
+    assert!(pre_commit.contains("snap create --auto -t git-pre-commit"));
+    assert!(pre_commit.ends_with("echo lint\n"));
+
+    let output = Command::new("sh")
+        .arg(".git/hooks/pre-commit")
+        .current_dir(&ctx.project_dir)
+        .output()
+        .unwrap();
+    assert!(String::from_utf8_lossy(&output.stdout).contains("lint"));
+    let output = ctx.run_mote(&["snap", "list", "--trigger", "git-pre-commit"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("git-pre-commit"));
+
+    let output = ctx.run_mote(&["hook", "status"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.matches("installed (").count(), 2, "{}", stdout);
+
+    let output = ctx.run_mote(&["hook", "uninstall"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        ctx.read_file(".git/hooks/pre-commit"),
+        "#!/bin/sh\necho lint\n"
+    );
+    assert!(!ctx.project_dir.join(".git/hooks/post-checkout").exists());
+}
+
+#[test]
+fn test_bare_snap_creates_snapshot_with_forwarded_flags() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file("a.txt", "one");
+
+    let output = ctx.run_mote(&["snap", "-m", "short form", "-t", "habit"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Created snapshot"));
+    let output = ctx.run_mote(&["snap", "list", "--trigger", "habit"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("short form"));
+
+    // --auto stays quiet and skips an unchanged tree
+    let output = ctx.run_mote(&["snap", "--auto"]);
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty(), "{:?}", output);
+    assert_eq!(snapshot_file_count(&ctx), 1);
+
+    let output = ctx.run_mote(&["snap", "-m", "x", "list"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("cannot be used with"));
+}
+
+#[test]
+fn test_global_color_choice_and_no_color() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file("a.txt", "one");
+
+    let run = |args: &[&str], envs: &[(&str, &str)]| {
+        let output = Command::new(&ctx.mote_bin)
+            .args(args)
+            .env_remove("NO_COLOR")
+            .envs(envs.iter().copied())
+            .current_dir(&ctx.project_dir)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    // Piped stdout gets no escape sequences by default
+    let created = run(&["snap", "-m", "first"], &[]);
+    assert!(created.contains("Created snapshot"));
+    assert!(!created.contains('\x1b'), "{:?}", created);
+    assert!(!run(&["snap", "list"], &[]).contains('\x1b'));
+
+    assert!(run(&["--color", "always", "snap", "list"], &[]).contains('\x1b'));
+    assert!(run(&["snap", "list", "--color=always"], &[]).contains('\x1b'));
+    assert!(run(&["snap", "list"], &[("MOTE_FORCE_TTY", "1")]).contains('\x1b'));
+    let no_color = [("MOTE_FORCE_TTY", "1"), ("NO_COLOR", "1")];
+    assert!(!run(&["snap", "list"], &no_color).contains('\x1b'));
+    assert!(!run(
+        &["--color", "never", "snap", "list"],
+        &[("MOTE_FORCE_TTY", "1")]
+    )
+    .contains('\x1b'));
+}
+
+#[test]
+fn test_unknown_snapshot_id_suggests_close_matches() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file("a.txt", "one");
+    let output = ctx.run_mote(&["snap", "create", "-m", "before refactor"]);
+    let id = extract_snapshot_id(&output);
+
+    // Swap the last character for a different hex digit
+    let mut typo = id[..6].to_string();
+    typo.push(if id.ends_with('0') { '1' } else { '0' });
+    let output = ctx.run_mote(&["snap", "show", &typo]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains(&format!("Snapshot not found: {}", typo)),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.contains(&format!("did you mean {} (", id)),
+        "{}",
+        stderr
+    );
+    assert!(stderr.contains("'before refactor')?"), "{}", stderr);
+}
+
+#[test]
+fn test_relative_snapshot_references() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    for content in ["one\n", "two\n", "three\n"] {
+        ctx.write_file("a.txt", content);
+        ctx.run_mote(&["snap", "-m", content.trim()]);
+    }
+
+    let output = ctx.run_mote(&["snap", "diff", "@~1", "@"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("-two\n+three"));
+
+    let output = ctx.run_mote(&["snap", "show", "@{2}:a.txt"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "one\n");
+
+    let output = ctx.run_mote(&["snap", "restore", "@~2", "--force"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(ctx.read_file("a.txt"), "one\n");
+
+    let output = ctx.run_mote(&["snap", "delete", "@~3", "--force"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("only 3 snapshot(s), @ through @~2"),
+        "{}",
+        stderr
+    );
+
+    let output = ctx.run_mote(&["snap", "show", "@-1"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("expected @, @~N or @{N}"));
+}
+
+#[test]
+#[cfg(unix)]
+fn test_non_utf8_file_names_are_skipped_consistently() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init"]);
+    ctx.write_file("a.txt", "one");
+    let bad_name = ctx.project_dir.join(OsStr::from_bytes(b"bad-\xff.txt"));
+    fs::write(&bad_name, "not utf-8").unwrap();
+
+    let output = ctx.run_mote(&["snap", "create"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("1 file(s) whose names aren't valid UTF-8"),
+        "{}",
+        stderr
+    );
+    let id = extract_snapshot_id(&output);
+
+    // Recorded as left out, not stored under a mangled name
+    let output = ctx.run_mote(&["snap", "show", &id, "--json"]);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["files"].as_array().unwrap().len(), 2);
+    assert_eq!(json["skipped_paths"][0], "bad-\u{fffd}.txt");
+    let output = ctx.run_mote(&["status", "--porcelain"]);
+    assert!(output.stdout.is_empty(), "{:?}", output);
+
+    // Restoring (even with --clean) neither deletes the file nor writes a
+    // lossily named copy of it
+    ctx.write_file("a.txt", "two");
+    let output = ctx.run_mote(&["snap", "restore", &id, "--clean", "--force", "--yes"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(ctx.read_file("a.txt"), "one");
+    assert_eq!(fs::read_to_string(&bad_name).unwrap(), "not utf-8");
+    let names: Vec<_> = fs::read_dir(&ctx.project_dir)
+        .unwrap()
+        .map(|e| e.unwrap().file_name())
+        .filter(|name| name.as_bytes().starts_with(b"bad-"))
+        .collect();
+    assert_eq!(names.len(), 1);
+}
+
+#[test]
+fn test_ignore_check_explains_and_sets_exit_code() {
+    let ctx = TestContext::new();
+    ctx.run_mote(&["init", "--minimal"]);
+    ctx.write_file(".moteignore", "*.log\nbuild/\n");
+    ctx.write_file("build/out.txt", "");
+    ctx.write_file("src/main.rs", "");
+
+    let output = ctx.run_mote(&["ignore", "check", "src/main.rs"]);
+    assert_eq!(output.status.code(), Some(0), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("captured src/main.rs"));
+
+    let output = ctx.run_mote(&["ignore", "check", "src/main.rs", "app.log", "build"]);
+    assert_eq!(output.status.code(), Some(1), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("ignored  app.log  .moteignore:1: *.log"),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("ignored  build  .moteignore:2: build/; not descended into"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn test_use_gitignore_flag_and_config() {
+    let ctx = TestContext::new();
+    ctx.run_mote_ctx(&["init", "--minimal"]);
+    ctx.write_file(".gitignore", "secret.env\n");
+    ctx.write_file("secret.env", "TOKEN=1");
+    ctx.write_file("a.txt", "one");
+
+    let files = |args: &[&str]| {
+        let mut full = args.to_vec();
+        full.extend(["snap", "create", "--checkpoint"]);
+        let output = ctx.run_mote_ctx(&full);
+        assert!(output.status.success(), "{:?}", output);
+        let output = ctx.run_mote_ctx(&["snap", "show", "latest", "--json"]);
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    assert!(files(&[]).contains("\"secret.env\""));
+    assert!(!files(&["--use-gitignore"]).contains("\"secret.env\""));
+
+    fs::write(
+        ctx.config_dir.join("config.toml"),
+        "[ignore]\nuse_gitignore = true\n",
+    )
+    .unwrap();
+    assert!(!files(&[]).contains("\"secret.env\""));
+
+    // The mote ignore file can re-include what git ignores
+    let output = ctx.run_mote_ctx(&["ignore", "add", "!secret.env"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(files(&[]).contains("\"secret.env\""));
+}
+
+#[test]
+fn test_ignore_files_layer_global_context_and_cli() {
+    let ctx = TestContext::new();
+    ctx.run_mote_ctx(&["init", "--minimal"]);
+    fs::create_dir_all(&ctx.config_dir).unwrap();
+    fs::write(ctx.config_dir.join("ignore"), "*.log\n*.bak\n").unwrap();
+    ctx.write_file(".moteignore", "!keep.log\n");
+    ctx.write_file("extra.ignore", "!old.bak\nkeep.log\n");
+    for name in ["a.txt", "app.log", "keep.log", "old.bak"] {
+        ctx.write_file(name, name);
+    }
+
+    let files = |args: &[&str]| {
+        let mut full = args.to_vec();
+        full.extend(["snap", "create", "--checkpoint"]);
+        let output = ctx.run_mote_ctx(&full);
+        assert!(output.status.success(), "{:?}", output);
+        let output = ctx.run_mote_ctx(&["snap", "show", "latest", "--json"]);
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        let mut paths: Vec<String> = json["files"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|f| f["path"].as_str().unwrap().to_string())
+            .filter(|path| !path.contains("ignore"))
+            .collect();
+        paths.sort();
+        paths
+    };
+
+    // The global file ignores, the project's ignore file re-includes
+    assert_eq!(files(&[]), ["a.txt", "keep.log"]);
+    // --ignore-file comes last and wins both conflicts
+    assert_eq!(
+        files(&["--ignore-file", "extra.ignore"]),
+        ["a.txt", "old.bak"]
+    );
+
+    // A missing --ignore-file is reported, the other layers still apply
+    let output = ctx.run_mote_ctx(&["--ignore-file", "nope.ignore", "status"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("nope.ignore"));
+}